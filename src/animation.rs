@@ -0,0 +1,167 @@
+use std::io::Write;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame};
+
+use crate::Image;
+
+/// A sequence of same-sized frames, each shown for its own delay, such as for animating noise or
+/// gradient parameters over time
+#[derive(Clone)]
+pub struct Animation {
+    // each frame with how many milliseconds it's shown for
+    frames: Vec<(Image, u32)>,
+    frame_delay: u32,
+    repeats: Option<u16>,
+    quantization_speed: i32,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            frames: Vec::new(),
+            frame_delay: 100,
+            repeats: None,
+            quantization_speed: 10,
+        }
+    }
+}
+
+impl Animation {
+    pub fn new() -> Self {
+        Animation::default()
+    }
+
+    /// Renders `frame_count` frames, passing `render` how far through the animation each one is, from 0
+    /// for the first frame up to but not including 1
+    pub fn from_fn(frame_count: usize, frame_delay: u32, render: impl FnMut(f64) -> Image) -> Result<Self, ()> {
+        let mut render = render;
+        let mut animation = Animation::new().with_frame_delay(frame_delay);
+        for frame in 0..frame_count {
+            animation.push_frame(render(frame as f64 / frame_count as f64))?;
+        }
+        Ok(animation)
+    }
+
+    /// How many milliseconds frames added with `push_frame` are shown for
+    pub fn with_frame_delay(self, frame_delay: u32) -> Self {
+        Animation { frame_delay, ..self }
+    }
+
+    /// How many times the animation plays after the first time, or forever if `None`, which is the default
+    pub fn with_repeats(self, repeats: Option<u16>) -> Self {
+        Animation { repeats, ..self }
+    }
+
+    /// From 1 for the best colors to 30 for the fastest encoding when reducing frames to a GIF's 256
+    /// colors
+    pub fn with_quantization_speed(self, quantization_speed: i32) -> Self {
+        Animation {
+            quantization_speed: quantization_speed.clamp(1, 30),
+            ..self
+        }
+    }
+
+    pub fn frame_delay(&self) -> u32 {
+        self.frame_delay
+    }
+
+    pub fn repeats(&self) -> Option<u16> {
+        self.repeats
+    }
+
+    pub fn quantization_speed(&self) -> i32 {
+        self.quantization_speed
+    }
+
+    /// Adds a frame shown for the animation's frame delay. Fails if it's not the same size as the first frame.
+    pub fn push_frame(&mut self, image: Image) -> Result<(), ()> {
+        self.push_frame_with_delay(image, self.frame_delay)
+    }
+
+    /// Adds a frame shown for `delay` milliseconds. Fails if it's not the same size as the first frame.
+    pub fn push_frame_with_delay(&mut self, image: Image, delay: u32) -> Result<(), ()> {
+        if let Some((first, _)) = self.frames.first()
+            && (first.width(), first.height()) != (image.width(), image.height()) {
+            return Err(());
+        }
+        self.frames.push((image, delay));
+        Ok(())
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &Image> {
+        self.frames.iter().map(|(image, _)| image)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Saves every frame as its own PNG, numbered from 0 with at least 4 digits after `prefix`, such as
+    /// for `ffmpeg -i prefix%04d.png`. Returns the names of the files written.
+    pub fn output_png_sequence(&self, prefix: &str) -> Result<Vec<String>, ()> {
+        let digits = self.frames.len().saturating_sub(1).to_string().len().max(4);
+        let mut filenames = Vec::with_capacity(self.frames.len());
+        for (index, (image, _)) in self.frames.iter().enumerate() {
+            let filename = format!("{prefix}{index:0digits$}.png");
+            image.output_to_image(&filename)?;
+            filenames.push(filename);
+        }
+        Ok(filenames)
+    }
+
+    pub fn output_apng(&self, filename: &str) -> Result<(), ()> {
+        self.write_apng(std::io::BufWriter::new(std::fs::File::create(filename).map_err(|_|())?))
+    }
+
+    /// Encodes the animation as an animated PNG, which keeps every color unlike a GIF. Delays are capped
+    /// at a little over a minute. Fails if there are no frames.
+    pub fn write_apng<W: Write>(&self, writer: W) -> Result<(), ()> {
+        let Some((first, _)) = self.frames.first() else {
+            return Err(());
+        };
+
+        let mut encoder = png::Encoder::new(writer, first.width().try_into().map_err(|_|())?, first.height().try_into().map_err(|_|())?);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        // 0 plays means forever
+        let plays = self.repeats.map_or(0, |repeats| repeats as u32 + 1);
+        encoder.set_animated(self.frames.len().try_into().map_err(|_|())?, plays).map_err(|_|())?;
+
+        let mut png_writer = encoder.write_header().map_err(|_|())?;
+        for (image, delay) in &self.frames {
+            png_writer.set_frame_delay((*delay).min(u16::MAX as u32) as u16, 1000).map_err(|_|())?;
+            let bytes: Vec<u8> = image.canvas.iter().flat_map(|color| [color.red, color.green, color.blue]).collect();
+            png_writer.write_image_data(&bytes).map_err(|_|())?;
+        }
+        png_writer.finish().map_err(|_|())
+    }
+
+    pub fn output_gif(&self, filename: &str) -> Result<(), ()> {
+        self.write_gif(std::io::BufWriter::new(std::fs::File::create(filename).map_err(|_|())?))
+    }
+
+    /// Encodes the animation as a GIF, reducing each frame to its own palette of 256 colors. Fails if
+    /// there are no frames.
+    pub fn write_gif<W: Write>(&self, writer: W) -> Result<(), ()> {
+        if self.frames.is_empty() {
+            return Err(());
+        }
+
+        let mut encoder = GifEncoder::new_with_speed(writer, self.quantization_speed);
+        encoder
+            .set_repeat(match self.repeats {
+                Some(repeats) => Repeat::Finite(repeats),
+                None => Repeat::Infinite,
+            })
+            .map_err(|_|())?;
+
+        for (image, delay) in &self.frames {
+            let rgba = image::DynamicImage::ImageRgb8(image.to_rgb_image()?).into_rgba8();
+            encoder
+                .encode_frame(Frame::from_parts(rgba, 0, 0, Delay::from_numer_denom_ms(*delay, 1)))
+                .map_err(|_|())?;
+        }
+        Ok(())
+    }
+}
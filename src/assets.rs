@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+/// Where images, masks and fonts come from when they're loaded by name, so they can be supplied
+/// without a real filesystem
+pub trait AssetResolver {
+    fn load(&self, name: &str) -> io::Result<Vec<u8>>;
+}
+
+/// Any `Fn(&str) -> io::Result<Vec<u8>>` can resolve assets
+impl<F: Fn(&str) -> io::Result<Vec<u8>>> AssetResolver for F {
+    fn load(&self, name: &str) -> io::Result<Vec<u8>> {
+        self(name)
+    }
+}
+
+/// Reads assets as files, relative to `root` if there is one and the working directory otherwise
+#[derive(Clone, Debug, Default)]
+pub struct FileSystemResolver {
+    root: Option<PathBuf>,
+}
+
+impl FileSystemResolver {
+    pub fn new() -> Self {
+        FileSystemResolver { root: None }
+    }
+
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        FileSystemResolver { root: Some(root.into()) }
+    }
+}
+
+impl AssetResolver for FileSystemResolver {
+    fn load(&self, name: &str) -> io::Result<Vec<u8>> {
+        match &self.root {
+            Some(root) => std::fs::read(root.join(name)),
+            None => std::fs::read(name),
+        }
+    }
+}
+
+/// Assets held in memory under the names they're loaded by
+#[derive(Clone, Debug, Default)]
+pub struct MemoryResolver {
+    assets: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        MemoryResolver { assets: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, name: &str, data: Vec<u8>) {
+        self.assets.insert(name.to_owned(), data);
+    }
+
+    pub fn with_asset(mut self, name: &str, data: Vec<u8>) -> Self {
+        self.insert(name, data);
+        self
+    }
+}
+
+impl AssetResolver for MemoryResolver {
+    fn load(&self, name: &str) -> io::Result<Vec<u8>> {
+        self.assets.get(name).cloned().ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No asset named {name}"),
+        ))
+    }
+}
+
+/// Loads and decodes an image asset
+pub(crate) fn load_image(resolver: &dyn AssetResolver, name: &str) -> Result<image::DynamicImage, ()> {
+    let data = resolver.load(name).map_err(|_|())?;
+    image::load_from_memory(&data).map_err(|_|())
+}
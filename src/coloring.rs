@@ -1,11 +1,316 @@
-use crate::shapes::Point;
+mod css;
+mod deep;
+mod hdr;
+mod hsl;
+mod oklab;
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::noise::{NoiseField, ScalarNoise};
+use crate::palette::Palette;
+use crate::shapes::{CheckInside, Point, Shape, SignedDistance, Triangle};
+use crate::Image;
+
+pub use deep::{SolidColor16, TransparentColor16};
+pub use hdr::{FColor, ToneMap};
+pub use hsl::{HslColor, HsvColor};
+pub use oklab::{OklabColor, OklchColor};
 
 
 pub trait Color: Sized + Copy {
     fn mix(color_weights: &[(Self, f64)]) -> Self;
+
+    /// How different the two colors look, 0 for the same color. Only distances between colors of the
+    /// same type can be compared.
+    fn distance(&self, other: &Self) -> f64;
+
+    /// How light the color looks, from 0 for black to 1 for white, evenly spaced to the eye like Oklab's lightness
+    fn perceived_lightness(&self) -> f64;
+
+    /// Rounds each of the red, green and blue channels to the closest of `levels` evenly spaced values
+    fn posterize(&self, levels: usize) -> Self;
+
+    /// The color with `adjustment`'s brightness, contrast, saturation and temperature applied
+    fn adjust(&self, adjustment: &Adjustment) -> Self;
+
+    /// Like `mix`, but blending in the color space `interpolation` picks. Color types that only
+    /// make sense in one space, like `OklabColor`, always mix in that one. The weights don't have to
+    /// add up to 1, since they get scaled so they do.
+    fn mix_in(_interpolation: Interpolation, color_weights: &[(Self, f64)]) -> Self {
+        Self::mix(&normalize_weights(color_weights))
+    }
+
+    /// Like `mix_in`, but rounding the result up instead of down when it's within `threshold` of the
+    /// next value up, so 0 always rounds down, 0.5 rounds to the nearest value and values close to 1
+    /// almost always round up. Varying the threshold from pixel to pixel dithers away banding. Color
+    /// types that aren't rounded just use `mix_in`.
+    fn mix_in_dithered(interpolation: Interpolation, color_weights: &[(Self, f64)], _threshold: f64) -> Self {
+        Self::mix_in(interpolation, color_weights)
+    }
+
+    /// Lays this color over `base_color` at `opacity`, from 0 to 1. Color types with transparency
+    /// also let `base_color` show through by their own alpha.
+    fn layer_over(&self, base_color: &Self, opacity: f64) -> Self {
+        Self::mix(&[(*base_color, 1. - opacity), (*self, opacity)])
+    }
+}
+
+/// Which color space gradients blend through. Blending sRGB values directly is how colors have always
+/// been mixed, but halfway between two saturated colors it passes through a muddy gray. In Oklab the
+/// brightness changes evenly instead, and Oklch also keeps the colors saturated by going around the
+/// color wheel, so red to blue passes through purple rather than gray. `LinearRgb` mixes the light
+/// itself, like real colored lights overlapping, which keeps mixes bright but doesn't space them evenly.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Interpolation {
+    #[default]
+    Srgb,
+    LinearRgb,
+    Oklab,
+    Oklch,
+}
+
+/// How a translucent color gets laid over what's under it. Blending the sRGB values directly is
+/// the usual way, but since those aren't proportional to the light they stand for, translucent
+/// overlaps come out darker than they should. `LinearLight` blends the light itself instead.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Compositing {
+    #[default]
+    Srgb,
+    LinearLight,
+}
+
+/// How a drawn color combines with the canvas under it, like the layer blend modes of image editors.
+/// The result is then laid over the canvas by the color's alpha, so only `Normal` draws the color as is.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    /// Darkens by multiplying the channels, like stacking slides. White leaves the canvas alone.
+    Multiply,
+    /// Lightens by multiplying the inverted channels, like two projectors on one screen. Black leaves the canvas alone.
+    Screen,
+    /// Multiplies the canvas's darks and screens its lights, adding contrast
+    Overlay,
+    /// Overlay with the two colors' roles swapped
+    HardLight,
+    /// A gentler overlay, like shining a diffuse light
+    SoftLight,
+    /// Adds the channels together, for glows and light that build up where they overlap
+    Add,
+    Subtract,
+    /// Keeps the darker value of each channel
+    Darken,
+    /// Keeps the lighter value of each channel
+    Lighten,
+    Difference,
+    /// Like `Difference` with less contrast
+    Exclusion,
+    /// Brightens the canvas to reflect the color
+    ColorDodge,
+    /// Darkens the canvas to reflect the color
+    ColorBurn,
+}
+
+/// Breaks up the banding of smooth gradients by nudging each pixel's rounding up or down in a
+/// pattern. The pattern is fine enough that it reads as the in-between color from a normal distance.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Dithering {
+    #[default]
+    None,
+    /// A regular 8×8 pattern. Cheap and even, but the grid can show on flat areas.
+    Bayer,
+    /// A 16×16 tile with no visible structure, at the cost of looking a little grainy
+    BlueNoise,
+}
+
+const BAYER_SIZE: usize = 8;
+const BLUE_NOISE_SIZE: usize = 16;
+
+impl Dithering {
+    /// How far the fractional part of a channel has to be before it's rounded up at pixel (`x`, `y`),
+    /// from 0 to 1. `None` always gives 0, which is the same as not dithering.
+    pub fn threshold(&self, x: usize, y: usize) -> f64 {
+        match self {
+            Dithering::None => 0.,
+            Dithering::Bayer => {
+                let rank = bayer_rank(x % BAYER_SIZE, y % BAYER_SIZE, BAYER_SIZE);
+                (rank as f64 + 0.5) / (BAYER_SIZE * BAYER_SIZE) as f64
+            },
+            Dithering::BlueNoise => {
+                let rank = blue_noise_ranks()[x % BLUE_NOISE_SIZE + (y % BLUE_NOISE_SIZE) * BLUE_NOISE_SIZE];
+                (rank as f64 + 0.5) / (BLUE_NOISE_SIZE * BLUE_NOISE_SIZE) as f64
+            },
+        }
+    }
+}
+
+/// Where (`x`, `y`) comes in the order of a `size` by `size` Bayer matrix, built up recursively
+/// from the 2×2 matrix
+fn bayer_rank(x: usize, y: usize, size: usize) -> usize {
+    if size == 1 {
+        return 0;
+    }
+    let half = size / 2;
+    let quadrant = match (x >= half, y >= half) {
+        (false, false) => 0,
+        (true, true) => 1,
+        (true, false) => 2,
+        (false, true) => 3,
+    };
+    4 * bayer_rank(x % half, y % half, half) + quadrant
+}
+
+/// The order to fill in a `BLUE_NOISE_SIZE` square tile, made with the void-and-cluster method.
+/// It only needs working out once, so it's kept after the first call.
+fn blue_noise_ranks() -> &'static [usize] {
+    static RANKS: std::sync::OnceLock<Vec<usize>> = std::sync::OnceLock::new();
+    RANKS.get_or_init(|| {
+        const CELLS: usize = BLUE_NOISE_SIZE * BLUE_NOISE_SIZE;
+        const SIGMA: f64 = 1.5;
+
+        // how crowded each cell is by the filled cells around it, wrapping around the edges so the tile repeats seamlessly
+        let mut energy = [0.; CELLS];
+        let mut filled = [false; CELLS];
+        let update = |energy: &mut [f64; CELLS], index: usize, sign: f64| {
+            let (x, y) = ((index % BLUE_NOISE_SIZE) as isize, (index / BLUE_NOISE_SIZE) as isize);
+            for (other, cell_energy) in energy.iter_mut().enumerate() {
+                let wrapped = |delta: isize| {
+                    let delta = delta.rem_euclid(BLUE_NOISE_SIZE as isize);
+                    delta.min(BLUE_NOISE_SIZE as isize - delta) as f64
+                };
+                let dx = wrapped((other % BLUE_NOISE_SIZE) as isize - x);
+                let dy = wrapped((other / BLUE_NOISE_SIZE) as isize - y);
+                *cell_energy += sign * (-(dx * dx + dy * dy) / (2. * SIGMA * SIGMA)).exp();
+            }
+        };
+        let tightest_cluster = |energy: &[f64; CELLS], filled: &[bool; CELLS]| (0..CELLS)
+            .filter(|&index| filled[index])
+            .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap();
+        let largest_void = |energy: &[f64; CELLS], filled: &[bool; CELLS]| (0..CELLS)
+            .filter(|&index| !filled[index])
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap();
+
+        // a scattering of about a tenth of the cells from a fixed hash, so the tile is the same every run
+        for (index, cell) in filled.iter_mut().enumerate() {
+            if (index as u32).wrapping_mul(2654435761) >> 28 < 2 {
+                *cell = true;
+                update(&mut energy, index, 1.);
+            }
+        }
+        let initial_count = filled.iter().filter(|&&cell| cell).count();
+
+        // spread them out evenly by moving the most crowded one into the biggest gap until that's where it already was
+        loop {
+            let cluster = tightest_cluster(&energy, &filled);
+            filled[cluster] = false;
+            update(&mut energy, cluster, -1.);
+            let void = largest_void(&energy, &filled);
+            filled[void] = true;
+            update(&mut energy, void, 1.);
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut ranks = vec![0; CELLS];
+
+        // the starting cells come first, taking away the most crowded each time
+        let (mut removing_energy, mut removing_filled) = (energy, filled);
+        for rank in (0..initial_count).rev() {
+            let cluster = tightest_cluster(&removing_energy, &removing_filled);
+            removing_filled[cluster] = false;
+            update(&mut removing_energy, cluster, -1.);
+            ranks[cluster] = rank;
+        }
+
+        // then the rest fill in the biggest gap each time
+        for rank in initial_count..CELLS {
+            let void = largest_void(&energy, &filled);
+            filled[void] = true;
+            update(&mut energy, void, 1.);
+            ranks[void] = rank;
+        }
+
+        ranks
+    })
+}
+
+impl BlendMode {
+    /// Blends one channel of the drawn color onto the same channel of the canvas, both from 0 to 1
+    fn blend_channel(&self, base: f64, top: f64) -> f64 {
+        let multiply = |base: f64, top: f64| base * top;
+        let screen = |base: f64, top: f64| base + top - base * top;
+        let hard_light = |base: f64, top: f64| if top <= 0.5 { multiply(base, 2. * top) } else { screen(base, 2. * top - 1.) };
+
+        match self {
+            BlendMode::Normal => top,
+            BlendMode::Multiply => multiply(base, top),
+            BlendMode::Screen => screen(base, top),
+            BlendMode::Overlay => hard_light(top, base),
+            BlendMode::HardLight => hard_light(base, top),
+            BlendMode::SoftLight => {
+                // the W3C compositing spec's version
+                if top <= 0.5 {
+                    base - (1. - 2. * top) * base * (1. - base)
+                } else {
+                    let curve = if base <= 0.25 { ((16. * base - 12.) * base + 4.) * base } else { base.sqrt() };
+                    base + (2. * top - 1.) * (curve - base)
+                }
+            },
+            BlendMode::Add => (base + top).min(1.),
+            BlendMode::Subtract => (base - top).max(0.),
+            BlendMode::Darken => base.min(top),
+            BlendMode::Lighten => base.max(top),
+            BlendMode::Difference => (base - top).abs(),
+            BlendMode::Exclusion => base + top - 2. * base * top,
+            BlendMode::ColorDodge => if base == 0. { 0. } else if top >= 1. { 1. } else { (base / (1. - top)).min(1.) },
+            BlendMode::ColorBurn => if base >= 1. { 1. } else if top <= 0. { 0. } else { 1. - ((1. - base) / top).min(1.) },
+        }
+    }
+}
+
+/// Converts an sRGB channel to linear light, from 0 to 1
+fn to_linear(channel: u8) -> f64 {
+    let channel = channel as f64 / u8::MAX as f64;
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The inverse of `to_linear`. Values outside 0 to 1 are clamped.
+fn from_linear(channel: f64) -> u8 {
+    let channel = channel.clamp(0., 1.);
+    let channel = if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1. / 2.4) - 0.055
+    };
+    (channel * u8::MAX as f64).round() as u8
+}
+
+/// Scales the weights to add up to 1, leaving them alone if they already do or are all 0
+fn normalize_weights<ColorType: Copy>(color_weights: &[(ColorType, f64)]) -> std::borrow::Cow<'_, [(ColorType, f64)]> {
+    let total: f64 = color_weights.iter().map(|(_, weight)| weight).sum();
+    if total == 0. || (total - 1.).abs() < 1e-9 {
+        std::borrow::Cow::Borrowed(color_weights)
+    } else {
+        color_weights.iter().map(|(color, weight)| (*color, weight / total)).collect()
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Mixes `color_weights` after converting them to `MixColor`
+fn mix_as<MixColor: Color + Into<TransparentColor>>(color_weights: &[(TransparentColor, f64)]) -> TransparentColor
+where TransparentColor: Into<MixColor> {
+    let converted_weights: Vec<(MixColor, f64)> = color_weights.iter().map(|(color, weight)| ((*color).into(), *weight)).collect();
+    MixColor::mix(&converted_weights).into()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct SolidColor {
     pub red: u8,
     pub green: u8,
@@ -21,33 +326,66 @@ impl Into<image::Rgb<u8>> for SolidColor{
 impl Color for SolidColor {
     fn mix(color_weights: &[(Self, f64)]) -> Self {
         let transparent_weights: Vec<(TransparentColor, f64)> = color_weights.iter().map(|(solid_color, weight)| 
-            (solid_color.clone().into(), *weight)
+            ((*solid_color).into(), *weight)
         ).collect();
         TransparentColor::mix(&transparent_weights).as_solid()
     }
+
+    fn distance(&self, other: &Self) -> f64 {
+        Into::<TransparentColor>::into(*self).distance(&(*other).into())
+    }
+
+    fn perceived_lightness(&self) -> f64 {
+        Into::<OklabColor>::into(*self).lightness
+    }
+
+    fn posterize(&self, levels: usize) -> Self {
+        Into::<TransparentColor>::into(*self).posterize(levels).as_solid()
+    }
+
+    fn adjust(&self, adjustment: &Adjustment) -> Self {
+        Into::<TransparentColor>::into(*self).adjust(adjustment).as_solid()
+    }
+
+    fn mix_in(interpolation: Interpolation, color_weights: &[(Self, f64)]) -> Self {
+        let transparent_weights: Vec<(TransparentColor, f64)> = color_weights.iter().map(|(solid_color, weight)|
+            ((*solid_color).into(), *weight)
+        ).collect();
+        TransparentColor::mix_in(interpolation, &transparent_weights).as_solid()
+    }
+
+    fn mix_in_dithered(interpolation: Interpolation, color_weights: &[(Self, f64)], threshold: f64) -> Self {
+        let transparent_weights: Vec<(TransparentColor, f64)> = color_weights.iter().map(|(solid_color, weight)|
+            ((*solid_color).into(), *weight)
+        ).collect();
+        TransparentColor::mix_in_dithered(interpolation, &transparent_weights, threshold).as_solid()
+    }
 }
 
 impl SolidColor {
-    pub const BLACK: SolidColor = SolidColor {
-        red: 0,
-        green: 0,
-        blue: 0,
-    };
-
     pub fn from_hex_code(hex_code: &str) -> SolidColor {
-        let orig_hex_code = hex_code;
-        let hex_code = if hex_code.chars().nth(0) == Some('#') {
-            &hex_code[1..]
-        } else { hex_code };
-
-        if hex_code.len() == 8 && u8::from_str_radix(&hex_code[6..8], 16).is_err(){
-            panic!("Invalid hex code \"{orig_hex_code}\"");
+        match TransparentColor::try_from_hex_code(hex_code).map(TryInto::<SolidColor>::try_into) {
+            Ok(Ok(solid_color)) => solid_color,
+            Ok(Err(())) => panic!("alpha component specified for a solid color. Did you mean to call Color::from_hex_code(\"{hex_code}\") instead?"),
+            Err(()) => panic!("Invalid hex code \"{hex_code}\""),
         }
+    }
 
-        match TransparentColor::from_hex_code(orig_hex_code).try_into() {
-            Ok(solid_color) => solid_color,
-            Err(()) => panic!("alpha component specified for a solid color. Did you mean to call Color::from_hex_code(\"{orig_hex_code}\") instead?")
-        }
+    /// Like `from_hex_code`, but returns an error instead of panicking
+    pub fn try_from_hex_code(hex_code: &str) -> Result<SolidColor, ()> {
+        TransparentColor::try_from_hex_code(hex_code)?.try_into()
+    }
+
+    /// How bright the color looks, from 0 for black to 1 for white, as defined by WCAG
+    pub fn relative_luminance(&self) -> f64 {
+        0.2126 * to_linear(self.red) + 0.7152 * to_linear(self.green) + 0.0722 * to_linear(self.blue)
+    }
+
+    /// The WCAG contrast ratio between the two colors, from 1 for the same brightness to 21 for black on white.
+    /// Text needs at least 4.5 to be readable.
+    pub fn contrast_ratio(&self, other: &SolidColor) -> f64 {
+        let (luminance1, luminance2) = (self.relative_luminance(), other.relative_luminance());
+        (luminance1.max(luminance2) + 0.05) / (luminance1.min(luminance2) + 0.05)
     }
 }
 
@@ -62,7 +400,7 @@ impl Into<TransparentColor> for SolidColor {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct TransparentColor {
     pub red: u8,
     pub green: u8,
@@ -78,28 +416,88 @@ impl Into<image::Rgba<u8>> for TransparentColor {
 
 impl Color for TransparentColor {
     fn mix(color_weights: &[(Self, f64)]) -> Self {
-        let mut running_red = 0.;
-        let mut running_green = 0.;
-        let mut running_blue = 0.;
-        let mut running_alpha = 0.;
-    
-        for (color, weight) in color_weights {
-            running_red += color.red as f64 * weight;
-            running_green += color.green as f64 * weight;
-            running_blue += color.blue as f64 * weight;
-            running_alpha += color.alpha as f64 * weight;
+        Self::mix_dithered(color_weights, 0.)
+    }
+
+    /// The "redmean" approximation of how far apart colors look, which weighs the channels by how
+    /// sensitive eyes are to them without leaving RGB. Alpha differences count like a channel.
+    fn distance(&self, other: &Self) -> f64 {
+        let mean_red = (self.red as f64 + other.red as f64) / 2.;
+        let diff = |channel1: u8, channel2: u8| channel1 as f64 - channel2 as f64;
+        let (red, green, blue, alpha) = (
+            diff(self.red, other.red), diff(self.green, other.green),
+            diff(self.blue, other.blue), diff(self.alpha, other.alpha),
+        );
+
+        ((2. + mean_red / 256.) * red * red + 4. * green * green + (2. + (255. - mean_red) / 256.) * blue * blue + 3. * alpha * alpha).sqrt()
+    }
+
+    /// Ignores alpha
+    fn perceived_lightness(&self) -> f64 {
+        Into::<OklabColor>::into(*self).lightness
+    }
+
+    /// Leaves alpha alone
+    fn posterize(&self, levels: usize) -> Self {
+        let step = u8::MAX as f64 / (levels.max(2) - 1) as f64;
+        let posterize_channel = |channel: u8| ((channel as f64 / step).round() * step).round() as u8;
+
+        TransparentColor {
+            red: posterize_channel(self.red),
+            green: posterize_channel(self.green),
+            blue: posterize_channel(self.blue),
+            alpha: self.alpha,
         }
-        
-        running_red = running_red.clamp(0., 255.);
-        running_green = running_green.clamp(0., 255.);
-        running_blue = running_blue.clamp(0., 255.);
-        running_alpha = running_alpha.clamp(0., 255.);
-    
+    }
+
+    /// Leaves alpha alone
+    fn adjust(&self, adjustment: &Adjustment) -> Self {
+        let [red, green, blue] = adjust_channels(adjustment, [self.red, self.green, self.blue].map(|channel| channel as f64 / 255.));
+        let to_channel = |channel: f64| (channel * 255.).round().clamp(0., 255.) as u8;
+
+        TransparentColor {
+            red: to_channel(red),
+            green: to_channel(green),
+            blue: to_channel(blue),
+            alpha: self.alpha,
+        }
+    }
+
+    fn mix_in(interpolation: Interpolation, color_weights: &[(Self, f64)]) -> Self {
+        let color_weights = &normalize_weights(color_weights);
+        match interpolation {
+            Interpolation::Srgb => Self::mix(color_weights),
+            Interpolation::LinearRgb => mix_as::<FColor>(color_weights),
+            Interpolation::Oklab => mix_as::<OklabColor>(color_weights),
+            Interpolation::Oklch => mix_as::<OklchColor>(color_weights),
+        }
+    }
+
+    /// Only mixing in sRGB is dithered
+    fn mix_in_dithered(interpolation: Interpolation, color_weights: &[(Self, f64)], threshold: f64) -> Self {
+        match interpolation {
+            Interpolation::Srgb => Self::mix_dithered(&normalize_weights(color_weights), threshold),
+            _ => Self::mix_in(interpolation, color_weights),
+        }
+    }
+
+    fn layer_over(&self, base_color: &Self, opacity: f64) -> Self {
+        let coverage = self.alpha as f64 / u8::MAX as f64 * opacity.clamp(0., 1.);
+        let base_coverage = base_color.alpha as f64 / u8::MAX as f64 * (1. - coverage);
+        let new_alpha = coverage + base_coverage;
+        if new_alpha == 0. {
+            return TransparentColor::TRANSPARENT;
+        }
+
+        let find_new_color = |color1: u8, color2: u8| {
+            ((color1 as f64 * base_coverage + color2 as f64 * coverage) / new_alpha).round().clamp(0., 255.) as u8
+        };
+
         TransparentColor {
-            red: running_red as u8,
-            green: running_green as u8,
-            blue: running_blue as u8,
-            alpha: running_alpha as u8,
+            red: find_new_color(base_color.red, self.red),
+            green: find_new_color(base_color.green, self.green),
+            blue: find_new_color(base_color.blue, self.blue),
+            alpha: (new_alpha * u8::MAX as f64).round() as u8,
         }
     }
 }
@@ -120,6 +518,34 @@ impl TryInto<SolidColor> for TransparentColor {
 }
 
 impl TransparentColor {
+    /// Mixes the channels, then rounds them down unless they're within `threshold` of the next value up
+    fn mix_dithered(color_weights: &[(Self, f64)], threshold: f64) -> Self {
+        let mut running_red = 0.;
+        let mut running_green = 0.;
+        let mut running_blue = 0.;
+        let mut running_alpha = 0.;
+    
+        for (color, weight) in color_weights {
+            running_red += color.red as f64 * weight;
+            running_green += color.green as f64 * weight;
+            running_blue += color.blue as f64 * weight;
+            running_alpha += color.alpha as f64 * weight;
+        }
+        
+        running_red = running_red.clamp(0., 255.);
+        running_green = running_green.clamp(0., 255.);
+        running_blue = running_blue.clamp(0., 255.);
+        running_alpha = running_alpha.clamp(0., 255.);
+    
+        // casting saturates, so pushing 255 past the top stays 255
+        TransparentColor {
+            red: (running_red + threshold) as u8,
+            green: (running_green + threshold) as u8,
+            blue: (running_blue + threshold) as u8,
+            alpha: (running_alpha + threshold) as u8,
+        }
+    }
+
     pub const TRANSPARENT: TransparentColor = TransparentColor {
         red: 0,
         green: 0,
@@ -128,35 +554,26 @@ impl TransparentColor {
     };
 
     pub fn from_hex_code(hex_code: &str) -> Self {
-        let orig_hex_code = hex_code;
-        let hex_code = if hex_code.chars().nth(0) == Some('#') {
-            &hex_code[1..]
-        } else { hex_code };
+        Self::try_from_hex_code(hex_code).unwrap_or_else(|()| panic!("Invalid hex code \"{hex_code}\""))
+    }
 
-        let red: u8;
-        let green: u8; 
-        let blue: u8;
-        let mut alpha: u8;
-        if hex_code.len() == 6 || hex_code.len() == 8 {
-            red = u8::from_str_radix(&hex_code[0..2], 16).expect(&format!("Invalid red component in hex code \"{orig_hex_code}\""));
-            green = u8::from_str_radix(&hex_code[2..4], 16).expect(&format!("Invalid green component in hex code \"{orig_hex_code}\""));
-            blue = u8::from_str_radix(&hex_code[4..6], 16).expect(&format!("Invalid blue component in hex code \"{orig_hex_code}\""));
-            alpha = u8::MAX;
-       } else {
-           panic!("Invalid hex code {orig_hex_code}");
-       }
+    /// Like `from_hex_code`, but returns an error instead of panicking
+    pub fn try_from_hex_code(hex_code: &str) -> Result<Self, ()> {
+        let hex_code = hex_code.strip_prefix('#').unwrap_or(hex_code);
 
-        if hex_code.len() == 8 {
-            alpha = u8::from_str_radix(&hex_code[4..6], 16).expect(&format!("Invalid alpha component in hex code \"{orig_hex_code}\""));
+        // checking every digit up front also keeps the slicing below on char boundaries
+        if (hex_code.len() != 6 && hex_code.len() != 8) || !hex_code.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(());
         }
+        let component = |start: usize| u8::from_str_radix(&hex_code[start..start + 2], 16)
+            .expect("Every digit was checked to be hex");
 
-
-        TransparentColor {
-            red,
-            green,
-            blue,
-            alpha,
-        }
+        Ok(TransparentColor {
+            red: component(0),
+            green: component(2),
+            blue: component(4),
+            alpha: if hex_code.len() == 8 { component(6) } else { u8::MAX },
+        })
     }
 
     pub fn as_solid(&self) -> SolidColor {
@@ -183,6 +600,35 @@ impl TransparentColor {
         }
     }
 
+    /// `draw_on_solid` for a whole run of pixels, each of `colors` drawn on the matching one of `base_colors`.
+    /// Channels are unpacked into arrays of 16 pixels at a time so the compiler can do the math with SIMD
+    /// instructions. The results are exactly the same as drawing one pixel at a time.
+    pub fn draw_span_on_solid(colors: &[TransparentColor], base_colors: &mut [SolidColor]) {
+        const LANES: usize = 16;
+        let max = u8::MAX as u16;
+
+        let mut color_chunks = colors.chunks_exact(LANES);
+        let mut base_chunks = base_colors.chunks_exact_mut(LANES);
+        for (colors, base_colors) in (&mut color_chunks).zip(&mut base_chunks) {
+            let alpha: [u16; LANES] = std::array::from_fn(|lane| colors[lane].alpha as u16);
+            let find_new_channel = |channel: fn(&TransparentColor) -> u8, base_channel: fn(&SolidColor) -> u8| -> [u8; LANES] {
+                let top: [u16; LANES] = std::array::from_fn(|lane| channel(&colors[lane]) as u16);
+                let base: [u16; LANES] = std::array::from_fn(|lane| base_channel(&base_colors[lane]) as u16);
+                std::array::from_fn(|lane| (base[lane] * (max - alpha[lane]) / max + top[lane] * alpha[lane] / max) as u8)
+            };
+
+            let red = find_new_channel(|color| color.red, |color| color.red);
+            let green = find_new_channel(|color| color.green, |color| color.green);
+            let blue = find_new_channel(|color| color.blue, |color| color.blue);
+            for (lane, base_color) in base_colors.iter_mut().enumerate() {
+                *base_color = SolidColor { red: red[lane], green: green[lane], blue: blue[lane] };
+            }
+        }
+
+        for (color, base_color) in color_chunks.remainder().iter().zip(base_chunks.into_remainder()) {
+            *base_color = color.draw_on_solid(base_color);
+        }
+    }
 
     pub fn draw_on(&self, base_color: &TransparentColor) -> TransparentColor {
         let new_alpha = self.alpha as u32 + base_color.alpha as u32 - (self.alpha as u32 * base_color.alpha as u32) / 255;
@@ -203,34 +649,278 @@ impl TransparentColor {
         }
     }
 
+    /// Like `draw_on_solid`, but blending in linear light
+    pub fn draw_on_solid_linear(&self, base_color: &SolidColor) -> SolidColor {
+        let coverage = self.alpha as f64 / u8::MAX as f64;
+        let find_new_color = |color1: u8, color2: u8| {
+            from_linear(to_linear(color1) * (1. - coverage) + to_linear(color2) * coverage)
+        };
+
+        SolidColor {
+            red: find_new_color(base_color.red, self.red),
+            green: find_new_color(base_color.green, self.green),
+            blue: find_new_color(base_color.blue, self.blue),
+        }
+    }
+
+    /// Like `draw_on`, but blending in linear light
+    pub fn draw_on_linear(&self, base_color: &TransparentColor) -> TransparentColor {
+        let coverage = self.alpha as f64 / u8::MAX as f64;
+        let base_coverage = base_color.alpha as f64 / u8::MAX as f64 * (1. - coverage);
+        let new_alpha = coverage + base_coverage;
+        if new_alpha == 0. {
+            return TransparentColor::TRANSPARENT;
+        }
+
+        let find_new_color = |color1: u8, color2: u8| {
+            from_linear((to_linear(color1) * base_coverage + to_linear(color2) * coverage) / new_alpha)
+        };
+
+        TransparentColor {
+            red: find_new_color(base_color.red, self.red),
+            green: find_new_color(base_color.green, self.green),
+            blue: find_new_color(base_color.blue, self.blue),
+            alpha: (new_alpha * u8::MAX as f64).round() as u8,
+        }
+    }
+
+    /// Draws this color on `base_color` the way `compositing` says to
+    pub fn draw_on_solid_with(&self, base_color: &SolidColor, compositing: Compositing) -> SolidColor {
+        match compositing {
+            Compositing::Srgb => self.draw_on_solid(base_color),
+            Compositing::LinearLight => self.draw_on_solid_linear(base_color),
+        }
+    }
+
+    /// Like `draw_on_solid_with`, but combining the colors with `blend_mode` first
+    pub fn blend_on_solid(&self, base_color: &SolidColor, blend_mode: BlendMode, compositing: Compositing) -> SolidColor {
+        if blend_mode == BlendMode::Normal {
+            return self.draw_on_solid_with(base_color, compositing);
+        }
+
+        let (decode, encode) = channel_codec(compositing);
+        let coverage = self.alpha as f64 / u8::MAX as f64;
+        let find_new_color = |color1: u8, color2: u8| {
+            let (base, top) = (decode(color1), decode(color2));
+            encode(base * (1. - coverage) + blend_mode.blend_channel(base, top) * coverage)
+        };
+
+        SolidColor {
+            red: find_new_color(base_color.red, self.red),
+            green: find_new_color(base_color.green, self.green),
+            blue: find_new_color(base_color.blue, self.blue),
+        }
+    }
+
+    /// Like `blend_on_solid`, but onto a color that may be see-through itself. Where `base_color` is
+    /// transparent the color is drawn as is, since there's nothing under it to blend with.
+    pub fn blend_on(&self, base_color: &TransparentColor, blend_mode: BlendMode, compositing: Compositing) -> TransparentColor {
+        let (decode, encode) = channel_codec(compositing);
+        let base_coverage = base_color.alpha as f64 / u8::MAX as f64;
+        let blend_channel = |color1: u8, color2: u8| {
+            let (base, top) = (decode(color1), decode(color2));
+            encode(top * (1. - base_coverage) + blend_mode.blend_channel(base, top) * base_coverage)
+        };
+
+        let blended = if blend_mode == BlendMode::Normal {
+            *self
+        } else {
+            TransparentColor {
+                red: blend_channel(base_color.red, self.red),
+                green: blend_channel(base_color.green, self.green),
+                blue: blend_channel(base_color.blue, self.blue),
+                alpha: self.alpha,
+            }
+        };
+
+        match compositing {
+            Compositing::Srgb => blended.layer_over(base_color, 1.),
+            Compositing::LinearLight => blended.draw_on_linear(base_color),
+        }
+    }
 }
 
 
+/// Turns a channel into the 0 to 1 values blend modes work on, and back again
+type ChannelCodec = (fn(u8) -> f64, fn(f64) -> u8);
+
+fn channel_codec(compositing: Compositing) -> ChannelCodec {
+    match compositing {
+        Compositing::Srgb => (
+            |channel| channel as f64 / u8::MAX as f64,
+            |channel| (channel.clamp(0., 1.) * u8::MAX as f64).round() as u8,
+        ),
+        Compositing::LinearLight => (to_linear, from_linear),
+    }
+}
+
 pub trait Coloring {
     type ColorType; 
     fn sample_color(&self, point: &Point) -> Self::ColorType;
+
+    /// Like `sample_color`, but passing `threshold` on to `Color::mix_in_dithered` wherever colors get mixed
+    fn sample_color_dithered(&self, point: &Point, _threshold: f64) -> Self::ColorType {
+        self.sample_color(point)
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ColorScheme<ColorType: Color> {
+    Solid(ColorType),
     LinearGradient(LinearGradient<ColorType>),
+    MultiStopGradient(MultiStopGradient<ColorType>),
+    RadialGradient(RadialGradient<ColorType>),
     ComplexGradient(ComplexGradient<ColorType>),
+    VertexGradient(VertexGradient<ColorType>),
+    Dots(Dots<ColorType>),
+    ImageFill(ImageFill<ColorType>),
+    NoiseFill(NoiseFill<ColorType>),
+    Palettized(Palettized<ColorType>),
+    ColorRamp(ColorRamp<ColorType>),
+    SdfGlow(SdfGlow<ColorType>),
+    Duotone(Duotone<ColorType>),
+    Posterize(Posterize<ColorType>),
+    Adjust(Adjust<ColorType>),
+    Warp(Warp<ColorType>),
+    Composite(Composite<ColorType>),
 }
 
 impl<ColorType: Color> Coloring for ColorScheme<ColorType> {
     type ColorType = ColorType;
     fn sample_color(&self, point: &Point) -> Self::ColorType {
         match self {
+            ColorScheme::Solid(color) => *color,
             ColorScheme::LinearGradient(grad) => grad.sample_color(point),
+            ColorScheme::MultiStopGradient(grad) => grad.sample_color(point),
+            ColorScheme::RadialGradient(grad) => grad.sample_color(point),
             ColorScheme::ComplexGradient(grad) => grad.sample_color(point),
+            ColorScheme::VertexGradient(grad) => grad.sample_color(point),
+            ColorScheme::Dots(dots) => dots.sample_color(point),
+            ColorScheme::ImageFill(fill) => fill.sample_color(point),
+            ColorScheme::NoiseFill(fill) => fill.sample_color(point),
+            ColorScheme::Palettized(palettized) => palettized.sample_color(point),
+            ColorScheme::ColorRamp(ramp) => ramp.sample_color(point),
+            ColorScheme::SdfGlow(glow) => glow.sample_color(point),
+            ColorScheme::Duotone(duotone) => duotone.sample_color(point),
+            ColorScheme::Posterize(posterize) => posterize.sample_color(point),
+            ColorScheme::Adjust(adjust) => adjust.sample_color(point),
+            ColorScheme::Warp(warp) => warp.sample_color(point),
+            ColorScheme::Composite(composite) => composite.sample_color(point),
+        }
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        match self {
+            ColorScheme::Solid(color) => *color,
+            ColorScheme::LinearGradient(grad) => grad.sample_color_dithered(point, threshold),
+            ColorScheme::MultiStopGradient(grad) => grad.sample_color_dithered(point, threshold),
+            ColorScheme::RadialGradient(grad) => grad.sample_color_dithered(point, threshold),
+            ColorScheme::ComplexGradient(grad) => grad.sample_color_dithered(point, threshold),
+            ColorScheme::VertexGradient(grad) => grad.sample_color_dithered(point, threshold),
+            ColorScheme::Dots(dots) => dots.sample_color_dithered(point, threshold),
+            ColorScheme::ImageFill(fill) => fill.sample_color_dithered(point, threshold),
+            ColorScheme::NoiseFill(fill) => fill.sample_color_dithered(point, threshold),
+            ColorScheme::Palettized(palettized) => palettized.sample_color_dithered(point, threshold),
+            ColorScheme::ColorRamp(ramp) => ramp.sample_color_dithered(point, threshold),
+            ColorScheme::SdfGlow(glow) => glow.sample_color_dithered(point, threshold),
+            ColorScheme::Duotone(duotone) => duotone.sample_color_dithered(point, threshold),
+            ColorScheme::Posterize(posterize) => posterize.sample_color_dithered(point, threshold),
+            ColorScheme::Adjust(adjust) => adjust.sample_color_dithered(point, threshold),
+            ColorScheme::Warp(warp) => warp.sample_color_dithered(point, threshold),
+            ColorScheme::Composite(composite) => composite.sample_color_dithered(point, threshold),
+        }
+    }
+}
+
+impl<ColorType: Color + PartialEq> ColorScheme<ColorType> {
+    /// Every color the scheme mixes between. Mixes never leave the range these cover.
+    fn key_colors(&self) -> Vec<ColorType> {
+        match self {
+            ColorScheme::Solid(color) => vec![*color],
+            ColorScheme::LinearGradient(grad) => vec![grad.pole1.1, grad.pole2.1],
+            ColorScheme::MultiStopGradient(grad) => grad.stops.iter().map(|(_, color)| *color).collect(),
+            ColorScheme::RadialGradient(grad) => vec![grad.inner_ring.1, grad.outer_ring.1],
+            ColorScheme::ComplexGradient(grad) => grad.poles.iter().map(|(_, color)| *color).collect(),
+            ColorScheme::VertexGradient(grad) => grad.colors.to_vec(),
+            ColorScheme::Dots(dots) => vec![dots.foreground, dots.background],
+            ColorScheme::ImageFill(fill) => fill.pixels.to_vec(),
+            ColorScheme::NoiseFill(fill) => fill.stops.iter().map(|(_, color)| *color).collect(),
+            ColorScheme::Palettized(palettized) => palettized.palette.clone(),
+            ColorScheme::ColorRamp(ramp) => ramp.stops.iter().map(|(_, color)| *color).collect(),
+            ColorScheme::SdfGlow(glow) => vec![glow.color, glow.background],
+            ColorScheme::Duotone(duotone) => duotone.tones.iter().map(|(_, color)| *color).collect(),
+            ColorScheme::Posterize(posterize) => posterize.inner.key_colors().iter().map(|color| color.posterize(posterize.levels)).collect(),
+            ColorScheme::Adjust(adjust) => adjust.inner.key_colors().iter().map(|color| color.adjust(&adjust.adjustment)).collect(),
+            ColorScheme::Warp(warp) => warp.inner.key_colors(),
+            ColorScheme::Composite(composite) => std::iter::once(composite.base.as_ref()).chain(composite.layers.iter().map(|layer| &layer.coloring))
+                .flat_map(|coloring| coloring.key_colors())
+                .collect(),
+        }
+    }
+
+    /// Whether the scheme lays colors over each other, which changes translucent ones even when they're
+    /// all the same
+    fn stacks_colors(&self) -> bool {
+        match self {
+            ColorScheme::Composite(composite) => !composite.layers.is_empty(),
+            ColorScheme::Posterize(posterize) => posterize.inner.stacks_colors(),
+            ColorScheme::Adjust(adjust) => adjust.inner.stacks_colors(),
+            ColorScheme::Warp(warp) => warp.inner.stacks_colors(),
+            _ => false,
+        }
+    }
+
+    /// Replaces a gradient between copies of the same color with just that color
+    pub fn fold_constant(&mut self) {
+        let key_colors = self.key_colors();
+        if !self.stacks_colors()
+            && let Some(first_color) = key_colors.first()
+            && key_colors.iter().all(|color| color == first_color) {
+            *self = ColorScheme::Solid(*first_color);
+        }
+    }
+}
+
+impl ColorScheme<TransparentColor> {
+    pub fn is_opaque(&self) -> bool {
+        self.key_colors().iter().all(|color| color.alpha == u8::MAX)
+    }
+
+    /// Whether the scheme is fully transparent everywhere
+    pub fn is_invisible(&self) -> bool {
+        self.key_colors().iter().all(|color| color.alpha == 0)
+    }
+}
+
+/// What a gradient does past its ends
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SpreadMode {
+    /// Keeps the color at the nearest end
+    #[default]
+    Clamp,
+    /// Starts over from the beginning, making stripes
+    Repeat,
+    /// Runs back and forth, so every other copy is mirrored
+    Reflect,
+}
+
+impl SpreadMode {
+    /// Maps how far along the gradient a point is, where 0 and 1 are the ends, back into 0..=1
+    fn apply(&self, position: f64) -> f64 {
+        match self {
+            SpreadMode::Clamp => position.clamp(0., 1.),
+            SpreadMode::Repeat => position.rem_euclid(1.),
+            SpreadMode::Reflect => 1. - (position.rem_euclid(2.) - 1.).abs(),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct LinearGradient<ColorType: Color> {
     pole1: (Point, ColorType),
     pole2: (Point, ColorType),
+    spread: SpreadMode,
+    interpolation: Interpolation,
 }
 
 impl<ColorType: Color> Into<ColorScheme<ColorType>> for LinearGradient<ColorType> {
@@ -241,69 +931,309 @@ impl<ColorType: Color> Into<ColorScheme<ColorType>> for LinearGradient<ColorType
 
 impl<ColorType: Color> LinearGradient<ColorType> {
     pub fn with_poles(pole1: (Point, ColorType), pole2: (Point, ColorType)) -> LinearGradient<ColorType> {
-        if pole1.0.x == pole2.0.x {
-            if pole1.0.y == pole2.0.y {
-                panic!("Gradient poles must be distinct");
-            } else if pole1.0.y < pole2.0.y {
-                LinearGradient {
-                    pole1, pole2
-                }
-            } else {
-            LinearGradient {
-                pole1: pole2,
-                pole2: pole1,
-            }
-            }
-        } else if pole1.0.x < pole2.0.x {
-            LinearGradient {
-                pole1, pole2
-            }
-        } else {
-            LinearGradient {
-                pole1: pole2,
-                pole2: pole1,
-            }
+        if pole1.0 == pole2.0 {
+            panic!("Gradient poles must be distinct");
+        }
+
+        // poles are kept left to right, or top to bottom when they line up vertically
+        let is_in_order = pole1.0.x < pole2.0.x || (pole1.0.x == pole2.0.x && pole1.0.y < pole2.0.y);
+        let (pole1, pole2) = if is_in_order { (pole1, pole2) } else { (pole2, pole1) };
+        LinearGradient {
+            pole1,
+            pole2,
+            spread: SpreadMode::Clamp,
+            interpolation: Interpolation::Srgb,
         }
     }
-}
 
-impl<ColorType: Color> Coloring for LinearGradient<ColorType> {
-    type ColorType = ColorType;
+    pub fn with_spread(self, spread: SpreadMode) -> Self {
+        LinearGradient { spread, ..self }
+    }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        LinearGradient { interpolation, ..self }
+    }
+
+    /// Recolors the pole at `location`, if there's one there
+    pub(crate) fn set_pole_color(&mut self, location: Point, color: ColorType) {
+        for pole in [&mut self.pole1, &mut self.pole2] {
+            if pole.0 == location {
+                pole.1 = color;
+            }
+        }
+    }
+}
+
+impl LinearGradient<TransparentColor> {
+    /// Fades `color` out to nothing going from `from` to `to`. Only the alpha changes along the way.
+    pub fn fade_out(color: impl Into<TransparentColor>, from: Point, to: Point) -> LinearGradient<TransparentColor> {
+        let color = color.into();
+        LinearGradient::with_poles((from, color), (to, TransparentColor { alpha: 0, ..color }))
+    }
+}
+
+impl LinearGradient<SolidColor> {
+    /// Finds the direction the colors of the image change the most along, and spans a gradient across the image
+    /// from the average color at one end to the average color at the other
+    pub fn fit_from_image(image: &Image) -> LinearGradient<SolidColor> {
+        const DIRECTIONS: usize = 16;
+        // how much of the image, from each end, gets averaged into a pole color
+        const END_PORTION: f64 = 0.2;
+
+        let width = image.width() as f64;
+        let height = image.height() as f64;
+        let center = Point { x: width / 2., y: height / 2. };
+        let stride = crate::palette::sample_stride(image);
+        let overall_color = crate::palette::average_color(&crate::palette::sample_pixels(image));
+
+        let mut best_fit: Option<(f64, LinearGradient<SolidColor>)> = None;
+        for direction_index in 0..DIRECTIONS {
+            let angle = std::f64::consts::PI * direction_index as f64 / DIRECTIONS as f64;
+            let (dir_y, dir_x) = angle.sin_cos();
+            let half_extent = (width * dir_x.abs() + height * dir_y.abs()) / 2.;
+            let end_threshold = half_extent * (1. - 2. * END_PORTION);
+
+            let mut near_colors = Vec::new();
+            let mut far_colors = Vec::new();
+            for y in (0..image.height()).step_by(stride) {
+                for x in (0..image.width()).step_by(stride) {
+                    let projection = (x as f64 + 0.5 - center.x) * dir_x + (y as f64 + 0.5 - center.y) * dir_y;
+                    if projection <= -end_threshold {
+                        near_colors.push(*image.get_pixel(x, y));
+                    } else if projection >= end_threshold {
+                        far_colors.push(*image.get_pixel(x, y));
+                    }
+                }
+            }
+
+            let near_color = if near_colors.is_empty() { overall_color } else { crate::palette::average_color(&near_colors) };
+            let far_color = if far_colors.is_empty() { overall_color } else { crate::palette::average_color(&far_colors) };
+            let channel_diff = |near: u8, far: u8| (near as f64 - far as f64).powi(2);
+            let score = channel_diff(near_color.red, far_color.red)
+                + channel_diff(near_color.green, far_color.green)
+                + channel_diff(near_color.blue, far_color.blue);
+
+            if best_fit.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+                let near_pole = Point { x: center.x - dir_x * half_extent, y: center.y - dir_y * half_extent };
+                let far_pole = Point { x: center.x + dir_x * half_extent, y: center.y + dir_y * half_extent };
+                best_fit = Some((score, LinearGradient::with_poles((near_pole, near_color), (far_pole, far_color))));
+            }
+        }
+
+        best_fit.expect("At least one direction is always checked").1
+    }
+}
+
+impl<ColorType: Color> Coloring for LinearGradient<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.sample_color_dithered(point, 0.)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        // how far along the line between the poles the point falls, from 0 at pole1 to 1 at pole2
+        let axis = self.pole2.0 - self.pole1.0;
+        let position = (*point - self.pole1.0).dot(&axis) / axis.dot(&axis);
+
+        let portion2 = self.spread.apply(position);
+        let portion1 = 1.0 - portion2;
+
+        Self::ColorType::mix_in_dithered(self.interpolation, &[(self.pole1.1, portion1), (self.pole2.1, portion2)], threshold)
+    }
+}
+
+/// A gradient along the axis from `start` to `end` through any number of colors. Each stop is an
+/// (offset, color) pair, where offset 0 is at `start` and 1 is at `end`, and the color changes evenly
+/// from each stop to the next. Past the first and last stops the color saturates. The spread mode
+/// repeats or reflects the whole 0 to 1 span, not just the part between the first and last stops.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiStopGradient<ColorType: Color> {
+    start: Point,
+    end: Point,
+    stops: Vec<(f64, ColorType)>,
+    spread: SpreadMode,
+    interpolation: Interpolation,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for MultiStopGradient<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::MultiStopGradient(self)
+    }
+}
+
+impl<ColorType: Color> MultiStopGradient<ColorType> {
+    /// Stops can be given in any order. Two stops at the same offset make a hard edge, going from
+    /// whichever was given first to the other.
+    pub fn new(start: Point, end: Point, mut stops: Vec<(f64, ColorType)>) -> MultiStopGradient<ColorType> {
+        if start == end {
+            panic!("Gradient start and end must be distinct");
+        }
+        if stops.is_empty() {
+            panic!("Gradients need at least one stop");
+        }
+        stops.sort_by(|(offset1, _), (offset2, _)| offset1.total_cmp(offset2));
+
+        MultiStopGradient {
+            start,
+            end,
+            stops,
+            spread: SpreadMode::Clamp,
+            interpolation: Interpolation::Srgb,
+        }
+    }
+
+    pub fn with_spread(self, spread: SpreadMode) -> Self {
+        MultiStopGradient { spread, ..self }
+    }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        MultiStopGradient { interpolation, ..self }
+    }
+
+    pub fn stops(&self) -> &[(f64, ColorType)] {
+        &self.stops
+    }
+
+    /// How far along the axis `point` is, where 0 is `start` and 1 is `end`
+    fn offset_of(&self, point: &Point) -> f64 {
+        let axis = self.end - self.start;
+        (*point - self.start).dot(&axis) / axis.dot(&axis)
+    }
+}
+
+impl<ColorType: Color> Coloring for MultiStopGradient<ColorType> {
+    type ColorType = ColorType;
 
     fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.sample_color_dithered(point, 0.)
+    }
 
-        // if beyond the bounds of the gradient, just saturate to the closest point
-        if self.pole1.0.x == self.pole2.0.x {
-            if point.y < self.pole1.0.y {
-                return self.pole1.1.clone();
-            } 
-            if point.y > self.pole2.0.y {
-                return self.pole2.1.clone()
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        let offset = match self.spread {
+            // clamping to the ends would move stops placed outside 0 to 1
+            SpreadMode::Clamp => self.offset_of(point),
+            spread => spread.apply(self.offset_of(point)),
+        };
+
+        sample_stops(&self.stops, offset, self.interpolation, threshold)
+    }
+}
+
+/// The color at `offset` along `stops`, which have to be sorted by offset. Past the first and last stops the color saturates.
+fn sample_stops<ColorType: Color>(stops: &[(f64, ColorType)], offset: f64, interpolation: Interpolation, threshold: f64) -> ColorType {
+    // the first stop past the offset, so the stops on either side of it are next_index - 1 and next_index
+    let next_index = stops.partition_point(|(stop_offset, _)| *stop_offset <= offset);
+    if next_index == 0 {
+        return stops[0].1;
+    }
+    if next_index == stops.len() {
+        return stops[next_index - 1].1;
+    }
+
+    let (offset1, color1) = stops[next_index - 1];
+    let (offset2, color2) = stops[next_index];
+    let portion2 = (offset - offset1) / (offset2 - offset1);
+    ColorType::mix_in_dithered(interpolation, &[(color1, 1. - portion2), (color2, portion2)], threshold)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RadialGradient<ColorType: Color> {
+    center: Point,
+    inner_ring: (f64, ColorType),
+    outer_ring: (f64, ColorType),
+    spread: SpreadMode,
+    interpolation: Interpolation,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for RadialGradient<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::RadialGradient(self)
+    }
+}
+
+impl<ColorType: Color> RadialGradient<ColorType> {
+    /// Rings are given as (radius, color). Inside the inner ring and outside the outer ring the color saturates,
+    /// unless the spread mode is changed.
+    pub fn with_rings(center: Point, inner_ring: (f64, ColorType), outer_ring: (f64, ColorType)) -> RadialGradient<ColorType> {
+        if inner_ring.0 == outer_ring.0 {
+            panic!("Gradient rings must be distinct");
+        } else if inner_ring.0 < outer_ring.0 {
+            RadialGradient {
+                center, inner_ring, outer_ring,
+                spread: SpreadMode::Clamp,
+                interpolation: Interpolation::Srgb,
             }
         } else {
-            if point.x < self.pole1.0.x {
-                return self.pole1.1.clone();
-            } 
-            if point.x > self.pole2.0.x {
-                return self.pole2.1.clone();
+            RadialGradient {
+                center,
+                inner_ring: outer_ring,
+                outer_ring: inner_ring,
+                spread: SpreadMode::Clamp,
+                interpolation: Interpolation::Srgb,
             }
         }
+    }
+
+    /// Makes concentric rings, with `Repeat` or `Reflect`
+    pub fn with_spread(self, spread: SpreadMode) -> Self {
+        RadialGradient { spread, ..self }
+    }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        RadialGradient { interpolation, ..self }
+    }
+}
+
+impl RadialGradient<TransparentColor> {
+    /// `color` out to `inner_radius`, then fading out to nothing at `outer_radius`. Only the alpha changes.
+    pub fn fade_out(color: impl Into<TransparentColor>, center: Point, inner_radius: f64, outer_radius: f64) -> RadialGradient<TransparentColor> {
+        let color = color.into();
+        RadialGradient::with_rings(center, (inner_radius, color), (outer_radius, TransparentColor { alpha: 0, ..color }))
+    }
 
-        let dist1 = point.dist_to(&self.pole1.0);
-        let dist2 = point.dist_to(&self.pole2.0);
+    /// Clear out to `inner_radius`, then darkening toward `color` at `outer_radius` and beyond, to frame
+    /// whatever's in the middle. Only the alpha changes.
+    pub fn vignette(color: impl Into<TransparentColor>, center: Point, inner_radius: f64, outer_radius: f64) -> RadialGradient<TransparentColor> {
+        let color = color.into();
+        RadialGradient::with_rings(center, (inner_radius, TransparentColor { alpha: 0, ..color }), (outer_radius, color))
+    }
+}
+
+impl<ColorType: Color> Coloring for RadialGradient<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.sample_color_dithered(point, 0.)
+    }
 
-        let total_dist = dist1 + dist2;
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        let dist = point.dist_to(&self.center);
 
-        let portion1 = dist1 / total_dist;
-        let portion2 = 1.0 - portion1;
+        let portion2 = self.spread.apply((dist - self.inner_ring.0) / (self.outer_ring.0 - self.inner_ring.0));
+        let portion1 = 1.0 - portion2;
 
-        Self::ColorType::mix(&[(self.pole1.1, portion1), (self.pole2.1, portion2)])
+        Self::ColorType::mix_in_dithered(self.interpolation, &[(self.inner_ring.1, portion1), (self.outer_ring.1, portion2)], threshold)
     }
 }
 
-#[derive(Clone, Debug)]
+/// How much each pole of a `ComplexGradient` counts at a point, depending on how far away it is
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Falloff {
+    /// Each pole counts in proportion to its distance, so the farthest poles have the most say.
+    /// This is how complex gradients have always worked.
+    #[default]
+    Proportional,
+    /// Inverse distance weighting: each pole counts as 1 / distance^power, so the nearest poles win out,
+    /// more sharply the higher the power. 2 is the usual choice.
+    InverseDistance { power: f64 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct ComplexGradient<ColorType: Color>{
     poles: Vec<(Point, ColorType)>,
+    interpolation: Interpolation,
+    falloff: Falloff,
 }
 
 impl<ColorType: Color> Into<ColorScheme<ColorType>> for ComplexGradient<ColorType> {
@@ -312,10 +1242,18 @@ impl<ColorType: Color> Into<ColorScheme<ColorType>> for ComplexGradient<ColorTyp
     }    
 }
 
+impl<ColorType: Color> Default for ComplexGradient<ColorType> {
+    fn default() -> Self {
+        ComplexGradient::new()
+    }
+}
+
 impl<ColorType: Color> ComplexGradient<ColorType> {
     pub const fn new() -> Self {
         ComplexGradient { 
-            poles: Vec::new()
+            poles: Vec::new(),
+            interpolation: Interpolation::Srgb,
+            falloff: Falloff::Proportional,
         }
     }
 
@@ -327,17 +1265,1011 @@ impl<ColorType: Color> ComplexGradient<ColorType> {
         }
         self.poles.push((location, color));
     }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        ComplexGradient { interpolation, ..self }
+    }
+
+    pub fn with_falloff(self, falloff: Falloff) -> Self {
+        ComplexGradient { falloff, ..self }
+    }
 }
 
 impl<ColorType: Color> Coloring for ComplexGradient<ColorType> {
     type ColorType = ColorType;
     fn sample_color(&self, point: &Point) -> Self::ColorType {
-        let total_dist: f64 = self.poles.iter().map(|(pole, _)|point.dist_to(pole)).sum();
+        self.sample_color_dithered(point, 0.)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        let weights: Vec<f64> = match self.falloff {
+            Falloff::Proportional => self.poles.iter().map(|(pole, _)| point.dist_to(pole)).collect(),
+            Falloff::InverseDistance { power } => {
+                // right on a pole, that pole is the only one that counts
+                if let Some((_, color)) = self.poles.iter().find(|(pole, _)| pole == point) {
+                    return *color;
+                }
+                self.poles.iter().map(|(pole, _)| point.dist_to(pole).powf(-power)).collect()
+            },
+        };
+
+        let total_weight: f64 = weights.iter().sum();
         let scaled_poles = 
-            &self.poles.iter().map(|(pole, color)|{
-                (*color, point.dist_to(pole) / total_dist)
+            &self.poles.iter().zip(weights).map(|((_, color), weight)|{
+                (*color, weight / total_weight)
             }).collect::<Vec<_>>();
-        Self::ColorType::mix(scaled_poles)
+        Self::ColorType::mix_in_dithered(self.interpolation, scaled_poles, threshold)
+    }
+}
+
+/// A color at each corner of a triangle, blended across it by barycentric weight like Gouraud shading.
+/// Outside the triangle the weights are clamped, so the colors never go past the ones at the corners.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexGradient<ColorType: Color> {
+    triangle: Triangle,
+    colors: [ColorType; 3],
+    interpolation: Interpolation,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for VertexGradient<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::VertexGradient(self)
+    }
+}
+
+impl<ColorType: Color> VertexGradient<ColorType> {
+    pub fn with_vertices(vertex1: (Point, ColorType), vertex2: (Point, ColorType), vertex3: (Point, ColorType)) -> VertexGradient<ColorType> {
+        let triangle = Triangle::new(vertex1.0, vertex2.0, vertex3.0);
+        if triangle.area() == 0. {
+            panic!("Gradient vertices must not be in a line");
+        }
+
+        VertexGradient {
+            triangle,
+            colors: [vertex1.1, vertex2.1, vertex3.1],
+            interpolation: Interpolation::Srgb,
+        }
+    }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        VertexGradient { interpolation, ..self }
+    }
+}
+
+impl<ColorType: Color> Coloring for VertexGradient<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.sample_color_dithered(point, 0.)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        let weights = self.triangle.barycentric(point).expect("Gradient triangles always have an area");
+        // outside the triangle some weights go negative, so they're clamped and the rest scaled back up
+        let clamped_weights = weights.map(|weight| weight.max(0.));
+        let total_weight: f64 = clamped_weights.iter().sum();
+
+        Self::ColorType::mix_in_dithered(self.interpolation, &[
+            (self.colors[0], clamped_weights[0] / total_weight),
+            (self.colors[1], clamped_weights[1] / total_weight),
+            (self.colors[2], clamped_weights[2] / total_weight),
+        ], threshold)
+    }
+}
+
+/// How the dots of a `Dots` coloring are arranged
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum DotLattice {
+    /// Rows and columns, like a grid
+    #[default]
+    Square,
+    /// Every other row shifted by half a dot, so each dot has six neighbors the same distance away
+    Hexagonal,
+}
+
+/// Polka dots of `foreground` on `background`, with a dot centered every `spacing` pixels. A size
+/// modulation coloring makes the dots bigger where it's darker, the way halftone printing does.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dots<ColorType: Color> {
+    spacing: f64,
+    radius: f64,
+    foreground: ColorType,
+    background: ColorType,
+    lattice: DotLattice,
+    // in radians
+    angle: f64,
+    size_modulation: Option<Box<ColorScheme<TransparentColor>>>,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for Dots<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::Dots(self)
+    }
+}
+
+impl<ColorType: Color> Dots<ColorType> {
+    pub fn new(spacing: f64, radius: f64, foreground: ColorType, background: ColorType) -> Dots<ColorType> {
+        if spacing <= 0. {
+            panic!("Dot spacing must be positive");
+        }
+
+        Dots {
+            spacing,
+            radius,
+            foreground,
+            background,
+            lattice: DotLattice::Square,
+            angle: 0.,
+            size_modulation: None,
+        }
+    }
+
+    pub fn with_lattice(self, lattice: DotLattice) -> Self {
+        Dots { lattice, ..self }
+    }
+
+    /// Turns the rows of dots by `angle` radians. Halftone screens usually sit at 45 degrees.
+    pub fn with_angle(self, angle: f64) -> Self {
+        Dots { angle, ..self }
+    }
+
+    /// Scales each dot by how dark `modulation` is at its center, from no dot where it's white to the
+    /// full radius where it's black. Transparent parts count as white.
+    pub fn with_size_modulation(self, modulation: ColorScheme<TransparentColor>) -> Self {
+        Dots {
+            size_modulation: Some(Box::new(modulation)),
+            ..self
+        }
+    }
+
+    /// The center of the dot closest to `point`
+    fn nearest_center(&self, point: &Point) -> Point {
+        // the lattice is laid out unrotated, so the point gets turned back into its frame and the center turned forward
+        let (sin, cos) = self.angle.sin_cos();
+        let rotate = |point: Point, sin: f64| Point {
+            x: point.x * cos - point.y * sin,
+            y: point.x * sin + point.y * cos,
+        };
+        let local = rotate(*point, -sin);
+
+        let local_center = match self.lattice {
+            DotLattice::Square => Point {
+                x: (local.x / self.spacing).round() * self.spacing,
+                y: (local.y / self.spacing).round() * self.spacing,
+            },
+            DotLattice::Hexagonal => {
+                let row_height = self.spacing * 3f64.sqrt() / 2.;
+                let row_above = (local.y / row_height).floor();
+                [row_above, row_above + 1.].into_iter()
+                    .map(|row| {
+                        let shift = if row.rem_euclid(2.) == 1. { self.spacing / 2. } else { 0. };
+                        Point {
+                            x: ((local.x - shift) / self.spacing).round() * self.spacing + shift,
+                            y: row * row_height,
+                        }
+                    })
+                    .min_by(|center1, center2| center1.square_dist_to(&local).total_cmp(&center2.square_dist_to(&local)))
+                    .expect("There are always two rows to pick from")
+            },
+        };
+        rotate(local_center, sin)
+    }
+}
+
+impl<ColorType: Color> Coloring for Dots<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.sample_color_dithered(point, 0.)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        let center = self.nearest_center(point);
+        let radius = match &self.size_modulation {
+            Some(modulation) => {
+                let darkness = 1. - modulation.sample_color(&center).draw_on_solid(&SolidColor::WHITE).relative_luminance();
+                self.radius * darkness
+            },
+            None => self.radius,
+        };
+
+        // a pixel wide blend at the edge keeps the dots round instead of jagged
+        let coverage = (radius - point.dist_to(&center) + 0.5).clamp(0., 1.);
+        Self::ColorType::mix_in_dithered(Interpolation::Srgb, &[(self.foreground, coverage), (self.background, 1. - coverage)], threshold)
+    }
+}
+
+/// How `ImageFill` picks a color between pixels
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ImageFilter {
+    /// Takes the closest pixel, keeping hard edges when the image is scaled up
+    Nearest,
+    /// Blends the four closest pixels, for smooth scaling
+    #[default]
+    Bilinear,
+}
+
+/// Paints with a raster image, so photos or textures can be clipped to a shape. The image's top left
+/// pixel sits at `offset`, and each of its pixels covers `scale` pixels of the canvas. Past its edges the
+/// tiling, as a `SpreadMode`, either keeps the edge colors going, repeats the image, or mirrors it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageFill<ColorType: Color> {
+    width: usize,
+    height: usize,
+    pixels: Arc<[ColorType]>,
+    offset: Point,
+    scale: f64,
+    tiling: SpreadMode,
+    filter: ImageFilter,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for ImageFill<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::ImageFill(self)
+    }
+}
+
+impl<ColorType: Color> ImageFill<ColorType> {
+    pub fn from_image(image: &Image) -> ImageFill<ColorType>
+    where SolidColor: Into<ColorType> {
+        if image.width() == 0 || image.height() == 0 {
+            panic!("Image fills need an image with at least one pixel");
+        }
+
+        ImageFill {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.canvas.iter().map(|color| (*color).into()).collect(),
+            offset: Point { x: 0., y: 0. },
+            scale: 1.,
+            tiling: SpreadMode::Clamp,
+            filter: ImageFilter::Bilinear,
+        }
+    }
+
+    pub fn with_offset(self, offset: Point) -> Self {
+        ImageFill { offset, ..self }
+    }
+
+    pub fn with_scale(self, scale: f64) -> Self {
+        if scale <= 0. {
+            panic!("Image fill scale must be positive");
+        }
+        ImageFill { scale, ..self }
+    }
+
+    pub fn with_tiling(self, tiling: SpreadMode) -> Self {
+        ImageFill { tiling, ..self }
+    }
+
+    pub fn with_filter(self, filter: ImageFilter) -> Self {
+        ImageFill { filter, ..self }
+    }
+
+    /// The pixel at (`x`, `y`), which can be anywhere, after tiling brings it back onto the image
+    fn get_tiled_pixel(&self, x: isize, y: isize) -> ColorType {
+        let tile = |index: isize, size: usize| -> usize {
+            let size = size as isize;
+            let index = match self.tiling {
+                SpreadMode::Clamp => index.clamp(0, size - 1),
+                SpreadMode::Repeat => index.rem_euclid(size),
+                SpreadMode::Reflect => {
+                    let index = index.rem_euclid(2 * size);
+                    if index < size { index } else { 2 * size - 1 - index }
+                },
+            };
+            index as usize
+        };
+        self.pixels[tile(x, self.width) + tile(y, self.height) * self.width]
+    }
+}
+
+impl<ColorType: Color> Coloring for ImageFill<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.sample_color_dithered(point, 0.)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        // where the point falls on the image, with each pixel centered on whole numbers like the canvas's are
+        let image_point = (*point - self.offset) / self.scale;
+
+        match self.filter {
+            ImageFilter::Nearest => self.get_tiled_pixel(image_point.x.round() as isize, image_point.y.round() as isize),
+            ImageFilter::Bilinear => {
+                let (left, top) = (image_point.x.floor(), image_point.y.floor());
+                let (right_portion, bottom_portion) = (image_point.x - left, image_point.y - top);
+                let (left, top) = (left as isize, top as isize);
+
+                Self::ColorType::mix_in_dithered(Interpolation::Srgb, &[
+                    (self.get_tiled_pixel(left, top), (1. - right_portion) * (1. - bottom_portion)),
+                    (self.get_tiled_pixel(left + 1, top), right_portion * (1. - bottom_portion)),
+                    (self.get_tiled_pixel(left, top + 1), (1. - right_portion) * bottom_portion),
+                    (self.get_tiled_pixel(left + 1, top + 1), right_portion * bottom_portion),
+                ], threshold)
+            },
+        }
+    }
+}
+
+/// Colors each point by a noise field instead of by geometry, the basis of clouds, marble and terrain.
+/// The field's value is mapped through color stops like a `MultiStopGradient`'s, with offset 0 for the
+/// lowest values the field gives and 1 for the highest. Features of the noise are about `scale` pixels across.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseFill<ColorType: Color> {
+    field: NoiseField,
+    scale: f64,
+    stops: Vec<(f64, ColorType)>,
+    interpolation: Interpolation,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for NoiseFill<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::NoiseFill(self)
+    }
+}
+
+impl<ColorType: Color> NoiseFill<ColorType> {
+    /// Stops can be given in any order, as with `MultiStopGradient::new`
+    pub fn new(field: impl Into<NoiseField>, scale: f64, mut stops: Vec<(f64, ColorType)>) -> NoiseFill<ColorType> {
+        if scale <= 0. {
+            panic!("Noise scale must be positive");
+        }
+        if stops.is_empty() {
+            panic!("Noise fills need at least one stop");
+        }
+        stops.sort_by(|(offset1, _), (offset2, _)| offset1.total_cmp(offset2));
+
+        NoiseFill {
+            field: field.into(),
+            scale,
+            stops,
+            interpolation: Interpolation::Srgb,
+        }
+    }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        NoiseFill { interpolation, ..self }
+    }
+
+    pub fn stops(&self) -> &[(f64, ColorType)] {
+        &self.stops
+    }
+}
+
+impl<ColorType: Color> Coloring for NoiseFill<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.sample_color_dithered(point, 0.)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        let value = self.field.value(point.x / self.scale, point.y / self.scale);
+        sample_stops(&self.stops, (value + 1.) / 2., self.interpolation, threshold)
+    }
+}
+
+/// Snaps every color another coloring gives to the closest one in `palette`, for the look of
+/// 8 and 16 color hardware
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palettized<ColorType: Color> {
+    inner: Box<ColorScheme<ColorType>>,
+    palette: Vec<ColorType>,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for Palettized<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::Palettized(self)
+    }
+}
+
+impl<ColorType: Color> Palettized<ColorType> {
+    pub fn new(inner: impl Into<ColorScheme<ColorType>>, palette: Vec<ColorType>) -> Palettized<ColorType> {
+        if palette.is_empty() {
+            panic!("Palettes need at least one color");
+        }
+
+        Palettized {
+            inner: Box::new(inner.into()),
+            palette,
+        }
+    }
+
+    /// Uses the colors of a `Palette`, such as one picked from an image with `Palette::from_image`
+    pub fn from_palette(inner: impl Into<ColorScheme<ColorType>>, palette: &Palette) -> Palettized<ColorType>
+    where SolidColor: Into<ColorType> {
+        Palettized::new(inner, palette.colors().iter().map(|color| (*color).into()).collect())
+    }
+
+    pub fn palette(&self) -> &[ColorType] {
+        &self.palette
+    }
+
+    fn closest(&self, color: ColorType) -> ColorType {
+        *self.palette.iter()
+            .min_by(|color1, color2| color1.distance(&color).total_cmp(&color2.distance(&color)))
+            .expect("Palettes always have a color")
+    }
+}
+
+impl<ColorType: Color> Coloring for Palettized<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.closest(self.inner.sample_color(point))
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        self.closest(self.inner.sample_color_dithered(point, threshold))
+    }
+}
+
+/// A number for every point, like a height, a distance or a measurement, for a `ColorRamp` to color
+#[derive(Clone)]
+pub struct ScalarField(Arc<dyn Fn(&Point) -> f64 + Send + Sync>);
+
+impl ScalarField {
+    pub fn new(field: impl Fn(&Point) -> f64 + Send + Sync + 'static) -> Self {
+        ScalarField(Arc::new(field))
+    }
+
+    pub fn value(&self, point: &Point) -> f64 {
+        (self.0)(point)
+    }
+}
+
+impl fmt::Debug for ScalarField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ScalarField(..)")
+    }
+}
+
+/// Closures can't be compared, so fields are only equal to copies of themselves
+impl PartialEq for ScalarField {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Colors each point by the value a scalar field has there, looked up in color stops. Unlike the
+/// gradients, the stops are at values of the field rather than places on the canvas, so a heightmap
+/// can go from blue at 0 through green at 10 to white at 100.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorRamp<ColorType: Color> {
+    field: ScalarField,
+    stops: Vec<(f64, ColorType)>,
+    interpolation: Interpolation,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for ColorRamp<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::ColorRamp(self)
+    }
+}
+
+impl<ColorType: Color> ColorRamp<ColorType> {
+    /// Stops are (value, color) pairs and can be given in any order. Values past the first and last stops saturate.
+    pub fn new(field: impl Fn(&Point) -> f64 + Send + Sync + 'static, mut stops: Vec<(f64, ColorType)>) -> ColorRamp<ColorType> {
+        if stops.is_empty() {
+            panic!("Color ramps need at least one stop");
+        }
+        stops.sort_by(|(value1, _), (value2, _)| value1.total_cmp(value2));
+
+        ColorRamp {
+            field: ScalarField::new(field),
+            stops,
+            interpolation: Interpolation::Srgb,
+        }
+    }
+
+    /// Colors by signed distance to `shape`: negative inside, 0 on the edge and positive outside, in pixels
+    pub fn from_distance(shape: Shape, stops: Vec<(f64, ColorType)>) -> ColorRamp<ColorType> {
+        ColorRamp::new(move |point| shape.signed_distance(point), stops)
+    }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        ColorRamp { interpolation, ..self }
+    }
+
+    pub fn stops(&self) -> &[(f64, ColorType)] {
+        &self.stops
+    }
+
+    /// The color for `value`, without looking at the field
+    pub fn color_at(&self, value: f64) -> ColorType {
+        sample_stops(&self.stops, value, self.interpolation, 0.)
+    }
+}
+
+impl<ColorType: Color> Coloring for ColorRamp<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.color_at(self.field.value(point))
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        sample_stops(&self.stops, self.field.value(point), self.interpolation, threshold)
+    }
+}
+
+/// How quickly a glow fades with distance from its shape
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum GlowFalloff {
+    /// A soft bell curve, with the radius at about 60% strength
+    #[default]
+    Gaussian,
+    /// Drops quickly near the shape but leaves a long faint haze, with the radius at about 37% strength
+    Exponential,
+    /// How light spreads from a real source, `1 / (1 + (distance / radius)²)`, with the radius at half strength
+    InverseSquare,
+}
+
+/// Glows around the edge of a shape, fading from `color` on the boundary to `background` farther away.
+/// A bright color over a background of the same color with no alpha gives the look of a neon sign.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SdfGlow<ColorType: Color> {
+    shape: Shape,
+    color: ColorType,
+    background: ColorType,
+    radius: f64,
+    falloff: GlowFalloff,
+    filled: bool,
+    interpolation: Interpolation,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for SdfGlow<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::SdfGlow(self)
+    }
+}
+
+impl<ColorType: Color> SdfGlow<ColorType> {
+    /// `radius` is in pixels and sets how far the glow reaches
+    pub fn new(shape: Shape, color: ColorType, background: ColorType, radius: f64) -> SdfGlow<ColorType> {
+        if radius <= 0. {
+            panic!("Glow radius must be positive");
+        }
+
+        SdfGlow {
+            shape,
+            color,
+            background,
+            radius,
+            falloff: GlowFalloff::Gaussian,
+            filled: false,
+            interpolation: Interpolation::Srgb,
+        }
+    }
+
+    pub fn with_falloff(self, falloff: GlowFalloff) -> Self {
+        SdfGlow { falloff, ..self }
+    }
+
+    /// By default the glow fades both ways from the edge, like a neon tube. Filled glows are full
+    /// strength all the way through the inside instead.
+    pub fn with_filled(self, filled: bool) -> Self {
+        SdfGlow { filled, ..self }
+    }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        SdfGlow { interpolation, ..self }
+    }
+
+    /// How strong the glow is at `point`, from 0 to 1
+    pub fn intensity(&self, point: &Point) -> f64 {
+        let signed_distance = self.shape.signed_distance(point);
+        let distance = if self.filled { signed_distance.max(0.) } else { signed_distance.abs() } / self.radius;
+
+        match self.falloff {
+            GlowFalloff::Gaussian => (-distance * distance / 2.).exp(),
+            GlowFalloff::Exponential => (-distance).exp(),
+            GlowFalloff::InverseSquare => 1. / (1. + distance * distance),
+        }
+    }
+}
+
+impl<ColorType: Color> Coloring for SdfGlow<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.sample_color_dithered(point, 0.)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        let intensity = self.intensity(point);
+        ColorType::mix_in_dithered(self.interpolation, &[(self.color, intensity), (self.background, 1. - intensity)], threshold)
+    }
+}
+
+/// Recolors another coloring by how light it is, going from the shadow color where it's black to the
+/// highlight color where it's white, for the look of a poster printed in two or three inks.
+/// Only the tones show through, so the inner coloring's own hues and transparency are lost.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Duotone<ColorType: Color> {
+    inner: Box<ColorScheme<ColorType>>,
+    // (lightness, color), from shadows to highlights
+    tones: Vec<(f64, ColorType)>,
+    interpolation: Interpolation,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for Duotone<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::Duotone(self)
+    }
+}
+
+impl<ColorType: Color> Duotone<ColorType> {
+    pub fn new(inner: impl Into<ColorScheme<ColorType>>, shadows: ColorType, highlights: ColorType) -> Duotone<ColorType> {
+        Duotone {
+            inner: Box::new(inner.into()),
+            tones: vec![(0., shadows), (1., highlights)],
+            interpolation: Interpolation::Srgb,
+        }
+    }
+
+    /// Like `new`, with a third color for the middle tones
+    pub fn tritone(inner: impl Into<ColorScheme<ColorType>>, shadows: ColorType, midtones: ColorType, highlights: ColorType) -> Duotone<ColorType> {
+        Duotone {
+            inner: Box::new(inner.into()),
+            tones: vec![(0., shadows), (0.5, midtones), (1., highlights)],
+            interpolation: Interpolation::Srgb,
+        }
+    }
+
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        Duotone { interpolation, ..self }
+    }
+}
+
+impl<ColorType: Color> Coloring for Duotone<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.sample_color_dithered(point, 0.)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        sample_stops(&self.tones, self.inner.sample_color(point).perceived_lightness(), self.interpolation, threshold)
+    }
+}
+
+/// Cuts another coloring down to `levels` values per channel, so smooth gradients turn into bands
+#[derive(Clone, Debug, PartialEq)]
+pub struct Posterize<ColorType: Color> {
+    inner: Box<ColorScheme<ColorType>>,
+    levels: usize,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for Posterize<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::Posterize(self)
+    }
+}
+
+impl<ColorType: Color> Posterize<ColorType> {
+    pub fn new(inner: impl Into<ColorScheme<ColorType>>, levels: usize) -> Posterize<ColorType> {
+        if levels < 2 {
+            panic!("Posterizing needs at least 2 levels");
+        }
+
+        Posterize {
+            inner: Box::new(inner.into()),
+            levels,
+        }
+    }
+
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
+}
+
+impl<ColorType: Color> Coloring for Posterize<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.inner.sample_color(point).posterize(self.levels)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        self.inner.sample_color_dithered(point, threshold).posterize(self.levels)
+    }
+}
+
+/// Applies `adjustment` to red, green and blue channels from 0 to 1. The results aren't clamped.
+fn adjust_channels(adjustment: &Adjustment, [red, green, blue]: [f64; 3]) -> [f64; 3] {
+    let warmth = adjustment.temperature.clamp(-1., 1.) * 0.2;
+    let (red, blue) = (red * (1. + warmth), blue * (1. - warmth));
+
+    // saturation pulls toward or pushes away from the gray with the same luma
+    let gray = 0.2126 * red + 0.7152 * green + 0.0722 * blue;
+    [red, green, blue].map(|channel| {
+        let saturated = gray + (channel - gray) * adjustment.saturation.max(0.);
+        let contrasted = (saturated - 0.5) * adjustment.contrast.max(0.) + 0.5;
+        contrasted + adjustment.brightness
+    })
+}
+
+/// Brightness, contrast, saturation and color temperature changes, all of which leave colors
+/// alone at their `NONE` values
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Adjustment {
+    /// Added to every channel, from -1 for black to 1 for white
+    pub brightness: f64,
+    /// Scales how far channels are from the middle. Below 1 flattens, above 1 punches up.
+    pub contrast: f64,
+    /// Scales how far colors are from gray. 0 is grayscale and above 1 is more vivid.
+    pub saturation: f64,
+    /// From -1 for cooler and bluer to 1 for warmer and more orange
+    pub temperature: f64,
+}
+
+impl Adjustment {
+    pub const NONE: Adjustment = Adjustment {
+        brightness: 0.,
+        contrast: 1.,
+        saturation: 1.,
+        temperature: 0.,
+    };
+}
+
+impl Default for Adjustment {
+    fn default() -> Self {
+        Adjustment::NONE
+    }
+}
+
+/// Adjusts the colors of another coloring, so one gradient can be reused warmer, cooler, brighter
+/// or more muted across a series of images
+#[derive(Clone, Debug, PartialEq)]
+pub struct Adjust<ColorType: Color> {
+    inner: Box<ColorScheme<ColorType>>,
+    adjustment: Adjustment,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for Adjust<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::Adjust(self)
+    }
+}
+
+impl<ColorType: Color> Adjust<ColorType> {
+    pub fn new(inner: impl Into<ColorScheme<ColorType>>, adjustment: Adjustment) -> Adjust<ColorType> {
+        Adjust {
+            inner: Box::new(inner.into()),
+            adjustment,
+        }
+    }
+
+    pub fn with_brightness(self, brightness: f64) -> Self {
+        Adjust { adjustment: Adjustment { brightness, ..self.adjustment }, ..self }
+    }
+
+    pub fn with_contrast(self, contrast: f64) -> Self {
+        Adjust { adjustment: Adjustment { contrast, ..self.adjustment }, ..self }
+    }
+
+    pub fn with_saturation(self, saturation: f64) -> Self {
+        Adjust { adjustment: Adjustment { saturation, ..self.adjustment }, ..self }
+    }
+
+    pub fn with_temperature(self, temperature: f64) -> Self {
+        Adjust { adjustment: Adjustment { temperature, ..self.adjustment }, ..self }
+    }
+
+    pub fn adjustment(&self) -> Adjustment {
+        self.adjustment
+    }
+}
+
+impl<ColorType: Color> Coloring for Adjust<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.inner.sample_color(point).adjust(&self.adjustment)
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        self.inner.sample_color_dithered(point, threshold).adjust(&self.adjustment)
     }
 }
 
+/// Samples another coloring at points pushed around by a noise field, so straight gradients and stripes
+/// swirl into marble, smoke or flowing shapes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warp<ColorType: Color> {
+    inner: Box<ColorScheme<ColorType>>,
+    field: NoiseField,
+    scale: f64,
+    strength: f64,
+    depth: usize,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for Warp<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::Warp(self)
+    }
+}
+
+impl<ColorType: Color> Warp<ColorType> {
+    /// Features of the noise are about `scale` pixels across, and points get pushed up to `strength` pixels
+    pub fn new(inner: impl Into<ColorScheme<ColorType>>, field: impl Into<NoiseField>, scale: f64, strength: f64) -> Warp<ColorType> {
+        if scale <= 0. {
+            panic!("Noise scale must be positive");
+        }
+
+        Warp {
+            inner: Box::new(inner.into()),
+            field: field.into(),
+            scale,
+            strength,
+            depth: 1,
+        }
+    }
+
+    /// How many times the warp is fed back into itself, like `DomainWarp::with_depth`
+    pub fn with_depth(self, depth: usize) -> Self {
+        Warp { depth, ..self }
+    }
+
+    fn warp_point(&self, point: &Point) -> Point {
+        let mut offset = (0., 0.);
+        for _ in 0..self.depth {
+            offset = self.field.warp_offset(
+                (point.x + self.strength * offset.0) / self.scale,
+                (point.y + self.strength * offset.1) / self.scale,
+            );
+        }
+
+        Point {
+            x: point.x + self.strength * offset.0,
+            y: point.y + self.strength * offset.1,
+        }
+    }
+}
+
+impl<ColorType: Color> Coloring for Warp<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.inner.sample_color(&self.warp_point(point))
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        self.inner.sample_color_dithered(&self.warp_point(point), threshold)
+    }
+}
+
+/// Stacks colorings on top of a base one within a single coloring, each with its own opacity and
+/// optionally only inside a mask shape, so one draw instruction can do the work of several full canvas passes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Composite<ColorType: Color> {
+    base: Box<ColorScheme<ColorType>>,
+    // bottom to top
+    layers: Vec<CompositeLayer<ColorType>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct CompositeLayer<ColorType: Color> {
+    coloring: ColorScheme<ColorType>,
+    opacity: f64,
+    mask: Option<Shape>,
+}
+
+impl<ColorType: Color> Into<ColorScheme<ColorType>> for Composite<ColorType> {
+    fn into(self) -> ColorScheme<ColorType> {
+        ColorScheme::Composite(self)
+    }
+}
+
+impl<ColorType: Color> Composite<ColorType> {
+    pub fn new(base: impl Into<ColorScheme<ColorType>>) -> Composite<ColorType> {
+        Composite {
+            base: Box::new(base.into()),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Adds a layer on top of the ones so far, at `opacity` from 0 to 1
+    pub fn with_layer(mut self, coloring: impl Into<ColorScheme<ColorType>>, opacity: f64) -> Self {
+        self.layers.push(CompositeLayer {
+            coloring: coloring.into(),
+            opacity,
+            mask: None,
+        });
+        self
+    }
+
+    /// Like `with_layer`, but the layer only shows inside `mask`
+    pub fn with_masked_layer(mut self, coloring: impl Into<ColorScheme<ColorType>>, opacity: f64, mask: impl Into<Shape>) -> Self {
+        self.layers.push(CompositeLayer {
+            coloring: coloring.into(),
+            opacity,
+            mask: Some(mask.into()),
+        });
+        self
+    }
+}
+
+impl<ColorType: Color> Coloring for Composite<ColorType> {
+    type ColorType = ColorType;
+
+    fn sample_color(&self, point: &Point) -> Self::ColorType {
+        self.layers.iter()
+            .filter(|layer| layer.opacity > 0. && layer.mask.as_ref().is_none_or(|mask| mask.contains(point)))
+            .fold(self.base.sample_color(point), |color, layer| layer.coloring.sample_color(point).layer_over(&color, layer.opacity))
+    }
+
+    fn sample_color_dithered(&self, point: &Point, threshold: f64) -> Self::ColorType {
+        self.layers.iter()
+            .filter(|layer| layer.opacity > 0. && layer.mask.as_ref().is_none_or(|mask| mask.contains(point)))
+            .fold(self.base.sample_color_dithered(point, threshold), |color, layer| {
+                layer.coloring.sample_color_dithered(point, threshold).layer_over(&color, layer.opacity)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dithered_mixing_rounds_up_within_threshold_of_the_next_value() {
+        let mix = |weight: f64, threshold: f64| {
+            let (low, high) = (TransparentColor { red: 10, green: 10, blue: 10, alpha: 10 }, TransparentColor { red: 11, green: 11, blue: 11, alpha: 11 });
+            TransparentColor::mix_in_dithered(Interpolation::Srgb, &[(low, 1. - weight), (high, weight)], threshold).red
+        };
+
+        assert_eq!([mix(0.3, 0.), mix(0.3, 0.5), mix(0.3, 0.75), mix(0.3, 0.999)], [10, 10, 11, 11]);
+        assert_eq!([mix(0.6, 0.), mix(0.6, 0.5), mix(0.6, 0.999)], [10, 11, 11]);
+        assert_eq!([mix(0., 0.999), mix(1., 0.999)], [10, 11]);
+    }
+
+    fn gray(value: u8) -> SolidColor {
+        SolidColor { red: value, green: value, blue: value }
+    }
+
+    /// A gradient that's 30% of the way from 10 to 11 at (3, 0)
+    fn shallow_gradient() -> ColorScheme<SolidColor> {
+        LinearGradient::with_poles((Point { x: 0., y: 0. }, gray(10)), (Point { x: 10., y: 0. }, gray(11))).into()
+    }
+
+    /// Checks that `coloring` passes the threshold on to the gradient it wraps
+    fn assert_dithers(coloring: impl Into<ColorScheme<SolidColor>>) {
+        let coloring = coloring.into();
+        let point = Point { x: 3., y: 0. };
+        assert_eq!(
+            (coloring.sample_color_dithered(&point, 0.), coloring.sample_color_dithered(&point, 0.999)),
+            (gray(10), gray(11)),
+            "{coloring:?}",
+        );
+    }
+
+    #[test]
+    fn wrappers_dither_what_they_wrap() {
+        assert_dithers(shallow_gradient());
+        assert_dithers(Palettized::new(shallow_gradient(), vec![gray(10), gray(11)]));
+        assert_dithers(Posterize::new(shallow_gradient(), 256));
+        assert_dithers(Composite::new(shallow_gradient()).with_layer(shallow_gradient(), 1.));
+        assert_dithers(Adjust::new(shallow_gradient(), Adjustment::default()));
+    }
+
+    #[test]
+    fn dot_edges_are_dithered() {
+        // 30% of the way into the dot's pixel wide edge
+        let dots = Dots::new(100., 10., gray(11), gray(10));
+        let point = Point { x: 10.2, y: 0. };
+        assert_eq!((dots.sample_color_dithered(&point, 0.), dots.sample_color_dithered(&point, 0.999)), (gray(10), gray(11)));
+    }
+
+    #[test]
+    fn bilinear_image_fills_are_dithered() {
+        let mut image = Image::with_size(2, 1, gray(10));
+        *image.get_pixel_mut(1, 0) = gray(11);
+        let fill: ImageFill<SolidColor> = ImageFill::from_image(&image);
+        let point = Point { x: 0.3, y: 0. };
+        assert_eq!((fill.sample_color_dithered(&point, 0.), fill.sample_color_dithered(&point, 0.999)), (gray(10), gray(11)));
+    }
+}
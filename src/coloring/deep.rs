@@ -0,0 +1,220 @@
+use super::{adjust_channels, Adjustment, Color, SolidColor, TransparentColor};
+
+/// An opaque color with 16 bits per channel, for gradients too smooth to fit in 8 bits without banding
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SolidColor16 {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+}
+
+/// `TransparentColor` with 16 bits per channel. Like `SolidColor16`, it always mixes in sRGB.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TransparentColor16 {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub alpha: u16,
+}
+
+/// Spreads an 8 bit channel over the 16 bit range, so 255 becomes 65535
+fn widen(channel: u8) -> u16 {
+    channel as u16 * 257
+}
+
+fn narrow(channel: u16) -> u8 {
+    ((channel as u32 + 128) / 257) as u8
+}
+
+fn to_channel16(channel: f64) -> u16 {
+    (channel * u16::MAX as f64).round().clamp(0., u16::MAX as f64) as u16
+}
+
+impl SolidColor16 {
+    pub const BLACK: SolidColor16 = SolidColor16 { red: 0, green: 0, blue: 0 };
+}
+
+impl TransparentColor16 {
+    pub const TRANSPARENT: TransparentColor16 = TransparentColor16 { red: 0, green: 0, blue: 0, alpha: 0 };
+
+    pub fn as_solid(&self) -> SolidColor16 {
+        SolidColor16 {
+            red: self.red,
+            green: self.green,
+            blue: self.blue,
+        }
+    }
+
+    pub fn draw_on_solid(&self, base_color: &SolidColor16) -> SolidColor16 {
+        let find_new_color = |color1: u16, color2: u16| -> u16 {
+            let (color1, color2, alpha2) = (color1 as u64, color2 as u64, self.alpha as u64);
+            let max = u16::MAX as u64;
+
+            ((color1 * (max - alpha2) + color2 * alpha2) / max) as u16
+        };
+
+        SolidColor16 {
+            red: find_new_color(base_color.red, self.red),
+            green: find_new_color(base_color.green, self.green),
+            blue: find_new_color(base_color.blue, self.blue),
+        }
+    }
+}
+
+impl Into<image::Rgb<u16>> for SolidColor16 {
+    fn into(self) -> image::Rgb<u16> {
+        image::Rgb::from([self.red, self.green, self.blue])
+    }
+}
+
+impl Into<image::Rgba<u16>> for TransparentColor16 {
+    fn into(self) -> image::Rgba<u16> {
+        image::Rgba::from([self.red, self.green, self.blue, self.alpha])
+    }
+}
+
+impl Into<TransparentColor16> for SolidColor16 {
+    fn into(self) -> TransparentColor16 {
+        TransparentColor16 {
+            red: self.red,
+            green: self.green,
+            blue: self.blue,
+            alpha: u16::MAX,
+        }
+    }
+}
+
+impl TryInto<SolidColor16> for TransparentColor16 {
+    type Error = ();
+    fn try_into(self) -> Result<SolidColor16, Self::Error> {
+        if self.alpha == u16::MAX {
+            Ok(self.as_solid())
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl Into<SolidColor16> for SolidColor {
+    fn into(self) -> SolidColor16 {
+        SolidColor16 {
+            red: widen(self.red),
+            green: widen(self.green),
+            blue: widen(self.blue),
+        }
+    }
+}
+
+impl Into<TransparentColor16> for TransparentColor {
+    fn into(self) -> TransparentColor16 {
+        TransparentColor16 {
+            red: widen(self.red),
+            green: widen(self.green),
+            blue: widen(self.blue),
+            alpha: widen(self.alpha),
+        }
+    }
+}
+
+impl Into<TransparentColor16> for SolidColor {
+    fn into(self) -> TransparentColor16 {
+        Into::<TransparentColor>::into(self).into()
+    }
+}
+
+/// Rounds to the closest 8 bit color
+impl Into<SolidColor> for SolidColor16 {
+    fn into(self) -> SolidColor {
+        SolidColor {
+            red: narrow(self.red),
+            green: narrow(self.green),
+            blue: narrow(self.blue),
+        }
+    }
+}
+
+/// Rounds to the closest 8 bit color
+impl Into<TransparentColor> for TransparentColor16 {
+    fn into(self) -> TransparentColor {
+        TransparentColor {
+            red: narrow(self.red),
+            green: narrow(self.green),
+            blue: narrow(self.blue),
+            alpha: narrow(self.alpha),
+        }
+    }
+}
+
+impl Color for TransparentColor16 {
+    fn mix(color_weights: &[(Self, f64)]) -> Self {
+        let mix_channel = |channel: fn(&TransparentColor16) -> u16| to_channel16(
+            color_weights.iter().map(|(color, weight)| channel(color) as f64 * weight).sum::<f64>() / u16::MAX as f64
+        );
+
+        TransparentColor16 {
+            red: mix_channel(|color| color.red),
+            green: mix_channel(|color| color.green),
+            blue: mix_channel(|color| color.blue),
+            alpha: mix_channel(|color| color.alpha),
+        }
+    }
+
+    /// The same as the distance between the closest 8 bit colors, so they can be compared
+    fn distance(&self, other: &Self) -> f64 {
+        Into::<TransparentColor>::into(*self).distance(&(*other).into())
+    }
+
+    fn perceived_lightness(&self) -> f64 {
+        Into::<TransparentColor>::into(*self).perceived_lightness()
+    }
+
+    /// Leaves alpha alone
+    fn posterize(&self, levels: usize) -> Self {
+        let step = u16::MAX as f64 / (levels.max(2) - 1) as f64;
+        let posterize_channel = |channel: u16| ((channel as f64 / step).round() * step).round() as u16;
+
+        TransparentColor16 {
+            red: posterize_channel(self.red),
+            green: posterize_channel(self.green),
+            blue: posterize_channel(self.blue),
+            alpha: self.alpha,
+        }
+    }
+
+    /// Leaves alpha alone
+    fn adjust(&self, adjustment: &Adjustment) -> Self {
+        let [red, green, blue] = adjust_channels(adjustment, [self.red, self.green, self.blue].map(|channel| channel as f64 / u16::MAX as f64));
+
+        TransparentColor16 {
+            red: to_channel16(red),
+            green: to_channel16(green),
+            blue: to_channel16(blue),
+            alpha: self.alpha,
+        }
+    }
+}
+
+impl Color for SolidColor16 {
+    fn mix(color_weights: &[(Self, f64)]) -> Self {
+        let transparent_weights: Vec<(TransparentColor16, f64)> = color_weights.iter().map(|(solid_color, weight)|
+            ((*solid_color).into(), *weight)
+        ).collect();
+        TransparentColor16::mix(&transparent_weights).as_solid()
+    }
+
+    fn distance(&self, other: &Self) -> f64 {
+        Into::<TransparentColor16>::into(*self).distance(&(*other).into())
+    }
+
+    fn perceived_lightness(&self) -> f64 {
+        Into::<TransparentColor16>::into(*self).perceived_lightness()
+    }
+
+    fn posterize(&self, levels: usize) -> Self {
+        Into::<TransparentColor16>::into(*self).posterize(levels).as_solid()
+    }
+
+    fn adjust(&self, adjustment: &Adjustment) -> Self {
+        Into::<TransparentColor16>::into(*self).adjust(adjustment).as_solid()
+    }
+}
@@ -0,0 +1,192 @@
+use super::{adjust_channels, to_linear, Adjustment, Color, OklabColor, SolidColor, TransparentColor};
+
+/// A color in linear light with floating point channels, where 1 is the brightest an 8 bit color can be
+/// but nothing stops channels going past it. Mixing and compositing these doesn't clip, so glows can
+/// pile up and be tone mapped back down at the end. Always mixes in linear light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FColor {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    /// From 0 to 1
+    pub alpha: f32,
+}
+
+/// How to squeeze the brightness of `FColor`s down to what an 8 bit image can show
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ToneMap {
+    /// Cuts off everything past 1, so bright areas flatten into white
+    Clamp,
+    /// `x / (1 + x)`, which never quite reaches white
+    #[default]
+    Reinhard,
+    /// The filmic curve ACES approximates, with more contrast than `Reinhard`
+    Aces,
+}
+
+impl ToneMap {
+    fn map_channel(&self, channel: f64) -> f64 {
+        let channel = channel.max(0.);
+        match self {
+            ToneMap::Clamp => channel.min(1.),
+            ToneMap::Reinhard => channel / (1. + channel),
+            ToneMap::Aces => ((channel * (2.51 * channel + 0.03)) / (channel * (2.43 * channel + 0.59) + 0.14)).clamp(0., 1.),
+        }
+    }
+}
+
+/// Like `from_linear`, but without clamping or rounding, so brighter than white stays brighter than white
+fn encode(channel: f64) -> f64 {
+    if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// The inverse of `encode`
+fn decode(channel: f64) -> f64 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl FColor {
+    pub const TRANSPARENT: FColor = FColor { red: 0., green: 0., blue: 0., alpha: 0. };
+
+    /// The color `intensity` times as bright, such as 4 for a highlight that should still glow after tone mapping
+    pub fn scaled(&self, intensity: f32) -> FColor {
+        FColor {
+            red: self.red * intensity,
+            green: self.green * intensity,
+            blue: self.blue * intensity,
+            alpha: self.alpha,
+        }
+    }
+
+    /// Lays the color over `base_color` by its alpha, without clipping either
+    pub fn draw_on(&self, base_color: &FColor) -> FColor {
+        let new_alpha = self.alpha + base_color.alpha * (1. - self.alpha);
+        if new_alpha == 0. {
+            return FColor::TRANSPARENT;
+        }
+        let find_new_color = |color1: f32, color2: f32| (color2 * self.alpha + color1 * base_color.alpha * (1. - self.alpha)) / new_alpha;
+
+        FColor {
+            red: find_new_color(base_color.red, self.red),
+            green: find_new_color(base_color.green, self.green),
+            blue: find_new_color(base_color.blue, self.blue),
+            alpha: new_alpha,
+        }
+    }
+
+    /// Adds the light of the color, weighted by its alpha, onto `base_color`, like a glow or bloom
+    pub fn add_to(&self, base_color: &FColor) -> FColor {
+        FColor {
+            red: base_color.red + self.red * self.alpha,
+            green: base_color.green + self.green * self.alpha,
+            blue: base_color.blue + self.blue * self.alpha,
+            alpha: base_color.alpha,
+        }
+    }
+
+    /// Brings the color down to 8 bits after multiplying its brightness by `exposure`
+    pub fn tone_map(&self, tone_map: ToneMap, exposure: f64) -> TransparentColor {
+        let to_channel = |channel: f32| {
+            let mapped = tone_map.map_channel(channel as f64 * exposure);
+            (encode(mapped).clamp(0., 1.) * u8::MAX as f64).round() as u8
+        };
+
+        TransparentColor {
+            red: to_channel(self.red),
+            green: to_channel(self.green),
+            blue: to_channel(self.blue),
+            alpha: (self.alpha.clamp(0., 1.) * u8::MAX as f32).round() as u8,
+        }
+    }
+}
+
+impl Into<FColor> for TransparentColor {
+    fn into(self) -> FColor {
+        FColor {
+            red: to_linear(self.red) as f32,
+            green: to_linear(self.green) as f32,
+            blue: to_linear(self.blue) as f32,
+            alpha: self.alpha as f32 / u8::MAX as f32,
+        }
+    }
+}
+
+impl Into<FColor> for SolidColor {
+    fn into(self) -> FColor {
+        Into::<TransparentColor>::into(self).into()
+    }
+}
+
+/// Clamps instead of tone mapping
+impl Into<TransparentColor> for FColor {
+    fn into(self) -> TransparentColor {
+        self.tone_map(ToneMap::Clamp, 1.)
+    }
+}
+
+impl Color for FColor {
+    fn mix(color_weights: &[(Self, f64)]) -> Self {
+        let mix_channel = |channel: fn(&FColor) -> f32| color_weights.iter()
+            .map(|(color, weight)| channel(color) as f64 * weight)
+            .sum::<f64>() as f32;
+
+        FColor {
+            red: mix_channel(|color| color.red),
+            green: mix_channel(|color| color.green),
+            blue: mix_channel(|color| color.blue),
+            alpha: mix_channel(|color| color.alpha).clamp(0., 1.),
+        }
+    }
+
+    /// Euclidean distance between the channels, so very bright colors can be far apart even if they'd
+    /// both clamp to white
+    fn distance(&self, other: &Self) -> f64 {
+        let differences = [
+            self.red - other.red,
+            self.green - other.green,
+            self.blue - other.blue,
+            self.alpha - other.alpha,
+        ];
+        differences.iter().map(|difference| (*difference as f64).powi(2)).sum::<f64>().sqrt()
+    }
+
+    /// Clamped to 1 for colors brighter than white
+    fn perceived_lightness(&self) -> f64 {
+        let [red, green, blue] = [self.red, self.green, self.blue].map(|channel| channel.max(0.) as f64);
+        OklabColor::from_linear_rgb(red, green, blue, u8::MAX).lightness.min(1.)
+    }
+
+    /// Posterizes the sRGB encoded channels, so the levels look evenly spaced. Channels past 1 are clamped.
+    fn posterize(&self, levels: usize) -> Self {
+        let step = 1. / (levels.max(2) - 1) as f64;
+        let posterize_channel = |channel: f32| decode((encode(channel.clamp(0., 1.) as f64) / step).round() * step) as f32;
+
+        FColor {
+            red: posterize_channel(self.red),
+            green: posterize_channel(self.green),
+            blue: posterize_channel(self.blue),
+            alpha: self.alpha,
+        }
+    }
+
+    /// Adjusts the sRGB encoded channels, like the 8 bit colors do, without clamping
+    fn adjust(&self, adjustment: &Adjustment) -> Self {
+        let [red, green, blue] = adjust_channels(adjustment, [self.red, self.green, self.blue].map(|channel| encode(channel as f64)))
+            .map(|channel| decode(channel.max(0.)) as f32);
+
+        FColor {
+            red,
+            green,
+            blue,
+            alpha: self.alpha,
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use super::{Adjustment, Color, SolidColor, TransparentColor};
+
+/// A color as hue, saturation and lightness. Hue is in degrees from 0 (red) through 120 (green)
+/// and 240 (blue), saturation and lightness go from 0 to 1, and lightness 0.5 is the purest color.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HslColor {
+    pub hue: f64,
+    pub saturation: f64,
+    pub lightness: f64,
+    pub alpha: u8,
+}
+
+/// A color as hue, saturation and value. Hue is the same as in `HslColor`, and value 1 with
+/// saturation 1 is the purest color.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HsvColor {
+    pub hue: f64,
+    pub saturation: f64,
+    pub value: f64,
+    pub alpha: u8,
+}
+
+impl HslColor {
+    /// The same color with its hue turned by `degrees`, such as 180 for the complementary color
+    pub fn rotate_hue(&self, degrees: f64) -> Self {
+        HslColor {
+            hue: normalize_hue(self.hue + degrees),
+            ..*self
+        }
+    }
+}
+
+impl HsvColor {
+    /// The same color with its hue turned by `degrees`, such as 180 for the complementary color
+    pub fn rotate_hue(&self, degrees: f64) -> Self {
+        HsvColor {
+            hue: normalize_hue(self.hue + degrees),
+            ..*self
+        }
+    }
+}
+
+/// Hue in degrees, the largest channel and the smallest channel, with channels from 0 to 1
+fn hue_and_range(color: &TransparentColor) -> (f64, f64, f64) {
+    let (red, green, blue) = (color.red as f64 / 255., color.green as f64 / 255., color.blue as f64 / 255.);
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let chroma = max - min;
+
+    let hue = if chroma == 0. {
+        0.
+    } else if max == red {
+        60. * ((green - blue) / chroma).rem_euclid(6.)
+    } else if max == green {
+        60. * ((blue - red) / chroma + 2.)
+    } else {
+        60. * ((red - green) / chroma + 4.)
+    };
+    (hue, max, min)
+}
+
+/// The RGB color with `hue` and `chroma`, shifted up so its smallest channel is `min`
+fn from_hue_and_chroma(hue: f64, chroma: f64, min: f64, alpha: u8) -> TransparentColor {
+    let hue_section = hue.rem_euclid(360.) / 60.;
+    let second_largest = chroma * (1. - (hue_section.rem_euclid(2.) - 1.).abs());
+    let (red, green, blue) = match hue_section as usize {
+        0 => (chroma, second_largest, 0.),
+        1 => (second_largest, chroma, 0.),
+        2 => (0., chroma, second_largest),
+        3 => (0., second_largest, chroma),
+        4 => (second_largest, 0., chroma),
+        _ => (chroma, 0., second_largest),
+    };
+
+    let to_channel = |channel: f64| ((channel + min) * 255.).round().clamp(0., 255.) as u8;
+    TransparentColor {
+        red: to_channel(red),
+        green: to_channel(green),
+        blue: to_channel(blue),
+        alpha,
+    }
+}
+
+impl Into<HslColor> for TransparentColor {
+    fn into(self) -> HslColor {
+        let (hue, max, min) = hue_and_range(&self);
+        let lightness = (max + min) / 2.;
+        let saturation = if max == min {
+            0.
+        } else {
+            (max - min) / (1. - (2. * lightness - 1.).abs())
+        };
+
+        HslColor {
+            hue,
+            saturation,
+            lightness,
+            alpha: self.alpha,
+        }
+    }
+}
+
+impl Into<HsvColor> for TransparentColor {
+    fn into(self) -> HsvColor {
+        let (hue, max, min) = hue_and_range(&self);
+        HsvColor {
+            hue,
+            saturation: if max == 0. { 0. } else { (max - min) / max },
+            value: max,
+            alpha: self.alpha,
+        }
+    }
+}
+
+impl Into<HslColor> for SolidColor {
+    fn into(self) -> HslColor {
+        Into::<TransparentColor>::into(self).into()
+    }
+}
+
+impl Into<HsvColor> for SolidColor {
+    fn into(self) -> HsvColor {
+        Into::<TransparentColor>::into(self).into()
+    }
+}
+
+impl Into<TransparentColor> for HslColor {
+    fn into(self) -> TransparentColor {
+        let saturation = self.saturation.clamp(0., 1.);
+        let lightness = self.lightness.clamp(0., 1.);
+        let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+        from_hue_and_chroma(self.hue, chroma, lightness - chroma / 2., self.alpha)
+    }
+}
+
+impl Into<TransparentColor> for HsvColor {
+    fn into(self) -> TransparentColor {
+        let saturation = self.saturation.clamp(0., 1.);
+        let value = self.value.clamp(0., 1.);
+        let chroma = value * saturation;
+        from_hue_and_chroma(self.hue, chroma, value - chroma, self.alpha)
+    }
+}
+
+impl TryInto<SolidColor> for HslColor {
+    type Error = ();
+    fn try_into(self) -> Result<SolidColor, Self::Error> {
+        Into::<TransparentColor>::into(self).try_into()
+    }
+}
+
+impl TryInto<SolidColor> for HsvColor {
+    type Error = ();
+    fn try_into(self) -> Result<SolidColor, Self::Error> {
+        Into::<TransparentColor>::into(self).try_into()
+    }
+}
+
+/// The weighted average of hues around the color wheel, so mixing 350 and 10 gives 0 rather than 180.
+/// Each hue also counts for as much as its saturation, since grays don't really have one.
+pub(super) fn mix_hues(hue_weights: impl Iterator<Item = (f64, f64)>) -> f64 {
+    let (x, y) = hue_weights.fold((0., 0.), |(x, y), (hue, weight)| {
+        let (sin, cos) = hue.to_radians().sin_cos();
+        (x + cos * weight, y + sin * weight)
+    });
+    normalize_hue(y.atan2(x).to_degrees())
+}
+
+/// Wraps `hue` into 0..360. `rem_euclid` alone can round tiny negative hues up to 360.
+pub(super) fn normalize_hue(hue: f64) -> f64 {
+    let hue = hue.rem_euclid(360.);
+    if hue >= 360. { 0. } else { hue }
+}
+
+pub(super) fn mix_alphas(alpha_weights: impl Iterator<Item = (u8, f64)>) -> u8 {
+    alpha_weights.map(|(alpha, weight)| alpha as f64 * weight).sum::<f64>().clamp(0., 255.) as u8
+}
+
+impl Color for HslColor {
+    fn mix(color_weights: &[(Self, f64)]) -> Self {
+        HslColor {
+            hue: mix_hues(color_weights.iter().map(|(color, weight)| (color.hue, weight * color.saturation))),
+            saturation: color_weights.iter().map(|(color, weight)| color.saturation * weight).sum::<f64>().clamp(0., 1.),
+            lightness: color_weights.iter().map(|(color, weight)| color.lightness * weight).sum::<f64>().clamp(0., 1.),
+            alpha: mix_alphas(color_weights.iter().map(|(color, weight)| (color.alpha, *weight))),
+        }
+    }
+
+    fn distance(&self, other: &Self) -> f64 {
+        Into::<TransparentColor>::into(*self).distance(&(*other).into())
+    }
+
+    fn perceived_lightness(&self) -> f64 {
+        Into::<TransparentColor>::into(*self).perceived_lightness()
+    }
+
+    fn posterize(&self, levels: usize) -> Self {
+        Into::<TransparentColor>::into(*self).posterize(levels).into()
+    }
+
+    fn adjust(&self, adjustment: &Adjustment) -> Self {
+        Into::<TransparentColor>::into(*self).adjust(adjustment).into()
+    }
+}
+
+impl Color for HsvColor {
+    fn mix(color_weights: &[(Self, f64)]) -> Self {
+        HsvColor {
+            hue: mix_hues(color_weights.iter().map(|(color, weight)| (color.hue, weight * color.saturation))),
+            saturation: color_weights.iter().map(|(color, weight)| color.saturation * weight).sum::<f64>().clamp(0., 1.),
+            value: color_weights.iter().map(|(color, weight)| color.value * weight).sum::<f64>().clamp(0., 1.),
+            alpha: mix_alphas(color_weights.iter().map(|(color, weight)| (color.alpha, *weight))),
+        }
+    }
+
+    fn distance(&self, other: &Self) -> f64 {
+        Into::<TransparentColor>::into(*self).distance(&(*other).into())
+    }
+
+    fn perceived_lightness(&self) -> f64 {
+        Into::<TransparentColor>::into(*self).perceived_lightness()
+    }
+
+    fn posterize(&self, levels: usize) -> Self {
+        Into::<TransparentColor>::into(*self).posterize(levels).into()
+    }
+
+    fn adjust(&self, adjustment: &Adjustment) -> Self {
+        Into::<TransparentColor>::into(*self).adjust(adjustment).into()
+    }
+}
@@ -0,0 +1,177 @@
+use super::hsl::{mix_alphas, mix_hues, normalize_hue};
+use super::{from_linear, to_linear, Adjustment, Color, SolidColor, TransparentColor};
+
+/// A color in Oklab, a color space where the same distance looks like the same amount of change
+/// everywhere. Lightness goes from 0 (black) to 1 (white), `a` runs from green to red and `b` from
+/// blue to yellow, both staying within about ±0.4 for colors that can be displayed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OklabColor {
+    pub lightness: f64,
+    pub a: f64,
+    pub b: f64,
+    pub alpha: u8,
+}
+
+/// Oklab in polar form: how colorful it is (`chroma`) and which way around the color wheel it
+/// points (`hue`, in degrees), like a perceptual version of `HslColor`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OklchColor {
+    pub lightness: f64,
+    pub chroma: f64,
+    pub hue: f64,
+    pub alpha: u8,
+}
+
+impl OklabColor {
+    /// The Oklab color of linear light channels, which may go past 1 for HDR colors
+    pub(super) fn from_linear_rgb(red: f64, green: f64, blue: f64, alpha: u8) -> OklabColor {
+        let long = (0.4122214708 * red + 0.5363325363 * green + 0.0514459929 * blue).cbrt();
+        let medium = (0.2119034982 * red + 0.6806995451 * green + 0.1073969566 * blue).cbrt();
+        let short = (0.0883024619 * red + 0.2817188376 * green + 0.6299787005 * blue).cbrt();
+
+        OklabColor {
+            lightness: 0.2104542553 * long + 0.7936177850 * medium - 0.0040720468 * short,
+            a: 1.9779984951 * long - 2.4285922050 * medium + 0.4505937099 * short,
+            b: 0.0259040371 * long + 0.7827717662 * medium - 0.8086757660 * short,
+            alpha,
+        }
+    }
+}
+
+impl Into<OklabColor> for TransparentColor {
+    fn into(self) -> OklabColor {
+        OklabColor::from_linear_rgb(to_linear(self.red), to_linear(self.green), to_linear(self.blue), self.alpha)
+    }
+}
+
+impl Into<TransparentColor> for OklabColor {
+    fn into(self) -> TransparentColor {
+        let long = (self.lightness + 0.3963377774 * self.a + 0.2158037573 * self.b).powi(3);
+        let medium = (self.lightness - 0.1055613458 * self.a - 0.0638541728 * self.b).powi(3);
+        let short = (self.lightness - 0.0894841775 * self.a - 1.2914855480 * self.b).powi(3);
+
+        TransparentColor {
+            red: from_linear(4.0767416621 * long - 3.3077115913 * medium + 0.2309699292 * short),
+            green: from_linear(-1.2684380046 * long + 2.6097574011 * medium - 0.3413193965 * short),
+            blue: from_linear(-0.0041960863 * long - 0.7034186147 * medium + 1.7076147010 * short),
+            alpha: self.alpha,
+        }
+    }
+}
+
+impl Into<OklchColor> for OklabColor {
+    fn into(self) -> OklchColor {
+        OklchColor {
+            lightness: self.lightness,
+            chroma: f64::hypot(self.a, self.b),
+            hue: normalize_hue(self.b.atan2(self.a).to_degrees()),
+            alpha: self.alpha,
+        }
+    }
+}
+
+impl Into<OklabColor> for OklchColor {
+    fn into(self) -> OklabColor {
+        let (sin, cos) = self.hue.to_radians().sin_cos();
+        OklabColor {
+            lightness: self.lightness,
+            a: self.chroma * cos,
+            b: self.chroma * sin,
+            alpha: self.alpha,
+        }
+    }
+}
+
+impl Into<OklchColor> for TransparentColor {
+    fn into(self) -> OklchColor {
+        Into::<OklabColor>::into(self).into()
+    }
+}
+
+impl Into<TransparentColor> for OklchColor {
+    fn into(self) -> TransparentColor {
+        Into::<OklabColor>::into(self).into()
+    }
+}
+
+impl Into<OklabColor> for SolidColor {
+    fn into(self) -> OklabColor {
+        Into::<TransparentColor>::into(self).into()
+    }
+}
+
+impl Into<OklchColor> for SolidColor {
+    fn into(self) -> OklchColor {
+        Into::<TransparentColor>::into(self).into()
+    }
+}
+
+impl TryInto<SolidColor> for OklabColor {
+    type Error = ();
+    fn try_into(self) -> Result<SolidColor, Self::Error> {
+        Into::<TransparentColor>::into(self).try_into()
+    }
+}
+
+impl TryInto<SolidColor> for OklchColor {
+    type Error = ();
+    fn try_into(self) -> Result<SolidColor, Self::Error> {
+        Into::<TransparentColor>::into(self).try_into()
+    }
+}
+
+impl Color for OklabColor {
+    fn mix(color_weights: &[(Self, f64)]) -> Self {
+        OklabColor {
+            lightness: color_weights.iter().map(|(color, weight)| color.lightness * weight).sum::<f64>().clamp(0., 1.),
+            a: color_weights.iter().map(|(color, weight)| color.a * weight).sum(),
+            b: color_weights.iter().map(|(color, weight)| color.b * weight).sum(),
+            alpha: mix_alphas(color_weights.iter().map(|(color, weight)| (color.alpha, *weight))),
+        }
+    }
+
+    fn distance(&self, other: &Self) -> f64 {
+        let (lightness, a, b) = (self.lightness - other.lightness, self.a - other.a, self.b - other.b);
+        let alpha = (self.alpha as f64 - other.alpha as f64) / u8::MAX as f64;
+        (lightness * lightness + a * a + b * b + alpha * alpha).sqrt()
+    }
+
+    fn perceived_lightness(&self) -> f64 {
+        self.lightness
+    }
+
+    fn posterize(&self, levels: usize) -> Self {
+        Into::<TransparentColor>::into(*self).posterize(levels).into()
+    }
+
+    fn adjust(&self, adjustment: &Adjustment) -> Self {
+        Into::<TransparentColor>::into(*self).adjust(adjustment).into()
+    }
+}
+
+impl Color for OklchColor {
+    fn mix(color_weights: &[(Self, f64)]) -> Self {
+        OklchColor {
+            lightness: color_weights.iter().map(|(color, weight)| color.lightness * weight).sum::<f64>().clamp(0., 1.),
+            chroma: color_weights.iter().map(|(color, weight)| color.chroma * weight).sum::<f64>().max(0.),
+            hue: mix_hues(color_weights.iter().map(|(color, weight)| (color.hue, weight * color.chroma))),
+            alpha: mix_alphas(color_weights.iter().map(|(color, weight)| (color.alpha, *weight))),
+        }
+    }
+
+    fn distance(&self, other: &Self) -> f64 {
+        Into::<OklabColor>::into(*self).distance(&(*other).into())
+    }
+
+    fn perceived_lightness(&self) -> f64 {
+        self.lightness
+    }
+
+    fn posterize(&self, levels: usize) -> Self {
+        Into::<TransparentColor>::into(*self).posterize(levels).into()
+    }
+
+    fn adjust(&self, adjustment: &Adjustment) -> Self {
+        Into::<TransparentColor>::into(*self).adjust(adjustment).into()
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
+
+use crate::coloring::SolidColor;
+use crate::shapes::Shape;
+
+/// Rules that randomly generated candidates, such as scenes or the settings for one, have to follow.
+/// `generate` keeps making new candidates until one passes every rule, so bulk generation only
+/// produces usable results.
+pub struct Constraints<T> {
+    rules: Vec<Rule<T>>,
+}
+
+struct Rule<T> {
+    description: String,
+    check: Box<dyn Fn(&T) -> bool>,
+}
+
+/// Why `Constraints::generate` gave up
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstraintFailure {
+    pub attempts: usize,
+    /// Each rule that ever failed, with how many candidates it failed on
+    pub failures: Vec<(String, usize)>,
+}
+
+impl Display for ConstraintFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No candidate passed every constraint after {} attempts.", self.attempts)?;
+        for (description, count) in self.failures.iter() {
+            write!(f, " \"{description}\" failed {count} times.")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for Constraints<T> {
+    fn default() -> Self {
+        Constraints::new()
+    }
+}
+
+impl<T> Constraints<T> {
+    pub fn new() -> Self {
+        Constraints { rules: Vec::new() }
+    }
+
+    /// Adds a rule that `check` has to return true for. `description` is what gets reported when it doesn't.
+    pub fn ensure(mut self, description: &str, check: impl Fn(&T) -> bool + 'static) -> Self {
+        self.rules.push(Rule {
+            description: description.to_owned(),
+            check: Box::new(check),
+        });
+        self
+    }
+
+    /// Requires the WCAG contrast ratio between the two colors `colors` picks out to be at least `min_ratio`,
+    /// like `ensure contrast(background, accent) > 4.5`
+    pub fn ensure_contrast(self, description: &str, colors: impl Fn(&T) -> (SolidColor, SolidColor) + 'static, min_ratio: f64) -> Self {
+        self.ensure(description, move |candidate| {
+            let (color1, color2) = colors(candidate);
+            color1.contrast_ratio(&color2) >= min_ratio
+        })
+    }
+
+    /// Requires the shape `shape` picks out to cover a fraction of the `width` by `height` canvas within
+    /// `fractions`, like `ensure coverage(shape) within 20%..60%` with `0.2..=0.6`
+    pub fn ensure_coverage(
+        self, description: &str,
+        shape: impl Fn(&T) -> Shape + 'static,
+        width: usize, height: usize,
+        fractions: RangeInclusive<f64>,
+    ) -> Self {
+        self.ensure(description, move |candidate| fractions.contains(&coverage(&shape(candidate), width, height)))
+    }
+
+    /// The descriptions of every rule `candidate` breaks
+    pub fn check(&self, candidate: &T) -> Vec<&str> {
+        self.rules.iter()
+            .filter(|rule| !(rule.check)(candidate))
+            .map(|rule| rule.description.as_str())
+            .collect()
+    }
+
+    /// Calls `generator` until it makes a candidate that passes every rule, up to `max_attempts` times
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(rules = self.rules.len(), max_attempts = max_attempts)))]
+    pub fn generate<R: rand::Rng>(&self, rng: &mut R, max_attempts: usize, mut generator: impl FnMut(&mut R) -> T) -> Result<T, ConstraintFailure> {
+        let mut failure_counts = vec![0; self.rules.len()];
+
+        for _ in 0..max_attempts {
+            let candidate = generator(rng);
+            let mut passed = true;
+            for (rule, failure_count) in self.rules.iter().zip(failure_counts.iter_mut()) {
+                if !(rule.check)(&candidate) {
+                    *failure_count += 1;
+                    passed = false;
+                }
+            }
+
+            if passed {
+                return Ok(candidate);
+            }
+        }
+
+        Err(ConstraintFailure {
+            attempts: max_attempts,
+            failures: self.rules.iter().zip(failure_counts)
+                .filter(|(_, count)| *count > 0)
+                .map(|(rule, count)| (rule.description.clone(), count))
+                .collect(),
+        })
+    }
+}
+
+/// The fraction of the pixels of a `width` by `height` canvas that `shape` covers
+pub fn coverage(shape: &Shape, width: usize, height: usize) -> f64 {
+    if width == 0 || height == 0 {
+        return 0.;
+    }
+
+    let covered_pixels: usize = (0..height).map(|y| shape.contains_row(y as f64, 0..width).len()).sum();
+    covered_pixels as f64 / (width * height) as f64
+}
@@ -0,0 +1,210 @@
+mod netpbm;
+mod qoi;
+
+use std::io::{Seek, Write};
+
+use image::codecs::{bmp::BmpEncoder, jpeg::JpegEncoder, png, tiff::TiffEncoder, webp::WebPEncoder};
+use image::{ExtendedColorType, ImageEncoder};
+
+/// The file formats images can be saved as
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Png,
+    Jpeg,
+    Bmp,
+    Tiff,
+    /// Always lossless, the only kind of WebP the `image` crate can write
+    WebP,
+    /// Lossless and much faster to write than PNG, for dumping lots of images quickly
+    Qoi,
+    /// Uncompressed and without transparency, but readable by almost anything
+    Ppm,
+    /// `Ppm` with transparency
+    Pam,
+}
+
+impl ExportFormat {
+    /// Guesses the format from a file name's extension, ignoring case
+    pub fn from_extension(filename: &str) -> Option<ExportFormat> {
+        let extension = std::path::Path::new(filename).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(ExportFormat::Png),
+            "jpg" | "jpeg" => Some(ExportFormat::Jpeg),
+            "bmp" => Some(ExportFormat::Bmp),
+            "tif" | "tiff" => Some(ExportFormat::Tiff),
+            "webp" => Some(ExportFormat::WebP),
+            "qoi" => Some(ExportFormat::Qoi),
+            "ppm" => Some(ExportFormat::Ppm),
+            "pam" => Some(ExportFormat::Pam),
+            _ => None,
+        }
+    }
+}
+
+/// How hard PNG encoding tries to shrink the file
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    /// The smallest files, but the slowest to write
+    Best,
+}
+
+/// Settings for the encoders of the formats that have any. Each format ignores the others' settings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExportOptions {
+    jpeg_quality: u8,
+    png_compression: PngCompression,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            jpeg_quality: 90,
+            png_compression: PngCompression::Default,
+        }
+    }
+}
+
+impl ExportOptions {
+    pub fn new() -> Self {
+        ExportOptions::default()
+    }
+
+    /// From 1 for the smallest files to 100 for the best looking ones
+    pub fn with_jpeg_quality(self, jpeg_quality: u8) -> Self {
+        ExportOptions { jpeg_quality: jpeg_quality.clamp(1, 100), ..self }
+    }
+
+    pub fn with_png_compression(self, png_compression: PngCompression) -> Self {
+        ExportOptions { png_compression, ..self }
+    }
+
+    pub fn jpeg_quality(&self) -> u8 {
+        self.jpeg_quality
+    }
+
+    pub fn png_compression(&self) -> PngCompression {
+        self.png_compression
+    }
+}
+
+/// Writes `width` by `height` pixels of `bytes`, laid out as `color_type` says, to `writer`. Fails for
+/// combinations the format can't hold, such as transparency in a JPEG.
+pub(crate) fn encode<W: Write + Seek>(
+    writer: W,
+    bytes: &[u8],
+    width: usize,
+    height: usize,
+    color_type: ExtendedColorType,
+    format: ExportFormat,
+    options: &ExportOptions,
+) -> Result<(), ()> {
+    let (width, height) = (width.try_into().map_err(|_|())?, height.try_into().map_err(|_|())?);
+    let mut writer = writer;
+
+    match format {
+        ExportFormat::Png => {
+            let compression = match options.png_compression {
+                PngCompression::Fast => png::CompressionType::Fast,
+                PngCompression::Default => png::CompressionType::Default,
+                PngCompression::Best => png::CompressionType::Best,
+            };
+            png::PngEncoder::new_with_quality(writer, compression, png::FilterType::Adaptive).write_image(bytes, width, height, color_type)
+        },
+        ExportFormat::Jpeg => JpegEncoder::new_with_quality(writer, options.jpeg_quality).write_image(bytes, width, height, color_type),
+        ExportFormat::Bmp => BmpEncoder::new(&mut writer).write_image(bytes, width, height, color_type),
+        ExportFormat::Tiff => TiffEncoder::new(writer).write_image(bytes, width, height, color_type),
+        ExportFormat::WebP => WebPEncoder::new_lossless(writer).write_image(bytes, width, height, color_type),
+        ExportFormat::Qoi | ExportFormat::Ppm | ExportFormat::Pam => {
+            let channels = match color_type {
+                ExtendedColorType::Rgb8 => 3,
+                ExtendedColorType::Rgba8 => 4,
+                _ => return Err(()),
+            };
+            let encoded = encode_without_image_crate(bytes, width as usize, height as usize, channels, format)?;
+            return writer.write_all(&encoded).map_err(|_|());
+        },
+    }.map_err(|_|())
+}
+
+/// Encodes the formats this crate writes itself, which don't need the `image` crate. `bytes` has 3
+/// channels per pixel for RGB or 4 for RGBA. Fails for other formats, and for PPM with transparency.
+pub(crate) fn encode_without_image_crate(bytes: &[u8], width: usize, height: usize, channels: usize, format: ExportFormat) -> Result<Vec<u8>, ()> {
+    match format {
+        ExportFormat::Qoi => qoi::encode(bytes, width, height, channels),
+        ExportFormat::Ppm if channels == 3 => netpbm::encode_ppm(bytes, width, height),
+        ExportFormat::Pam => netpbm::encode_pam(bytes, width, height, channels),
+        _ => Err(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageFormat;
+
+    fn encode_to_vec(bytes: &[u8], width: usize, height: usize, color_type: ExtendedColorType, format: ExportFormat) -> Vec<u8> {
+        let mut encoded = std::io::Cursor::new(Vec::new());
+        encode(&mut encoded, bytes, width, height, color_type, format, &ExportOptions::default()).unwrap();
+        encoded.into_inner()
+    }
+
+    fn gradient(width: usize, height: usize, channels: usize) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|index| [(index % width * 255 / width) as u8, (index / width * 255 / height) as u8, 128, (index % 7 * 40) as u8].into_iter().take(channels))
+            .collect()
+    }
+
+    #[test]
+    fn lossless_formats_decode_to_the_same_pixels() {
+        let (width, height) = (23, 17);
+        let formats = [
+            (ExportFormat::Png, ImageFormat::Png, true),
+            (ExportFormat::Bmp, ImageFormat::Bmp, true),
+            (ExportFormat::Tiff, ImageFormat::Tiff, true),
+            (ExportFormat::WebP, ImageFormat::WebP, true),
+            (ExportFormat::Qoi, ImageFormat::Qoi, true),
+            (ExportFormat::Ppm, ImageFormat::Pnm, false),
+            // the image crate can't read PAMs with transparency
+            (ExportFormat::Pam, ImageFormat::Pnm, false),
+        ];
+
+        for (format, image_format, has_alpha) in formats {
+            let rgb = gradient(width, height, 3);
+            let decoded = image::load_from_memory_with_format(&encode_to_vec(&rgb, width, height, ExtendedColorType::Rgb8, format), image_format).unwrap();
+            assert_eq!(decoded.into_rgb8().into_raw(), rgb, "{format:?} RGB");
+
+            if has_alpha {
+                let rgba = gradient(width, height, 4);
+                let decoded = image::load_from_memory_with_format(&encode_to_vec(&rgba, width, height, ExtendedColorType::Rgba8, format), image_format).unwrap();
+                assert_eq!(decoded.into_rgba8().into_raw(), rgba, "{format:?} RGBA");
+            }
+        }
+
+        let rgba = gradient(width, height, 4);
+        let encoded = encode_to_vec(&rgba, width, height, ExtendedColorType::Rgba8, ExportFormat::Pam);
+        let header = b"P7\nWIDTH 23\nHEIGHT 17\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n";
+        assert_eq!((&encoded[..header.len()], &encoded[header.len()..]), (&header[..], &rgba[..]));
+    }
+
+    #[test]
+    fn jpeg_decodes_to_nearly_the_same_pixels() {
+        let (width, height) = (32, 24);
+        let rgb = gradient(width, height, 3);
+        let decoded = image::load_from_memory_with_format(&encode_to_vec(&rgb, width, height, ExtendedColorType::Rgb8, ExportFormat::Jpeg), ImageFormat::Jpeg).unwrap();
+
+        assert_eq!((decoded.width() as usize, decoded.height() as usize), (width, height));
+        for (decoded, original) in decoded.into_rgb8().into_raw().into_iter().zip(rgb) {
+            assert!(decoded.abs_diff(original) <= 8, "{decoded} is too far from {original}");
+        }
+    }
+
+    #[test]
+    fn formats_without_transparency_reject_it() {
+        let mut encoded = std::io::Cursor::new(Vec::new());
+        assert_eq!(encode(&mut encoded, &gradient(4, 4, 4), 4, 4, ExtendedColorType::Rgba8, ExportFormat::Ppm, &ExportOptions::default()), Err(()));
+        assert_eq!(encode(&mut encoded, &gradient(4, 4, 4), 4, 4, ExtendedColorType::Rgba8, ExportFormat::Jpeg, &ExportOptions::default()), Err(()));
+    }
+}
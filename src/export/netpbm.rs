@@ -0,0 +1,28 @@
+/// Encodes `width` by `height` RGB pixels of `bytes` as a binary PPM file, the simplest format most
+/// image tools can open
+pub(super) fn encode_ppm(bytes: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ()> {
+    if bytes.len() != width * height * 3 {
+        return Err(());
+    }
+
+    let mut encoded = format!("P6\n{width} {height}\n255\n").into_bytes();
+    encoded.extend_from_slice(bytes);
+    Ok(encoded)
+}
+
+/// Encodes `width` by `height` pixels of `bytes` as a PAM file, PPM's cousin that can also hold
+/// transparency. `bytes` has 3 channels per pixel for RGB or 4 for RGBA.
+pub(super) fn encode_pam(bytes: &[u8], width: usize, height: usize, channels: usize) -> Result<Vec<u8>, ()> {
+    let tuple_type = match channels {
+        3 => "RGB",
+        4 => "RGB_ALPHA",
+        _ => return Err(()),
+    };
+    if bytes.len() != width * height * channels {
+        return Err(());
+    }
+
+    let mut encoded = format!("P7\nWIDTH {width}\nHEIGHT {height}\nDEPTH {channels}\nMAXVAL 255\nTUPLTYPE {tuple_type}\nENDHDR\n").into_bytes();
+    encoded.extend_from_slice(bytes);
+    Ok(encoded)
+}
@@ -0,0 +1,125 @@
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+const OP_RUN: u8 = 0xc0;
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+const MAX_RUN: u8 = 62;
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+/// Encodes `width` by `height` pixels of `bytes` as a QOI ("Quite OK Image", https://qoiformat.org) file.
+/// It's lossless like PNG but many times faster to write, since each pixel is only compared against a few
+/// recent ones instead of being compressed. `bytes` has 3 channels per pixel for RGB or 4 for RGBA.
+/// Fails if the sizes don't fit the format or don't match the bytes.
+pub(super) fn encode(bytes: &[u8], width: usize, height: usize, channels: usize) -> Result<Vec<u8>, ()> {
+    let (qoi_width, qoi_height): (u32, u32) = (width.try_into().map_err(|_|())?, height.try_into().map_err(|_|())?);
+    if !(channels == 3 || channels == 4) || bytes.len() != width * height * channels {
+        return Err(());
+    }
+
+    let mut encoded = Vec::with_capacity(14 + bytes.len() / 2 + END_MARKER.len());
+    encoded.extend_from_slice(b"qoif");
+    encoded.extend_from_slice(&qoi_width.to_be_bytes());
+    encoded.extend_from_slice(&qoi_height.to_be_bytes());
+    // sRGB with linear alpha
+    encoded.extend_from_slice(&[channels as u8, 0]);
+
+    let mut seen = [[0_u8; 4]; 64];
+    let mut previous = [0, 0, 0, u8::MAX];
+    let mut run = 0;
+    let pixel_count = width * height;
+
+    for (index, pixel) in bytes.chunks_exact(channels).enumerate() {
+        let pixel = [pixel[0], pixel[1], pixel[2], if channels == 4 { pixel[3] } else { u8::MAX }];
+
+        if pixel == previous {
+            run += 1;
+            if run == MAX_RUN || index + 1 == pixel_count {
+                encoded.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+
+        if run > 0 {
+            encoded.push(OP_RUN | (run - 1));
+            run = 0;
+        }
+
+        let [red, green, blue, alpha] = pixel;
+        let hash = (red as usize * 3 + green as usize * 5 + blue as usize * 7 + alpha as usize * 11) % 64;
+        if seen[hash] == pixel {
+            encoded.push(OP_INDEX | hash as u8);
+        } else {
+            seen[hash] = pixel;
+
+            if alpha == previous[3] {
+                let difference = |channel: usize| pixel[channel].wrapping_sub(previous[channel]) as i8;
+                let (red_difference, green_difference, blue_difference) = (difference(0), difference(1), difference(2));
+                let red_from_green = red_difference.wrapping_sub(green_difference);
+                let blue_from_green = blue_difference.wrapping_sub(green_difference);
+
+                if [red_difference, green_difference, blue_difference].iter().all(|difference| (-2..=1).contains(difference)) {
+                    encoded.push(OP_DIFF | ((red_difference + 2) as u8) << 4 | ((green_difference + 2) as u8) << 2 | (blue_difference + 2) as u8);
+                } else if (-32..=31).contains(&green_difference) && (-8..=7).contains(&red_from_green) && (-8..=7).contains(&blue_from_green) {
+                    encoded.push(OP_LUMA | (green_difference + 32) as u8);
+                    encoded.push(((red_from_green + 8) as u8) << 4 | (blue_from_green + 8) as u8);
+                } else {
+                    encoded.extend_from_slice(&[OP_RGB, red, green, blue]);
+                }
+            } else {
+                encoded.extend_from_slice(&[OP_RGBA, red, green, blue, alpha]);
+            }
+        }
+        previous = pixel;
+    }
+
+    encoded.extend_from_slice(&END_MARKER);
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    /// Pixels that need every op: long runs, repeats of older pixels, small and large steps, and alpha changes
+    fn test_pixels(width: usize, height: usize, channels: usize) -> Vec<u8> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let palette: Vec<[u8; 4]> = (0..8).map(|_| rng.random()).collect();
+        (0..width * height)
+            .flat_map(|index| {
+                let pixel = match index / 100 % 5 {
+                    0 => [10, 20, 30, 255],
+                    1 => [(index % 4) as u8, 40 + (index % 3) as u8, 50, 255],
+                    2 => [(index * 7) as u8, (index * 5) as u8, (index * 6) as u8, 255],
+                    3 => palette[index % palette.len()],
+                    _ => rng.random(),
+                };
+                pixel.into_iter().take(channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_to_the_same_pixels() {
+        for channels in [3, 4] {
+            let (width, height) = (37, 41);
+            let bytes = test_pixels(width, height, channels);
+            let encoded = encode(&bytes, width, height, channels).unwrap();
+            assert!(encoded.ends_with(&END_MARKER));
+
+            let decoded = image::load_from_memory_with_format(&encoded, image::ImageFormat::Qoi).unwrap();
+            assert_eq!((decoded.width() as usize, decoded.height() as usize), (width, height));
+            let decoded = if channels == 3 { decoded.into_rgb8().into_raw() } else { decoded.into_rgba8().into_raw() };
+            assert_eq!(decoded, bytes, "{channels} channels");
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_sizes() {
+        assert_eq!(encode(&[0; 12], 2, 2, 4), Err(()));
+        assert_eq!(encode(&[0; 12], 2, 2, 2), Err(()));
+        assert!(encode(&[0; 12], 2, 2, 3).is_ok());
+    }
+}
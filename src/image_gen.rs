@@ -1,22 +1,143 @@
+/// Runs `$body` inside a tracing span named `$name` when the `tracing` feature is enabled
+macro_rules! traced {
+    ($name:literal, $body:block) => {{
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($name).entered();
+        $body
+    }};
+}
+
 pub mod shapes;
 pub mod noise;
 pub mod coloring;
+pub mod scene;
+pub mod noisy;
+pub mod theme;
+pub mod palette;
+pub mod presets;
+pub mod terminal;
+pub mod limits;
+pub mod assets;
+pub mod ora;
+pub mod layers;
+pub mod animation;
+pub mod export;
+pub mod constraints;
+pub mod scoring;
+#[cfg(feature = "text")]
+pub mod text;
+
+pub use noisy::read_noisy_file;
 
-use image::{RgbImage, ImageBuffer};
+use std::ops::Range;
+
+use image::{GrayImage, RgbImage, RgbaImage, ImageBuffer};
 use shapes::CheckInside;
 use coloring::{Coloring, TransparentColor};
 
+#[derive(Clone)]
 pub struct Image {
     canvas_width: usize,
     canvas: Vec<coloring::SolidColor>,
+    compositing: coloring::Compositing,
+    dithering: coloring::Dithering,
+    antialiasing: shapes::Antialiasing,
+}
+
+/// Per-pixel coverage of a single draw instruction, from 0 (untouched) to 255 (fully covered)
+pub struct Matte {
+    width: usize,
+    coverage: Vec<u8>,
 }
 
 pub struct DrawInstruction<R: rand::Rng> {
+    /// Added to the instruction's own transparent layer before it's clipped to the shape
     pub pre_clip_noise: Option<Box<dyn noise::Noise<R>>>,
     pub clipping_shape: shapes::Shape,
     pub coloring: coloring::ColorScheme<coloring::TransparentColor>,
+    /// Added to the instruction's own layer after it's clipped, before it's drawn
     pub post_clip_noise: Option<Box<dyn noise::Noise<R>>>,
+    /// Added to the whole image after the instruction is drawn
     pub post_draw_noise: Option<Box<dyn noise::Noise<R>>>,
+    /// Scales the alpha of every color the instruction draws, from 0 for invisible to 1 for unchanged
+    pub opacity: f64,
+    pub blend_mode: coloring::BlendMode,
+    /// Overrides the image's dithering for this instruction when set
+    pub dithering: Option<coloring::Dithering>,
+}
+
+/// Everything about a draw instruction except where it gets drawn, so it can be shared between instructions
+pub struct Style<R: rand::Rng> {
+    pub pre_clip_noise: Option<Box<dyn noise::Noise<R>>>,
+    pub coloring: coloring::ColorScheme<coloring::TransparentColor>,
+    pub post_clip_noise: Option<Box<dyn noise::Noise<R>>>,
+    pub post_draw_noise: Option<Box<dyn noise::Noise<R>>>,
+    pub opacity: f64,
+    pub blend_mode: coloring::BlendMode,
+    pub dithering: Option<coloring::Dithering>,
+}
+
+struct DrawSettings<'a, R: rand::Rng> {
+    pre_clip_noise: Option<&'a dyn noise::Noise<R>>,
+    clipping_shape: &'a shapes::Shape,
+    coloring: &'a coloring::ColorScheme<coloring::TransparentColor>,
+    post_clip_noise: Option<&'a dyn noise::Noise<R>>,
+    post_draw_noise: Option<&'a dyn noise::Noise<R>>,
+    opacity: f64,
+    blend_mode: coloring::BlendMode,
+    dithering: Option<coloring::Dithering>,
+    // rows colored in after this are left transparent, since the render is going to be thrown away
+    deadline: Option<std::time::Instant>,
+}
+
+impl<R: rand::Rng> DrawSettings<'_, R> {
+    fn has_noise(&self) -> bool {
+        self.pre_clip_noise.is_some() || self.post_clip_noise.is_some() || self.post_draw_noise.is_some()
+    }
+
+    /// Whether everything under the shape gets painted over, not counting noise. Other blend modes
+    /// mix with what's under even when opaque.
+    fn is_opaque(&self) -> bool {
+        self.blend_mode == coloring::BlendMode::Normal && self.opacity >= 1. && self.coloring.is_opaque()
+    }
+
+    /// Whether the draw leaves the canvas the way it was, not counting noise
+    fn is_invisible(&self) -> bool {
+        // the opacity scales every alpha, so one that rounds 255 down to 0 hides everything
+        (self.opacity.clamp(0., 1.) * u8::MAX as f64).round() == 0. || self.coloring.is_invisible()
+    }
+}
+
+impl<R: rand::Rng> DrawInstruction<R> {
+    fn settings(&self) -> DrawSettings<'_, R> {
+        DrawSettings {
+            pre_clip_noise: self.pre_clip_noise.as_deref(),
+            clipping_shape: &self.clipping_shape,
+            coloring: &self.coloring,
+            post_clip_noise: self.post_clip_noise.as_deref(),
+            post_draw_noise: self.post_draw_noise.as_deref(),
+            opacity: self.opacity,
+            blend_mode: self.blend_mode,
+            dithering: self.dithering,
+            deadline: None,
+        }
+    }
+}
+
+impl<R: rand::Rng> Style<R> {
+    fn settings<'a>(&'a self, clipping_shape: &'a shapes::Shape) -> DrawSettings<'a, R> {
+        DrawSettings {
+            pre_clip_noise: self.pre_clip_noise.as_deref(),
+            clipping_shape,
+            coloring: &self.coloring,
+            post_clip_noise: self.post_clip_noise.as_deref(),
+            post_draw_noise: self.post_draw_noise.as_deref(),
+            opacity: self.opacity,
+            blend_mode: self.blend_mode,
+            dithering: self.dithering,
+            deadline: None,
+        }
+    }
 }
 
 
@@ -25,9 +146,83 @@ impl Image {
         Image { 
             canvas_width: width,
             canvas: vec![background_color; width * height],
+            compositing: coloring::Compositing::Srgb,
+            dithering: coloring::Dithering::None,
+            antialiasing: shapes::Antialiasing::None,
         }
     }
 
+    /// Changes how translucent colors are drawn onto the image from now on, such as
+    /// `Compositing::LinearLight` to keep translucent overlaps from darkening
+    pub fn with_compositing(self, compositing: coloring::Compositing) -> Self {
+        Image { compositing, ..self }
+    }
+
+    pub fn set_compositing(&mut self, compositing: coloring::Compositing) {
+        self.compositing = compositing;
+    }
+
+    pub fn compositing(&self) -> coloring::Compositing {
+        self.compositing
+    }
+
+    /// Dithers gradients drawn onto the image from now on to hide their banding, unless an
+    /// instruction picks its own dithering
+    pub fn with_dithering(self, dithering: coloring::Dithering) -> Self {
+        Image { dithering, ..self }
+    }
+
+    pub fn set_dithering(&mut self, dithering: coloring::Dithering) {
+        self.dithering = dithering;
+    }
+
+    pub fn dithering(&self) -> coloring::Dithering {
+        self.dithering
+    }
+
+    /// Smooths the edges of shapes drawn onto the image from now on
+    pub fn with_antialiasing(self, antialiasing: shapes::Antialiasing) -> Self {
+        Image { antialiasing, ..self }
+    }
+
+    pub fn set_antialiasing(&mut self, antialiasing: shapes::Antialiasing) {
+        self.antialiasing = antialiasing;
+    }
+
+    pub fn antialiasing(&self) -> shapes::Antialiasing {
+        self.antialiasing
+    }
+
+    /// Loads an existing image file, flattening away any transparency
+    pub fn open(filename: &str) -> Result<Self, ()> {
+        Image::open_with(&assets::FileSystemResolver::new(), filename)
+    }
+
+    /// Like `open`, but gets the image from `resolver`
+    pub fn open_with(resolver: &dyn assets::AssetResolver, name: &str) -> Result<Self, ()> {
+        let image = assets::load_image(resolver, name)?.to_rgb8();
+
+        Ok(Image {
+            canvas_width: image.width().try_into().map_err(|_|())?,
+            canvas: image.pixels().map(|pixel| coloring::SolidColor {
+                red: pixel[0],
+                green: pixel[1],
+                blue: pixel[2],
+            }).collect(),
+            compositing: coloring::Compositing::Srgb,
+            dithering: coloring::Dithering::None,
+            antialiasing: shapes::Antialiasing::None,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.canvas_width
+    }
+
+    pub fn height(&self) -> usize {
+        self.canvas_height()
+    }
+
     fn canvas_height(&self) -> usize {
         self.canvas.len() / self.canvas_width
     }
@@ -36,72 +231,940 @@ impl Image {
     }
     
     pub fn get_pixel(&self, x: usize, y: usize) -> &coloring::SolidColor {
-        return &self.canvas[self.get_index(x, y)]
+        &self.canvas[self.get_index(x, y)]
     }
     
     pub fn get_pixel_mut(&mut self, x: usize, y: usize) -> &mut coloring::SolidColor {
         let index = self.get_index(x, y);
-        return &mut self.canvas[index]
+        &mut self.canvas[index]
     }
     
     pub fn swap_pixels(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
-        let tmp_pixel = self.get_pixel(x1 as usize, y1 as usize).to_owned();
-        *self.get_pixel_mut(x1 as usize, y1 as usize) = self.get_pixel(x2 as usize, y2 as usize).to_owned();
-        *self.get_pixel_mut(x2 as usize, y2 as usize) = tmp_pixel;
+        let tmp_pixel = self.get_pixel(x1, y1).to_owned();
+        *self.get_pixel_mut(x1, y1) = self.get_pixel(x2, y2).to_owned();
+        *self.get_pixel_mut(x2, y2) = tmp_pixel;
     }
     
-    pub fn output_to_image(&self, filename: &str)-> Result<(),()>{
-        let image:RgbImage = ImageBuffer::from_raw(
+    /// A copy of the pixels inside `rect`, clipped to the image. Like `Rect::contains`, both of its edges
+    /// are inclusive, so cropping to a rect that was just drawn keeps every pixel it covered. Fails if
+    /// that leaves no pixels.
+    pub fn crop(&self, rect: &shapes::Rect) -> Result<Image, ()> {
+        Ok(self.view(rect)?.to_image())
+    }
+
+    /// Like `crop`, but borrows the pixels instead of copying them
+    pub fn view(&self, rect: &shapes::Rect) -> Result<ImageView<'_>, ()> {
+        let (min, max) = (rect.min_point(), rect.max_point());
+        let columns = shapes::columns_between(min.x, max.x, &(0..self.canvas_width));
+        let rows = shapes::columns_between(min.y, max.y, &(0..self.canvas_height()));
+        if columns.is_empty() || rows.is_empty() {
+            return Err(());
+        }
+
+        Ok(ImageView {
+            image: self,
+            x: columns.start,
+            y: rows.start,
+            width: columns.len(),
+            height: rows.len(),
+        })
+    }
+
+    /// Puts tiles rendered from `Scene::partition` back together into a `width` by `height` image.
+    /// Fails if a tile doesn't fit or any pixel isn't covered by a tile.
+    pub fn assemble(width: usize, height: usize, tiles: impl IntoIterator<Item = scene::Tile>) -> Result<Self, ()> {
+        let mut image = Image::with_size(width, height, coloring::SolidColor::BLACK);
+        let mut covered = vec![false; width * height];
+
+        for tile in tiles {
+            let job = tile.job();
+            if job.x + job.width > width || job.y + job.height > height {
+                return Err(());
+            }
+            for row in 0..job.height {
+                let start = image.get_index(job.x, job.y + row);
+                let tile_start = tile.image().get_index(0, row);
+                image.canvas[start..start + job.width].copy_from_slice(&tile.image().canvas[tile_start..tile_start + job.width]);
+                covered[start..start + job.width].fill(true);
+            }
+        }
+
+        if covered.contains(&false) {
+            return Err(());
+        }
+        Ok(image)
+    }
+
+    /// Downsamples the image to `cols` characters wide and draws it with 24-bit colored half blocks, for previewing in a terminal
+    pub fn render_ansi(&self, cols: usize) -> String {
+        if cols == 0 || self.canvas.is_empty() {
+            return String::new();
+        }
+
+        // every character is two square cells stacked on top of each other
+        let cell_size = self.canvas_width as f64 / cols as f64;
+        let cell_rows = (self.canvas_height() as f64 / cell_size).ceil() as usize;
+
+        let average_cell = |col: usize, row: usize| {
+            let min_x = (col as f64 * cell_size) as usize;
+            let max_x = (((col + 1) as f64 * cell_size) as usize).clamp(min_x + 1, self.canvas_width);
+            let min_y = ((row as f64 * cell_size) as usize).min(self.canvas_height() - 1);
+            let max_y = (((row + 1) as f64 * cell_size) as usize).clamp(min_y + 1, self.canvas_height());
+
+            let colors: Vec<coloring::SolidColor> = (min_y..max_y)
+                .flat_map(|y| (min_x..max_x).map(move |x| (x, y)))
+                .map(|(x, y)| *self.get_pixel(x, y))
+                .collect();
+            palette::average_color(&colors)
+        };
+
+        let mut output = String::new();
+        for row in (0..cell_rows).step_by(2) {
+            for col in 0..cols {
+                let top = average_cell(col, row);
+                if row + 1 < cell_rows {
+                    let bottom = average_cell(col, row + 1);
+                    output.push_str(&format!(
+                        "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                        top.red, top.green, top.blue, bottom.red, bottom.green, bottom.blue
+                    ));
+                } else {
+                    output.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[49m\u{2580}", top.red, top.green, top.blue));
+                }
+            }
+            output.push_str("\x1b[0m\n");
+        }
+        output
+    }
+
+    fn to_rgb_image(&self) -> Result<RgbImage, ()> {
+        Ok(ImageBuffer::from_raw(
             self.canvas_width.try_into().map_err(|_|())?,
             self.canvas_height().try_into().map_err(|_|())?,
             self.canvas.iter().map(|color| [color.red, color.green, color.blue]).collect::<Vec<[u8;3]>>().into_iter().flatten().collect())
-        .expect("Image values have a width/height that matches the canvas size");
+        .expect("Image values have a width/height that matches the canvas size"))
+    }
+
+    pub fn output_to_image(&self, filename: &str)-> Result<(),()>{
+        self.to_rgb_image()?.save(filename).map_err(|_|())
+    }
+
+    /// Saves the image as a binary PPM file without going through the `image` crate
+    pub fn output_ppm(&self, filename: &str) -> Result<(),()> {
+        let bytes: Vec<u8> = self.canvas.iter().flat_map(|color| [color.red, color.green, color.blue]).collect();
+        let encoded = export::encode_without_image_crate(&bytes, self.canvas_width, self.canvas_height(), 3, export::ExportFormat::Ppm)?;
+        std::fs::write(filename, encoded).map_err(|_|())
+    }
+
+    /// Saves the image as `format` whatever the file's extension, with the encoder settings in `options`
+    pub fn output_as(&self, filename: &str, format: export::ExportFormat, options: &export::ExportOptions) -> Result<(),()> {
+        let file = std::io::BufWriter::new(std::fs::File::create(filename).map_err(|_|())?);
+        export::encode(file, self.to_rgb_image()?.as_raw(), self.canvas_width, self.canvas_height(), image::ExtendedColorType::Rgb8, format, options)
+    }
+
+    /// Encodes the image as `format` with the default encoder settings, such as to send it over a network
+    /// or store it without going through a file
+    pub fn encode_to_vec(&self, format: export::ExportFormat) -> Result<Vec<u8>,()> {
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        export::encode(&mut cursor, self.to_rgb_image()?.as_raw(), self.canvas_width, self.canvas_height(), image::ExtendedColorType::Rgb8, format, &export::ExportOptions::default())?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Like `encode_to_vec`, but writes the encoded image to `writer`
+    pub fn write_to<W: std::io::Write>(&self, writer: W, format: export::ExportFormat) -> Result<(),()> {
+        let mut writer = writer;
+        writer.write_all(&self.encode_to_vec(format)?).map_err(|_|())
+    }
+}
+
+impl Image {
+    pub fn draw_custom<R: rand::Rng>(&mut self, instruction: &DrawInstruction<R>, rng: &mut R) {
+        self.draw_with_settings(instruction.settings(), shapes::Point::ORIGIN, rng);
+    }
+
+    pub fn draw_custom_with_matte<R: rand::Rng>(&mut self, instruction: &DrawInstruction<R>, rng: &mut R) -> Matte {
+        self.draw_with_settings(instruction.settings(), shapes::Point::ORIGIN, rng).matte(self.canvas_width, self.canvas_height())
+    }
+
+    pub fn draw_styled<R: rand::Rng>(&mut self, clipping_shape: &shapes::Shape, style: &Style<R>, rng: &mut R) {
+        self.draw_with_settings(style.settings(clipping_shape), shapes::Point::ORIGIN, rng);
+    }
+
+    pub fn draw_styled_with_matte<R: rand::Rng>(&mut self, clipping_shape: &shapes::Shape, style: &Style<R>, rng: &mut R) -> Matte {
+        self.draw_with_settings(style.settings(clipping_shape), shapes::Point::ORIGIN, rng).matte(self.canvas_width, self.canvas_height())
+    }
+
+    /// Draws as if the image were the part of a bigger canvas whose top left corner is at `origin`. Returns
+    /// the layer that was drawn, which only covers the part of the canvas the instruction could touch.
+    fn draw_with_settings<R: rand::Rng>(&mut self, instruction: DrawSettings<'_, R>, origin: shapes::Point, rng: &mut R) -> RenderedLayer {
+        let new_layer = render_layer(self.canvas_width, self.canvas_height(), &instruction, origin, self.dithering, self.antialiasing, rng);
+
+        traced!("composite", {
+            let (blend_mode, compositing) = (instruction.blend_mode, self.compositing);
+            new_layer.composite_onto(&mut self.canvas, self.canvas_width, |colors, canvas_colors| {
+                if blend_mode == coloring::BlendMode::Normal && compositing == coloring::Compositing::Srgb {
+                    TransparentColor::draw_span_on_solid(colors, canvas_colors);
+                    return;
+                }
+                for (color, canvas_color) in colors.iter().zip(canvas_colors) {
+                    if color.alpha != 0 {
+                        *canvas_color = color.blend_on_solid(canvas_color, blend_mode, compositing);
+                    }
+                }
+            });
+        });
         
+
+        if let Some(noise) = instruction.post_draw_noise {
+            traced!("post_draw_noise", {
+                noise.add_noise(&mut noise::OffsetCanvas::new(self, origin), rng);
+            });
+        }
+
+        new_layer
+    }
+
+    /// Stamps `other` onto the image with its top left corner at `position`, rounded to the nearest
+    /// pixel. Whatever hangs off the edges is left out, so a pre-rendered sprite can be drawn any number
+    /// of times without evaluating its colorings again.
+    pub fn draw_image(&mut self, other: &Image, position: shapes::Point, opacity: f64, blend_mode: coloring::BlendMode) {
+        let alpha = (opacity.clamp(0., 1.) * 255.).round() as u8;
+        self.draw_pixels(other.canvas_width, other.canvas_height(), position, blend_mode, |index| {
+            TransparentColor { alpha, ..other.canvas[index].into() }
+        });
+    }
+
+    /// Like `draw_image`, but keeps the transparency of `other`
+    pub fn draw_transparent_image(&mut self, other: &TransparentImage, position: shapes::Point, opacity: f64, blend_mode: coloring::BlendMode) {
+        let opacity = opacity.clamp(0., 1.);
+        self.draw_pixels(other.canvas_width, other.height(), position, blend_mode, |index| {
+            let color = other.canvas[index];
+            TransparentColor {
+                alpha: (color.alpha as f64 * opacity).round() as u8,
+                ..color
+            }
+        });
+    }
+
+    /// Composites a `width` by `height` block of pixels, given by their index in the block, onto the image
+    fn draw_pixels(&mut self, width: usize, height: usize, position: shapes::Point, blend_mode: coloring::BlendMode, pixel: impl Fn(usize) -> TransparentColor) {
+        let (left, top) = (position.x.round(), position.y.round());
+        // the columns and rows of the block that land on the canvas
+        let columns = (-left).max(0.).min(width as f64) as usize..(self.canvas_width as f64 - left).clamp(0., width as f64) as usize;
+        let rows = (-top).max(0.).min(height as f64) as usize..(self.canvas_height() as f64 - top).clamp(0., height as f64) as usize;
+        if columns.is_empty() || rows.is_empty() {
+            return;
+        }
+
+        let compositing = self.compositing;
+        let mut colors = Vec::with_capacity(columns.len());
+        for y in rows {
+            colors.clear();
+            colors.extend((y * width + columns.start..y * width + columns.end).map(&pixel));
+
+            let start = self.get_index((left + columns.start as f64) as usize, (top + y as f64) as usize);
+            let canvas_colors = &mut self.canvas[start..start + columns.len()];
+            if blend_mode == coloring::BlendMode::Normal && compositing == coloring::Compositing::Srgb {
+                TransparentColor::draw_span_on_solid(&colors, canvas_colors);
+                continue;
+            }
+            for (color, canvas_color) in colors.iter().zip(canvas_colors) {
+                if color.alpha != 0 {
+                    *canvas_color = color.blend_on_solid(canvas_color, blend_mode, compositing);
+                }
+            }
+        }
+    }
+
+}
+
+/// A rectangular part of an `Image`, made with `Image::view`. Pixels are read relative to its own top
+/// left corner.
+#[derive(Copy, Clone)]
+pub struct ImageView<'a> {
+    image: &'a Image,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<'a> ImageView<'a> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Where the view's top left corner is in the image it's part of
+    pub fn position(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> &'a coloring::SolidColor {
+        assert!(x < self.width && y < self.height, "Pixel is outside the view");
+        self.image.get_pixel(self.x + x, self.y + y)
+    }
+
+    /// Each row of the view from the top down
+    pub fn rows(&self) -> impl Iterator<Item = &'a [coloring::SolidColor]> {
+        let (image, x, width) = (self.image, self.x, self.width);
+        (self.y..self.y + self.height).map(move |row| {
+            let start = image.get_index(x, row);
+            &image.canvas[start..start + width]
+        })
+    }
+
+    /// A copy of the view as its own image, with the same settings as the image it's part of
+    pub fn to_image(&self) -> Image {
+        Image {
+            canvas_width: self.width,
+            canvas: self.rows().flatten().copied().collect(),
+            compositing: self.image.compositing,
+            dithering: self.image.dithering,
+            antialiasing: self.image.antialiasing,
+        }
+    }
+}
+
+/// The part of a canvas a draw instruction can touch, colored in and clipped but not yet composited
+struct RenderedLayer {
+    left: usize,
+    top: usize,
+    width: usize,
+    pixels: Vec<TransparentColor>,
+    /// The runs of columns in each row that can be anything but transparent, when they're known
+    spans: Option<Vec<Vec<Range<usize>>>>,
+}
+
+impl RenderedLayer {
+    fn height(&self) -> usize {
+        self.pixels.len().checked_div(self.width).unwrap_or(0)
+    }
+
+    /// The layer's coverage over the whole `canvas_width` by `canvas_height` canvas
+    fn matte(&self, canvas_width: usize, canvas_height: usize) -> Matte {
+        let mut coverage = vec![0; canvas_width * canvas_height];
+        for y in 0..self.height() {
+            for x in 0..self.width {
+                coverage[self.left + x + (self.top + y) * canvas_width] = self.pixels[x + y * self.width].alpha;
+            }
+        }
+
+        Matte {
+            width: canvas_width,
+            coverage,
+        }
+    }
+
+    /// Lays the layer onto `canvas` a run of pixels at a time, with `combine_span(layer colors, canvas colors)`
+    fn composite_onto<T: Send>(&self, canvas: &mut [T], canvas_width: usize, combine_span: impl Fn(&[TransparentColor], &mut [T]) + Send + Sync) {
+        let rows = &mut canvas[self.top * canvas_width..(self.top + self.height()) * canvas_width];
+        for_each_row(rows, canvas_width, |y, row| {
+            let row = &mut row[self.left..self.left + self.width];
+            let layer_row = &self.pixels[y * self.width..(y + 1) * self.width];
+            let whole_row = 0..self.width;
+            let spans = self.spans.as_ref().map_or(std::slice::from_ref(&whole_row), |spans| &spans[y][..]);
+
+            for span in spans {
+                combine_span(&layer_row[span.clone()], &mut row[span.clone()]);
+            }
+        });
+    }
+}
+
+/// The columns and rows of a `width` by `height` canvas whose top left corner is at `origin` that
+/// `bounds` reaches, with a pixel to spare on each side for antialiased edges
+fn pixel_bounds(bounds: &shapes::Rect, origin: shapes::Point, width: usize, height: usize) -> (Range<usize>, Range<usize>) {
+    let (min, max) = (bounds.min_point(), bounds.max_point());
+    let to_pixel = |value: f64, limit: usize| value.clamp(0., limit as f64) as usize;
+
+    (
+        to_pixel((min.x - origin.x - 1.).floor(), width)..to_pixel((max.x - origin.x + 1.).ceil() + 1., width),
+        to_pixel((min.y - origin.y - 1.).floor(), height)..to_pixel((max.y - origin.y + 1.).ceil() + 1., height),
+    )
+}
+
+/// Colors in, clips and noises the layer a draw instruction lays over a `width` by `height` canvas,
+/// ready to be composited. `dithering` is used unless the instruction picks its own, and `antialiasing`
+/// fades out pixels only partly inside the clipping shape.
+///
+/// Only the part of the canvas inside the clipping shape's bounding rect is rendered, unless the
+/// instruction has noise for its layer. Those noises can move colors anywhere on the canvas, so they
+/// get the whole thing.
+fn render_layer<R: rand::Rng>(
+    width: usize,
+    height: usize,
+    instruction: &DrawSettings<'_, R>,
+    origin: shapes::Point,
+    dithering: coloring::Dithering,
+    antialiasing: shapes::Antialiasing,
+    rng: &mut R,
+) -> RenderedLayer {
+    let dithering = instruction.dithering.unwrap_or(dithering);
+    let (columns, rows) = if instruction.pre_clip_noise.is_none() && instruction.post_clip_noise.is_none() {
+        pixel_bounds(&instruction.clipping_shape.bounding_rect(), origin, width, height)
+    } else {
+        (0..width, 0..height)
+    };
+    let (left, top) = (columns.start, rows.start);
+    let layer_width = columns.len();
+
+    // without antialiasing every pixel is either in or out, so the shape can hand back whole runs of
+    // pixels inside it at once instead of being asked about each one
+    let spans: Option<Vec<Vec<Range<usize>>>> = (antialiasing == shapes::Antialiasing::None && origin.x >= 0. && origin.x.fract() == 0.)
+        .then(|| traced!("spans", {
+            let first_column = origin.x as usize + left;
+            rows.clone().map(|row| {
+                instruction.clipping_shape.contains_row(origin.y + row as f64, first_column..first_column + layer_width)
+                    .ranges().iter()
+                    .map(|span| span.start - first_column..span.end - first_column)
+                    .collect()
+            }).collect()
+        }));
+    // noise before clipping can move colors from outside the shape into it, so everything has to be colored in
+    let sampled_spans = spans.as_ref().filter(|_| instruction.pre_clip_noise.is_none());
+
+    // where the layer's top left pixel is on the whole image, for noise
+    let layer_origin = shapes::Point { x: origin.x + left as f64, y: origin.y + top as f64 };
+
+    let mut new_layer = RenderedLayer {
+        left,
+        top,
+        width: layer_width,
+        pixels: vec![coloring::TransparentColor::TRANSPARENT; columns.len() * rows.len()],
+        spans: None,
+    };
+    
+    traced!("sample_coloring", {
+        let deadline = instruction.deadline;
+        for_each_row(&mut new_layer.pixels, layer_width, |y, row| {
+            if deadline.is_some_and(|deadline| std::time::Instant::now() > deadline) {
+                return;
+            }
+            let sample = |x: usize| {
+                let point = shapes::Point {x: origin.x + (left + x) as f64, y: origin.y + (top + y) as f64};
+
+                match dithering {
+                    coloring::Dithering::None => instruction.coloring.sample_color(&point),
+                    // keyed to the whole canvas so tiles rendered apart still line up
+                    _ => instruction.coloring.sample_color_dithered(&point, dithering.threshold(point.x as usize, point.y as usize)),
+                }
+            };
+
+            let Some(spans) = sampled_spans else {
+                for (x, color) in row.iter_mut().enumerate() {
+                    *color = sample(x);
+                }
+                return;
+            };
+            for span in &spans[y] {
+                match instruction.coloring {
+                    coloring::ColorScheme::Solid(solid_color) => row[span.clone()].fill(*solid_color),
+                    _ => for x in span.clone() {
+                        row[x] = sample(x);
+                    },
+                }
+            }
+        });
+    });
+
+    if let Some(noise) = instruction.pre_clip_noise {
+        traced!("pre_clip_noise", {
+            noise.add_noise(&mut noise::OffsetCanvas::new(&mut noise::TransparentCanvas::new(layer_width, &mut new_layer.pixels), layer_origin), rng);
+        });
+    }
+    
+    // the centers of the canvas's edge pixels, for antialiasing shapes that reach them
+    let canvas = shapes::Rect::from_points(&origin, &shapes::Point {x: origin.x + width.saturating_sub(1) as f64, y: origin.y + height.saturating_sub(1) as f64});
+    traced!("clip", {
+        match &spans {
+            // only the spans were colored in, so there's nothing to clip
+            Some(_) if sampled_spans.is_some() => {}
+            Some(spans) => for_each_row(&mut new_layer.pixels, layer_width, |y, row| {
+                let mut gap_start = 0;
+                for span in &spans[y] {
+                    row[gap_start..span.start].fill(TransparentColor::TRANSPARENT);
+                    gap_start = span.end;
+                }
+                row[gap_start..].fill(TransparentColor::TRANSPARENT);
+            }),
+            None => for_each_row(&mut new_layer.pixels, layer_width, |y, row| {
+                for (x, color) in row.iter_mut().enumerate() {
+                    let point = shapes::Point {x: origin.x + (left + x) as f64, y: origin.y + (top + y) as f64};
+                    
+                    let coverage = antialiasing.coverage(instruction.clipping_shape, &point, &canvas);
+                    if coverage < 1. {
+                        color.alpha = (color.alpha as f64 * coverage).round() as u8;
+                    }
+                }
+            }),
+        }
+    });
+
+
+    if let Some(noise) = instruction.post_clip_noise {
+        traced!("post_clip_noise", {
+            noise.add_noise(&mut noise::OffsetCanvas::new(&mut noise::TransparentCanvas::new(layer_width, &mut new_layer.pixels), layer_origin), rng);
+        });
+    }
+
+    if instruction.opacity != 1. {
+        let opacity = instruction.opacity.clamp(0., 1.);
+        for color in new_layer.pixels.iter_mut() {
+            color.alpha = (color.alpha as f64 * opacity).round() as u8;
+        }
+    }
+
+    // noise after clipping can move colors out of the spans
+    if instruction.post_clip_noise.is_none() {
+        new_layer.spans = spans;
+    }
+    new_layer
+}
+
+/// Calls `fill_row` with the index and pixels of each row of a `width` pixel wide canvas. With the
+/// `rayon` feature the rows are spread across every core.
+fn for_each_row<T: Send>(pixels: &mut [T], width: usize, fill_row: impl Fn(usize, &mut [T]) + Send + Sync) {
+    if width == 0 {
+        return;
+    }
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        pixels.par_chunks_mut(width).enumerate().for_each(|(y, row)| fill_row(y, row));
+    }
+    #[cfg(not(feature = "rayon"))]
+    pixels.chunks_mut(width).enumerate().for_each(|(y, row)| fill_row(y, row));
+}
+
+impl Matte {
+    fn height(&self) -> usize {
+        self.coverage.len() / self.width
+    }
+
+    pub fn get_coverage(&self, x: usize, y: usize) -> u8 {
+        self.coverage[x + y * self.width]
+    }
+
+    pub fn output_to_image(&self, filename: &str) -> Result<(),()> {
+        let image: GrayImage = ImageBuffer::from_raw(
+            self.width.try_into().map_err(|_|())?,
+            self.height().try_into().map_err(|_|())?,
+            self.coverage.clone())
+        .expect("Matte values have a width/height that matches the canvas size");
+
         image.save(filename).map_err(|_|())
     }
 }
 
-impl<R: rand::Rng> Image {
-    pub fn draw_custom(&mut self, instruction: DrawInstruction<R>, rng: &mut R) {
-        let mut new_layer = vec![coloring::TransparentColor::TRANSPARENT; self.canvas.len()];
-        
-        for y in 0..self.canvas_height() {
+/// An image whose pixels can be see-through, for assets that get composited over other artwork later.
+/// It starts out fully transparent and takes the same draw instructions as an `Image`.
+#[derive(Clone)]
+pub struct TransparentImage {
+    canvas_width: usize,
+    canvas: Vec<coloring::TransparentColor>,
+    compositing: coloring::Compositing,
+    dithering: coloring::Dithering,
+    antialiasing: shapes::Antialiasing,
+}
+
+impl TransparentImage {
+    pub fn with_size(width: usize, height: usize) -> Self {
+        TransparentImage {
+            canvas_width: width,
+            canvas: vec![coloring::TransparentColor::TRANSPARENT; width * height],
+            compositing: coloring::Compositing::Srgb,
+            dithering: coloring::Dithering::None,
+            antialiasing: shapes::Antialiasing::None,
+        }
+    }
+
+    /// Like `Image::with_compositing`
+    pub fn with_compositing(self, compositing: coloring::Compositing) -> Self {
+        TransparentImage { compositing, ..self }
+    }
+
+    pub fn set_compositing(&mut self, compositing: coloring::Compositing) {
+        self.compositing = compositing;
+    }
+
+    pub fn compositing(&self) -> coloring::Compositing {
+        self.compositing
+    }
+
+    /// Like `Image::with_dithering`
+    pub fn with_dithering(self, dithering: coloring::Dithering) -> Self {
+        TransparentImage { dithering, ..self }
+    }
+
+    pub fn set_dithering(&mut self, dithering: coloring::Dithering) {
+        self.dithering = dithering;
+    }
+
+    pub fn dithering(&self) -> coloring::Dithering {
+        self.dithering
+    }
+
+    /// Like `Image::with_antialiasing`
+    pub fn with_antialiasing(self, antialiasing: shapes::Antialiasing) -> Self {
+        TransparentImage { antialiasing, ..self }
+    }
+
+    pub fn set_antialiasing(&mut self, antialiasing: shapes::Antialiasing) {
+        self.antialiasing = antialiasing;
+    }
+
+    pub fn antialiasing(&self) -> shapes::Antialiasing {
+        self.antialiasing
+    }
+
+    pub fn width(&self) -> usize {
+        self.canvas_width
+    }
+
+    pub fn height(&self) -> usize {
+        self.canvas.len() / self.canvas_width
+    }
+
+    fn get_index(&self, x: usize, y: usize) -> usize {
+        x + y * self.canvas_width
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> &coloring::TransparentColor {
+        &self.canvas[self.get_index(x, y)]
+    }
+
+    pub fn get_pixel_mut(&mut self, x: usize, y: usize) -> &mut coloring::TransparentColor {
+        let index = self.get_index(x, y);
+        &mut self.canvas[index]
+    }
+
+    pub fn draw_custom<R: rand::Rng>(&mut self, instruction: &DrawInstruction<R>, rng: &mut R) {
+        self.draw_with_settings(instruction.settings(), rng);
+    }
+
+    pub fn draw_custom_with_matte<R: rand::Rng>(&mut self, instruction: &DrawInstruction<R>, rng: &mut R) -> Matte {
+        self.draw_with_settings(instruction.settings(), rng).matte(self.canvas_width, self.height())
+    }
+
+    pub fn draw_styled<R: rand::Rng>(&mut self, clipping_shape: &shapes::Shape, style: &Style<R>, rng: &mut R) {
+        self.draw_with_settings(style.settings(clipping_shape), rng);
+    }
+
+    pub fn draw_styled_with_matte<R: rand::Rng>(&mut self, clipping_shape: &shapes::Shape, style: &Style<R>, rng: &mut R) -> Matte {
+        self.draw_with_settings(style.settings(clipping_shape), rng).matte(self.canvas_width, self.height())
+    }
+
+    fn draw_with_settings<R: rand::Rng>(&mut self, instruction: DrawSettings<'_, R>, rng: &mut R) -> RenderedLayer {
+        let new_layer = render_layer(self.canvas_width, self.height(), &instruction, shapes::Point::ORIGIN, self.dithering, self.antialiasing, rng);
+
+        traced!("composite", {
+            let (blend_mode, compositing) = (instruction.blend_mode, self.compositing);
+            new_layer.composite_onto(&mut self.canvas, self.canvas_width, |colors, canvas_colors| {
+                for (color, canvas_color) in colors.iter().zip(canvas_colors) {
+                    *canvas_color = match color.alpha {
+                        0 => *canvas_color,
+                        u8::MAX if blend_mode == coloring::BlendMode::Normal => *color,
+                        _ => color.blend_on(canvas_color, blend_mode, compositing),
+                    };
+                }
+            });
+        });
+
+        if let Some(noise) = instruction.post_draw_noise {
+            traced!("post_draw_noise", {
+                noise.add_noise(&mut noise::TransparentCanvas::new(self.canvas_width, &mut self.canvas), rng);
+            });
+        }
+
+        new_layer
+    }
+
+    /// Lays the image over a solid background, such as to preview it
+    pub fn flatten_onto(&self, background_color: coloring::SolidColor) -> Image {
+        let mut image = Image::with_size(self.canvas_width, self.height(), background_color)
+            .with_compositing(self.compositing)
+            .with_dithering(self.dithering)
+            .with_antialiasing(self.antialiasing);
+        for (pixel, color) in image.canvas.iter_mut().zip(&self.canvas) {
+            *pixel = color.draw_on_solid_with(pixel, self.compositing);
+        }
+        image
+    }
+
+    fn to_rgba_image(&self) -> Result<RgbaImage, ()> {
+        Ok(ImageBuffer::from_raw(
+            self.canvas_width.try_into().map_err(|_|())?,
+            self.height().try_into().map_err(|_|())?,
+            self.canvas.iter().flat_map(|color| [color.red, color.green, color.blue, color.alpha]).collect())
+        .expect("Image values have a width/height that matches the canvas size"))
+    }
+
+    /// Keeps the transparency for formats that can hold it, like PNG
+    pub fn output_to_image(&self, filename: &str) -> Result<(),()> {
+        self.to_rgba_image()?.save(filename).map_err(|_|())
+    }
+
+    /// Saves the image as a PAM file, which keeps the transparency, without going through the `image` crate
+    pub fn output_pam(&self, filename: &str) -> Result<(),()> {
+        let bytes: Vec<u8> = self.canvas.iter().flat_map(|color| [color.red, color.green, color.blue, color.alpha]).collect();
+        let encoded = export::encode_without_image_crate(&bytes, self.canvas_width, self.height(), 4, export::ExportFormat::Pam)?;
+        std::fs::write(filename, encoded).map_err(|_|())
+    }
+
+    /// Like `Image::output_as`. Fails for formats that can't hold transparency, like JPEG.
+    pub fn output_as(&self, filename: &str, format: export::ExportFormat, options: &export::ExportOptions) -> Result<(),()> {
+        let file = std::io::BufWriter::new(std::fs::File::create(filename).map_err(|_|())?);
+        export::encode(file, self.to_rgba_image()?.as_raw(), self.canvas_width, self.height(), image::ExtendedColorType::Rgba8, format, options)
+    }
+
+    /// Like `Image::encode_to_vec`
+    pub fn encode_to_vec(&self, format: export::ExportFormat) -> Result<Vec<u8>,()> {
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        export::encode(&mut cursor, self.to_rgba_image()?.as_raw(), self.canvas_width, self.height(), image::ExtendedColorType::Rgba8, format, &export::ExportOptions::default())?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Like `Image::write_to`
+    pub fn write_to<W: std::io::Write>(&self, writer: W, format: export::ExportFormat) -> Result<(),()> {
+        let mut writer = writer;
+        writer.write_all(&self.encode_to_vec(format)?).map_err(|_|())
+    }
+}
+
+/// Fully opaque everywhere
+impl Into<TransparentImage> for &Image {
+    fn into(self) -> TransparentImage {
+        TransparentImage {
+            canvas_width: self.canvas_width,
+            canvas: self.canvas.iter().map(|color| (*color).into()).collect(),
+            compositing: self.compositing,
+            dithering: self.dithering,
+            antialiasing: self.antialiasing,
+        }
+    }
+}
+
+/// An image with 16 bits per channel, for smooth gradients that band at 8 bits even with dithering.
+/// It only takes plain colorings, since noise works on 8 bit images.
+#[derive(Clone)]
+pub struct Image16 {
+    canvas_width: usize,
+    canvas: Vec<coloring::SolidColor16>,
+}
+
+impl Image16 {
+    pub fn with_size(width: usize, height: usize, background_color: coloring::SolidColor16) -> Self {
+        Image16 {
+            canvas_width: width,
+            canvas: vec![background_color; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.canvas_width
+    }
+
+    pub fn height(&self) -> usize {
+        self.canvas.len() / self.canvas_width
+    }
+
+    fn get_index(&self, x: usize, y: usize) -> usize {
+        x + y * self.canvas_width
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> &coloring::SolidColor16 {
+        &self.canvas[self.get_index(x, y)]
+    }
+
+    pub fn get_pixel_mut(&mut self, x: usize, y: usize) -> &mut coloring::SolidColor16 {
+        let index = self.get_index(x, y);
+        &mut self.canvas[index]
+    }
+
+    /// Draws `coloring` everywhere inside `clipping_shape`
+    pub fn draw(&mut self, clipping_shape: &shapes::Shape, coloring: &coloring::ColorScheme<coloring::TransparentColor16>) {
+        for y in 0..self.height() {
             for x in 0..self.canvas_width {
                 let point = shapes::Point {x: x as f64, y: y as f64};
-
-                new_layer[self.get_index(x, y)] = instruction.coloring.sample_color(&point);
+                if clipping_shape.contains(&point) {
+                    let index = self.get_index(x, y);
+                    self.canvas[index] = coloring.sample_color(&point).draw_on_solid(&self.canvas[index]);
+                }
             }
         }
+    }
 
-        if let Some(noise) = instruction.pre_clip_noise {
-            noise.add_noise(new_layer, rng);
+    /// Rounds every pixel to the closest 8 bit color, such as to add noise to it
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::with_size(self.canvas_width, self.height(), coloring::SolidColor::BLACK);
+        for (pixel, color) in image.canvas.iter_mut().zip(&self.canvas) {
+            *pixel = (*color).into();
         }
-        
-        for y  in 0..self.canvas_height() {
+        image
+    }
+
+    fn to_rgb16_image(&self) -> Result<ImageBuffer<image::Rgb<u16>, Vec<u16>>, ()> {
+        Ok(ImageBuffer::from_raw(
+            self.canvas_width.try_into().map_err(|_|())?,
+            self.height().try_into().map_err(|_|())?,
+            self.canvas.iter().flat_map(|color| [color.red, color.green, color.blue]).collect())
+        .expect("Image values have a width/height that matches the canvas size"))
+    }
+
+    /// Saves all 16 bits of each channel for formats that can hold them, like PNG
+    pub fn output_to_image(&self, filename: &str) -> Result<(),()> {
+        self.to_rgb16_image()?.save(filename).map_err(|_|())
+    }
+}
+
+impl Into<Image16> for &Image {
+    fn into(self) -> Image16 {
+        Image16 {
+            canvas_width: self.canvas_width,
+            canvas: self.canvas.iter().map(|color| (*color).into()).collect(),
+        }
+    }
+}
+
+/// An image in floating point linear light, so overlapping glows can add up past white without clipping.
+/// Gets tone mapped down to an 8 bit `Image` when it's done.
+#[derive(Clone)]
+pub struct HdrImage {
+    canvas_width: usize,
+    canvas: Vec<coloring::FColor>,
+}
+
+impl HdrImage {
+    pub fn with_size(width: usize, height: usize, background_color: coloring::FColor) -> Self {
+        HdrImage {
+            canvas_width: width,
+            canvas: vec![background_color; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.canvas_width
+    }
+
+    pub fn height(&self) -> usize {
+        self.canvas.len() / self.canvas_width
+    }
+
+    fn get_index(&self, x: usize, y: usize) -> usize {
+        x + y * self.canvas_width
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> &coloring::FColor {
+        &self.canvas[self.get_index(x, y)]
+    }
+
+    pub fn get_pixel_mut(&mut self, x: usize, y: usize) -> &mut coloring::FColor {
+        let index = self.get_index(x, y);
+        &mut self.canvas[index]
+    }
+
+    fn draw_with(&mut self, clipping_shape: &shapes::Shape, coloring: &coloring::ColorScheme<coloring::FColor>, combine: fn(&coloring::FColor, &coloring::FColor) -> coloring::FColor) {
+        for y in 0..self.height() {
             for x in 0..self.canvas_width {
                 let point = shapes::Point {x: x as f64, y: y as f64};
-                
-                // TODO antialiasing
-                if !instruction.clipping_shape.contains(&point){
-                    new_layer[self.get_index(x, y)] = TransparentColor::TRANSPARENT;
+                if clipping_shape.contains(&point) {
+                    let index = self.get_index(x, y);
+                    self.canvas[index] = combine(&coloring.sample_color(&point), &self.canvas[index]);
                 }
             }
         }
+    }
+
+    /// Lays `coloring` over everything inside `clipping_shape`
+    pub fn draw(&mut self, clipping_shape: &shapes::Shape, coloring: &coloring::ColorScheme<coloring::FColor>) {
+        self.draw_with(clipping_shape, coloring, coloring::FColor::draw_on);
+    }
 
+    /// Adds the light of `coloring` to everything inside `clipping_shape`, for glows and bloom
+    pub fn add_light(&mut self, clipping_shape: &shapes::Shape, coloring: &coloring::ColorScheme<coloring::FColor>) {
+        self.draw_with(clipping_shape, coloring, coloring::FColor::add_to);
+    }
 
-        if let Some(noise) = instruction.post_clip_noise {
-            noise.add_noise(self, rng);
+    /// Brings every pixel down to 8 bits, after multiplying its brightness by `exposure`
+    pub fn tone_map(&self, tone_map: coloring::ToneMap, exposure: f64) -> Image {
+        let mut image = Image::with_size(self.canvas_width, self.height(), coloring::SolidColor::BLACK);
+        for (pixel, color) in image.canvas.iter_mut().zip(&self.canvas) {
+            *pixel = color.tone_map(tone_map, exposure).draw_on_solid(&coloring::SolidColor::BLACK);
         }
+        image
+    }
 
-        for (index, canvas_color) in self.canvas.iter_mut().enumerate() {
-            *canvas_color = new_layer[index].draw_on_solid(canvas_color);
+    pub fn output_to_image(&self, filename: &str, tone_map: coloring::ToneMap) -> Result<(),()> {
+        self.tone_map(tone_map, 1.).output_to_image(filename)
+    }
+}
+
+impl Into<HdrImage> for &Image {
+    fn into(self) -> HdrImage {
+        HdrImage {
+            canvas_width: self.canvas_width,
+            canvas: self.canvas.iter().map(|color| (*color).into()).collect(),
         }
-        
+    }
+}
 
-        if let Some(noise) = instruction.post_draw_noise {
-            noise.add_noise(self, rng);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coloring::SolidColor;
+    use shapes::{Point, Rect};
+
+    #[test]
+    fn crop_keeps_every_pixel_inside_the_rect() {
+        for rect in [
+            Rect::from_points(&Point { x: 2., y: 3. }, &Point { x: 6., y: 5. }),
+            Rect::from_points(&Point { x: 1.5, y: 2.5 }, &Point { x: 4.2, y: 4.9 }),
+            Rect::from_points(&Point { x: -3., y: 4. }, &Point { x: 12., y: 20. }),
+        ] {
+            let mut image = Image::with_size(10, 8, SolidColor::BLACK);
+            for y in 0..image.height() {
+                for x in 0..image.width() {
+                    if rect.contains(&Point { x: x as f64, y: y as f64 }) {
+                        *image.get_pixel_mut(x, y) = SolidColor::WHITE;
+                    }
+                }
+            }
+            let covered = image.canvas.iter().filter(|color| **color == SolidColor::WHITE).count();
+
+            let cropped = image.crop(&rect).unwrap();
+            assert_eq!(cropped.width() * cropped.height(), covered, "{rect:?}");
+            assert!(cropped.canvas.iter().all(|color| *color == SolidColor::WHITE), "{rect:?}");
         }
-        
+
+        let image = Image::with_size(10, 8, SolidColor::BLACK);
+        assert!(image.crop(&Rect::from_points(&Point { x: 2.2, y: 1. }, &Point { x: 2.8, y: 5. })).is_err());
     }
 
+    #[test]
+    fn small_shapes_only_render_their_bounding_rows() {
+        let mut image = Image::with_size(200, 150, SolidColor::BLACK);
+        let instruction: DrawInstruction<rand::rngs::StdRng> = DrawInstruction {
+            pre_clip_noise: None,
+            clipping_shape: shapes::Ellipse::circle(Point { x: 50., y: 40. }, 5.).into(),
+            coloring: coloring::ColorScheme::Solid(SolidColor::WHITE.into()),
+            post_clip_noise: None,
+            post_draw_noise: None,
+            opacity: 1.,
+            blend_mode: coloring::BlendMode::Normal,
+            dithering: None,
+        };
+        let mut rng = rand::SeedableRng::seed_from_u64(0);
+
+        // a pixel to spare on each side of the circle's bounding rect
+        let layer = image.draw_with_settings(instruction.settings(), Point::ORIGIN, &mut rng);
+        assert_eq!((layer.left, layer.top, layer.width, layer.height()), (44, 34, 13, 13));
+
+        let matte = image.draw_custom_with_matte(&instruction, &mut rng);
+        assert_eq!((matte.get_coverage(50, 40), matte.get_coverage(50, 30), matte.get_coverage(199, 149)), (u8::MAX, 0, 0));
+    }
 }
@@ -0,0 +1,163 @@
+use crate::coloring::{BlendMode, TransparentColor};
+use crate::{shapes, DrawInstruction, Image, Style, TransparentImage};
+
+/// A see-through layer of a `LayeredImage`, drawn on by name and composited with its own opacity and
+/// blend mode only when the image is flattened
+#[derive(Clone)]
+pub struct ImageLayer {
+    name: String,
+    image: TransparentImage,
+    opacity: f64,
+    blend_mode: BlendMode,
+    visible: bool,
+}
+
+impl ImageLayer {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn image(&self) -> &TransparentImage {
+        &self.image
+    }
+
+    pub fn image_mut(&mut self) -> &mut TransparentImage {
+        &mut self.image
+    }
+
+    pub fn opacity(&self) -> f64 {
+        self.opacity
+    }
+
+    /// From 0 for invisible to 1 for unchanged
+    pub fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = opacity.clamp(0., 1.);
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Hidden layers keep what's drawn on them but are left out when flattening
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Makes every pixel of the layer transparent again, such as to redraw it after a change
+    pub fn clear(&mut self) {
+        self.image.canvas.fill(TransparentColor::TRANSPARENT);
+    }
+}
+
+/// A background image with named layers stacked on top of it. Nothing is composited until `flatten`,
+/// so a layer can be cleared and redrawn, restyled or hidden without touching the others.
+#[derive(Clone)]
+pub struct LayeredImage {
+    background: Image,
+    // bottom to top
+    layers: Vec<ImageLayer>,
+}
+
+impl LayeredImage {
+    pub fn new(background: Image) -> Self {
+        LayeredImage {
+            background,
+            layers: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.background.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.background.height()
+    }
+
+    pub fn background(&self) -> &Image {
+        &self.background
+    }
+
+    pub fn background_mut(&mut self) -> &mut Image {
+        &mut self.background
+    }
+
+    /// Adds a transparent layer on top of the others, drawing with the background's compositing,
+    /// dithering and antialiasing. Fails if there's already a layer called `name`.
+    pub fn add_layer(&mut self, name: &str) -> Result<&mut ImageLayer, ()> {
+        if self.layer(name).is_some() {
+            return Err(());
+        }
+
+        self.layers.push(ImageLayer {
+            name: name.to_owned(),
+            image: TransparentImage::with_size(self.width(), self.height())
+                .with_compositing(self.background.compositing())
+                .with_dithering(self.background.dithering())
+                .with_antialiasing(self.background.antialiasing()),
+            opacity: 1.,
+            blend_mode: BlendMode::Normal,
+            visible: true,
+        });
+        Ok(self.layers.last_mut().expect("A layer was just added"))
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&ImageLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut ImageLayer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    pub fn remove_layer(&mut self, name: &str) -> Option<ImageLayer> {
+        let index = self.layers.iter().position(|layer| layer.name == name)?;
+        Some(self.layers.remove(index))
+    }
+
+    /// Every layer from the bottom up
+    pub fn layers(&self) -> &[ImageLayer] {
+        &self.layers
+    }
+
+    /// Draws onto the layer called `name`. Fails if there isn't one.
+    pub fn draw_custom<R: rand::Rng>(&mut self, name: &str, instruction: &DrawInstruction<R>, rng: &mut R) -> Result<(), ()> {
+        self.layer_mut(name).ok_or(())?.image.draw_custom(instruction, rng);
+        Ok(())
+    }
+
+    /// Like `draw_custom`, but with a shared style
+    pub fn draw_styled<R: rand::Rng>(&mut self, name: &str, clipping_shape: &shapes::Shape, style: &Style<R>, rng: &mut R) -> Result<(), ()> {
+        self.layer_mut(name).ok_or(())?.image.draw_styled(clipping_shape, style, rng);
+        Ok(())
+    }
+
+    /// Composites the visible layers onto a copy of the background, bottom to top
+    pub fn flatten(&self) -> Image {
+        let mut image = self.background.clone();
+        for layer in self.layers.iter().filter(|layer| layer.visible && layer.opacity > 0.) {
+            for (canvas_color, color) in image.canvas.iter_mut().zip(&layer.image.canvas) {
+                let color = TransparentColor {
+                    alpha: (color.alpha as f64 * layer.opacity).round() as u8,
+                    ..*color
+                };
+                *canvas_color = color.blend_on_solid(canvas_color, layer.blend_mode, image.compositing);
+            }
+        }
+        image
+    }
+}
+
+impl Into<LayeredImage> for Image {
+    fn into(self) -> LayeredImage {
+        LayeredImage::new(self)
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// Caps on how much work a scene is allowed to cause, for when scenes come from people you don't trust.
+/// `None` means unlimited.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Limits {
+    pub max_canvas_pixels: Option<usize>,
+    pub max_instructions: Option<usize>,
+    pub max_expression_depth: Option<usize>,
+    /// Checked between instructions and between the rows each instruction colors in, so a render can
+    /// run over by about one row plus any noise the instruction adds
+    pub max_render_time: Option<Duration>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LimitExceeded {
+    CanvasPixels { limit: usize, requested: usize },
+    Instructions { limit: usize },
+    ExpressionDepth { limit: usize },
+    RenderTime { limit: Duration },
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::CanvasPixels { limit, requested } => write!(f, "The canvas has {requested} pixels, but at most {limit} are allowed"),
+            LimitExceeded::Instructions { limit } => write!(f, "More than {limit} draw instructions"),
+            LimitExceeded::ExpressionDepth { limit } => write!(f, "Expressions are nested more than {limit} levels deep"),
+            LimitExceeded::RenderTime { limit } => write!(f, "Rendering took longer than {limit:?}"),
+        }
+    }
+}
+
+impl Limits {
+    pub const UNLIMITED: Limits = Limits {
+        max_canvas_pixels: None,
+        max_instructions: None,
+        max_expression_depth: None,
+        max_render_time: None,
+    };
+
+    /// Reads limits from a flat JSON object such as
+    /// `{"max_canvas_pixels":4000000,"max_instructions":500,"max_expression_depth":32,"max_render_time_ms":2000}`.
+    /// Missing or `null` fields are unlimited. Only this flat shape is read: values have to be whole
+    /// numbers or `null`, and anything else, such as strings, nested objects, repeated fields or fields
+    /// it doesn't know, fails.
+    pub fn from_json(json: &str) -> Result<Limits, ()> {
+        let fields = json.trim().strip_prefix('{').and_then(|json| json.strip_suffix('}')).ok_or(())?;
+
+        let mut limits = Limits::UNLIMITED;
+        if fields.trim().is_empty() {
+            return Ok(limits);
+        }
+        let mut seen = Vec::new();
+        for field in fields.split(',') {
+            let (name, value) = field.split_once(':').ok_or(())?;
+            let name = name.trim().strip_prefix('"').and_then(|name| name.strip_suffix('"')).ok_or(())?;
+            let value = match value.trim() {
+                "null" => None,
+                // `parse` would also take a leading +, which JSON doesn't allow
+                value if value.bytes().all(|byte| byte.is_ascii_digit()) => Some(value.parse::<usize>().map_err(|_|())?),
+                _ => return Err(()),
+            };
+            if seen.contains(&name) {
+                return Err(());
+            }
+            seen.push(name);
+
+            match name {
+                "max_canvas_pixels" => limits.max_canvas_pixels = value,
+                "max_instructions" => limits.max_instructions = value,
+                "max_expression_depth" => limits.max_expression_depth = value,
+                "max_render_time_ms" => limits.max_render_time = value.map(|millis| Duration::from_millis(millis as u64)),
+                _ => return Err(()),
+            }
+        }
+        Ok(limits)
+    }
+
+    pub fn check_canvas(&self, width: usize, height: usize) -> Result<(), LimitExceeded> {
+        match self.max_canvas_pixels {
+            Some(limit) if width.saturating_mul(height) > limit => Err(LimitExceeded::CanvasPixels {
+                limit,
+                requested: width.saturating_mul(height),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_instructions(&self, instruction_count: usize) -> Result<(), LimitExceeded> {
+        match self.max_instructions {
+            Some(limit) if instruction_count > limit => Err(LimitExceeded::Instructions { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_expression_depth(&self, depth: usize) -> Result<(), LimitExceeded> {
+        match self.max_expression_depth {
+            Some(limit) if depth > limit => Err(LimitExceeded::ExpressionDepth { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_render_time(&self, elapsed: Duration) -> Result<(), LimitExceeded> {
+        match self.max_render_time {
+            Some(limit) if elapsed > limit => Err(LimitExceeded::RenderTime { limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_field() {
+        let limits = Limits::from_json(r#"{"max_canvas_pixels":4000000,"max_instructions":500,"max_expression_depth":32,"max_render_time_ms":2000}"#);
+        assert_eq!(limits, Ok(Limits {
+            max_canvas_pixels: Some(4_000_000),
+            max_instructions: Some(500),
+            max_expression_depth: Some(32),
+            max_render_time: Some(Duration::from_millis(2000)),
+        }));
+    }
+
+    #[test]
+    fn missing_and_null_fields_are_unlimited() {
+        assert_eq!(Limits::from_json("{}"), Ok(Limits::UNLIMITED));
+        assert_eq!(Limits::from_json(" { \n } "), Ok(Limits::UNLIMITED));
+        assert_eq!(
+            Limits::from_json(r#"{"max_instructions": null, "max_expression_depth": 8}"#),
+            Ok(Limits { max_expression_depth: Some(8), ..Limits::UNLIMITED }),
+        );
+    }
+
+    #[test]
+    fn whitespace_between_tokens_is_allowed() {
+        let json = "\n{\n\t\"max_instructions\" :  12 ,\r\n  \"max_render_time_ms\":\t5\n}\n";
+        assert_eq!(
+            Limits::from_json(json),
+            Ok(Limits { max_instructions: Some(12), max_render_time: Some(Duration::from_millis(5)), ..Limits::UNLIMITED }),
+        );
+    }
+
+    #[test]
+    fn anything_but_the_flat_shape_is_an_error() {
+        for json in [
+            "",
+            "null",
+            "[]",
+            r#"{"max_instructions":5"#,
+            r#"{"max_instructions":5,}"#,
+            r#"{"max_instructions":5}}"#,
+            r#"{"max_instructions":"5"}"#,
+            r#"{"max_instructions":"1,2"}"#,
+            r#"{"max_instructions":{"value":5}}"#,
+            r#"{"max_instructions":[1,2]}"#,
+            r#"{"max_instructions":-5}"#,
+            r#"{"max_instructions":+5}"#,
+            r#"{"max_instructions":5.5}"#,
+            r#"{"max_instructions":1e3}"#,
+            r#"{"max_instructions":}"#,
+            r#"{"max_instructions":99999999999999999999999}"#,
+            r#"{max_instructions:5}"#,
+            r#"{"max_instructions":5,"max_instructions":6}"#,
+            r#"{"max_colors":5}"#,
+            r#"{"max_instructions,max_expression_depth":5}"#,
+        ] {
+            assert_eq!(Limits::from_json(json), Err(()), "{json}");
+        }
+    }
+}
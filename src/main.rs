@@ -1,14 +1,20 @@
-use image_gen::{coloring::{LinearGradient, SolidColor }, shapes::{Area, Ellipse, Point, Rect}, DrawInstruction, Image};
+use image_gen::{coloring::{BlendMode, LinearGradient, SolidColor }, shapes::{Area, Ellipse, Point, Rect}, DrawInstruction, Image};
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|arg| arg == "fmt") {
+        std::process::exit(format_files(&args[2..]));
+    }
+
     //image_gen::read_noisy_file("./Sample.noisy")
     let mut image = Image::with_size(2560,1440,SolidColor::BLACK);
+    let mut rng = rand::rng();
     
     let origin = Point::ORIGIN;
     let far_corner = Point {x: 2560.0, y: 1440.0};
     let screen_area = Area::bounding_area(&origin, &far_corner);
     
-    image.draw_custom(DrawInstruction{
+    image.draw_custom(&DrawInstruction{
         pre_clip_noise: None,
         clipping_shape: Rect::from_points(&origin, &far_corner).into(),
         coloring: LinearGradient::with_poles(
@@ -16,15 +22,16 @@ fn main() {
             (far_corner, SolidColor {red: 6, green: 167, blue: 125 }.into())
         ).into(),
         post_clip_noise: None,
-    });
+        post_draw_noise: None,
+        opacity: 1.,
+        blend_mode: BlendMode::Normal,
+        dithering: None,
+    }, &mut rng);
     
-    let center = Point {
-        x: (far_corner.x + origin.x) / 2.,
-        y: (far_corner.y + origin.y) / 2.,
-    };
+    let center = origin.midpoint(&far_corner);
     let radius = 3. * f64::min(screen_area.height, screen_area.width) / 8.;
     
-    image.draw_custom(DrawInstruction { 
+    image.draw_custom(&DrawInstruction { 
         pre_clip_noise: None,
         clipping_shape: Ellipse::circle(center, radius).into(),
         coloring: LinearGradient::with_poles(
@@ -32,7 +39,55 @@ fn main() {
             (far_corner, SolidColor {red: 180, green: 121, blue: 6 }.into())
         ).into(),
         post_clip_noise: None,
-    });
+        post_draw_noise: None,
+        opacity: 1.,
+        blend_mode: BlendMode::Normal,
+        dithering: None,
+    }, &mut rng);
     
+    if std::env::args().any(|arg| arg == "--preview-ansi") {
+        print!("{}", image.render_ansi(80));
+    }
+    if std::env::args().any(|arg| arg == "--preview-terminal") {
+        // iTerm2's protocol sends the full image, everything else gets a downscaled Sixel
+        let supports_iterm2 = matches!(std::env::var("TERM_PROGRAM").as_deref(), Ok("iTerm.app" | "WezTerm"));
+        match supports_iterm2.then(|| image.render_iterm2()) {
+            Some(Ok(preview)) => print!("{preview}"),
+            _ => print!("{}", image.render_sixel(800)),
+        }
+    }
+
     let _ = image.output_to_image("./output.png");
 }
+
+/// `fmt [--check] files...` rewrites each file in the standard layout. With `--check` it only
+/// lists the files that would change. Returns the exit code.
+fn format_files(args: &[String]) -> i32 {
+    let check_only = args.iter().any(|arg| arg == "--check");
+    let mut exit_code = 0;
+
+    for filename in args.iter().filter(|arg| *arg != "--check") {
+        let result = std::fs::read_to_string(filename)
+            .map_err(image_gen::noisy::ReadFileError::from)
+            .and_then(|source| Ok((image_gen::noisy::format_noisy(&source)?, source)));
+
+        match result {
+            Ok((formatted, source)) if formatted != source => {
+                if check_only {
+                    println!("{filename} is not formatted");
+                    exit_code = 1;
+                } else if let Err(error) = std::fs::write(filename, formatted) {
+                    eprintln!("{filename}: {error}");
+                    exit_code = 1;
+                }
+            },
+            Ok(_) => {},
+            Err(error) => {
+                eprintln!("{filename}: {error}");
+                exit_code = 1;
+            },
+        }
+    }
+
+    exit_code
+}
@@ -1,16 +1,291 @@
 
+mod blue_noise;
+mod blur;
+mod convolve;
+mod damage;
+mod displacement;
+mod fields;
+mod gaussian;
+mod glitch;
+mod poisson;
+mod sort;
+
 use std::marker::PhantomData;
 
-use crate::{shapes::{CheckInside, Point, Rect}, Image};
+use rand_distr::{Distribution, Exp, Normal};
+
+use crate::{coloring::TransparentColor, shapes::{CheckInside, Point, Rect}, Image};
+
+pub use blue_noise::PoissonDiskSampler;
+pub use blur::GaussianBlur;
+pub use convolve::Convolve;
+pub use damage::FilmDamage;
+pub use displacement::DisplacementNoise;
+pub use fields::{BrightnessNoise, DomainWarp, Fbm, NoiseField, PerlinNoise, ScalarNoise, SimplexNoise, ValueNoise};
+pub use gaussian::GaussianNoise;
+pub use glitch::GlitchShift;
+pub use poisson::PoissonNoise;
+pub use sort::{PixelSort, SortDirection};
 
 
 
 pub trait Noise<R: rand::Rng>{
-    fn add_noise(&self, image: &mut Image, rng: &mut R);
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R);
+}
+
+/// Several noises added one after the other, so a single noise slot can hold a whole chain of effects
+pub struct NoiseStack<R: rand::Rng>(pub Vec<Box<dyn Noise<R>>>);
+
+impl<R: rand::Rng> NoiseStack<R> {
+    pub fn new() -> Self {
+        NoiseStack(Vec::new())
+    }
+
+    /// Adds `noise` after everything already in the stack
+    pub fn with(mut self, noise: impl Noise<R> + 'static) -> Self {
+        self.0.push(Box::new(noise));
+        self
+    }
+}
+
+impl<R: rand::Rng> Default for NoiseStack<R> {
+    fn default() -> Self {
+        NoiseStack::new()
+    }
+}
+
+impl<R: rand::Rng> Noise<R> for NoiseStack<R> {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R) {
+        for noise in &self.0 {
+            noise.add_noise(canvas, rng);
+        }
+    }
+}
+
+/// Anything noise can be added to: the solid canvas of an `Image`, or the transparent layer a draw
+/// instruction colors in before it's clipped. Pixels always come out as `TransparentColor`s, and on
+/// solid canvases they're fully opaque and any alpha put back is ignored.
+pub trait NoiseCanvas {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn get(&self, x: usize, y: usize) -> TransparentColor;
+    fn set(&mut self, x: usize, y: usize, color: TransparentColor);
+
+    /// Where the top left pixel is on the whole image, for noise that depends on where pixels are, so
+    /// tiles rendered separately still line up
+    fn origin(&self) -> Point {
+        Point::ORIGIN
+    }
+
+    fn swap(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
+        let color1 = self.get(x1, y1);
+        self.set(x1, y1, self.get(x2, y2));
+        self.set(x2, y2, color1);
+    }
+
+    /// Every pixel in row `y`, left to right
+    fn row(&self, y: usize) -> Vec<TransparentColor> {
+        (0..self.width()).map(|x| self.get(x, y)).collect()
+    }
+
+    fn set_row(&mut self, y: usize, colors: &[TransparentColor]) {
+        for (x, color) in colors.iter().enumerate().take(self.width()) {
+            self.set(x, y, *color);
+        }
+    }
+
+    /// Every pixel in column `x`, top to bottom
+    fn column(&self, x: usize) -> Vec<TransparentColor> {
+        (0..self.height()).map(|y| self.get(x, y)).collect()
+    }
+
+    fn set_column(&mut self, x: usize, colors: &[TransparentColor]) {
+        for (y, color) in colors.iter().enumerate().take(self.height()) {
+            self.set(x, y, *color);
+        }
+    }
+}
+
+impl NoiseCanvas for Image {
+    fn width(&self) -> usize {
+        self.width()
+    }
+
+    fn height(&self) -> usize {
+        self.height()
+    }
+
+    fn get(&self, x: usize, y: usize) -> TransparentColor {
+        (*self.get_pixel(x, y)).into()
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: TransparentColor) {
+        *self.get_pixel_mut(x, y) = color.as_solid();
+    }
+
+    fn swap(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
+        self.swap_pixels(x1, y1, x2, y2);
+    }
+}
+
+/// A row by row grid of transparent pixels, such as a draw instruction's layer before it's clipped
+pub struct TransparentCanvas<'a> {
+    width: usize,
+    pixels: &'a mut [TransparentColor],
+}
+
+impl<'a> TransparentCanvas<'a> {
+    pub fn new(width: usize, pixels: &'a mut [TransparentColor]) -> Self {
+        if width == 0 || !pixels.len().is_multiple_of(width) {
+            panic!("Canvas pixels must fill whole rows");
+        }
+
+        TransparentCanvas { width, pixels }
+    }
+}
+
+impl NoiseCanvas for TransparentCanvas<'_> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.pixels.len() / self.width
+    }
+
+    fn get(&self, x: usize, y: usize) -> TransparentColor {
+        self.pixels[x + y * self.width]
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: TransparentColor) {
+        self.pixels[x + y * self.width] = color;
+    }
+
+    fn swap(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
+        self.pixels.swap(x1 + y1 * self.width, x2 + y2 * self.width);
+    }
+}
+
+/// Another canvas that's part of a bigger image, with its top left pixel at `origin` on that image
+pub struct OffsetCanvas<'a> {
+    canvas: &'a mut dyn NoiseCanvas,
+    origin: Point,
+}
+
+impl<'a> OffsetCanvas<'a> {
+    pub fn new(canvas: &'a mut dyn NoiseCanvas, origin: Point) -> Self {
+        OffsetCanvas { canvas, origin }
+    }
+}
+
+impl NoiseCanvas for OffsetCanvas<'_> {
+    fn width(&self) -> usize {
+        self.canvas.width()
+    }
+
+    fn height(&self) -> usize {
+        self.canvas.height()
+    }
+
+    fn get(&self, x: usize, y: usize) -> TransparentColor {
+        self.canvas.get(x, y)
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: TransparentColor) {
+        self.canvas.set(x, y, color);
+    }
+
+    fn origin(&self) -> Point {
+        self.canvas.origin() + self.origin
+    }
+
+    fn swap(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
+        self.canvas.swap(x1, y1, x2, y2);
+    }
 }
 
+/// Picks random points for noises that work on a few pixels at a time
 pub trait PointSampler<R: rand::Rng>{
-    fn sample(rng: &mut R) -> Point;
+    fn sample(&self, rng: &mut R) -> Point;
+}
+
+/// Points spread evenly over a rect
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UniformSampler {
+    bounds: Rect,
+}
+
+impl UniformSampler {
+    pub fn new(bounds: Rect) -> Self {
+        UniformSampler { bounds }
+    }
+}
+
+impl<R: rand::Rng> PointSampler<R> for UniformSampler {
+    fn sample(&self, rng: &mut R) -> Point {
+        let (min, max) = (self.bounds.min_point(), self.bounds.max_point());
+        Point {
+            x: if min.x < max.x { rng.random_range(min.x..max.x) } else { min.x },
+            y: if min.y < max.y { rng.random_range(min.y..max.y) } else { min.y },
+        }
+    }
+}
+
+/// Points bunched up around the center of a rect in a bell curve
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NormalSampler {
+    center: Point,
+    distribution: Normal<f64>,
+}
+
+impl NormalSampler {
+    /// `std_dev` is in pixels. About two thirds of the points land within that distance of the center on each axis.
+    pub fn new(bounds: Rect, std_dev: f64) -> Self {
+        NormalSampler {
+            center: bounds.center(),
+            distribution: Normal::new(0., std_dev).expect("Standard deviation must be finite and not negative"),
+        }
+    }
+}
+
+impl<R: rand::Rng> PointSampler<R> for NormalSampler {
+    fn sample(&self, rng: &mut R) -> Point {
+        Point {
+            x: self.center.x + self.distribution.sample(rng),
+            y: self.center.y + self.distribution.sample(rng),
+        }
+    }
+}
+
+/// Points that fall off exponentially from the center of a rect on each axis, so they're more tightly
+/// packed in the middle than with `NormalSampler` but also stray farther out
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ExponentialSampler {
+    center: Point,
+    distribution: Exp<f64>,
+}
+
+impl ExponentialSampler {
+    /// `mean_distance` is in pixels, the average distance from the center on each axis
+    pub fn new(bounds: Rect, mean_distance: f64) -> Self {
+        ExponentialSampler {
+            center: bounds.center(),
+            distribution: Exp::new(1. / mean_distance).expect("Mean distance must be positive"),
+        }
+    }
+}
+
+impl<R: rand::Rng> PointSampler<R> for ExponentialSampler {
+    fn sample(&self, rng: &mut R) -> Point {
+        let mut offset = || {
+            let distance = self.distribution.sample(rng);
+            if rng.random() { distance } else { -distance }
+        };
+        Point {
+            x: self.center.x + offset(),
+            y: self.center.y + offset(),
+        }
+    }
 }
 
 pub struct NoiseTypes<R: rand::Rng,N: PointSampler<R>> {
@@ -20,68 +295,424 @@ pub struct NoiseTypes<R: rand::Rng,N: PointSampler<R>> {
 }
 
 impl<R: rand::Rng, N: PointSampler<R>> Noise<R> for NoiseTypes<R, N> {
-    fn add_noise(&self, image: &mut Image, rng: &mut R) {
-        self.inner_add_noise(image, rng);
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R) {
+        self.inner_add_noise(canvas, rng);
     }
 }
 
 enum NoisingBehavior {
     BoundedNoise(BoundedNoise),
+    LocalSwap(LocalSwapNoise),
+    DirectionalSmear(DirectionalSmear),
 }
 
-impl<R, N> NoiseTypes<R, N> {
-    fn inner_add_noise(&self, image: &mut Image, rng: &mut R)  {
-        let mut sample_point = ||self.sampler.sample(rng);
+impl<R: rand::Rng, N: PointSampler<R>> NoiseTypes<R, N> {
+    fn inner_add_noise(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R)  {
         match &self.noising_behavior {
-            NoisingBehavior::BoundedNoise(bounded_noise) => bounded_noise.add_noise(image, &mut self.sampler),
+            NoisingBehavior::BoundedNoise(bounded_noise) => bounded_noise.add_noise(canvas, &mut || self.sampler.sample(rng)),
+            NoisingBehavior::LocalSwap(local_swap) => local_swap.add_noise(canvas, &self.sampler, rng),
+            NoisingBehavior::DirectionalSmear(smear) => smear.add_noise(canvas, &self.sampler, rng),
         }       
     }
 }
 
+/// How many swaps a noise makes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NoiseAmount {
+    /// Swaps for every pixel of the noise's bounds that's on the canvas, so 0.1 moves about a fifth of
+    /// them however big the bounds are
+    Density(f64),
+    /// Exactly this many swaps, however big the bounds are
+    Count(usize),
+}
+
+impl Into<NoiseAmount> for f64 {
+    fn into(self) -> NoiseAmount {
+        NoiseAmount::Density(self)
+    }
+}
+
+impl Into<NoiseAmount> for usize {
+    fn into(self) -> NoiseAmount {
+        NoiseAmount::Count(self)
+    }
+}
+
+impl NoiseAmount {
+    fn iterations(&self, bounds: &Rect, canvas: &dyn NoiseCanvas) -> usize {
+        match self {
+            NoiseAmount::Density(density) => {
+                let canvas_rect = Rect::from_points(&Point { x: 0., y: 0. }, &Point { x: canvas.width() as f64, y: canvas.height() as f64 });
+                let area = bounds.intersection(&canvas_rect).map_or(0., |overlap| overlap.area());
+                (area * density) as usize
+            }
+            NoiseAmount::Count(count) => *count,
+        }
+    }
+}
+
 pub struct BoundedNoise {
     bounds: Rect,    
-    swap_density: f64,
+    amount: NoiseAmount,
 }
 
 impl BoundedNoise {
-    fn add_noise(&self, image: &mut Image, sample_point: &mut dyn FnMut() -> Point) {
-        
-        let total_iters = image.canvas_width as f64 * image.canvas_height() as f64 * self.swap_density;
-        
-        for _ in 0..(total_iters as usize){
-            let point1 = self.sample_bounded_point(sample_point);
-            let point2 = self.sample_bounded_point(sample_point);
+    /// Swaps pairs of pixels inside `bounds`, picked by `sampler`. `amount` is either a density, such as
+    /// 0.1 swaps for every pixel inside `bounds`, or a count, such as 500 swaps.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<R: rand::Rng, N: PointSampler<R>>(sampler: N, bounds: Rect, amount: impl Into<NoiseAmount>) -> NoiseTypes<R, N> {
+        NoiseTypes {
+            sampler,
+            noising_behavior: NoisingBehavior::BoundedNoise(BoundedNoise { 
+                bounds,
+                amount: amount.into(),
+            }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Swaps pixels anywhere inside `bounds` equally
+    pub fn uniform<R: rand::Rng>(bounds: Rect, amount: impl Into<NoiseAmount>) -> NoiseTypes<R, UniformSampler> {
+        BoundedNoise::new(UniformSampler::new(bounds), bounds, amount)
+    }
+
+    /// Swaps pixels mostly near the center of `bounds`, fading out in a bell curve
+    pub fn normal<R: rand::Rng>(bounds: Rect, std_dev: f64, amount: impl Into<NoiseAmount>) -> NoiseTypes<R, NormalSampler> {
+        BoundedNoise::new(NormalSampler::new(bounds, std_dev), bounds, amount)
+    }
+
+    /// Swaps pixels mostly right around the center of `bounds`, with a few strays farther out
+    pub fn exponential<R: rand::Rng>(bounds: Rect, mean_distance: f64, amount: impl Into<NoiseAmount>) -> NoiseTypes<R, ExponentialSampler> {
+        BoundedNoise::new(ExponentialSampler::new(bounds, mean_distance), bounds, amount)
+    }
+
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, sample_point: &mut dyn FnMut() -> Point) {
+        for _ in 0..self.amount.iterations(&self.bounds, canvas) {
+            let (Some(point1), Some(point2)) = (self.sample_bounded_point(canvas, sample_point), self.sample_bounded_point(canvas, sample_point)) else {
+                continue;
+            };
             
-            image.swap_pixels(point1.x as usize, point1.y as usize, point2.x as usize, point2.y as usize);
+            canvas.swap(point1.x as usize, point1.y as usize, point2.x as usize, point2.y as usize);
         }
     }
     
-    fn sample_bounded_point(&self, sample_point: &mut dyn FnMut() -> Point) -> Point {
-        const MAX_RETRIES: usize = 200;
-        
-        let max_bound_point = self.bounds.max_point();
-        let random_point = sample_point();
-        for _ in 0..MAX_RETRIES {
-            if self.bounds.contains(&random_point) && random_point.x != max_bound_point.x && random_point.y != max_bound_point.y {
-                return random_point;
-            }
+    /// A point inside both the bounds and the canvas, or nothing if the sampler keeps missing
+    fn sample_bounded_point(&self, canvas: &dyn NoiseCanvas, sample_point: &mut dyn FnMut() -> Point) -> Option<Point> {
+        sample_swappable_point(&self.bounds, canvas, sample_point)
+    }
+}
+
+fn sample_swappable_point(bounds: &Rect, canvas: &dyn NoiseCanvas, sample_point: &mut dyn FnMut() -> Point) -> Option<Point> {
+    const MAX_RETRIES: usize = 200;
+
+    (0..MAX_RETRIES).map(|_| sample_point()).find(|point| is_swappable(bounds, canvas, point))
+}
+
+/// Whether `point` is inside both `bounds` and `canvas`
+fn is_swappable(bounds: &Rect, canvas: &dyn NoiseCanvas, point: &Point) -> bool {
+    let max_bound_point = bounds.max_point();
+    bounds.contains(point) && point.x != max_bound_point.x && point.y != max_bound_point.y
+        && point.x >= 0. && point.y >= 0. && point.x < canvas.width() as f64 && point.y < canvas.height() as f64
+}
+
+/// Swaps each point `sampler` picks with a partner `partner_offset` away, skipping partners that land
+/// outside the bounds or the canvas
+fn swap_with_partners<R: rand::Rng>(
+    bounds: &Rect,
+    amount: NoiseAmount,
+    canvas: &mut dyn NoiseCanvas,
+    sampler: &dyn PointSampler<R>,
+    rng: &mut R,
+    partner_offset: impl Fn(&mut R) -> (f64, f64),
+) {
+    for _ in 0..amount.iterations(bounds, canvas) {
+        let Some(point1) = sample_swappable_point(bounds, canvas, &mut || sampler.sample(rng)) else {
+            continue;
+        };
+        let (dx, dy) = partner_offset(rng);
+        let point2 = Point { x: point1.x + dx, y: point1.y + dy };
+        if is_swappable(bounds, canvas, &point2) {
+            canvas.swap(point1.x as usize, point1.y as usize, point2.x as usize, point2.y as usize);
         }
-        return random_point;
     }
 }
 
-impl<D: rand_distr::Distribution<f64>, R: rand::Rng> BoundedNoise {
-    fn new(distr: D, bounds: Rect, swap_density: f64) -> NoiseTypes<R> {
+/// Like `BoundedNoise`, but each pixel only swaps with one at most `max_radius` pixels away, which
+/// scrambles the image locally without moving colors far
+pub struct LocalSwapNoise {
+    bounds: Rect,
+    amount: NoiseAmount,
+    max_radius: f64,
+}
 
+impl LocalSwapNoise {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<R: rand::Rng, N: PointSampler<R>>(sampler: N, bounds: Rect, amount: impl Into<NoiseAmount>, max_radius: f64) -> NoiseTypes<R, N> {
         NoiseTypes {
-            sample_point: Box::new(move |r: &mut R| Point {
-                x: distr.sample(r),
-                y: distr.sample(r),
+            sampler,
+            noising_behavior: NoisingBehavior::LocalSwap(LocalSwapNoise {
+                bounds,
+                amount: amount.into(),
+                max_radius,
             }),
-            noising_behavior: NoisingBehavior::BoundedNoise(BoundedNoise { 
+            _marker: PhantomData,
+        }
+    }
+
+    fn add_noise<R: rand::Rng>(&self, canvas: &mut dyn NoiseCanvas, sampler: &dyn PointSampler<R>, rng: &mut R) {
+        swap_with_partners(&self.bounds, self.amount, canvas, sampler, rng, |rng| {
+            // the square root spreads partners evenly over the disk instead of bunching them in the middle
+            let distance = self.max_radius * rng.random::<f64>().sqrt();
+            let (sin, cos) = rng.random_range(0. ..std::f64::consts::TAU).sin_cos();
+            (distance * cos, distance * sin)
+        });
+    }
+}
+
+/// Like `BoundedNoise`, but each pixel only swaps with one up to `max_distance` pixels further along
+/// `angle`, which smears the image in that direction like motion blur
+pub struct DirectionalSmear {
+    bounds: Rect,
+    amount: NoiseAmount,
+    angle: f64,
+    max_distance: f64,
+}
+
+impl DirectionalSmear {
+    /// `angle` is in radians, with 0 smearing toward the right and π/2 smearing downward
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<R: rand::Rng, N: PointSampler<R>>(sampler: N, bounds: Rect, amount: impl Into<NoiseAmount>, angle: f64, max_distance: f64) -> NoiseTypes<R, N> {
+        NoiseTypes {
+            sampler,
+            noising_behavior: NoisingBehavior::DirectionalSmear(DirectionalSmear {
                 bounds,
-                swap_density,
+                amount: amount.into(),
+                angle,
+                max_distance,
             }),
+            _marker: PhantomData,
+        }
+    }
+
+    fn add_noise<R: rand::Rng>(&self, canvas: &mut dyn NoiseCanvas, sampler: &dyn PointSampler<R>, rng: &mut R) {
+        let (sin, cos) = self.angle.sin_cos();
+        swap_with_partners(&self.bounds, self.amount, canvas, sampler, rng, |rng| {
+            let distance = rng.random_range(0. ..=self.max_distance);
+            (distance * cos, distance * sin)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::coloring::SolidColor;
+
+    type Rng = rand::rngs::StdRng;
+
+    /// Every pixel a different color, so moved pixels can be told apart
+    fn numbered_image(width: usize, height: usize) -> Image {
+        let mut image = Image::with_size(width, height, SolidColor::BLACK);
+        for y in 0..height {
+            for x in 0..width {
+                *image.get_pixel_mut(x, y) = SolidColor { red: x as u8, green: y as u8, blue: 0 };
+            }
         }
+        image
     }
-}
\ No newline at end of file
+
+    fn bounds() -> Rect {
+        Rect::from_points(&Point { x: 2., y: 3. }, &Point { x: 12., y: 9. })
+    }
+
+    /// A canvas that remembers every swap made on it
+    struct SwapLog {
+        image: Image,
+        swaps: Vec<((usize, usize), (usize, usize))>,
+    }
+
+    impl SwapLog {
+        fn new(width: usize, height: usize) -> Self {
+            SwapLog { image: numbered_image(width, height), swaps: Vec::new() }
+        }
+    }
+
+    impl NoiseCanvas for SwapLog {
+        fn width(&self) -> usize {
+            self.image.width()
+        }
+
+        fn height(&self) -> usize {
+            self.image.height()
+        }
+
+        fn get(&self, x: usize, y: usize) -> TransparentColor {
+            self.image.get(x, y)
+        }
+
+        fn set(&mut self, x: usize, y: usize, color: TransparentColor) {
+            self.image.set(x, y, color);
+        }
+
+        fn swap(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
+            self.swaps.push(((x1, y1), (x2, y2)));
+            self.image.swap(x1, y1, x2, y2);
+        }
+    }
+
+    #[test]
+    fn samplers_spread_points_around_the_bounds() {
+        let mut rng = Rng::seed_from_u64(0);
+        let (min, max, center) = (bounds().min_point(), bounds().max_point(), bounds().center());
+
+        let uniform = UniformSampler::new(bounds());
+        for _ in 0..1000 {
+            let point = uniform.sample(&mut rng);
+            assert!(point.x >= min.x && point.x < max.x && point.y >= min.y && point.y < max.y, "{point:?}");
+        }
+
+        let mean_offset = |sampler: &dyn PointSampler<Rng>, rng: &mut Rng| {
+            let points: Vec<Point> = (0..4000).map(|_| sampler.sample(rng)).collect();
+            let mean = points.iter().fold(Point::ORIGIN, |total, point| total + *point);
+            let distance = points.iter().map(|point| (point.x - center.x).abs()).sum::<f64>() / points.len() as f64;
+            (mean.x / points.len() as f64 - center.x, mean.y / points.len() as f64 - center.y, distance)
+        };
+
+        // a normal distribution's mean distance from the middle is √(2/π) standard deviations
+        let (x, y, distance) = mean_offset(&NormalSampler::new(bounds(), 3.), &mut rng);
+        assert!(x.abs() < 0.2 && y.abs() < 0.2 && (distance - 3. * (2. / std::f64::consts::PI).sqrt()).abs() < 0.2);
+
+        let (x, y, distance) = mean_offset(&ExponentialSampler::new(bounds(), 2.), &mut rng);
+        assert!(x.abs() < 0.2 && y.abs() < 0.2 && (distance - 2.).abs() < 0.2);
+    }
+
+    #[test]
+    fn bounded_noise_only_shuffles_pixels_inside_its_bounds() {
+        let mut image = numbered_image(20, 15);
+        BoundedNoise::uniform::<Rng>(bounds(), 0.5).add_noise(&mut image, &mut Rng::seed_from_u64(0));
+
+        let original = numbered_image(20, 15);
+        let inside = |color: &SolidColor| (2..12).contains(&color.red) && (3..9).contains(&color.green);
+        for y in 0..15 {
+            for x in 0..20 {
+                let color = image.get_pixel(x, y);
+                if inside(original.get_pixel(x, y)) {
+                    assert!(inside(color), "({x}, {y})");
+                } else {
+                    assert_eq!(color, original.get_pixel(x, y));
+                }
+            }
+        }
+
+        let mut shuffled = image.canvas.clone();
+        let mut sorted = original.canvas.clone();
+        shuffled.sort_by_key(|color| (color.red, color.green));
+        sorted.sort_by_key(|color| (color.red, color.green));
+        assert_eq!(shuffled, sorted);
+        assert_ne!(image.canvas, original.canvas);
+    }
+
+    #[test]
+    fn transparent_canvases_are_row_by_row() {
+        let mut pixels: Vec<TransparentColor> = (0..6).map(|index| TransparentColor { red: index, green: 0, blue: 0, alpha: 100 }).collect();
+        let mut canvas = TransparentCanvas::new(3, &mut pixels);
+        assert_eq!((canvas.width(), canvas.height()), (3, 2));
+        assert_eq!(canvas.get(1, 1).red, 4);
+        assert_eq!(canvas.row(1).iter().map(|color| color.red).collect::<Vec<_>>(), [3, 4, 5]);
+        assert_eq!(canvas.column(2).iter().map(|color| color.red).collect::<Vec<_>>(), [2, 5]);
+
+        canvas.swap(0, 0, 2, 1);
+        canvas.set(1, 0, TransparentColor::TRANSPARENT);
+        assert_eq!(pixels.iter().map(|color| color.red).collect::<Vec<_>>(), [5, 0, 2, 3, 4, 0]);
+        assert_eq!(pixels[1], TransparentColor::TRANSPARENT);
+    }
+
+    #[test]
+    #[should_panic(expected = "whole rows")]
+    fn transparent_canvases_need_whole_rows() {
+        TransparentCanvas::new(4, &mut [TransparentColor::TRANSPARENT; 6]);
+    }
+
+    #[test]
+    fn offset_canvases_add_up_their_origins() {
+        let mut image = numbered_image(4, 3);
+        let mut inner = OffsetCanvas::new(&mut image, Point { x: 10., y: 20. });
+        let mut outer = OffsetCanvas::new(&mut inner, Point { x: 1., y: 2. });
+        assert_eq!(outer.origin(), Point { x: 11., y: 22. });
+
+        outer.swap(0, 0, 3, 2);
+        assert_eq!((outer.width(), outer.height()), (4, 3));
+        assert_eq!(image.get_pixel(0, 0), &SolidColor { red: 3, green: 2, blue: 0 });
+    }
+
+    #[test]
+    fn local_swaps_stay_close_and_smears_follow_their_angle() {
+        let mut canvas = SwapLog::new(20, 15);
+        LocalSwapNoise::new(UniformSampler::new(bounds()), bounds(), 200, 2.5).add_noise(&mut canvas, &mut Rng::seed_from_u64(0));
+        assert!(canvas.swaps.len() > 100);
+        for ((x1, y1), (x2, y2)) in canvas.swaps {
+            // both ends are rounded down to whole pixels, which can stretch the distance by up to a pixel on each axis
+            let (dx, dy) = (x1.abs_diff(x2) as f64, y1.abs_diff(y2) as f64);
+            assert!(dx.hypot(dy) <= 2.5 + std::f64::consts::SQRT_2, "({x1}, {y1}) to ({x2}, {y2})");
+            assert!((2..12).contains(&x2) && (3..9).contains(&y2));
+        }
+
+        let mut canvas = SwapLog::new(20, 15);
+        DirectionalSmear::new(UniformSampler::new(bounds()), bounds(), 200, 0., 3.).add_noise(&mut canvas, &mut Rng::seed_from_u64(0));
+        assert!(canvas.swaps.len() > 100);
+        for ((x1, y1), (x2, y2)) in canvas.swaps {
+            assert!(y1 == y2 && x2 >= x1 && x2 - x1 <= 3, "({x1}, {y1}) to ({x2}, {y2})");
+        }
+    }
+
+    #[test]
+    fn amounts_pick_how_many_swaps_are_made() {
+        // half of these bounds hang off the left of the canvas
+        let overhanging = Rect::from_points(&Point { x: -10., y: 3. }, &Point { x: 10., y: 9. });
+        let swaps = |amount: NoiseAmount| {
+            let mut canvas = SwapLog::new(20, 15);
+            BoundedNoise::uniform::<Rng>(overhanging, amount).add_noise(&mut canvas, &mut Rng::seed_from_u64(0));
+            canvas.swaps
+        };
+
+        for count in [0, 1, 37, 500] {
+            let swaps = swaps(NoiseAmount::Count(count));
+            assert_eq!(swaps.len(), count);
+            assert!(swaps.iter().flat_map(|(point1, point2)| [point1, point2]).all(|(x, y)| (0..10).contains(x) && (3..9).contains(y)));
+        }
+
+        // density only counts the part of the bounds on the canvas
+        assert_eq!(swaps(NoiseAmount::Density(0.5)).len(), 30);
+        assert_eq!(swaps(0.25.into()).len(), 15);
+        assert_eq!(swaps(7_usize.into()).len(), 7);
+    }
+
+    /// Paints the whole canvas one color
+    struct Fill(SolidColor);
+
+    impl<R: rand::Rng> Noise<R> for Fill {
+        fn add_noise(&self, canvas: &mut dyn NoiseCanvas, _rng: &mut R) {
+            for y in 0..canvas.height() {
+                for x in 0..canvas.width() {
+                    canvas.set(x, y, self.0.into());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stacks_add_noises_in_order() {
+        let red = SolidColor { red: 255, green: 0, blue: 0 };
+        let stacked = |stack: NoiseStack<Rng>| {
+            let mut image = numbered_image(4, 3);
+            stack.add_noise(&mut image, &mut Rng::seed_from_u64(0));
+            image.canvas
+        };
+
+        assert_eq!(stacked(NoiseStack::new()), numbered_image(4, 3).canvas);
+        assert!(stacked(NoiseStack::new().with(Fill(red)).with(Fill(SolidColor::WHITE))).iter().all(|color| *color == SolidColor::WHITE));
+        assert!(stacked(NoiseStack::new().with(Fill(SolidColor::WHITE)).with(Fill(red))).iter().all(|color| *color == red));
+    }
+}
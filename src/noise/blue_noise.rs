@@ -0,0 +1,139 @@
+use std::f64::consts::TAU;
+
+use crate::shapes::{Point, Rect};
+
+/// Scatters points over a rect as densely as it can while keeping every pair at least `min_distance`
+/// apart. Unlike uniformly random points they never clump or leave big gaps, which looks much more
+/// natural for stippling, scattering shapes and dither masks. The same seeded `rng` gives the same points.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PoissonDiskSampler {
+    bounds: Rect,
+    min_distance: f64,
+    attempts: usize,
+}
+
+impl PoissonDiskSampler {
+    pub fn new(bounds: Rect, min_distance: f64) -> Self {
+        if min_distance <= 0. {
+            panic!("Minimum distance must be positive");
+        }
+
+        PoissonDiskSampler {
+            bounds,
+            min_distance,
+            attempts: 30,
+        }
+    }
+
+    /// How many spots are tried around each point before giving up on fitting another one next to it.
+    /// More fills the rect more tightly but takes longer.
+    pub fn with_attempts(self, attempts: usize) -> Self {
+        PoissonDiskSampler { attempts, ..self }
+    }
+
+    pub fn min_distance(&self) -> f64 {
+        self.min_distance
+    }
+
+    /// Every point, in the order they were placed. Rects with no area get no points.
+    pub fn generate<R: rand::Rng>(&self, rng: &mut R) -> Vec<Point> {
+        let (min, max) = (self.bounds.min_point(), self.bounds.max_point());
+        if min.x >= max.x || min.y >= max.y {
+            return Vec::new();
+        }
+
+        // cells small enough that each holds at most one point
+        let cell_size = self.min_distance / 2_f64.sqrt();
+        let columns = ((max.x - min.x) / cell_size).ceil() as usize;
+        let rows = ((max.y - min.y) / cell_size).ceil() as usize;
+        let mut grid: Vec<Option<usize>> = vec![None; columns * rows];
+        let cell_of = |point: &Point| (
+            (((point.x - min.x) / cell_size) as usize).min(columns - 1),
+            (((point.y - min.y) / cell_size) as usize).min(rows - 1),
+        );
+
+        let mut points = Vec::new();
+        let mut active = Vec::new();
+        let place = |point: Point, grid: &mut Vec<Option<usize>>, points: &mut Vec<Point>, active: &mut Vec<usize>| {
+            let (column, row) = cell_of(&point);
+            grid[column + row * columns] = Some(points.len());
+            active.push(points.len());
+            points.push(point);
+        };
+
+        let first = Point { x: rng.random_range(min.x..max.x), y: rng.random_range(min.y..max.y) };
+        place(first, &mut grid, &mut points, &mut active);
+
+        while !active.is_empty() {
+            let active_index = rng.random_range(0..active.len());
+            let around = points[active[active_index]];
+
+            let found = (0..self.attempts).find_map(|_| {
+                let angle = rng.random_range(0. ..TAU);
+                let distance = rng.random_range(self.min_distance..2. * self.min_distance);
+                let candidate = Point { x: around.x + distance * angle.cos(), y: around.y + distance * angle.sin() };
+                if candidate.x < min.x || candidate.y < min.y || candidate.x >= max.x || candidate.y >= max.y {
+                    return None;
+                }
+
+                let (column, row) = cell_of(&candidate);
+                let too_close = (row.saturating_sub(2)..(row + 3).min(rows))
+                    .flat_map(|row| (column.saturating_sub(2)..(column + 3).min(columns)).map(move |column| column + row * columns))
+                    .filter_map(|cell| grid[cell])
+                    .any(|index| {
+                        let (dx, dy) = (points[index].x - candidate.x, points[index].y - candidate.y);
+                        dx * dx + dy * dy < self.min_distance * self.min_distance
+                    });
+                (!too_close).then_some(candidate)
+            });
+
+            match found {
+                Some(point) => place(point, &mut grid, &mut points, &mut active),
+                None => {
+                    active.swap_remove(active_index);
+                }
+            }
+        }
+
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn bounds() -> Rect {
+        Rect::from_points(&Point { x: -10., y: 5. }, &Point { x: 50., y: 45. })
+    }
+
+    #[test]
+    fn points_keep_their_distance_inside_the_bounds() {
+        let sampler = PoissonDiskSampler::new(bounds(), 3.);
+        let points = sampler.generate(&mut rand::rngs::StdRng::seed_from_u64(0));
+        assert_eq!(points, sampler.generate(&mut rand::rngs::StdRng::seed_from_u64(0)));
+
+        for (index, point) in points.iter().enumerate() {
+            assert!(point.x >= -10. && point.x < 50. && point.y >= 5. && point.y < 45., "{point:?}");
+            for other in &points[index + 1..] {
+                assert!((point.x - other.x).hypot(point.y - other.y) >= 3., "{point:?} and {other:?}");
+            }
+        }
+
+        // and no gaps so big that another point would easily have fit
+        for y in (5..45).step_by(2) {
+            for x in (-10..50).step_by(2) {
+                let (x, y) = (x as f64, y as f64);
+                assert!(points.iter().any(|point| (point.x - x).hypot(point.y - y) < 6.), "({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn empty_bounds_get_no_points() {
+        let line = Rect::from_points(&Point { x: 0., y: 5. }, &Point { x: 20., y: 5. });
+        assert!(PoissonDiskSampler::new(line, 1.).generate(&mut rand::rngs::StdRng::seed_from_u64(0)).is_empty());
+    }
+}
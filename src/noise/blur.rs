@@ -0,0 +1,147 @@
+use super::{Noise, NoiseCanvas};
+use crate::coloring::TransparentColor;
+use crate::shapes::{CheckInside, Point, Shape};
+
+/// Softens an image, or only the part inside a mask shape, such as a background behind sharp foreground shapes
+#[derive(Clone, Debug, PartialEq)]
+pub struct GaussianBlur {
+    radius: f64,
+    mask: Option<Shape>,
+}
+
+impl GaussianBlur {
+    /// `radius` is the standard deviation of the blur in pixels. Colors spread about three times that far.
+    pub fn new(radius: f64) -> Self {
+        if radius < 0. {
+            panic!("Blur radius can't be negative");
+        }
+
+        GaussianBlur {
+            radius,
+            mask: None,
+        }
+    }
+
+    /// Only blurs pixels inside `mask`. Colors from outside it still bleed in.
+    pub fn with_mask(self, mask: Shape) -> Self {
+        GaussianBlur { mask: Some(mask), ..self }
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// Weights for offsets from `-reach` to `reach`, adding up to 1
+    fn kernel(&self) -> Vec<f64> {
+        let reach = (self.radius * 3.).ceil() as isize;
+        let weights: Vec<f64> = (-reach..=reach)
+            .map(|offset| (-(offset * offset) as f64 / (2. * self.radius * self.radius)).exp())
+            .collect();
+        let total: f64 = weights.iter().sum();
+        weights.iter().map(|weight| weight / total).collect()
+    }
+}
+
+/// Blurs `pixels` along one direction. `neighbor` gives the index `offset` steps away from an index,
+/// stopping at the edge of the image.
+fn blur_pass(pixels: &[[f64; 4]], kernel: &[f64], neighbor: impl Fn(usize, isize) -> usize) -> Vec<[f64; 4]> {
+    let reach = (kernel.len() / 2) as isize;
+    (0..pixels.len()).map(|index| {
+        kernel.iter().enumerate().fold([0.; 4], |mut total, (kernel_index, weight)| {
+            let pixel = pixels[neighbor(index, kernel_index as isize - reach)];
+            for (total_channel, channel) in total.iter_mut().zip(pixel) {
+                *total_channel += channel * weight;
+            }
+            total
+        })
+    }).collect()
+}
+
+impl<R: rand::Rng> Noise<R> for GaussianBlur {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, _rng: &mut R) {
+        if self.radius == 0. || canvas.width() == 0 || canvas.height() == 0 {
+            return;
+        }
+
+        let (width, height) = (canvas.width() as isize, canvas.height() as isize);
+        let kernel = self.kernel();
+        // premultiplied by alpha, so invisible pixels don't bleed their color into the blur
+        let pixels: Vec<[f64; 4]> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let color = canvas.get(x as usize, y as usize);
+                let alpha = color.alpha as f64 / u8::MAX as f64;
+                [color.red as f64 * alpha, color.green as f64 * alpha, color.blue as f64 * alpha, color.alpha as f64]
+            })
+            .collect();
+
+        // a Gaussian blur is the same as blurring across and then down, which is much less work
+        let across = blur_pass(&pixels, &kernel, |index, offset| {
+            let (x, y) = (index as isize % width, index as isize / width);
+            ((x + offset).clamp(0, width - 1) + y * width) as usize
+        });
+        let blurred = blur_pass(&across, &kernel, |index, offset| {
+            let (x, y) = (index as isize % width, index as isize / width);
+            (x + (y + offset).clamp(0, height - 1) * width) as usize
+        });
+
+        let origin = canvas.origin();
+        for (index, [red, green, blue, alpha]) in blurred.into_iter().enumerate() {
+            let (x, y) = ((index as isize % width) as usize, (index as isize / width) as usize);
+            if let Some(mask) = &self.mask
+                && !mask.contains(&Point { x: origin.x + x as f64, y: origin.y + y as f64 }) {
+                continue;
+            }
+
+            let unpremultiply = |channel: f64| if alpha == 0. { 0 } else { (channel * u8::MAX as f64 / alpha).round().clamp(0., 255.) as u8 };
+            canvas.set(x, y, TransparentColor {
+                red: unpremultiply(red),
+                green: unpremultiply(green),
+                blue: unpremultiply(blue),
+                alpha: alpha.round() as u8,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::coloring::SolidColor;
+    use crate::noise::TransparentCanvas;
+    use crate::shapes::Rect;
+    use crate::Image;
+
+    #[test]
+    fn flat_images_stay_flat() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let gray = SolidColor { red: 90, green: 120, blue: 200 };
+        let mut image = Image::with_size(9, 7, gray);
+        GaussianBlur::new(2.5).add_noise(&mut image, &mut rng);
+        assert!(image.canvas.iter().all(|color| *color == gray));
+
+        let glass = TransparentColor { red: 200, green: 40, blue: 10, alpha: 120 };
+        let mut pixels = vec![glass; 9 * 7];
+        GaussianBlur::new(1.5).add_noise(&mut TransparentCanvas::new(9, &mut pixels), &mut rng);
+        assert!(pixels.iter().all(|color| *color == glass));
+    }
+
+    #[test]
+    fn masks_only_blur_inside_them() {
+        let mut image = Image::with_size(10, 6, SolidColor::BLACK);
+        for y in 0..image.height() {
+            for x in 5..image.width() {
+                *image.get_pixel_mut(x, y) = SolidColor::WHITE;
+            }
+        }
+
+        let mask = Rect::from_points(&Point { x: 0., y: 0. }, &Point { x: 4.5, y: 5. });
+        GaussianBlur::new(1.).with_mask(mask.into()).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        for y in 0..image.height() {
+            assert_eq!(*image.get_pixel(0, y), SolidColor::BLACK);
+            assert!((1..u8::MAX).contains(&image.get_pixel(4, y).red));
+            assert!((5..image.width()).all(|x| *image.get_pixel(x, y) == SolidColor::WHITE));
+        }
+    }
+}
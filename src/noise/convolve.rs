@@ -0,0 +1,171 @@
+use super::{Noise, NoiseCanvas};
+use crate::coloring::{SpreadMode, TransparentColor};
+
+/// Replaces every pixel with a weighted sum of the square of pixels around it, which covers sharpening,
+/// embossing, edge detection and most other classic image filters. Only the colors are filtered, not alpha.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Convolve {
+    size: usize,
+    // row by row, top to bottom
+    kernel: Vec<f64>,
+    edges: SpreadMode,
+    bias: f64,
+}
+
+impl Convolve {
+    /// `kernel` has `size` rows of `size` weights, top to bottom, and `size` has to be odd so it
+    /// has a middle. Pixels past the edge of the image repeat the edge until changed.
+    pub fn new(size: usize, kernel: Vec<f64>) -> Self {
+        if size.is_multiple_of(2) {
+            panic!("Convolution kernels need an odd size");
+        } else if kernel.len() != size * size {
+            panic!("A convolution kernel of size {size} needs {} weights, not {}", size * size, kernel.len());
+        }
+
+        Convolve {
+            size,
+            kernel,
+            edges: SpreadMode::Clamp,
+            bias: 0.,
+        }
+    }
+
+    /// Brings out edges and fine detail
+    pub fn sharpen() -> Self {
+        Convolve::new(3, vec![
+            0., -1., 0.,
+            -1., 5., -1.,
+            0., -1., 0.,
+        ])
+    }
+
+    /// Makes the image look pressed into gray paper, lit from the top left
+    pub fn emboss() -> Self {
+        Convolve::new(3, vec![
+            -2., -1., 0.,
+            -1., 0., 1.,
+            0., 1., 2.,
+        ]).with_bias(128.)
+    }
+
+    /// Leaves only the edges, light on black
+    pub fn edge_detect() -> Self {
+        Convolve::new(3, vec![
+            -1., -1., -1.,
+            -1., 8., -1.,
+            -1., -1., -1.,
+        ])
+    }
+
+    /// Averages each `size` by `size` square evenly
+    pub fn box_blur(size: usize) -> Self {
+        Convolve::new(size, vec![1. / (size * size) as f64; size * size])
+    }
+
+    /// What to use for pixels past the edge of the image: `Clamp` repeats the edge, `Repeat` wraps
+    /// around to the other side and `Reflect` mirrors the image
+    pub fn with_edges(self, edges: SpreadMode) -> Self {
+        Convolve { edges, ..self }
+    }
+
+    /// Added to every channel afterward, such as 128 to show negative results as darker than gray
+    pub fn with_bias(self, bias: f64) -> Self {
+        Convolve { bias, ..self }
+    }
+
+    /// Brings a row or column `position` back onto an image `size` pixels across
+    fn wrap(&self, position: isize, size: usize) -> usize {
+        let size = size as isize;
+        let wrapped = match self.edges {
+            SpreadMode::Clamp => position.clamp(0, size - 1),
+            SpreadMode::Repeat => position.rem_euclid(size),
+            SpreadMode::Reflect => {
+                let position = position.rem_euclid(2 * size);
+                if position < size { position } else { 2 * size - 1 - position }
+            },
+        };
+        wrapped as usize
+    }
+}
+
+impl<R: rand::Rng> Noise<R> for Convolve {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, _rng: &mut R) {
+        let (width, height) = (canvas.width(), canvas.height());
+        let source: Vec<TransparentColor> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| canvas.get(x, y)).collect();
+        let reach = (self.size / 2) as isize;
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut total = [self.bias; 3];
+                for (kernel_index, weight) in self.kernel.iter().enumerate() {
+                    let source_x = self.wrap(x as isize + (kernel_index % self.size) as isize - reach, width);
+                    let source_y = self.wrap(y as isize + (kernel_index / self.size) as isize - reach, height);
+                    let color = source[source_x + source_y * width];
+
+                    total[0] += color.red as f64 * weight;
+                    total[1] += color.green as f64 * weight;
+                    total[2] += color.blue as f64 * weight;
+                }
+
+                let [red, green, blue] = total.map(|channel| channel.round().clamp(0., 255.) as u8);
+                canvas.set(x, y, TransparentColor { red, green, blue, alpha: source[x + y * width].alpha });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::coloring::SolidColor;
+    use crate::Image;
+
+    const EDGES: [SpreadMode; 3] = [SpreadMode::Clamp, SpreadMode::Repeat, SpreadMode::Reflect];
+
+    #[test]
+    fn flat_images_stay_flat() {
+        let color = SolidColor { red: 90, green: 120, blue: 200 };
+        for edges in EDGES {
+            for convolve in [Convolve::box_blur(3), Convolve::box_blur(5), Convolve::sharpen()] {
+                let mut image = Image::with_size(6, 4, color);
+                convolve.with_edges(edges).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+                assert!(image.canvas.iter().all(|pixel| *pixel == color), "{edges:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn edges_fill_in_past_the_image() {
+        // takes each pixel from two to the left of it
+        let mut kernel = vec![0.; 25];
+        kernel[10] = 1.;
+        let shift = Convolve::new(5, kernel);
+
+        for (edges, expected) in [
+            (SpreadMode::Clamp, [10, 10, 10, 20]),
+            (SpreadMode::Repeat, [30, 40, 10, 20]),
+            (SpreadMode::Reflect, [20, 10, 10, 20]),
+        ] {
+            let mut image = Image::with_size(4, 1, SolidColor::BLACK);
+            for x in 0..4 {
+                image.get_pixel_mut(x, 0).red = 10 * (x as u8 + 1);
+            }
+            shift.clone().with_edges(edges).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+            assert_eq!(image.canvas.iter().map(|pixel| pixel.red).collect::<Vec<_>>(), expected, "{edges:?}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "odd size")]
+    fn kernels_need_a_middle() {
+        Convolve::new(2, vec![0.25; 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "needs 9 weights")]
+    fn kernels_need_a_weight_for_every_pixel() {
+        Convolve::new(3, vec![1.; 8]);
+    }
+}
@@ -0,0 +1,131 @@
+use super::{Noise, NoiseCanvas};
+use crate::coloring::{Color, SolidColor, TransparentColor};
+
+/// Wear and tear like on old film: thin scratches running down the image, dust specks and faint blotches
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FilmDamage {
+    scratches: usize,
+    specks: usize,
+    blotches: usize,
+    scratch_color: SolidColor,
+    dust_color: SolidColor,
+}
+
+impl FilmDamage {
+    /// Light scratches and dark dust until the colors are changed
+    pub fn new(scratches: usize, specks: usize, blotches: usize) -> Self {
+        FilmDamage {
+            scratches,
+            specks,
+            blotches,
+            scratch_color: SolidColor { red: 235, green: 230, blue: 215 },
+            dust_color: SolidColor { red: 20, green: 18, blue: 15 },
+        }
+    }
+
+    pub fn with_scratch_color(self, scratch_color: SolidColor) -> Self {
+        FilmDamage { scratch_color, ..self }
+    }
+
+    /// The color of the specks and blotches
+    pub fn with_dust_color(self, dust_color: SolidColor) -> Self {
+        FilmDamage { dust_color, ..self }
+    }
+
+    /// Lays `color` over the pixel at (`x`, `y`) with `opacity`, if it's on the canvas
+    fn mark(canvas: &mut dyn NoiseCanvas, x: f64, y: f64, color: SolidColor, opacity: f64) {
+        if x < 0. || y < 0. || x >= canvas.width() as f64 || y >= canvas.height() as f64 {
+            return;
+        }
+
+        let (x, y) = (x as usize, y as usize);
+        let mark: TransparentColor = color.into();
+        canvas.set(x, y, mark.layer_over(&canvas.get(x, y), opacity));
+    }
+
+    /// A hair-thin line running most of the way down the image, wandering a little side to side
+    fn draw_scratch<R: rand::Rng>(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R) {
+        let height = canvas.height() as f64;
+        let (start, length) = (rng.random_range(-0.2..0.6) * height, rng.random_range(0.3..1.2) * height);
+        let mut x = rng.random_range(0. ..canvas.width() as f64);
+        let (drift, opacity) = (rng.random_range(-0.05..0.05), rng.random_range(0.3..0.8));
+
+        for y in (start.max(0.) as usize)..((start + length).min(height) as usize) {
+            x += drift + rng.random_range(-0.15..0.15);
+            FilmDamage::mark(canvas, x, y as f64, self.scratch_color, opacity * rng.random_range(0.6..1.));
+        }
+    }
+
+    /// A round spot, solid in the middle and fading out at the edge
+    fn draw_spot(&self, canvas: &mut dyn NoiseCanvas, center: (f64, f64), radius: f64, opacity: f64) {
+        let reach = radius.ceil() as isize + 1;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let distance = ((dx * dx + dy * dy) as f64).sqrt();
+                let coverage = (radius + 0.5 - distance).clamp(0., 1.);
+                if coverage > 0. {
+                    FilmDamage::mark(canvas, center.0 + dx as f64, center.1 + dy as f64, self.dust_color, opacity * coverage);
+                }
+            }
+        }
+    }
+}
+
+impl<R: rand::Rng> Noise<R> for FilmDamage {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R) {
+        if canvas.width() == 0 || canvas.height() == 0 {
+            return;
+        }
+        let (width, height) = (canvas.width() as f64, canvas.height() as f64);
+        let random_point = |rng: &mut R| (rng.random_range(0. ..width), rng.random_range(0. ..height));
+
+        for _ in 0..self.blotches {
+            // a cluster of overlapping faint spots, so the blotch isn't perfectly round
+            let center = random_point(rng);
+            let size = rng.random_range(4. ..16.);
+            for _ in 0..6 {
+                let offset = (rng.random_range(-size..size) / 2., rng.random_range(-size..size) / 2.);
+                self.draw_spot(canvas, (center.0 + offset.0, center.1 + offset.1), size * rng.random_range(0.4..0.8), 0.08);
+            }
+        }
+
+        for _ in 0..self.scratches {
+            self.draw_scratch(canvas, rng);
+        }
+
+        for _ in 0..self.specks {
+            let center = random_point(rng);
+            self.draw_spot(canvas, center, rng.random_range(0.3..2.), rng.random_range(0.5..0.9));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::Image;
+
+    #[test]
+    fn no_damage_changes_nothing() {
+        let mut image = Image::with_size(20, 15, SolidColor::WHITE);
+        FilmDamage::new(0, 0, 0).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        assert!(image.canvas.iter().all(|color| *color == SolidColor::WHITE));
+
+        let mut empty = Image::with_size(0, 0, SolidColor::WHITE);
+        FilmDamage::new(3, 10, 2).add_noise(&mut empty, &mut rand::rngs::StdRng::seed_from_u64(0));
+    }
+
+    #[test]
+    fn dust_darkens_and_scratches_lighten() {
+        let mut image = Image::with_size(40, 30, SolidColor::WHITE);
+        FilmDamage::new(0, 20, 3).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        assert!(image.canvas.iter().any(|color| color.red < 200));
+
+        let mut image = Image::with_size(40, 30, SolidColor::BLACK);
+        FilmDamage::new(4, 0, 0).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        assert!(image.canvas.iter().any(|color| color.red > 50));
+        assert!(image.canvas.iter().all(|color| color.red <= 235 && color.blue <= 215));
+    }
+}
@@ -0,0 +1,71 @@
+use rand_distr::Distribution;
+
+use super::{Noise, NoiseCanvas};
+
+/// Moves every pixel by its own random offset, for frosted glass or heat shimmer. Unlike swapping
+/// pixels, this smears colors a short way in every direction, and offsets past the edge of the image
+/// stop at the edge.
+pub struct DisplacementNoise<D: Distribution<f64>> {
+    distribution: D,
+}
+
+impl<D: Distribution<f64>> DisplacementNoise<D> {
+    /// Both the x and y offsets of each pixel are drawn from `distribution`, in pixels, such as
+    /// `Normal::new(0., 2.)` for a light frosting
+    pub fn new(distribution: D) -> Self {
+        DisplacementNoise { distribution }
+    }
+}
+
+impl<D: Distribution<f64>, R: rand::Rng> Noise<R> for DisplacementNoise<D> {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R) {
+        let (width, height) = (canvas.width(), canvas.height());
+        let source: Vec<_> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| canvas.get(x, y)).collect();
+        let displaced = |position: usize, offset: f64, size: usize| (position as f64 + offset).round().clamp(0., (size - 1) as f64) as usize;
+
+        for y in 0..height {
+            for x in 0..width {
+                let source_x = displaced(x, self.distribution.sample(rng), width);
+                let source_y = displaced(y, self.distribution.sample(rng), height);
+                canvas.set(x, y, source[source_x + source_y * width]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_distr::Normal;
+
+    use super::*;
+    use crate::coloring::SolidColor;
+    use crate::Image;
+
+    fn numbered_image() -> Image {
+        let mut image = Image::with_size(10, 7, SolidColor::BLACK);
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                *image.get_pixel_mut(x, y) = SolidColor { red: x as u8, green: y as u8, blue: 0 };
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn zero_offsets_change_nothing() {
+        let mut image = numbered_image();
+        DisplacementNoise::new(Normal::new(0., 0.).unwrap()).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        assert_eq!(image.canvas, numbered_image().canvas);
+    }
+
+    #[test]
+    fn far_offsets_stop_at_the_edge() {
+        let mut image = numbered_image();
+        DisplacementNoise::new(Normal::new(0., 100.).unwrap()).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+
+        // nearly every offset is past the edge, so nearly every pixel comes from a corner or side
+        let on_edge = |color: &SolidColor| color.red == 0 || color.red == 9 || color.green == 0 || color.green == 6;
+        assert!(image.canvas.iter().filter(|color| on_edge(color)).count() > image.canvas.len() * 9 / 10);
+    }
+}
@@ -0,0 +1,455 @@
+use super::{Noise, NoiseCanvas};
+
+/// A smooth random value at every point, from -1 to 1, for colorings and effects that want
+/// organic variation instead of geometry. The same field always gives the same value at the same point.
+pub trait ScalarNoise {
+    fn value(&self, x: f64, y: f64) -> f64;
+}
+
+/// Every kind of scalar noise, so colorings can hold one without knowing which
+#[derive(Clone, Debug, PartialEq)]
+pub enum NoiseField {
+    Value(ValueNoise),
+    Perlin(PerlinNoise),
+    Simplex(SimplexNoise),
+    Fbm(Fbm),
+    DomainWarp(DomainWarp),
+}
+
+impl ScalarNoise for NoiseField {
+    fn value(&self, x: f64, y: f64) -> f64 {
+        match self {
+            NoiseField::Value(noise) => noise.value(x, y),
+            NoiseField::Perlin(noise) => noise.value(x, y),
+            NoiseField::Simplex(noise) => noise.value(x, y),
+            NoiseField::Fbm(noise) => noise.value(x, y),
+            NoiseField::DomainWarp(noise) => noise.value(x, y),
+        }
+    }
+}
+
+impl NoiseField {
+    /// A direction to push the point (`x`, `y`) in, from two samples of the field far enough apart
+    /// that they don't follow each other. Both parts are from -1 to 1.
+    pub fn warp_offset(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.value(x, y), self.value(x + 31.7, y + 47.3))
+    }
+}
+
+/// The simplest smooth noise: a random value at every whole number point, eased between in the
+/// gaps. Features are about one unit across, so scale the coordinates to change their size.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ValueNoise {
+    seed: u64,
+}
+
+impl Into<NoiseField> for ValueNoise {
+    fn into(self) -> NoiseField {
+        NoiseField::Value(self)
+    }
+}
+
+impl ValueNoise {
+    pub fn new(seed: u64) -> Self {
+        ValueNoise { seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn lattice_value(&self, x: i64, y: i64) -> f64 {
+        hash_to_unit(hash_lattice_point(self.seed, x, y)) * 2. - 1.
+    }
+}
+
+impl ScalarNoise for ValueNoise {
+    fn value(&self, x: f64, y: f64) -> f64 {
+        let (left, top) = (x.floor(), y.floor());
+        let (right_portion, bottom_portion) = (smoothstep(x - left), smoothstep(y - top));
+        let (left, top) = (left as i64, top as i64);
+
+        let top_value = lerp(self.lattice_value(left, top), self.lattice_value(left + 1, top), right_portion);
+        let bottom_value = lerp(self.lattice_value(left, top + 1), self.lattice_value(left + 1, top + 1), right_portion);
+        lerp(top_value, bottom_value, bottom_portion)
+    }
+}
+
+/// Classic gradient noise: a random slope at every whole number point, blended in the gaps. It has
+/// fewer blocky artifacts than `ValueNoise`, is 0 at every whole number point, and its features are
+/// also about one unit across.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PerlinNoise {
+    seed: u64,
+}
+
+impl Into<NoiseField> for PerlinNoise {
+    fn into(self) -> NoiseField {
+        NoiseField::Perlin(self)
+    }
+}
+
+impl PerlinNoise {
+    pub fn new(seed: u64) -> Self {
+        PerlinNoise { seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// How much the slope at lattice point (`x`, `y`) raises the point `offset` away from it
+    fn gradient_dot(&self, x: i64, y: i64, offset: (f64, f64)) -> f64 {
+        let angle = hash_to_unit(hash_lattice_point(self.seed, x, y)) * std::f64::consts::TAU;
+        let (sin, cos) = angle.sin_cos();
+        cos * offset.0 + sin * offset.1
+    }
+}
+
+impl ScalarNoise for PerlinNoise {
+    fn value(&self, x: f64, y: f64) -> f64 {
+        let (left, top) = (x.floor(), y.floor());
+        let (dx, dy) = (x - left, y - top);
+        let (right_portion, bottom_portion) = (quintic_fade(dx), quintic_fade(dy));
+        let (left, top) = (left as i64, top as i64);
+
+        let top_value = lerp(self.gradient_dot(left, top, (dx, dy)), self.gradient_dot(left + 1, top, (dx - 1., dy)), right_portion);
+        let bottom_value = lerp(self.gradient_dot(left, top + 1, (dx, dy - 1.)), self.gradient_dot(left + 1, top + 1, (dx - 1., dy - 1.)), right_portion);
+
+        // the most a 2D gradient noise can reach is √½, so this stretches it out to -1 to 1
+        (lerp(top_value, bottom_value, bottom_portion) * std::f64::consts::SQRT_2).clamp(-1., 1.)
+    }
+}
+
+/// Gradient noise on a grid of triangles instead of squares, so it doesn't line up with the x and y axes
+/// the way `PerlinNoise` can. Good for clouds and other organic textures. Features are about one unit across.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SimplexNoise {
+    seed: u64,
+}
+
+impl Into<NoiseField> for SimplexNoise {
+    fn into(self) -> NoiseField {
+        NoiseField::Simplex(self)
+    }
+}
+
+impl SimplexNoise {
+    pub fn new(seed: u64) -> Self {
+        SimplexNoise { seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// How much the corner at lattice point (`x`, `y`) adds to a point `offset` away from it
+    fn corner_contribution(&self, x: i64, y: i64, offset: (f64, f64)) -> f64 {
+        let falloff = 0.5 - offset.0 * offset.0 - offset.1 * offset.1;
+        if falloff <= 0. {
+            return 0.;
+        }
+
+        let angle = hash_to_unit(hash_lattice_point(self.seed, x, y)) * std::f64::consts::TAU;
+        let (sin, cos) = angle.sin_cos();
+        falloff.powi(4) * (cos * offset.0 + sin * offset.1)
+    }
+}
+
+impl ScalarNoise for SimplexNoise {
+    fn value(&self, x: f64, y: f64) -> f64 {
+        // skews the grid of triangles into squares and back
+        let skew = (3f64.sqrt() - 1.) / 2.;
+        let unskew = (3. - 3f64.sqrt()) / 6.;
+
+        let skewed = (x + y) * skew;
+        let (cell_x, cell_y) = ((x + skewed).floor(), (y + skewed).floor());
+        let unskewed = (cell_x + cell_y) * unskew;
+        let offset0 = (x - (cell_x - unskewed), y - (cell_y - unskewed));
+
+        // which of the cell's two triangles the point is in
+        let (step_x, step_y) = if offset0.0 > offset0.1 { (1, 0) } else { (0, 1) };
+        let offset1 = (offset0.0 - step_x as f64 + unskew, offset0.1 - step_y as f64 + unskew);
+        let offset2 = (offset0.0 - 1. + 2. * unskew, offset0.1 - 1. + 2. * unskew);
+
+        let (cell_x, cell_y) = (cell_x as i64, cell_y as i64);
+        let total = self.corner_contribution(cell_x, cell_y, offset0)
+            + self.corner_contribution(cell_x + step_x, cell_y + step_y, offset1)
+            + self.corner_contribution(cell_x + 1, cell_y + 1, offset2);
+
+        // brings the largest a sum of three corners can be up to 1
+        (total * SIMPLEX_SCALE).clamp(-1., 1.)
+    }
+}
+
+const SIMPLEX_SCALE: f64 = 99.2;
+
+/// Fractal Brownian motion: layers of another noise at finer and finer scales, each fainter than the
+/// last, so there's detail at every size like in clouds and terrain
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fbm {
+    source: Box<NoiseField>,
+    octaves: usize,
+    lacunarity: f64,
+    persistence: f64,
+}
+
+impl Into<NoiseField> for Fbm {
+    fn into(self) -> NoiseField {
+        NoiseField::Fbm(self)
+    }
+}
+
+impl Fbm {
+    /// Each octave is twice as fine and half as strong as the last, until changed
+    pub fn new(source: impl Into<NoiseField>, octaves: usize) -> Self {
+        if octaves == 0 {
+            panic!("Fractal noise needs at least one octave");
+        }
+
+        Fbm {
+            source: Box::new(source.into()),
+            octaves,
+            lacunarity: 2.,
+            persistence: 0.5,
+        }
+    }
+
+    /// How many times finer each octave is than the last
+    pub fn with_lacunarity(self, lacunarity: f64) -> Self {
+        Fbm { lacunarity, ..self }
+    }
+
+    /// How strong each octave is compared to the last. Higher is rougher.
+    pub fn with_persistence(self, persistence: f64) -> Self {
+        Fbm { persistence, ..self }
+    }
+
+    pub fn octaves(&self) -> usize {
+        self.octaves
+    }
+}
+
+impl ScalarNoise for Fbm {
+    fn value(&self, x: f64, y: f64) -> f64 {
+        let (mut frequency, mut amplitude) = (1., 1.);
+        let (mut total, mut total_amplitude) = (0., 0.);
+
+        for octave in 0..self.octaves {
+            // shifts each octave so they don't all line up at the origin
+            let shift = octave as f64 * 17.31;
+            total += self.source.value(x * frequency + shift, y * frequency + shift) * amplitude;
+            total_amplitude += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+
+        if total_amplitude == 0. { 0. } else { total / total_amplitude }
+    }
+}
+
+/// Samples one noise at points pushed around by another, which swirls it into marble, smoke or flowing shapes
+#[derive(Clone, Debug, PartialEq)]
+pub struct DomainWarp {
+    source: Box<NoiseField>,
+    warp: Box<NoiseField>,
+    strength: f64,
+    depth: usize,
+}
+
+impl Into<NoiseField> for DomainWarp {
+    fn into(self) -> NoiseField {
+        NoiseField::DomainWarp(self)
+    }
+}
+
+impl DomainWarp {
+    /// Points get pushed up to `strength` units by `warp` before `source` is sampled there
+    pub fn new(source: impl Into<NoiseField>, warp: impl Into<NoiseField>, strength: f64) -> Self {
+        DomainWarp {
+            source: Box::new(source.into()),
+            warp: Box::new(warp.into()),
+            strength,
+            depth: 1,
+        }
+    }
+
+    /// How many times the warp is fed back into itself. Each time twists the result further.
+    pub fn with_depth(self, depth: usize) -> Self {
+        DomainWarp { depth, ..self }
+    }
+
+    /// Where (`x`, `y`) ends up after being warped
+    pub fn warp_point(&self, x: f64, y: f64) -> (f64, f64) {
+        let mut offset = (0., 0.);
+        for _ in 0..self.depth {
+            offset = self.warp.warp_offset(x + self.strength * offset.0, y + self.strength * offset.1);
+        }
+        (x + self.strength * offset.0, y + self.strength * offset.1)
+    }
+}
+
+impl ScalarNoise for DomainWarp {
+    fn value(&self, x: f64, y: f64) -> f64 {
+        let (x, y) = self.warp_point(x, y);
+        self.source.value(x, y)
+    }
+}
+
+/// Brightens and darkens an image by a scalar noise field, for blotchy film or paper textures
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrightnessNoise {
+    field: NoiseField,
+    scale: f64,
+    strength: f64,
+}
+
+impl BrightnessNoise {
+    /// `scale` is about how many pixels across the blotches are, and `strength` is the most a channel
+    /// can change, from 0 to 1
+    pub fn new(field: impl Into<NoiseField>, scale: f64, strength: f64) -> Self {
+        if scale <= 0. {
+            panic!("Noise scale must be positive");
+        }
+
+        BrightnessNoise {
+            field: field.into(),
+            scale,
+            strength,
+        }
+    }
+}
+
+impl<R: rand::Rng> Noise<R> for BrightnessNoise {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, _rng: &mut R) {
+        let origin = canvas.origin();
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                let (x_on_image, y_on_image) = (origin.x + x as f64, origin.y + y as f64);
+                let shift = self.field.value(x_on_image / self.scale, y_on_image / self.scale) * self.strength * u8::MAX as f64;
+                let shift_channel = |channel: u8| (channel as f64 + shift).round().clamp(0., 255.) as u8;
+
+                let mut pixel = canvas.get(x, y);
+                pixel.red = shift_channel(pixel.red);
+                pixel.green = shift_channel(pixel.green);
+                pixel.blue = shift_channel(pixel.blue);
+                canvas.set(x, y, pixel);
+            }
+        }
+    }
+}
+
+fn lerp(value1: f64, value2: f64, portion2: f64) -> f64 {
+    value1 + (value2 - value1) * portion2
+}
+
+/// Eases from 0 to 1 so the noise has no creases at whole numbers
+fn smoothstep(portion: f64) -> f64 {
+    portion * portion * (3. - 2. * portion)
+}
+
+/// Like `smoothstep`, but also flat in its second derivative, which gradient noise needs to not show the grid
+fn quintic_fade(portion: f64) -> f64 {
+    portion * portion * portion * (portion * (portion * 6. - 15.) + 10.)
+}
+
+/// Scrambles a lattice point and seed into 64 random looking bits, using the SplitMix64 finalizer
+pub(crate) fn hash_lattice_point(seed: u64, x: i64, y: i64) -> u64 {
+    let mut hash = seed ^ (x as u64).wrapping_mul(0x9E3779B97F4A7C15) ^ (y as u64).wrapping_mul(0xC2B2AE3D27D4EB4F);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D049BB133111EB);
+    hash ^ (hash >> 31)
+}
+
+/// The top 53 bits of `hash` as a number from 0 to 1
+pub(crate) fn hash_to_unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::coloring::SolidColor;
+    use crate::noise::OffsetCanvas;
+    use crate::shapes::Point;
+    use crate::Image;
+
+    /// Values of `noise` over a patch that crosses lattice lines and the origin
+    fn sample_patch(noise: &impl ScalarNoise) -> Vec<f64> {
+        (0..20).flat_map(|y| (0..20).map(move |x| (x as f64 * 0.37 - 3.1, y as f64 * 0.41 - 4.3)))
+            .map(|(x, y)| noise.value(x, y))
+            .collect()
+    }
+
+    fn assert_seeded(noise: impl Fn(u64) -> NoiseField) {
+        let values = sample_patch(&noise(7));
+        assert_eq!(values, sample_patch(&noise(7)));
+        assert_ne!(values, sample_patch(&noise(8)));
+        assert!(values.iter().all(|value| (-1. ..=1.).contains(value)));
+        assert!(values.iter().any(|value| *value != 0.));
+    }
+
+    #[test]
+    fn perlin_noise_is_seeded_and_zero_on_the_lattice() {
+        assert_seeded(|seed| PerlinNoise::new(seed).into());
+
+        let noise = PerlinNoise::new(3);
+        for (x, y) in [(0., 0.), (4., -2.), (-7., 11.)] {
+            assert_eq!(noise.value(x, y), 0.);
+        }
+    }
+
+    #[test]
+    fn simplex_noise_is_seeded() {
+        assert_seeded(|seed| SimplexNoise::new(seed).into());
+    }
+
+    #[test]
+    fn fbm_is_seeded_and_one_octave_is_its_source() {
+        assert_seeded(|seed| Fbm::new(SimplexNoise::new(seed), 4).into());
+
+        let source = PerlinNoise::new(2);
+        assert_eq!(sample_patch(&Fbm::new(source, 1)), sample_patch(&source));
+    }
+
+    #[test]
+    fn domain_warp_stays_within_its_strength() {
+        let source = PerlinNoise::new(4);
+        let warp = DomainWarp::new(source, SimplexNoise::new(5), 0.8);
+        assert_eq!(sample_patch(&warp.clone().with_depth(0)), sample_patch(&source));
+        assert_ne!(sample_patch(&warp), sample_patch(&source));
+
+        for depth in 1..4 {
+            let warp = warp.clone().with_depth(depth);
+            let (x, y) = warp.warp_point(2.3, -1.7);
+            assert!((x - 2.3).abs() <= 0.8 && (y + 1.7).abs() <= 0.8, "depth {depth}");
+            assert_eq!(warp.value(2.3, -1.7), source.value(x, y));
+        }
+    }
+
+    #[test]
+    fn brightness_noise_on_an_offset_tile_matches_the_full_image() {
+        let noise = BrightnessNoise::new(PerlinNoise::new(5), 4., 0.3);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let gray = SolidColor { red: 128, green: 128, blue: 128 };
+
+        let mut full = Image::with_size(12, 10, gray);
+        noise.add_noise(&mut full, &mut rng);
+
+        let mut tile = Image::with_size(5, 4, gray);
+        noise.add_noise(&mut OffsetCanvas::new(&mut tile, Point { x: 6., y: 3. }), &mut rng);
+
+        for y in 0..tile.height() {
+            for x in 0..tile.width() {
+                assert_eq!(tile.get_pixel(x, y), full.get_pixel(x + 6, y + 3), "({x}, {y})");
+            }
+        }
+
+        // without the origin the tile would sample the field at the top left of the image instead
+        let mut unshifted = Image::with_size(5, 4, gray);
+        noise.add_noise(&mut unshifted, &mut rng);
+        assert_ne!(unshifted.canvas, tile.canvas);
+    }
+}
@@ -0,0 +1,89 @@
+use rand_distr::{Distribution, StandardNormal};
+
+use super::{Noise, NoiseCanvas};
+
+/// Adds normally distributed noise to every channel of every pixel, like the sensor noise of a camera
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GaussianNoise {
+    sigma: f64,
+    correlation: f64,
+}
+
+impl GaussianNoise {
+    /// `sigma` is the standard deviation, in channel values from 0 to 255. Each channel gets its own
+    /// noise until `with_correlation` says otherwise.
+    pub fn new(sigma: f64) -> Self {
+        if sigma < 0. {
+            panic!("Noise sigma can't be negative");
+        }
+
+        GaussianNoise {
+            sigma,
+            correlation: 0.,
+        }
+    }
+
+    /// How much the noise is shared between channels, from 0 for colored speckles to 1 for
+    /// the same amount on every channel, which only changes brightness
+    pub fn with_correlation(self, correlation: f64) -> Self {
+        GaussianNoise { correlation: correlation.clamp(0., 1.), ..self }
+    }
+
+    pub fn sigma(&self) -> f64 {
+        self.sigma
+    }
+}
+
+impl<R: rand::Rng> Noise<R> for GaussianNoise {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R) {
+        // mixing shared and separate noise this way keeps the standard deviation at sigma
+        let shared_weight = self.correlation.sqrt() * self.sigma;
+        let separate_weight = (1. - self.correlation).sqrt() * self.sigma;
+
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                let shared: f64 = StandardNormal.sample(rng);
+                let mut perturb = |channel: u8| {
+                    let separate: f64 = StandardNormal.sample(rng);
+                    (channel as f64 + shared * shared_weight + separate * separate_weight).round().clamp(0., 255.) as u8
+                };
+
+                let mut pixel = canvas.get(x, y);
+                pixel.red = perturb(pixel.red);
+                pixel.green = perturb(pixel.green);
+                pixel.blue = perturb(pixel.blue);
+                canvas.set(x, y, pixel);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::coloring::SolidColor;
+    use crate::Image;
+
+    const GRAY: SolidColor = SolidColor { red: 100, green: 100, blue: 100 };
+
+    #[test]
+    fn zero_sigma_changes_nothing() {
+        let mut image = Image::with_size(8, 6, GRAY);
+        GaussianNoise::new(0.).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        assert!(image.canvas.iter().all(|color| *color == GRAY));
+    }
+
+    #[test]
+    fn fully_correlated_noise_keeps_grays_gray() {
+        let mut image = Image::with_size(8, 6, GRAY);
+        GaussianNoise::new(20.).with_correlation(1.).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        assert!(image.canvas.iter().all(|color| color.red == color.green && color.green == color.blue));
+        assert!(image.canvas.iter().any(|color| *color != GRAY));
+
+        let mut image = Image::with_size(8, 6, GRAY);
+        GaussianNoise::new(20.).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        assert!(image.canvas.iter().any(|color| color.red != color.green || color.green != color.blue));
+    }
+}
@@ -0,0 +1,113 @@
+use super::{Noise, NoiseCanvas};
+use crate::coloring::TransparentColor;
+
+/// Shifts random horizontal slices of the image sideways, wrapping around the edges, for a
+/// datamoshed, glitchy look. The color channels can be shifted by different amounts too.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GlitchShift {
+    slices: usize,
+    max_slice_height: usize,
+    max_shift: usize,
+    channel_split: usize,
+}
+
+impl GlitchShift {
+    /// Shifts `slices` slices up to `max_slice_height` pixels tall by up to `max_shift` pixels either way
+    pub fn new(slices: usize, max_slice_height: usize, max_shift: usize) -> Self {
+        if max_slice_height == 0 {
+            panic!("Glitch slices must be at least a pixel tall");
+        }
+
+        GlitchShift {
+            slices,
+            max_slice_height,
+            max_shift,
+            channel_split: 0,
+        }
+    }
+
+    /// Also shifts red and blue up to `channel_split` more pixels away from green in each slice,
+    /// like a badly aligned video signal
+    pub fn with_channel_split(self, channel_split: usize) -> Self {
+        GlitchShift { channel_split, ..self }
+    }
+}
+
+impl<R: rand::Rng> Noise<R> for GlitchShift {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R) {
+        let (width, height) = (canvas.width(), canvas.height());
+        if width == 0 || height == 0 {
+            return;
+        }
+        let max_shift = self.max_shift as i64;
+        let max_split = self.channel_split as i64;
+
+        for _ in 0..self.slices {
+            let top = rng.random_range(0..height);
+            let bottom = (top + rng.random_range(1..=self.max_slice_height)).min(height);
+            let shift = rng.random_range(-max_shift..=max_shift);
+            let (red_shift, blue_shift) = (
+                shift + rng.random_range(-max_split..=max_split),
+                shift + rng.random_range(-max_split..=max_split),
+            );
+
+            for y in top..bottom {
+                let row = canvas.row(y);
+                let shifted = |x: usize, shift: i64| row[(x as i64 - shift).rem_euclid(width as i64) as usize];
+
+                for x in 0..width {
+                    canvas.set(x, y, TransparentColor {
+                        red: shifted(x, red_shift).red,
+                        blue: shifted(x, blue_shift).blue,
+                        ..shifted(x, shift)
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::coloring::SolidColor;
+    use crate::Image;
+
+    #[test]
+    fn slices_wrap_around_within_their_rows() {
+        let mut image = Image::with_size(12, 10, SolidColor::BLACK);
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                *image.get_pixel_mut(x, y) = SolidColor { red: x as u8, green: x as u8, blue: x as u8 };
+            }
+        }
+        GlitchShift::new(6, 3, 5).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+
+        let mut shifted_rows = 0;
+        for y in 0..image.height() {
+            // every row is still 0 to 11 in order, just rotated
+            let row: Vec<u8> = (0..image.width()).map(|x| image.get_pixel(x, y).green).collect();
+            let rotation = row[0] as usize;
+            assert!(row.iter().enumerate().all(|(x, value)| *value as usize == (x + rotation) % 12), "{row:?}");
+            shifted_rows += (rotation != 0) as usize;
+        }
+        assert!(shifted_rows > 0);
+        assert!(image.canvas.iter().all(|color| color.red == color.green && color.blue == color.green));
+    }
+
+    #[test]
+    fn channel_splits_pull_red_and_blue_away_from_green() {
+        let mut image = Image::with_size(12, 10, SolidColor::BLACK);
+        for y in 0..image.height() {
+            *image.get_pixel_mut(5, y) = SolidColor::WHITE;
+        }
+        GlitchShift::new(10, 4, 0).with_channel_split(3).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+
+        // with no shift green stays put, and only red and blue move
+        assert!((0..image.height()).all(|y| image.get_pixel(5, y).green == u8::MAX));
+        assert_eq!(image.canvas.iter().filter(|color| color.green == u8::MAX).count(), image.height());
+        assert!(image.canvas.iter().any(|color| color.red != color.green || color.blue != color.green));
+    }
+}
@@ -0,0 +1,91 @@
+use rand_distr::{Distribution, Poisson};
+
+use super::{Noise, NoiseCanvas};
+use crate::coloring::{FColor, TransparentColor};
+
+/// Shot noise: each channel counts a random number of photons, so like in a real camera the noise
+/// grows with brightness but gets more noticeable relative to the signal in dark areas
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PoissonNoise {
+    photons_at_white: f64,
+}
+
+impl PoissonNoise {
+    /// `photons_at_white` is how many photons a fully lit channel catches on average. Fewer photons
+    /// means a darker, noisier exposure, and a few hundred or less looks like a low-light photo.
+    pub fn new(photons_at_white: f64) -> Self {
+        if photons_at_white <= 0. {
+            panic!("Shot noise needs a positive photon count");
+        }
+
+        PoissonNoise { photons_at_white }
+    }
+
+    pub fn photons_at_white(&self) -> f64 {
+        self.photons_at_white
+    }
+
+    /// A random photon count for a channel in linear light, brought back to the same scale
+    fn count_photons<R: rand::Rng>(&self, channel: f32, rng: &mut R) -> f32 {
+        let expected = channel as f64 * self.photons_at_white;
+        if expected <= 0. {
+            return 0.;
+        }
+        match Poisson::new(expected) {
+            Ok(distribution) => (distribution.sample(rng) / self.photons_at_white) as f32,
+            // so many photons that the noise is too small to see
+            Err(_) => channel,
+        }
+    }
+}
+
+impl<R: rand::Rng> Noise<R> for PoissonNoise {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, rng: &mut R) {
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                // photons are counted in linear light, since that's what's proportional to them
+                let pixel = canvas.get(x, y);
+                let light: FColor = pixel.into();
+                let noisy_light = FColor {
+                    red: self.count_photons(light.red, rng),
+                    green: self.count_photons(light.green, rng),
+                    blue: self.count_photons(light.blue, rng),
+                    alpha: light.alpha,
+                };
+                canvas.set(x, y, TransparentColor { alpha: pixel.alpha, ..noisy_light.into() });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::coloring::SolidColor;
+    use crate::Image;
+
+    #[test]
+    fn black_catches_no_photons() {
+        let mut image = Image::with_size(8, 6, SolidColor::BLACK);
+        PoissonNoise::new(50.).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        assert!(image.canvas.iter().all(|color| *color == SolidColor::BLACK));
+    }
+
+    #[test]
+    fn shot_noise_averages_out_to_the_original_light() {
+        let gray = SolidColor { red: 128, green: 128, blue: 128 };
+        let mut image = Image::with_size(32, 32, gray);
+        PoissonNoise::new(100.).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+
+        let light = |color: &SolidColor| {
+            let color: TransparentColor = (*color).into();
+            let light: FColor = color.into();
+            light.green
+        };
+        let mean = image.canvas.iter().map(light).sum::<f32>() / image.canvas.len() as f32;
+        assert!((mean - light(&gray)).abs() < 0.01, "{mean}");
+        assert!(image.canvas.iter().any(|color| *color != gray));
+    }
+}
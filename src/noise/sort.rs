@@ -0,0 +1,117 @@
+use super::{Noise, NoiseCanvas};
+use crate::coloring::{Color, TransparentColor};
+
+/// Which way pixels get sorted
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SortDirection {
+    /// Along each row, darkest on the left
+    #[default]
+    Rows,
+    /// Down each column, darkest at the top
+    Columns,
+}
+
+/// Sorts runs of pixels by how light they are, the classic glitch art streaks. Only runs of pixels
+/// whose lightness is within the thresholds get sorted, so the darkest and brightest parts of the
+/// image hold still and break the streaks up.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PixelSort {
+    direction: SortDirection,
+    lower_threshold: f64,
+    upper_threshold: f64,
+    reversed: bool,
+}
+
+impl PixelSort {
+    /// The thresholds are lightnesses from 0 to 1. Something like 0.25 to 0.8 leaves shadows and highlights alone.
+    pub fn new(direction: SortDirection, lower_threshold: f64, upper_threshold: f64) -> Self {
+        if lower_threshold > upper_threshold {
+            panic!("Pixel sorting thresholds are backwards");
+        }
+
+        PixelSort {
+            direction,
+            lower_threshold,
+            upper_threshold,
+            reversed: false,
+        }
+    }
+
+    /// Puts the lightest pixels first instead
+    pub fn with_reversed(self, reversed: bool) -> Self {
+        PixelSort { reversed, ..self }
+    }
+
+    fn sort_line(&self, line: &mut [TransparentColor]) {
+        let lightnesses: Vec<f64> = line.iter().map(|color| color.perceived_lightness()).collect();
+        let in_span = |index: usize| (self.lower_threshold..=self.upper_threshold).contains(&lightnesses[index]);
+
+        let mut start = 0;
+        while start < line.len() {
+            if !in_span(start) {
+                start += 1;
+                continue;
+            }
+            let end = (start..line.len()).find(|index| !in_span(*index)).unwrap_or(line.len());
+
+            let mut span: Vec<(f64, TransparentColor)> = (start..end).map(|index| (lightnesses[index], line[index])).collect();
+            span.sort_by(|(lightness1, _), (lightness2, _)| lightness1.total_cmp(lightness2));
+            if self.reversed {
+                span.reverse();
+            }
+            for (offset, (_, color)) in span.into_iter().enumerate() {
+                line[start + offset] = color;
+            }
+
+            start = end;
+        }
+    }
+}
+
+impl<R: rand::Rng> Noise<R> for PixelSort {
+    fn add_noise(&self, canvas: &mut dyn NoiseCanvas, _rng: &mut R) {
+        match self.direction {
+            SortDirection::Rows => for y in 0..canvas.height() {
+                let mut row = canvas.row(y);
+                self.sort_line(&mut row);
+                canvas.set_row(y, &row);
+            },
+            SortDirection::Columns => for x in 0..canvas.width() {
+                let mut column = canvas.column(x);
+                self.sort_line(&mut column);
+                canvas.set_column(x, &column);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::coloring::SolidColor;
+    use crate::Image;
+
+    const GRAYS: [u8; 7] = [200, 255, 150, 100, 0, 180, 120];
+
+    fn sorted(direction: SortDirection, reversed: bool) -> Vec<u8> {
+        let (width, height) = if direction == SortDirection::Rows { (GRAYS.len(), 1) } else { (1, GRAYS.len()) };
+        let mut image = Image::with_size(width, height, SolidColor::BLACK);
+        for (index, gray) in GRAYS.into_iter().enumerate() {
+            image.canvas[index] = SolidColor { red: gray, green: gray, blue: gray };
+        }
+
+        PixelSort::new(direction, 0.25, 0.9).with_reversed(reversed).add_noise(&mut image, &mut rand::rngs::StdRng::seed_from_u64(0));
+        image.canvas.iter().map(|color| color.green).collect()
+    }
+
+    #[test]
+    fn only_runs_between_the_thresholds_get_sorted() {
+        // black and white are outside the thresholds, so they hold still and split the runs up
+        for direction in [SortDirection::Rows, SortDirection::Columns] {
+            assert_eq!(sorted(direction, false), [200, 255, 100, 150, 0, 120, 180]);
+            assert_eq!(sorted(direction, true), [200, 255, 150, 100, 0, 180, 120]);
+        }
+    }
+}
@@ -0,0 +1,977 @@
+pub mod syntax;
+mod format;
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use pest::iterators::Pair;
+use pest::Parser;
+use pest_derive::Parser;
+
+use crate::coloring::{BlendMode, ColorScheme, LinearGradient, SolidColor, TransparentColor};
+use crate::limits::{LimitExceeded, Limits};
+use crate::scene::{ColorPart, Scene, ThemeBinding, ThemeTarget};
+use crate::shapes::{Ellipse, Point, Rect, Shape};
+use crate::theme::Theme;
+use crate::{DrawInstruction, Style};
+
+pub use format::format_noisy;
+
+#[derive(Parser)]
+#[grammar = "noisy.pest"]
+struct NoisyParser;
+
+#[derive(Debug)]
+pub enum ReadFileError {
+    IOError(std::io::Error),
+    SyntaxError(String),
+    LimitExceeded(LimitExceeded),
+}
+
+impl From<LimitExceeded> for ReadFileError {
+    fn from(error: LimitExceeded) -> Self {
+        ReadFileError::LimitExceeded(error)
+    }
+}
+
+impl From<std::io::Error> for ReadFileError {
+    fn from(error: std::io::Error) -> Self {
+        ReadFileError::IOError(error)
+    }
+}
+
+impl Display for ReadFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadFileError::IOError(error) => write!(f, "{error}"),
+            ReadFileError::SyntaxError(message) => write!(f, "{message}"),
+            ReadFileError::LimitExceeded(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Color(TransparentColor),
+}
+
+impl Into<Value> for f64 {
+    fn into(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl Into<Value> for TransparentColor {
+    fn into(self) -> Value {
+        Value::Color(self)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{number}"),
+            Value::Color(color) => write!(f, "#{:02X}{:02X}{:02X}{:02X}", color.red, color.green, color.blue, color.alpha),
+        }
+    }
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %noisy_filename.as_ref().display())))]
+pub fn read_noisy_file<R, P>(noisy_filename: P) -> Result<Scene<R>, ReadFileError>
+where R: rand::Rng, P: AsRef<std::path::Path> {
+    NoisyLoader::new().read_file(noisy_filename)
+}
+
+/// Reads a .noisy file, filling in any `theme.<slot>` colors from `theme`
+pub fn read_noisy_file_themed<R, P>(noisy_filename: P, theme: &Theme) -> Result<Scene<R>, ReadFileError>
+where R: rand::Rng, P: AsRef<std::path::Path> {
+    NoisyLoader::new().with_theme(theme).read_file(noisy_filename)
+}
+
+pub fn parse_noisy<R: rand::Rng>(source: &str) -> Result<Scene<R>, ReadFileError> {
+    NoisyLoader::new().parse(source)
+}
+
+pub fn parse_noisy_themed<R: rand::Rng>(source: &str, theme: &Theme) -> Result<Scene<R>, ReadFileError> {
+    NoisyLoader::new().with_theme(theme).parse(source)
+}
+
+/// How deeply the fuzzing entry points let expressions nest, far more than any real file needs
+const FUZZ_EXPRESSION_DEPTH: usize = 256;
+
+/// Parses raw bytes, for fuzz targets. This never panics: invalid UTF-8 is a syntax error,
+/// and nesting is capped so hostile input can't overflow the stack.
+pub fn parse_noisy_bytes<R: rand::Rng>(source: &[u8]) -> Result<Scene<R>, ReadFileError> {
+    let source = std::str::from_utf8(source).map_err(|error| ReadFileError::SyntaxError(
+        format!("The file isn't valid UTF-8: {error}")
+    ))?;
+
+    NoisyLoader::new()
+        .with_limits(Limits { max_expression_depth: Some(FUZZ_EXPRESSION_DEPTH), ..Limits::UNLIMITED })
+        .parse(source)
+}
+
+/// Evaluates a single expression such as `(1920 - 40) / 2`, for fuzz targets. Constants and
+/// theme slots are errors since there's nothing to look them up in. This never panics.
+pub fn parse_expression(source: &str) -> Result<Value, ReadFileError> {
+    let limits = Limits { max_expression_depth: Some(FUZZ_EXPRESSION_DEPTH), ..Limits::UNLIMITED };
+    check_nesting(source, &limits)?;
+
+    let expression = NoisyParser::parse(Rule::lone_expression, source)
+        .map_err(|error| ReadFileError::SyntaxError(error.to_string()))?
+        .next()
+        .expect("The lone_expression rule always produces exactly one pair")
+        .into_inner()
+        .next()
+        .expect("lone_expression always contains an expression");
+
+    evaluate(expression, &Environment {
+        constants: HashMap::new(),
+        constant_slots: HashMap::new(),
+        overrides: &HashMap::new(),
+        theme: None,
+        limits: &limits,
+    }, 0)
+}
+
+/// A constant that frontends can tune, declared with `#param name: min..max = default`. The range is
+/// optional, and only numbers can have one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub default: Value,
+    pub range: Option<(f64, f64)>,
+}
+
+impl Parameter {
+    /// Errors if `value` isn't the same type as the default or is outside the range
+    fn check(&self, value: Value, line_num: usize) -> Result<(), ReadFileError> {
+        match (self.default, value, self.range) {
+            (Value::Number(_), Value::Number(number), Some((min, max))) if !(min..=max).contains(&number) => Err(ReadFileError::SyntaxError(
+                format!("#param {} on line {line_num} must be between {min} and {max}, but it's {number}", self.name)
+            )),
+            (Value::Number(_), Value::Number(_), _) => Ok(()),
+            (Value::Color(_), Value::Color(_), None) => Ok(()),
+            (Value::Color(_), Value::Color(_), Some(_)) => Err(ReadFileError::SyntaxError(
+                format!("#param {} on line {line_num} is a color, so it can't have a range", self.name)
+            )),
+            _ => Err(ReadFileError::SyntaxError(
+                format!("#param {} on line {line_num} can't be both a number and a color", self.name)
+            )),
+        }
+    }
+}
+
+/// The params of a .noisy file, for frontends that build controls for them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParameterSchema {
+    parameters: Vec<Parameter>,
+}
+
+impl ParameterSchema {
+    pub(crate) fn new(parameters: Vec<Parameter>) -> Self {
+        ParameterSchema { parameters }
+    }
+
+    /// In the order the file declares them
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// Such as `{"parameters":[{"name":"radius","type":"number","default":120,"min":10,"max":500}]}`.
+    /// Colors are hex codes, and numbers that JSON can't hold, like infinity, are `null`.
+    pub fn to_json(&self) -> String {
+        let json_number = |number: f64| if number.is_finite() { number.to_string() } else { "null".to_owned() };
+
+        let parameters: Vec<String> = self.parameters.iter().map(|parameter| {
+            // names only have letters, digits and underscores, so they never need escaping
+            let mut json = format!("{{\"name\":\"{}\"", parameter.name);
+            match parameter.default {
+                Value::Number(number) => json.push_str(&format!(",\"type\":\"number\",\"default\":{}", json_number(number))),
+                Value::Color(_) => json.push_str(&format!(",\"type\":\"color\",\"default\":\"{}\"", parameter.default)),
+            }
+            if let Some((min, max)) = parameter.range {
+                json.push_str(&format!(",\"min\":{},\"max\":{}", json_number(min), json_number(max)));
+            }
+            json.push('}');
+            json
+        }).collect();
+
+        format!("{{\"parameters\":[{}]}}", parameters.join(","))
+    }
+}
+
+/// Loads .noisy files with a theme, resource limits and/or replacement constants
+#[derive(Clone, Debug, Default)]
+pub struct NoisyLoader<'a> {
+    theme: Option<&'a Theme>,
+    limits: Limits,
+    // lowercase, like constants in the file
+    overrides: HashMap<String, Value>,
+}
+
+impl<'a> NoisyLoader<'a> {
+    pub fn new() -> Self {
+        NoisyLoader {
+            theme: None,
+            limits: Limits::UNLIMITED,
+            overrides: HashMap::new(),
+        }
+    }
+
+    pub fn with_theme(mut self, theme: &'a Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Uses `value` for the file's `#const` or `#param` called `name` instead of what the file sets it to. Loading fails
+    /// if the file doesn't define that constant.
+    pub fn with_constant(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.overrides.insert(name.to_lowercase(), value.into());
+        self
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = %noisy_filename.as_ref().display())))]
+    pub fn read_file<R, P>(&self, noisy_filename: P) -> Result<Scene<R>, ReadFileError>
+    where R: rand::Rng, P: AsRef<std::path::Path> {
+        self.parse(&std::fs::read_to_string(noisy_filename)?)
+    }
+
+    pub fn parse<R: rand::Rng>(&self, source: &str) -> Result<Scene<R>, ReadFileError> {
+        check_nesting(source, &self.limits)?;
+
+        let mut scene = parse_with_environment(source, Environment {
+            constants: HashMap::new(),
+            constant_slots: HashMap::new(),
+            overrides: &self.overrides,
+            theme: self.theme,
+            limits: &self.limits,
+        })?;
+        if let Some(theme) = self.theme {
+            scene.set_theme(theme.clone()).expect("Every theme slot the file uses was just filled in from the theme");
+        }
+        scene.set_source(NoisySource {
+            text: source.to_owned(),
+            theme: self.theme.cloned(),
+            limits: self.limits,
+            overrides: self.overrides.clone(),
+        });
+        Ok(scene)
+    }
+}
+
+/// Everything a scene was loaded from, so it can be loaded again with different constants
+#[derive(Clone, Debug)]
+pub(crate) struct NoisySource {
+    text: String,
+    theme: Option<Theme>,
+    limits: Limits,
+    overrides: HashMap<String, Value>,
+}
+
+impl NoisySource {
+    /// Loads the scene again the same way, but with `#const name` set to `value`
+    pub(crate) fn reload_with<R: rand::Rng>(&self, name: &str, value: Value) -> Result<Scene<R>, ReadFileError> {
+        NoisyLoader {
+            theme: self.theme.as_ref(),
+            limits: self.limits,
+            overrides: self.overrides.clone(),
+        }.with_constant(name, value).parse(&self.text)
+    }
+}
+
+/// Deep nesting can overflow the stack while parsing, before evaluation gets to count it, so this
+/// looks for it up front. Runs of minus signs are counted as negations, which overestimates `a - -b` by one
+fn check_nesting(source: &str, limits: &Limits) -> Result<(), LimitExceeded> {
+    if limits.max_expression_depth.is_none() {
+        return Ok(());
+    }
+
+    let mut paren_depth: usize = 0;
+    let mut minus_run: usize = 0;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                chars.by_ref().find(|&c| c == '\n');
+            },
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                chars.by_ref().find(|&c| {
+                    let closes = previous == '*' && c == '/';
+                    previous = c;
+                    closes
+                });
+            },
+            ('(', _) => paren_depth += 1,
+            (')', _) => paren_depth = paren_depth.saturating_sub(1),
+            ('-', _) => minus_run += 1,
+            (c, _) if c.is_whitespace() => {},
+            _ => minus_run = 0,
+        }
+        limits.check_expression_depth(paren_depth + minus_run)?;
+    }
+    Ok(())
+}
+
+struct Environment<'a> {
+    // names are case insensitive, so everything is stored lowercase
+    constants: HashMap<String, Value>,
+    // the constants that are just a copy of a theme slot
+    constant_slots: HashMap<String, String>,
+    overrides: &'a HashMap<String, Value>,
+    theme: Option<&'a Theme>,
+    limits: &'a Limits,
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(bytes = source.len()), err(Display)))]
+fn parse_with_environment<R: rand::Rng>(source: &str, mut environment: Environment<'_>) -> Result<Scene<R>, ReadFileError> {
+    let file = NoisyParser::parse(Rule::file, source)
+        .map_err(|error| ReadFileError::SyntaxError(error.to_string()))?
+        .next()
+        .expect("The file rule always produces exactly one pair");
+
+    let mut scene: Option<Scene<R>> = None;
+    let mut parameters = Vec::new();
+
+    for statement in file.into_inner() {
+        let line_num = statement.line_col().0;
+        match statement.as_rule() {
+            Rule::constant => {
+                let mut pieces = statement.into_inner();
+                let label = pieces.next().expect("Constants always have a name").as_str().to_lowercase();
+                let expression = pieces.next().expect("Constants always have a value");
+                let value = match environment.overrides.get(&label) {
+                    Some(value) => *value,
+                    None => {
+                        if let Some(slot) = theme_slot(&expression, &environment) {
+                            environment.constant_slots.insert(label.clone(), slot);
+                        }
+                        evaluate(expression, &environment, 0)?
+                    },
+                };
+
+                if let Some(existing_value) = environment.constants.get(&label) {
+                    return Err(ReadFileError::SyntaxError(
+                        format!("Invalid #const definition on line {line_num}. {label} is already used for another variable with value {existing_value}. Capitalization is ignored.")
+                    ));
+                }
+                environment.constants.insert(label, value);
+            },
+            Rule::param => {
+                let (parameter, slot) = read_param(statement, &environment)?;
+                let value = match environment.overrides.get(&parameter.name) {
+                    Some(value) => *value,
+                    None => {
+                        if let Some(slot) = slot {
+                            environment.constant_slots.insert(parameter.name.clone(), slot);
+                        }
+                        parameter.default
+                    },
+                };
+                parameter.check(value, line_num)?;
+
+                if let Some(existing_value) = environment.constants.get(&parameter.name) {
+                    return Err(ReadFileError::SyntaxError(
+                        format!("Invalid #param definition on line {line_num}. {} is already used for another variable with value {existing_value}. Capitalization is ignored.", parameter.name)
+                    ));
+                }
+                environment.constants.insert(parameter.name.clone(), value);
+                parameters.push(parameter);
+            },
+            Rule::block => {
+                let block = Block::read(statement, &environment)?;
+                #[cfg(feature = "tracing")]
+                tracing::debug!(kind = %block.kind, line = line_num, "parsed block");
+                match (block.kind.as_str(), scene.as_mut()) {
+                    ("canvas", None) => scene = Some(block.into_canvas(environment.limits)?),
+                    ("canvas", Some(_)) => return Err(ReadFileError::SyntaxError(
+                        format!("Duplicate canvas block on line {line_num}. There can only be one canvas.")
+                    )),
+                    (_, None) => return Err(ReadFileError::SyntaxError(
+                        format!("The {} block on line {line_num} comes before the canvas block.", block.kind)
+                    )),
+                    (_, Some(scene)) => {
+                        block.add_to_scene(scene)?;
+                        environment.limits.check_instructions(scene.instructions().len())?;
+                    },
+                }
+            },
+            Rule::EOI => {},
+            _ => unreachable!("The file rule only contains constants, params and blocks"),
+        }
+    }
+
+    if let Some(name) = environment.overrides.keys().find(|name| !environment.constants.contains_key(*name)) {
+        return Err(ReadFileError::SyntaxError(
+            format!("{name} was given a value, but there's no #const {name} in the file")
+        ));
+    }
+
+    let mut scene = scene.ok_or_else(|| ReadFileError::SyntaxError("Missing canvas block".into()))?;
+    scene.set_parameters(parameters);
+    Ok(scene)
+}
+
+/// Also returns the theme slot the default is a copy of, if it is one
+fn read_param(param: Pair<Rule>, environment: &Environment<'_>) -> Result<(Parameter, Option<String>), ReadFileError> {
+    let line_num = param.line_col().0;
+    let mut pieces: Vec<Pair<Rule>> = param.into_inner().collect();
+    let name = pieces.remove(0).as_str().to_lowercase();
+    let default_expression = pieces.pop().expect("Params always have a default");
+    let slot = theme_slot(&default_expression, environment);
+    let default = evaluate(default_expression, environment, 0)?;
+
+    let range = match pieces.as_slice() {
+        [min, max] => match (evaluate(min.clone(), environment, 0)?, evaluate(max.clone(), environment, 0)?) {
+            (Value::Number(min), Value::Number(max)) if min <= max => Some((min, max)),
+            (Value::Number(_), Value::Number(_)) => return Err(ReadFileError::SyntaxError(
+                format!("The range of #param {name} on line {line_num} ends before it starts")
+            )),
+            _ => return Err(ReadFileError::SyntaxError(
+                format!("The range of #param {name} on line {line_num} must be made of numbers")
+            )),
+        },
+        [] => None,
+        _ => unreachable!("Params have either a range or nothing between the name and the default"),
+    };
+
+    let parameter = Parameter { name, default, range };
+    parameter.check(default, line_num)?;
+    Ok((parameter, slot))
+}
+
+/// The theme slot `expression` is nothing but a copy of, directly or through a constant, so the color
+/// can follow the scene's theme when it changes
+fn theme_slot(expression: &Pair<Rule>, environment: &Environment<'_>) -> Option<String> {
+    match expression.as_rule() {
+        Rule::expression | Rule::term => {
+            let mut pieces = expression.clone().into_inner();
+            let operand = pieces.next()?;
+            if pieces.next().is_some() {
+                return None;
+            }
+            theme_slot(&operand, environment)
+        },
+        Rule::theme_slot => Some(expression.as_str()["theme.".len()..].to_lowercase()),
+        Rule::name => environment.constant_slots.get(&expression.as_str().to_lowercase()).cloned(),
+        _ => None,
+    }
+}
+
+/// `depth` counts parentheses and negations, since those are what nest
+fn evaluate(expression: Pair<Rule>, environment: &Environment<'_>, depth: usize) -> Result<Value, ReadFileError> {
+    environment.limits.check_expression_depth(depth)?;
+    let nested_depth = |operand: &Pair<Rule>| match operand.as_rule() {
+        Rule::expression | Rule::negation => depth + 1,
+        _ => depth,
+    };
+    let line_num = expression.line_col().0;
+    let as_number = |value: Value| match value {
+        Value::Number(number) => Ok(number),
+        Value::Color(_) => Err(ReadFileError::SyntaxError(
+            format!("Colors cannot be used in arithmetic on line {line_num}")
+        )),
+    };
+
+    match expression.as_rule() {
+        Rule::expression | Rule::term => {
+            let mut pieces = expression.into_inner();
+            let operand = pieces.next().expect("Expressions have at least one operand");
+            let mut value = evaluate(operand.clone(), environment, nested_depth(&operand))?;
+
+            while let Some(operator) = pieces.next() {
+                let lhs = as_number(value)?;
+                let operand = pieces.next().expect("Operators are always followed by an operand");
+                let rhs = as_number(evaluate(operand.clone(), environment, nested_depth(&operand))?)?;
+                value = Value::Number(match operator.as_str() {
+                    "+" => lhs + rhs,
+                    "-" => lhs - rhs,
+                    "*" => lhs * rhs,
+                    "/" => lhs / rhs,
+                    _ => unreachable!("The grammar only allows + - * /"),
+                });
+            }
+            Ok(value)
+        },
+        Rule::negation => {
+            let inner = expression.into_inner().next().expect("Negations always have an operand");
+            Ok(Value::Number(-as_number(evaluate(inner.clone(), environment, nested_depth(&inner))?)?))
+        },
+        Rule::number => Ok(Value::Number(expression.as_str().parse().expect("The grammar only allows valid numbers"))),
+        Rule::color => expression.as_str().parse::<TransparentColor>().map(Value::Color).map_err(|()| ReadFileError::SyntaxError(
+            format!("Invalid color {} on line {line_num}", expression.as_str())
+        )),
+        Rule::name => {
+            let label = expression.as_str().to_lowercase();
+            // constants can shadow the CSS color names
+            environment.constants.get(&label).copied()
+                .or_else(|| SolidColor::from_name(&label).map(|color| Value::Color(color.into())))
+                .ok_or_else(|| ReadFileError::SyntaxError(
+                    format!("Unknown constant {label} on line {line_num}. Constants must be defined before they are used.")
+                ))
+        },
+        Rule::theme_slot => {
+            let slot = &expression.as_str()["theme.".len()..];
+            let theme = environment.theme.ok_or_else(|| ReadFileError::SyntaxError(
+                format!("theme.{slot} is used on line {line_num}, but no theme was given")
+            ))?;
+            theme.get_slot(slot).map(Value::Color).ok_or_else(|| ReadFileError::SyntaxError(
+                format!("The theme doesn't have a {slot} slot, which is used on line {line_num}")
+            ))
+        },
+        _ => unreachable!("Only expression rules are evaluated"),
+    }
+}
+
+/// A coloring and the theme slots that fill in its colors
+type ThemedColoring = (ColorScheme<TransparentColor>, Vec<(String, ColorPart)>);
+
+struct Block {
+    kind: String,
+    label: Option<String>,
+    line_num: usize,
+    properties: HashMap<String, Value>,
+    // the properties that are just a copy of a theme slot
+    property_slots: HashMap<String, String>,
+    style_name: Option<String>,
+}
+
+impl Block {
+    fn read(block: Pair<Rule>, environment: &Environment<'_>) -> Result<Self, ReadFileError> {
+        let line_num = block.line_col().0;
+        let mut kind = None;
+        let mut label = None;
+        let mut properties = HashMap::new();
+        let mut property_slots = HashMap::new();
+        let mut style_name = None;
+
+        for piece in block.into_inner() {
+            match piece.as_rule() {
+                Rule::name if kind.is_none() => kind = Some(piece.as_str().to_lowercase()),
+                Rule::name => label = Some(piece.as_str().to_lowercase()),
+                Rule::property => {
+                    let property_line = piece.line_col().0;
+                    let mut pieces = piece.into_inner();
+                    let property = pieces.next().expect("Properties always have a name").as_str().to_lowercase();
+                    let expression = pieces.next().expect("Properties always have a value");
+
+                    if properties.contains_key(&property) || (property == "style" && style_name.is_some()) {
+                        return Err(ReadFileError::SyntaxError(
+                            format!("Duplicate property {property} on line {property_line}")
+                        ));
+                    }
+
+                    // styles are referenced by name rather than evaluated
+                    if property == "style" {
+                        // the expression's text can run on into a comment after it, so the name is taken from its one token
+                        let mut tokens = expression.clone().into_inner().flatten();
+                        let name = match (tokens.next(), tokens.next(), tokens.next()) {
+                            (Some(_), Some(name), None) if name.as_rule() == Rule::name => name.as_str(),
+                            _ => return Err(ReadFileError::SyntaxError(
+                                format!("Invalid style name {} on line {property_line}", expression.as_str().trim())
+                            )),
+                        };
+                        style_name = Some(name.to_lowercase());
+                    } else {
+                        if let Some(slot) = theme_slot(&expression, environment) {
+                            property_slots.insert(property.clone(), slot);
+                        }
+                        properties.insert(property, evaluate(expression, environment, 0)?);
+                    }
+                },
+                _ => unreachable!("Blocks only contain names and properties"),
+            }
+        }
+
+        Ok(Block {
+            kind: kind.expect("Blocks always have a name"),
+            label,
+            line_num,
+            properties,
+            property_slots,
+            style_name,
+        })
+    }
+
+    fn take_number(&mut self, property: &str) -> Result<Option<f64>, ReadFileError> {
+        match self.properties.remove(property) {
+            None => Ok(None),
+            Some(Value::Number(number)) => Ok(Some(number)),
+            Some(Value::Color(_)) => Err(ReadFileError::SyntaxError(
+                format!("Property {property} of the {} block on line {} must be a number", self.kind, self.line_num)
+            )),
+        }
+    }
+
+    fn require_number(&mut self, property: &str) -> Result<f64, ReadFileError> {
+        self.take_number(property)?.ok_or_else(|| ReadFileError::SyntaxError(
+            format!("The {} block on line {} is missing the {property} property", self.kind, self.line_num)
+        ))
+    }
+
+    fn take_color(&mut self, property: &str) -> Result<Option<TransparentColor>, ReadFileError> {
+        match self.properties.remove(property) {
+            None => Ok(None),
+            Some(Value::Color(color)) => Ok(Some(color)),
+            Some(Value::Number(_)) => Err(ReadFileError::SyntaxError(
+                format!("Property {property} of the {} block on line {} must be a color", self.kind, self.line_num)
+            )),
+        }
+    }
+
+    fn require_color(&mut self, property: &str) -> Result<TransparentColor, ReadFileError> {
+        self.take_color(property)?.ok_or_else(|| ReadFileError::SyntaxError(
+            format!("The {} block on line {} is missing the {property} property", self.kind, self.line_num)
+        ))
+    }
+
+    /// Also returns the theme slots any of the coloring's colors are copies of
+    fn take_coloring(&mut self) -> Result<Option<ThemedColoring>, ReadFileError> {
+        let solid_color = self.take_color("color")?;
+        let has_gradient = self.properties.keys().any(|property| property.starts_with("gradientpole"));
+
+        match (solid_color, has_gradient) {
+            (Some(_), true) => Err(ReadFileError::SyntaxError(
+                format!("The {} block on line {} has both a color and a gradient", self.kind, self.line_num)
+            )),
+            (Some(color), false) => {
+                let slots = self.property_slots.get("color").map(|slot| (slot.clone(), ColorPart::Solid));
+                Ok(Some((ColorScheme::Solid(color), slots.into_iter().collect())))
+            },
+            (None, true) => {
+                let pole1 = Point { x: self.require_number("gradientpole1x")?, y: self.require_number("gradientpole1y")? };
+                let color1 = self.require_color("gradientpole1color")?;
+                let pole2 = Point { x: self.require_number("gradientpole2x")?, y: self.require_number("gradientpole2y")? };
+                let color2 = self.require_color("gradientpole2color")?;
+
+                if pole1 == pole2 {
+                    return Err(ReadFileError::SyntaxError(
+                        format!("The gradient poles of the {} block on line {} must be distinct", self.kind, self.line_num)
+                    ));
+                }
+                let slots = [("gradientpole1color", pole1), ("gradientpole2color", pole2)].into_iter()
+                    .filter_map(|(property, location)| self.property_slots.get(property).map(|slot| (slot.clone(), ColorPart::GradientPole(location))))
+                    .collect();
+                Ok(Some((LinearGradient::with_poles((pole1, color1), (pole2, color2)).into(), slots)))
+            },
+            (None, false) => Ok(None),
+        }
+    }
+
+    fn take_shape(&mut self) -> Result<Shape, ReadFileError> {
+        match self.kind.as_str() {
+            "rectangle" => {
+                let point1 = Point { x: self.require_number("point1x")?, y: self.require_number("point1y")? };
+                let point2 = Point { x: self.require_number("point2x")?, y: self.require_number("point2y")? };
+                Ok(Rect::from_points(&point1, &point2).into())
+            },
+            "circle" => {
+                let center = Point { x: self.require_number("centerx")?, y: self.require_number("centery")? };
+                Ok(Ellipse::circle(center, self.require_number("radius")?).into())
+            },
+            kind => Err(ReadFileError::SyntaxError(
+                format!("Unknown block type {kind} on line {}", self.line_num)
+            )),
+        }
+    }
+
+    /// Errors on any properties that weren't used while building the block
+    fn finish(&self) -> Result<(), ReadFileError> {
+        let mut leftover: Vec<&str> = self.properties.keys().map(String::as_str).collect();
+        if leftover.is_empty() {
+            return Ok(());
+        }
+        leftover.sort();
+        Err(ReadFileError::SyntaxError(
+            format!("Unknown properties for the {} block on line {}: {}", self.kind, self.line_num, leftover.join(", "))
+        ))
+    }
+
+    fn into_canvas<R: rand::Rng>(mut self, limits: &Limits) -> Result<Scene<R>, ReadFileError> {
+        let as_dimension = |block: &Block, property: &str, value: f64| {
+            if value >= 1. && value.fract() == 0. {
+                Ok(value as usize)
+            } else {
+                Err(ReadFileError::SyntaxError(
+                    format!("The canvas {property} on line {} must be a positive whole number", block.line_num)
+                ))
+            }
+        };
+
+        let width = self.require_number("width")?;
+        let width = as_dimension(&self, "width", width)?;
+        let height = self.require_number("height")?;
+        let height = as_dimension(&self, "height", height)?;
+        let background_slot = self.property_slots.get("color").cloned();
+        let background_color = match self.take_color("color")? {
+            None => SolidColor::BLACK,
+            Some(color) => color.try_into().map_err(|_| ReadFileError::SyntaxError(
+                format!("The canvas color on line {} cannot be transparent", self.line_num)
+            ))?,
+        };
+        self.finish()?;
+        limits.check_canvas(width, height)?;
+
+        let mut scene = Scene::new(width, height, background_color);
+        if let Some(slot) = background_slot {
+            scene.bind_theme_slot(ThemeBinding { slot, target: ThemeTarget::Background, part: ColorPart::Solid });
+        }
+        Ok(scene)
+    }
+
+    fn add_to_scene<R: rand::Rng>(mut self, scene: &mut Scene<R>) -> Result<(), ReadFileError> {
+        if self.kind == "style" {
+            let label = self.label.take().ok_or_else(|| ReadFileError::SyntaxError(
+                format!("The style block on line {} needs a name", self.line_num)
+            ))?;
+            if self.style_name.is_some() {
+                return Err(ReadFileError::SyntaxError(
+                    format!("The style block on line {} cannot reference another style", self.line_num)
+                ));
+            }
+            if scene.get_style(&label).is_some() {
+                return Err(ReadFileError::SyntaxError(
+                    format!("Duplicate style {label} on line {}", self.line_num)
+                ));
+            }
+
+            let (coloring, slots) = self.take_coloring()?.unwrap_or_else(|| (ColorScheme::Solid(TransparentColor::TRANSPARENT), Vec::new()));
+            let opacity = self.take_number("opacity")?.unwrap_or(1.);
+            self.finish()?;
+            scene.add_style(&label, Style {
+                pre_clip_noise: None,
+                coloring,
+                post_clip_noise: None,
+                post_draw_noise: None,
+                opacity,
+                blend_mode: BlendMode::Normal,
+                dithering: None,
+            });
+            for (slot, part) in slots {
+                scene.bind_theme_slot(ThemeBinding { slot, target: ThemeTarget::Style(label.clone()), part });
+            }
+            return Ok(());
+        }
+
+        if let Some(label) = &self.label {
+            return Err(ReadFileError::SyntaxError(
+                format!("Only style blocks can be named, but the {} block on line {} is named {label}", self.kind, self.line_num)
+            ));
+        }
+
+        let clipping_shape = self.take_shape()?;
+        let coloring = self.take_coloring()?;
+        let opacity = self.take_number("opacity")?;
+        self.finish()?;
+
+        match (self.style_name, coloring) {
+            (Some(_), Some(_)) => Err(ReadFileError::SyntaxError(
+                format!("The {} block on line {} has both a style and its own coloring", self.kind, self.line_num)
+            )),
+            (Some(_), None) if opacity.is_some() => Err(ReadFileError::SyntaxError(
+                format!("The {} block on line {} has both a style and its own opacity. Set the opacity in the style instead.", self.kind, self.line_num)
+            )),
+            (Some(style_name), None) => scene.push_styled(clipping_shape, &style_name).map_err(|_| ReadFileError::SyntaxError(
+                format!("Unknown style {style_name} on line {}. Styles must be defined before they are used.", self.line_num)
+            )),
+            (None, coloring) => {
+                let (coloring, slots) = coloring.unwrap_or_else(|| (ColorScheme::Solid(TransparentColor::TRANSPARENT), Vec::new()));
+                for (slot, part) in slots {
+                    scene.bind_theme_slot(ThemeBinding { slot, target: ThemeTarget::Instruction(scene.instructions().len()), part });
+                }
+                scene.push(DrawInstruction {
+                    pre_clip_noise: None,
+                    clipping_shape,
+                    coloring,
+                    post_clip_noise: None,
+                    post_draw_noise: None,
+                    opacity: opacity.unwrap_or(1.),
+                    blend_mode: BlendMode::Normal,
+                    dithering: None,
+                });
+                Ok(())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::Image;
+
+    const SAMPLE: &str = "#const W = 64
+#const H = W / 2
+#param radius: 1..40 = 10
+#param tint = #BA2E37
+
+canvas {
+    width W
+    height H
+    color rgb(5, 47, 95)
+}
+
+style accent {
+    gradientPole1x 0
+    gradientPole1y 0
+    gradientPole1color tint
+    gradientPole2x W
+    gradientPole2y H
+    gradientPole2color #B57905
+}
+
+rectangle {
+    point1x 4
+    point1y 4
+    point2x W - 4
+    point2y H - (2 * 2)
+    color #06A77C80
+}
+
+circle {
+    centerX W / 2
+    centerY H / 2
+    radius radius
+    style accent
+}
+";
+
+    fn render(scene: &Scene<StdRng>) -> Image {
+        scene.render(&mut StdRng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn expressions_follow_precedence() {
+        assert_eq!(parse_expression("(1920 - 40) / 2").unwrap(), Value::Number(940.));
+        assert_eq!(parse_expression("1 + 2 * 3").unwrap(), Value::Number(7.));
+        assert_eq!(parse_expression("- -4 - 1").unwrap(), Value::Number(3.));
+        assert_eq!(parse_expression("0.5 * 3").unwrap(), Value::Number(1.5));
+    }
+
+    #[test]
+    fn expression_values_round_trip_through_display() {
+        for source in ["12.25", "#BA2E37", "#052F5F80", "rgba(10, 20, 30, 0.5)", "rebeccapurple"] {
+            let value = parse_expression(source).unwrap();
+            assert_eq!(parse_expression(&value.to_string()).unwrap(), value, "{source} came back as {value}");
+        }
+    }
+
+    #[test]
+    fn bad_expressions_are_errors() {
+        for source in ["", "(", ")", "1 +", "* 2", "#12345", "#1234567", "#GGGGGG", "rgb(1, 2)", "undefined_name", "theme.accent1", "((((1)))"] {
+            assert!(parse_expression(source).is_err(), "{source:?} parsed");
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_instead_of_a_stack_overflow() {
+        let parentheses = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(matches!(parse_expression(&parentheses), Err(ReadFileError::LimitExceeded(_))));
+
+        let negations = format!("{}1", "-".repeat(100_000));
+        assert!(matches!(parse_expression(&negations), Err(ReadFileError::LimitExceeded(_))));
+
+        let file = format!("canvas {{\n    width {parentheses}\n    height 1\n}}\n");
+        assert!(matches!(parse_noisy_bytes::<StdRng>(file.as_bytes()), Err(ReadFileError::LimitExceeded(_))));
+    }
+
+    #[test]
+    fn bytes_parse_the_same_as_text() {
+        let from_bytes = parse_noisy_bytes::<StdRng>(SAMPLE.as_bytes()).unwrap();
+        let from_text = parse_noisy::<StdRng>(SAMPLE).unwrap();
+        assert_eq!(render(&from_bytes).canvas, render(&from_text).canvas);
+    }
+
+    #[test]
+    fn invalid_utf8_is_a_syntax_error() {
+        assert!(matches!(parse_noisy_bytes::<StdRng>(b"canvas { width \xFF }"), Err(ReadFileError::SyntaxError(_))));
+    }
+
+    #[test]
+    fn truncated_and_corrupted_files_never_panic() {
+        let bytes = SAMPLE.as_bytes();
+        for end in 0..=bytes.len() {
+            let _ = parse_noisy_bytes::<StdRng>(&bytes[..end]);
+        }
+
+        for index in 0..bytes.len() {
+            for replacement in [b'{', b'}', b'#', b'(', b'-', b'0', 0xFF] {
+                let mut corrupted = bytes.to_vec();
+                corrupted[index] = replacement;
+                let _ = parse_noisy_bytes::<StdRng>(&corrupted);
+            }
+        }
+    }
+
+    const THEMED: &str = "#const ink = theme.Foreground
+
+canvas {
+    width 16
+    height 8
+    color theme.background
+}
+
+style accent {
+    gradientPole1x 0
+    gradientPole1y 0
+    gradientPole1color theme.accent1
+    gradientPole2x 16
+    gradientPole2y 8
+    gradientPole2color theme.accent2
+}
+
+rectangle {
+    point1x 0
+    point1y 0
+    point2x 7
+    point2y 7
+    color ink
+}
+
+circle {
+    centerX 12
+    centerY 4
+    radius 3
+    style accent
+}
+";
+
+    #[test]
+    fn theme_slots_are_filled_in_from_the_theme() {
+        let scene = parse_noisy_themed::<StdRng>(THEMED, &Theme::light()).unwrap();
+        let image = render(&scene);
+        let light = |slot: &str| Theme::light().get_slot(slot).unwrap().as_solid();
+        assert_eq!(*image.get_pixel(15, 0), light("background"));
+        assert_eq!(*image.get_pixel(3, 3), light("foreground"));
+        assert_ne!(*image.get_pixel(12, 4), light("background"));
+
+        assert!(parse_noisy::<StdRng>(THEMED).is_err());
+        assert!(parse_noisy_themed::<StdRng>(THEMED, &Theme::new().with_slot("background", SolidColor::BLACK.into())).is_err());
+    }
+
+    #[test]
+    fn changing_the_theme_matches_loading_with_it() {
+        let mut scene = parse_noisy_themed::<StdRng>(THEMED, &Theme::light()).unwrap();
+        scene.set_theme(Theme::dark()).unwrap();
+        assert_eq!(render(&scene).canvas, render(&parse_noisy_themed(THEMED, &Theme::dark()).unwrap()).canvas);
+
+        // a theme missing a slot the scene uses is turned away, and the old one stays
+        let mut incomplete = Theme::new();
+        for slot in ["background", "foreground", "accent1"] {
+            incomplete.set_slot(slot, Theme::high_contrast().get_slot(slot).unwrap());
+        }
+        assert!(scene.set_theme(incomplete).is_err());
+        assert_eq!(render(&scene).canvas, render(&parse_noisy_themed(THEMED, &Theme::dark()).unwrap()).canvas);
+    }
+}
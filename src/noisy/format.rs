@@ -0,0 +1,285 @@
+use super::syntax::{SyntaxKind, SyntaxNode, SyntaxTree};
+use super::ReadFileError;
+
+/// Properties in the order they're written out, in groups that get a blank line between them.
+/// Anything not listed goes last, in its original order.
+const PROPERTY_GROUPS: [&[&str]; 4] = [
+    &["width", "height", "point1x", "point1y", "point2x", "point2y", "centerx", "centery", "radius"],
+    &["style", "color"],
+    &["gradientpole1x", "gradientpole1y", "gradientpole1color"],
+    &["gradientpole2x", "gradientpole2y", "gradientpole2color"],
+];
+
+/// Rewrites a .noisy file in the standard layout: one tab of indentation, spaces around operators,
+/// properties in a fixed order and blank lines around blocks. Comments are kept.
+pub fn format_noisy(source: &str) -> Result<String, ReadFileError> {
+    let tree = SyntaxTree::parse(source);
+    if let Some(error) = tree.errors().first() {
+        let line_num = source[..error.range.start].matches('\n').count() + 1;
+        return Err(ReadFileError::SyntaxError(format!("{} on line {line_num}", error.message)));
+    }
+
+    let mut output = String::new();
+    // the kind of the last thing written and how many line breaks came after it in the original
+    let mut previous: Option<SyntaxKind> = None;
+    let mut newlines = 0;
+    for child in tree.root().children() {
+        let text = child.text(source);
+        match child.kind() {
+            SyntaxKind::Whitespace => newlines += text.matches('\n').count(),
+            SyntaxKind::Comment if newlines == 0 && matches!(previous, Some(SyntaxKind::Constant | SyntaxKind::Param | SyntaxKind::Block)) => {
+                // a comment on the same line as the end of a constant, param or block stays there
+                output.pop();
+                output.push(' ');
+                output.push_str(text);
+                output.push('\n');
+            },
+            kind => {
+                let needs_blank_line = newlines >= 2
+                    || (previous != Some(SyntaxKind::Comment) && (previous == Some(SyntaxKind::Block) || kind == SyntaxKind::Block));
+                if previous.is_some() && needs_blank_line {
+                    output.push('\n');
+                }
+
+                match kind {
+                    SyntaxKind::Constant => format_constant(child, source, &mut output),
+                    SyntaxKind::Param => format_param(child, source, &mut output),
+                    SyntaxKind::Block => format_block(child, source, &mut output),
+                    _ => output.push_str(text),
+                }
+                output.push('\n');
+                previous = Some(kind);
+                newlines = 0;
+            },
+        }
+    }
+
+    Ok(output)
+}
+
+fn format_constant(constant: &SyntaxNode, source: &str, output: &mut String) {
+    let mut pieces = constant.children().iter().filter(|child| !child.kind().is_trivia());
+    let name = pieces.find(|child| child.kind() == SyntaxKind::Name).expect("Constants always have a name");
+    let expression = pieces.find(|child| child.kind() == SyntaxKind::Expression).expect("Constants always have a value");
+
+    output.push_str("#const ");
+    output.push_str(name.text(source));
+    output.push_str(" = ");
+    output.push_str(&format_expression(expression, source));
+}
+
+fn format_param(param: &SyntaxNode, source: &str, output: &mut String) {
+    let mut pieces = param.children().iter().filter(|child| !child.kind().is_trivia());
+    let name = pieces.find(|child| child.kind() == SyntaxKind::Name).expect("Params always have a name");
+    let expressions: Vec<&SyntaxNode> = pieces.filter(|child| child.kind() == SyntaxKind::Expression).collect();
+
+    output.push_str("#param ");
+    output.push_str(name.text(source));
+    if let [min, max, _] = expressions.as_slice() {
+        output.push_str(": ");
+        output.push_str(&format_expression(min, source));
+        output.push_str("..");
+        output.push_str(&format_expression(max, source));
+    }
+    output.push_str(" = ");
+    output.push_str(&format_expression(expressions.last().expect("Params always have a default"), source));
+}
+
+/// A property along with the comments that belong to it
+struct PropertyLine<'a> {
+    leading_comments: Vec<&'a str>,
+    text: String,
+    trailing_comment: Option<&'a str>,
+    // index into PROPERTY_GROUPS and position within it
+    rank: (usize, usize),
+}
+
+fn format_block(block: &SyntaxNode, source: &str, output: &mut String) {
+    let mut header = Vec::new();
+    let mut lines: Vec<PropertyLine> = Vec::new();
+    let mut comments = Vec::new();
+    let mut newlines = 0;
+
+    for child in block.children() {
+        let text = child.text(source);
+        match child.kind() {
+            SyntaxKind::Name => header.push(text),
+            SyntaxKind::Whitespace => newlines += text.matches('\n').count(),
+            SyntaxKind::Comment => match lines.last_mut() {
+                Some(line) if newlines == 0 && comments.is_empty() && line.trailing_comment.is_none() => line.trailing_comment = Some(text),
+                _ => comments.push(text),
+            },
+            SyntaxKind::Property => {
+                let mut pieces = child.children().iter().filter(|piece| !piece.kind().is_trivia());
+                let name = pieces.next().expect("Properties always have a name").text(source);
+                let expression = pieces.next().expect("Properties always have a value");
+
+                lines.push(PropertyLine {
+                    leading_comments: std::mem::take(&mut comments),
+                    text: format!("{name} {}", format_expression(expression, source)),
+                    trailing_comment: None,
+                    rank: property_rank(name),
+                });
+                newlines = 0;
+            },
+            _ => {},
+        }
+    }
+    lines.sort_by_key(|line| line.rank);
+
+    output.push_str(&header.join(" "));
+    output.push_str(" {\n");
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 && line.rank.0 != lines[index - 1].rank.0 {
+            output.push('\n');
+        }
+        for comment in line.leading_comments.iter() {
+            output.push('\t');
+            output.push_str(comment);
+            output.push('\n');
+        }
+        output.push('\t');
+        output.push_str(&line.text);
+        if let Some(comment) = line.trailing_comment {
+            output.push(' ');
+            output.push_str(comment);
+        }
+        output.push('\n');
+    }
+    // comments after the last property
+    if !comments.is_empty() && !lines.is_empty() {
+        output.push('\n');
+    }
+    for comment in comments {
+        output.push('\t');
+        output.push_str(comment);
+        output.push('\n');
+    }
+    output.push('}');
+}
+
+fn property_rank(name: &str) -> (usize, usize) {
+    let name = name.to_lowercase();
+    PROPERTY_GROUPS.iter().enumerate()
+        .find_map(|(group_index, group)| group.iter().position(|property| *property == name).map(|position| (group_index, position)))
+        .unwrap_or((PROPERTY_GROUPS.len(), 0))
+}
+
+fn format_expression(expression: &SyntaxNode, source: &str) -> String {
+    // there's no good place to move a comment in the middle of an expression, so those are left alone
+    if expression.tokens().any(|token| token.kind() == SyntaxKind::Comment) {
+        return expression.text(source).to_owned();
+    }
+
+    let mut formatted = String::new();
+    for child in expression.children() {
+        match child.kind() {
+            SyntaxKind::Whitespace => {},
+            SyntaxKind::AddOp | SyntaxKind::MulOp => {
+                formatted.push(' ');
+                formatted.push_str(child.text(source));
+                formatted.push(' ');
+            },
+            SyntaxKind::Expression | SyntaxKind::Term | SyntaxKind::Negation => formatted.push_str(&format_expression(child, source)),
+            _ => formatted.push_str(child.text(source)),
+        }
+    }
+    if expression.children().is_empty() {
+        formatted.push_str(expression.text(source));
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::noisy::parse_noisy;
+    use crate::scene::Scene;
+
+    const SAMPLES: [&str; 3] = [
+        "#const W=64
+#const H = W/2
+canvas {width W
+height H
+color #052F5F}
+rectangle {
+  gradientPole2color #06A77C
+  point2y H   point2x W
+  point1x 0 point1y 0
+  gradientPole1color #052F5F gradientPole2y H gradientPole1x 0
+  gradientPole1y 0 gradientPole2x W
+}",
+        "// sizes
+#param radius: 1..(40-2) = 10 // tuned by hand
+#param tint = #BA2E37
+#const W = 64
+
+
+canvas {
+	width W
+	height W
+}
+style accent {
+	color tint
+	opacity 0.5
+}
+circle {
+	// in the middle
+	centerX W/2
+	centerY W/2
+	radius radius
+	style accent /* shared */
+}",
+        "canvas { width 16 height 16 color rgb(5, 47, 95) }
+/* a comment
+   over lines */
+circle { centerX - -8 centerY 8*(1+0) radius 4 color rebeccapurple
+// left over
+}",
+    ];
+
+    fn render(source: &str) -> Vec<crate::coloring::SolidColor> {
+        let scene: Scene<StdRng> = parse_noisy(source).unwrap();
+        scene.render(&mut StdRng::seed_from_u64(0)).canvas
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        for sample in SAMPLES {
+            let formatted = format_noisy(sample).unwrap();
+            assert_eq!(format_noisy(&formatted).unwrap(), formatted, "formatting {sample:?} twice changed it");
+        }
+    }
+
+    #[test]
+    fn formatting_keeps_the_render() {
+        for sample in SAMPLES {
+            let formatted = format_noisy(sample).unwrap();
+            assert!(render(&formatted) == render(sample), "formatting {sample:?} changed the image");
+        }
+    }
+
+    #[test]
+    fn formatting_keeps_params_and_comments() {
+        let formatted = format_noisy(SAMPLES[1]).unwrap();
+        let original: Scene<StdRng> = parse_noisy(SAMPLES[1]).unwrap();
+        let reformatted: Scene<StdRng> = parse_noisy(&formatted).unwrap();
+        assert_eq!(original.parameter_schema(), reformatted.parameter_schema());
+
+        assert!(formatted.starts_with("// sizes\n#param radius: 1..(40 - 2) = 10 // tuned by hand\n"));
+        for comment in ["// in the middle", "/* shared */"] {
+            assert!(formatted.contains(comment), "{comment} went missing from {formatted:?}");
+        }
+    }
+
+    #[test]
+    fn syntax_errors_are_reported_with_their_line() {
+        let Err(ReadFileError::SyntaxError(message)) = format_noisy("canvas {\n\twidth 1\n\theight\n}") else {
+            panic!("a property without a value formatted");
+        };
+        assert!(message.ends_with("on line 4"), "{message}");
+    }
+}
@@ -0,0 +1,401 @@
+use std::ops::Range;
+
+use pest::error::InputLocation;
+use pest::iterators::Pair;
+use pest::Parser;
+
+use super::{NoisyParser, Rule};
+
+/// What a node in a syntax tree represents. The first group mirrors the grammar, the rest are the
+/// bits of text between grammar nodes that a lossless tree still has to keep.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SyntaxKind {
+    File,
+    Constant,
+    Param,
+    Block,
+    Property,
+    Expression,
+    Term,
+    Negation,
+    AddOp,
+    MulOp,
+    Number,
+    Color,
+    ThemeSlot,
+    Name,
+
+    Keyword,
+    Punctuation,
+    Whitespace,
+    Comment,
+    /// Text that couldn't be parsed
+    Error,
+}
+
+impl SyntaxKind {
+    /// Whitespace and comments, which don't change what the file means
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+
+    fn from_rule(rule: Rule) -> Self {
+        match rule {
+            Rule::file => SyntaxKind::File,
+            Rule::constant => SyntaxKind::Constant,
+            Rule::param => SyntaxKind::Param,
+            Rule::block => SyntaxKind::Block,
+            Rule::property => SyntaxKind::Property,
+            Rule::expression => SyntaxKind::Expression,
+            Rule::term => SyntaxKind::Term,
+            Rule::negation => SyntaxKind::Negation,
+            Rule::add_op => SyntaxKind::AddOp,
+            Rule::mul_op => SyntaxKind::MulOp,
+            Rule::number => SyntaxKind::Number,
+            Rule::color => SyntaxKind::Color,
+            Rule::theme_slot => SyntaxKind::ThemeSlot,
+            Rule::name => SyntaxKind::Name,
+            _ => unreachable!("Only rules from the file rule end up in the tree"),
+        }
+    }
+}
+
+/// A node covering `range` bytes of the source. Leaves are tokens, and the text of all the leaves
+/// in order is exactly the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxNode {
+    kind: SyntaxKind,
+    range: Range<usize>,
+    children: Vec<SyntaxNode>,
+}
+
+impl SyntaxNode {
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    pub fn children(&self) -> &[SyntaxNode] {
+        &self.children
+    }
+
+    /// `source` has to be the source the node was parsed from
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.range.clone()]
+    }
+
+    /// Every leaf under this node, in source order
+    pub fn tokens(&self) -> Box<dyn Iterator<Item = &SyntaxNode> + '_> {
+        if self.children.is_empty() {
+            Box::new(std::iter::once(self))
+        } else {
+            Box::new(self.children.iter().flat_map(SyntaxNode::tokens))
+        }
+    }
+
+    fn leaf(kind: SyntaxKind, range: Range<usize>) -> Self {
+        SyntaxNode {
+            kind,
+            range,
+            children: Vec::new(),
+        }
+    }
+
+    fn shift(&mut self, delta: isize) {
+        self.range = offset_by(self.range.start, delta)..offset_by(self.range.end, delta);
+        for child in self.children.iter_mut() {
+            child.shift(delta);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub range: Range<usize>,
+}
+
+/// A lossless parse of a .noisy file that keeps the byte range of every token, for editors.
+/// It only checks syntax, so things like unknown properties aren't errors here.
+#[derive(Clone, Debug)]
+pub struct SyntaxTree {
+    source: String,
+    root: SyntaxNode,
+    errors: Vec<SyntaxError>,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str) -> Self {
+        let (root, errors) = match parse_items(source, 0) {
+            Ok(items) => (SyntaxNode {
+                kind: SyntaxKind::File,
+                range: 0..source.len(),
+                children: items,
+            }, Vec::new()),
+            // there's no error recovery yet, so the whole file becomes one error node
+            Err(error) => (SyntaxNode {
+                kind: SyntaxKind::File,
+                range: 0..source.len(),
+                children: vec![SyntaxNode::leaf(SyntaxKind::Error, 0..source.len())],
+            }, vec![error]),
+        };
+
+        SyntaxTree {
+            source: source.to_owned(),
+            root,
+            errors,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    pub fn errors(&self) -> &[SyntaxError] {
+        &self.errors
+    }
+
+    /// The deepest node under `offset`, such as the name being typed for completion
+    pub fn node_at(&self, offset: usize) -> Option<&SyntaxNode> {
+        if offset > self.source.len() {
+            return None;
+        }
+
+        let mut node = &self.root;
+        while let Some(child) = node.children.iter().find(|child| child.range.contains(&offset)) {
+            node = child;
+        }
+        Some(node)
+    }
+
+    /// Replaces `range` of the source with `replacement` and updates the tree to match. Only the
+    /// top level constants, params and blocks touching the edit get parsed again, unless that can't be done
+    /// in isolation, in which case the whole file is. Returns the range of the new source that was reparsed.
+    ///
+    /// Panics if `range` isn't on char boundaries within the source, like `String::replace_range`.
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) -> Range<usize> {
+        self.source.replace_range(range.clone(), replacement);
+        let delta = replacement.len() as isize - range.len() as isize;
+
+        if !self.errors.is_empty() {
+            return self.reparse_all();
+        }
+
+        // anything that touches the edit could merge with the new text, so it's included too
+        let items = &self.root.children;
+        let first = items.partition_point(|item| item.range.end < range.start);
+        let last = items.partition_point(|item| item.range.start <= range.end);
+        if first == last {
+            // only happens for edits to an empty file
+            return self.reparse_all();
+        }
+
+        let reparse_range = items[first].range.start..offset_by(items[last - 1].range.end, delta);
+        let Ok(new_items) = parse_items(&self.source[reparse_range.clone()], reparse_range.start) else {
+            // e.g. an opened block comment that swallows the rest of the file
+            return self.reparse_all();
+        };
+
+        // whitespace on either side of the reparsed text would have been one token in a full parse
+        let is_whitespace = |item: Option<&SyntaxNode>| item.is_some_and(|item| item.kind == SyntaxKind::Whitespace);
+        let before = first.checked_sub(1).and_then(|index| self.root.children.get(index));
+        if (is_whitespace(before) && is_whitespace(new_items.first()))
+            || (is_whitespace(self.root.children.get(last)) && is_whitespace(new_items.last())) {
+            return self.reparse_all();
+        }
+
+        for item in self.root.children[last..].iter_mut() {
+            item.shift(delta);
+        }
+        self.root.children.splice(first..last, new_items);
+        self.root.range = 0..self.source.len();
+
+        reparse_range
+    }
+
+    fn reparse_all(&mut self) -> Range<usize> {
+        *self = SyntaxTree::parse(&self.source);
+        0..self.source.len()
+    }
+}
+
+fn offset_by(position: usize, delta: isize) -> usize {
+    position.checked_add_signed(delta).expect("Text after an edit can't move before the start of the file")
+}
+
+/// Parses `source` as a sequence of top level items, with every range moved along by `offset`
+fn parse_items(source: &str, offset: usize) -> Result<Vec<SyntaxNode>, SyntaxError> {
+    let file = NoisyParser::parse(Rule::file, source)
+        .map_err(|error| SyntaxError {
+            message: error.variant.message().into_owned(),
+            range: match error.location {
+                InputLocation::Pos(position) => offset + position..offset + position,
+                InputLocation::Span((start, end)) => offset + start..offset + end,
+            },
+        })?
+        .next()
+        .expect("The file rule always produces exactly one pair");
+
+    Ok(build_node(file, source, offset).children)
+}
+
+fn build_node(pair: Pair<Rule>, source: &str, offset: usize) -> SyntaxNode {
+    let kind = SyntaxKind::from_rule(pair.as_rule());
+    let span = pair.as_span();
+
+    // pest leaves out whitespace, comments and literal text, so those are the gaps between children
+    let mut children = Vec::new();
+    let mut cursor = span.start();
+    for child in pair.into_inner().filter(|child| child.as_rule() != Rule::EOI) {
+        push_gap_tokens(source, cursor..child.as_span().start(), offset, &mut children);
+        cursor = child.as_span().end();
+        let mut child = build_node(child, source, offset);
+        let trailing_trivia = split_trailing_trivia(&mut child);
+        children.push(child);
+        children.extend(trailing_trivia);
+    }
+    // tokens like names and numbers are leaves, but an empty file still has its whitespace and comments
+    if !children.is_empty() || kind == SyntaxKind::File {
+        push_gap_tokens(source, cursor..span.end(), offset, &mut children);
+    }
+
+    SyntaxNode {
+        kind,
+        range: offset + span.start()..offset + span.end(),
+        children,
+    }
+}
+
+/// pest counts whitespace and comments skipped while looking for another repetition as part of the
+/// rule, so they get moved out to sit between nodes instead
+fn split_trailing_trivia(node: &mut SyntaxNode) -> Vec<SyntaxNode> {
+    let trivia_start = node.children.iter().rposition(|child| !child.kind.is_trivia()).map_or(0, |index| index + 1);
+    let trailing_trivia = node.children.split_off(trivia_start);
+    if let Some(first_trivia) = trailing_trivia.first() {
+        node.range.end = first_trivia.range.start;
+    }
+    trailing_trivia
+}
+
+/// Splits text the grammar skipped over into whitespace, comment, keyword and punctuation tokens
+fn push_gap_tokens(source: &str, gap: Range<usize>, offset: usize, tokens: &mut Vec<SyntaxNode>) {
+    let mut start = gap.start;
+    while start < gap.end {
+        let rest = &source[start..gap.end];
+        let (kind, len) = if rest.starts_with("//") {
+            (SyntaxKind::Comment, rest.find(['\r', '\n']).unwrap_or(rest.len()))
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            // the search starts after the opening `/*` so `/*/` doesn't count as closed
+            (SyntaxKind::Comment, comment.find("*/").map_or(rest.len(), |end| end + 4))
+        } else if let Some(directive) = rest.strip_prefix('#') {
+            // colors are tokens of their own, so a `#` between them starts a directive like `#const`
+            (SyntaxKind::Keyword, 1 + directive.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(directive.len()))
+        } else {
+            let first_char = rest.chars().next().expect("The gap isn't empty");
+            if first_char.is_whitespace() {
+                (SyntaxKind::Whitespace, rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len()))
+            } else {
+                (SyntaxKind::Punctuation, first_char.len_utf8())
+            }
+        };
+
+        tokens.push(SyntaxNode::leaf(kind, offset + start..offset + start + len));
+        start += len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "// the size
+#const W = 64
+#param radius: 1..40 = 10
+#param tint = #BA2E37
+
+/* the background */
+canvas {
+    width W
+    height W / 2
+}
+
+style accent {
+    color tint
+}
+
+circle {
+    centerX -W / 2
+    radius radius // tuned
+    style accent
+}
+";
+
+    fn assert_matches_full_parse(tree: &SyntaxTree) {
+        let full = SyntaxTree::parse(tree.source());
+        assert_eq!(tree.root(), full.root(), "the incremental parse of {:?} differs", tree.source());
+        assert_eq!(tree.errors(), full.errors(), "the incremental parse of {:?} differs", tree.source());
+    }
+
+    #[test]
+    fn tokens_cover_the_source() {
+        let tree = SyntaxTree::parse(SAMPLE);
+        assert!(tree.errors().is_empty());
+
+        let text: String = tree.root().tokens().map(|token| token.text(SAMPLE)).collect();
+        assert_eq!(text, SAMPLE);
+        assert!(tree.root().tokens().all(|token| token.kind() != SyntaxKind::Punctuation || token.range().len() == 1));
+    }
+
+    #[test]
+    fn directives_start_with_keywords() {
+        let tree = SyntaxTree::parse(SAMPLE);
+        let keywords: Vec<&str> = tree.root().tokens()
+            .filter(|token| token.kind() == SyntaxKind::Keyword)
+            .map(|token| token.text(SAMPLE))
+            .collect();
+        assert_eq!(keywords, ["#const", "#param", "#param"]);
+    }
+
+    #[test]
+    fn insertions_match_a_full_parse() {
+        let insertions = ["x", " ", "\n", "1", "-", "#", "{", "}", "/*", "*/", "//", "..", "#const c = 3\n", "#param p = 2\n", "dot {\n}\n"];
+        for position in 0..=SAMPLE.len() {
+            for insertion in insertions {
+                let mut tree = SyntaxTree::parse(SAMPLE);
+                tree.edit(position..position, insertion);
+                assert_matches_full_parse(&tree);
+            }
+        }
+    }
+
+    #[test]
+    fn deletions_match_a_full_parse() {
+        for start in 0..SAMPLE.len() {
+            for len in 1..=12.min(SAMPLE.len() - start) {
+                let mut tree = SyntaxTree::parse(SAMPLE);
+                tree.edit(start..start + len, "");
+                assert_matches_full_parse(&tree);
+            }
+        }
+    }
+
+    #[test]
+    fn edits_after_a_broken_edit_match_a_full_parse() {
+        let mut tree = SyntaxTree::parse(SAMPLE);
+        let brace = SAMPLE.find('}').unwrap();
+        tree.edit(brace..brace + 1, "");
+        assert!(!tree.errors().is_empty());
+        assert_matches_full_parse(&tree);
+
+        tree.edit(brace..brace, "}");
+        assert!(tree.errors().is_empty());
+        assert_matches_full_parse(&tree);
+    }
+}
@@ -0,0 +1,346 @@
+use std::io::Cursor;
+
+use image::{ImageFormat, RgbaImage};
+
+use crate::coloring::{Color, TransparentColor};
+use crate::Image;
+
+/// The largest width or height the thumbnail in an OpenRaster file is allowed to have
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// One named layer of a layered export, covering the `width` by `height` area with its top left corner at (`x`, `y`)
+#[derive(Clone, Debug)]
+pub struct Layer {
+    name: String,
+    x: usize,
+    y: usize,
+    width: usize,
+    pixels: Vec<TransparentColor>,
+}
+
+impl Layer {
+    /// A layer the size of `image` with every pixel opaque
+    pub fn opaque(name: &str, image: &Image) -> Self {
+        Layer {
+            name: name.to_owned(),
+            x: 0,
+            y: 0,
+            width: image.width(),
+            pixels: image.canvas.iter().map(|color| (*color).into()).collect(),
+        }
+    }
+
+    /// The pixels that changed between `before` and `after`, cropped to the smallest area holding all of them.
+    /// Stacking this on top of `before` gives back `after`. Returns `None` if nothing changed.
+    pub(crate) fn from_changes(name: &str, before: &Image, after: &Image) -> Option<Self> {
+        let changed = |x: usize, y: usize| before.get_pixel(x, y) != after.get_pixel(x, y);
+
+        let rows: Vec<usize> = (0..after.height()).filter(|y| (0..after.width()).any(|x| changed(x, *y))).collect();
+        let columns: Vec<usize> = (0..after.width()).filter(|x| rows.iter().any(|y| changed(*x, *y))).collect();
+        let (top, bottom) = (*rows.first()?, *rows.last()?);
+        let (left, right) = (*columns.first()?, *columns.last()?);
+
+        Some(Layer {
+            name: name.to_owned(),
+            x: left,
+            y: top,
+            width: right - left + 1,
+            pixels: (top..=bottom).flat_map(|y| (left..=right).map(move |x| (x, y)))
+                .map(|(x, y)| if changed(x, y) { (*after.get_pixel(x, y)).into() } else { TransparentColor::TRANSPARENT })
+                .collect(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.pixels.len().checked_div(self.width).unwrap_or(0)
+    }
+
+    /// `x` and `y` are relative to the layer, not the canvas
+    pub fn get_pixel(&self, x: usize, y: usize) -> &TransparentColor {
+        &self.pixels[x + y * self.width]
+    }
+
+    fn to_rgba_image(&self) -> Result<RgbaImage, ()> {
+        Ok(RgbaImage::from_raw(
+            self.width.try_into().map_err(|_|())?,
+            self.height().try_into().map_err(|_|())?,
+            self.pixels.iter().flat_map(|color| [color.red, color.green, color.blue, color.alpha]).collect())
+        .expect("Layer values have a width/height that matches the pixel count"))
+    }
+}
+
+/// Saves `layers`, listed bottom to top, as an OpenRaster (.ora) file that GIMP and Krita open with the layers intact
+pub fn write_ora(filename: &str, width: usize, height: usize, layers: &[Layer]) -> Result<(),()> {
+    std::fs::write(filename, encode_ora(width, height, layers)?).map_err(|_|())
+}
+
+/// Like `write_ora`, but returns the file's bytes instead of saving them
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(layers = layers.len())))]
+pub fn encode_ora(width: usize, height: usize, layers: &[Layer]) -> Result<Vec<u8>, ()> {
+    if layers.iter().any(|layer| layer.x + layer.width > width || layer.y + layer.height() > height) {
+        return Err(());
+    }
+
+    // the mimetype has to come first so the format can be recognized without unzipping
+    let mut archive = StoredZip::new();
+    archive.add("mimetype", b"image/openraster".to_vec())?;
+
+    // stack.xml lists layers from the top down
+    let mut stack = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<image version=\"0.0.5\" w=\"{width}\" h=\"{height}\">\n<stack>\n");
+    for (index, layer) in layers.iter().enumerate().rev() {
+        stack.push_str(&format!(
+            "<layer name=\"{}\" src=\"data/layer{index}.png\" x=\"{}\" y=\"{}\" opacity=\"1.0\" visibility=\"visible\"/>\n",
+            escape_xml(&layer.name), layer.x, layer.y,
+        ));
+    }
+    stack.push_str("</stack>\n</image>\n");
+    archive.add("stack.xml", stack.into_bytes())?;
+
+    let mut merged = Layer {
+        name: String::new(),
+        x: 0,
+        y: 0,
+        width,
+        pixels: vec![TransparentColor::TRANSPARENT; width * height],
+    };
+    for (index, layer) in layers.iter().enumerate() {
+        archive.add(&format!("data/layer{index}.png"), encode_png(&layer.to_rgba_image()?)?)?;
+
+        for y in 0..layer.height() {
+            for x in 0..layer.width {
+                let merged_color = &mut merged.pixels[layer.x + x + (layer.y + y) * width];
+                *merged_color = layer.get_pixel(x, y).layer_over(merged_color, 1.);
+            }
+        }
+    }
+
+    let merged = merged.to_rgba_image()?;
+    let longest_side = merged.width().max(merged.height());
+    let thumbnail = if longest_side <= THUMBNAIL_SIZE {
+        merged.clone()
+    } else {
+        image::imageops::thumbnail(
+            &merged,
+            (merged.width() * THUMBNAIL_SIZE / longest_side).max(1),
+            (merged.height() * THUMBNAIL_SIZE / longest_side).max(1),
+        )
+    };
+    archive.add("Thumbnails/thumbnail.png", encode_png(&thumbnail)?)?;
+    archive.add("mergedimage.png", encode_png(&merged)?)?;
+
+    archive.finish()
+}
+
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, ()> {
+    let mut bytes = Vec::new();
+    image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png).map_err(|_|())?;
+    Ok(bytes)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Just enough of a zip writer for OpenRaster: files are stored without compression, which is
+/// also what the format wants for the mimetype. The PNGs inside are compressed already.
+struct StoredZip {
+    bytes: Vec<u8>,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+impl StoredZip {
+    // version 2.0, the first one that has directories
+    const VERSION: u16 = 20;
+    // 1980-01-01, the earliest date zip can hold
+    const DATE: u16 = (1 << 5) | 1;
+
+    fn new() -> Self {
+        StoredZip {
+            bytes: Vec::new(),
+            central_directory: Vec::new(),
+            entries: 0,
+        }
+    }
+
+    /// Fails if the archive gets too big or has too many files for a zip without the 64-bit extensions
+    fn add(&mut self, name: &str, data: Vec<u8>) -> Result<(), ()> {
+        let offset: u32 = self.bytes.len().try_into().map_err(|_|())?;
+        let size: u32 = data.len().try_into().map_err(|_|())?;
+        let name_length: u16 = name.len().try_into().map_err(|_|())?;
+        let entries = self.entries.checked_add(1).ok_or(())?;
+
+        // the part that local and central directory headers share
+        let mut entry_info = Vec::new();
+        entry_info.extend(Self::VERSION.to_le_bytes()); // version needed
+        entry_info.extend(0u16.to_le_bytes()); // flags
+        entry_info.extend(0u16.to_le_bytes()); // stored, no compression
+        entry_info.extend(0u16.to_le_bytes()); // time
+        entry_info.extend(Self::DATE.to_le_bytes());
+        entry_info.extend(crc32(&data).to_le_bytes());
+        entry_info.extend(size.to_le_bytes()); // compressed size
+        entry_info.extend(size.to_le_bytes()); // uncompressed size
+        entry_info.extend(name_length.to_le_bytes());
+        entry_info.extend(0u16.to_le_bytes()); // extra field length
+
+        self.bytes.extend(0x04034b50u32.to_le_bytes());
+        self.bytes.extend(&entry_info);
+        self.bytes.extend(name.as_bytes());
+        self.bytes.extend(data);
+
+        self.central_directory.extend(0x02014b50u32.to_le_bytes());
+        self.central_directory.extend(Self::VERSION.to_le_bytes()); // version made by
+        self.central_directory.extend(&entry_info);
+        self.central_directory.extend(0u16.to_le_bytes()); // comment length
+        self.central_directory.extend(0u16.to_le_bytes()); // disk number
+        self.central_directory.extend(0u16.to_le_bytes()); // internal attributes
+        self.central_directory.extend(0u32.to_le_bytes()); // external attributes
+        self.central_directory.extend(offset.to_le_bytes());
+        self.central_directory.extend(name.as_bytes());
+
+        self.entries = entries;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, ()> {
+        let directory_offset: u32 = self.bytes.len().try_into().map_err(|_|())?;
+        let directory_size: u32 = self.central_directory.len().try_into().map_err(|_|())?;
+        self.bytes.append(&mut self.central_directory);
+
+        // end of central directory record
+        self.bytes.extend(0x06054b50u32.to_le_bytes());
+        self.bytes.extend(0u16.to_le_bytes()); // this disk
+        self.bytes.extend(0u16.to_le_bytes()); // disk with the central directory
+        self.bytes.extend(self.entries.to_le_bytes());
+        self.bytes.extend(self.entries.to_le_bytes());
+        self.bytes.extend(directory_size.to_le_bytes());
+        self.bytes.extend(directory_offset.to_le_bytes());
+        self.bytes.extend(0u16.to_le_bytes()); // comment length
+        Ok(self.bytes)
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coloring::{BlendMode, Compositing, SolidColor};
+
+    /// The contents of the file called `name` in a zip that only stores files
+    fn stored_file<'a>(archive: &'a [u8], name: &str) -> &'a [u8] {
+        let mut offset = 0;
+        while archive[offset..offset + 4] == 0x04034b50u32.to_le_bytes() {
+            let read_u16 = |at: usize| u16::from_le_bytes([archive[offset + at], archive[offset + at + 1]]) as usize;
+            let size = u32::from_le_bytes(archive[offset + 18..offset + 22].try_into().unwrap()) as usize;
+            let name_start = offset + 30;
+            let data_start = name_start + read_u16(26) + read_u16(28);
+            if &archive[name_start..name_start + read_u16(26)] == name.as_bytes() {
+                return &archive[data_start..data_start + size];
+            }
+            offset = data_start + size;
+        }
+        panic!("{name} isn't in the archive");
+    }
+
+    fn merged_pixels(layers: &[Layer], width: usize, height: usize) -> Vec<TransparentColor> {
+        let archive = encode_ora(width, height, layers).unwrap();
+        let merged = image::load_from_memory(stored_file(&archive, "mergedimage.png")).unwrap().into_rgba8();
+        assert_eq!((merged.width() as usize, merged.height() as usize), (width, height));
+        merged.pixels().map(|pixel| TransparentColor { red: pixel[0], green: pixel[1], blue: pixel[2], alpha: pixel[3] }).collect()
+    }
+
+    #[test]
+    fn one_opaque_layer_merges_to_itself() {
+        let mut image = Image::with_size(3, 2, SolidColor { red: 250, green: 128, blue: 3 });
+        *image.get_pixel_mut(1, 1) = SolidColor::WHITE;
+        let layer = Layer::opaque("background", &image);
+
+        assert_eq!(merged_pixels(std::slice::from_ref(&layer), 3, 2), layer.pixels);
+    }
+
+    #[test]
+    fn one_translucent_layer_merges_to_itself() {
+        let layer = Layer {
+            name: "glow".to_owned(),
+            x: 1,
+            y: 0,
+            width: 2,
+            pixels: vec![
+                TransparentColor { red: 255, green: 200, blue: 10, alpha: 128 },
+                TransparentColor::TRANSPARENT,
+                TransparentColor { red: 30, green: 255, blue: 90, alpha: 255 },
+                TransparentColor { red: 90, green: 90, blue: 255, alpha: 1 },
+            ],
+        };
+
+        let merged = merged_pixels(std::slice::from_ref(&layer), 3, 2);
+        for y in 0..2 {
+            assert_eq!(merged[y * 3], TransparentColor::TRANSPARENT);
+            for x in 0..2 {
+                assert_eq!(merged[1 + x + y * 3], *layer.get_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn layers_stack_like_transparent_images_blend() {
+        let background = Image::with_size(2, 1, SolidColor { red: 200, green: 220, blue: 240 });
+        let top = Layer {
+            name: "top".to_owned(),
+            x: 0,
+            y: 0,
+            width: 2,
+            pixels: vec![TransparentColor { red: 255, green: 0, blue: 0, alpha: 128 }, TransparentColor::TRANSPARENT],
+        };
+
+        let merged = merged_pixels(&[Layer::opaque("background", &background), top.clone()], 2, 1);
+        let expected = top.pixels[0].blend_on(&(*background.get_pixel(0, 0)).into(), BlendMode::Normal, Compositing::Srgb);
+        assert_eq!(merged[0], expected);
+        assert_eq!(merged[1], (*background.get_pixel(1, 0)).into());
+    }
+
+    #[test]
+    fn layers_of_empty_images_have_no_rows() {
+        let layer = Layer::opaque("empty", &Image::with_size(0, 0, SolidColor::BLACK));
+        assert_eq!((layer.width(), layer.height()), (0, 0));
+    }
+
+    #[test]
+    fn zips_with_too_many_files_are_an_error() {
+        let mut archive = StoredZip::new();
+        for _ in 0..u16::MAX {
+            archive.add("a", Vec::new()).unwrap();
+        }
+        assert_eq!(archive.add("a", Vec::new()), Err(()));
+        assert!(archive.finish().is_ok());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::coloring::{HslColor, SolidColor, TransparentColor};
+use crate::Image;
+
+#[derive(Clone, Debug)]
+pub struct Palette {
+    colors: Vec<SolidColor>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<SolidColor>) -> Self {
+        Palette {
+            colors,
+        }
+    }
+
+    pub fn colors(&self) -> &[SolidColor] {
+        &self.colors
+    }
+
+    /// Picks up to `size` colors that represent the image well, using median cut. A `size` of 0 gives an
+    /// empty palette.
+    pub fn from_image(image: &Image, size: usize) -> Self {
+        if size == 0 {
+            return Palette::new(Vec::new());
+        }
+
+        let mut boxes = vec![sample_pixels(image)];
+
+        while boxes.len() < size {
+            let widest_box = boxes.iter().enumerate()
+                .filter(|(_, colors)| colors.len() > 1)
+                .map(|(index, colors)| {
+                    let (channel, range) = (0..3).map(|channel| {
+                        let values = colors.iter().map(|color| channel_value(color, channel));
+                        (channel, values.clone().max().unwrap_or(0) - values.min().unwrap_or(0))
+                    }).max_by_key(|(_, range)| *range).expect("There are always 3 channels");
+                    (index, channel, range)
+                })
+                .max_by_key(|(_, _, range)| *range);
+
+            let Some((index, channel, range)) = widest_box else {
+                break;
+            };
+            if range == 0 {
+                break;
+            }
+
+            let mut lower_half = boxes.swap_remove(index);
+            lower_half.sort_by_key(|color| channel_value(color, channel));
+            let upper_half = lower_half.split_off(lower_half.len() / 2);
+            boxes.push(lower_half);
+            boxes.push(upper_half);
+        }
+
+        Palette {
+            colors: boxes.iter().filter(|colors| !colors.is_empty()).map(|colors| average_color(colors)).collect(),
+        }
+    }
+}
+
+fn from_hsl(color: HslColor) -> SolidColor {
+    Into::<TransparentColor>::into(color).as_solid()
+}
+
+/// `base` with its hue turned by each of `degrees` in turn
+fn rotations(base: SolidColor, degrees: &[f64]) -> Vec<SolidColor> {
+    let base: HslColor = base.into();
+    degrees.iter().map(|degrees| from_hsl(base.rotate_hue(*degrees))).collect()
+}
+
+/// `base` and the color opposite it on the color wheel
+pub fn complementary(base: SolidColor) -> Vec<SolidColor> {
+    rotations(base, &[0., 180.])
+}
+
+/// `base` and the two colors either side of its complement, which contrasts less harshly than `complementary`
+pub fn split_complementary(base: SolidColor) -> Vec<SolidColor> {
+    rotations(base, &[0., 150., 210.])
+}
+
+/// `base` and the two colors a third of the way around the color wheel from it
+pub fn triadic(base: SolidColor) -> Vec<SolidColor> {
+    rotations(base, &[0., 120., 240.])
+}
+
+/// `base` and the colors a quarter, half and three quarters of the way around the color wheel
+pub fn tetradic(base: SolidColor) -> Vec<SolidColor> {
+    rotations(base, &[0., 90., 180., 270.])
+}
+
+/// `count` neighboring hues spread evenly over `spread` degrees, centered on `base`
+pub fn analogous(base: SolidColor, count: usize, spread: f64) -> Vec<SolidColor> {
+    // a single hue has nothing to spread over, so it's just `base`
+    let (first, step) = if count > 1 { (-spread / 2., spread / (count - 1) as f64) } else { (0., 0.) };
+    let degrees: Vec<f64> = (0..count).map(|index| first + index as f64 * step).collect();
+    rotations(base, &degrees)
+}
+
+/// `count` shades of `base`, from dark to light with the same hue and saturation
+pub fn monochromatic(base: SolidColor, count: usize) -> Vec<SolidColor> {
+    let base: HslColor = base.into();
+    (0..count).map(|index| from_hsl(HslColor {
+        lightness: (index as f64 + 1.) / (count as f64 + 1.),
+        ..base
+    })).collect()
+}
+
+/// `base` and `count - 1` random colors that go with it: the hues step around the color wheel from `base` by
+/// the golden angle, so they never bunch up, and saturation and lightness only wander a little from `base`'s.
+/// Seed `rng` to get the same palette every time.
+pub fn random_harmonious<R: rand::Rng>(base: SolidColor, count: usize, rng: &mut R) -> Vec<SolidColor> {
+    const GOLDEN_ANGLE: f64 = 137.50776405;
+
+    let base: HslColor = base.into();
+    let start = rng.random_range(0. ..360.);
+    (0..count).map(|index| {
+        if index == 0 {
+            return from_hsl(base);
+        }
+        from_hsl(HslColor {
+            saturation: (base.saturation + rng.random_range(-0.15..0.15)).clamp(0., 1.),
+            lightness: (base.lightness + rng.random_range(-0.15..0.15)).clamp(0.1, 0.9),
+            ..base.rotate_hue(start + index as f64 * GOLDEN_ANGLE)
+        })
+    }).collect()
+}
+
+/// Distance between sampled pixels so that large images don't need every pixel looked at
+pub(crate) fn sample_stride(image: &Image) -> usize {
+    const MAX_SAMPLES: f64 = (1 << 16) as f64;
+
+    (image.canvas.len() as f64 / MAX_SAMPLES).sqrt().ceil().max(1.) as usize
+}
+
+pub(crate) fn sample_pixels(image: &Image) -> Vec<SolidColor> {
+    let stride = sample_stride(image);
+    (0..image.height()).step_by(stride)
+        .flat_map(|y| (0..image.width()).step_by(stride).map(move |x| (x, y)))
+        .map(|(x, y)| *image.get_pixel(x, y))
+        .collect()
+}
+
+pub(crate) fn average_color(colors: &[SolidColor]) -> SolidColor {
+    let count = colors.len().max(1) as f64;
+    let (red, green, blue) = colors.iter().fold((0., 0., 0.), |(red, green, blue), color| {
+        (red + color.red as f64, green + color.green as f64, blue + color.blue as f64)
+    });
+
+    SolidColor {
+        red: (red / count).round() as u8,
+        green: (green / count).round() as u8,
+        blue: (blue / count).round() as u8,
+    }
+}
+
+fn channel_value(color: &SolidColor, channel: usize) -> u8 {
+    match channel {
+        0 => color.red,
+        1 => color.green,
+        _ => color.blue,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: SolidColor = SolidColor { red: 255, green: 0, blue: 0 };
+    const GREEN: SolidColor = SolidColor { red: 0, green: 255, blue: 0 };
+    const BLUE: SolidColor = SolidColor { red: 0, green: 0, blue: 255 };
+
+    /// Four stripes of different colors, all the same size so median cuts fall between them
+    fn striped_image() -> Image {
+        let mut image = Image::with_size(8, 4, SolidColor::WHITE);
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                *image.get_pixel_mut(x, y) = [RED, GREEN, BLUE, SolidColor::WHITE][x / 2];
+            }
+        }
+        image
+    }
+
+    fn sorted(mut colors: Vec<SolidColor>) -> Vec<SolidColor> {
+        colors.sort_by_key(|color| (color.red, color.green, color.blue));
+        colors
+    }
+
+    #[test]
+    fn median_cut_picks_up_to_size_colors() {
+        let image = striped_image();
+        assert_eq!(sorted(Palette::from_image(&image, 4).colors().to_vec()), sorted(vec![RED, GREEN, BLUE, SolidColor::WHITE]));
+        assert_eq!(Palette::from_image(&image, 2).colors().len(), 2);
+        assert_eq!(Palette::from_image(&image, 1).colors().len(), 1);
+
+        // there's no splitting a box of one color, so asking for more colors than the image has gives each once
+        assert_eq!(Palette::from_image(&image, 10).colors().len(), 4);
+        assert_eq!(Palette::from_image(&Image::with_size(6, 6, GREEN), 3).colors(), [GREEN]);
+    }
+
+    #[test]
+    fn empty_palettes() {
+        assert!(Palette::from_image(&striped_image(), 0).colors().is_empty());
+        assert!(Palette::from_image(&Image::with_size(3, 0, RED), 3).colors().is_empty());
+    }
+
+    fn hues(colors: &[SolidColor]) -> Vec<f64> {
+        colors.iter().map(|color| Into::<HslColor>::into(*color).hue.round()).collect()
+    }
+
+    #[test]
+    fn harmonies_turn_the_hue() {
+        let orange = SolidColor { red: 255, green: 128, blue: 0 };
+        let hue = hues(&[orange])[0];
+        let turned = |degrees: &[f64]| degrees.iter().map(|degrees| (hue + degrees).rem_euclid(360.)).collect::<Vec<_>>();
+
+        assert_eq!(triadic(RED), [RED, GREEN, BLUE]);
+        assert_eq!(hues(&complementary(orange)), turned(&[0., 180.]));
+        assert_eq!(hues(&split_complementary(orange)), turned(&[0., 150., 210.]));
+        assert_eq!(hues(&triadic(orange)), turned(&[0., 120., 240.]));
+        assert_eq!(hues(&tetradic(orange)), turned(&[0., 90., 180., 270.]));
+        assert_eq!(hues(&analogous(orange, 5, 60.)), turned(&[-30., -15., 0., 15., 30.]));
+        assert_eq!(hues(&analogous(orange, 1, 60.)), turned(&[0.]));
+    }
+
+    #[test]
+    fn monochromatic_shades_go_from_dark_to_light() {
+        let shades = monochromatic(BLUE, 4);
+        assert_eq!(hues(&shades), [240.; 4]);
+        let lightnesses: Vec<f64> = shades.iter().map(|color| Into::<HslColor>::into(*color).lightness).collect();
+        assert!(lightnesses.windows(2).all(|pair| pair[0] < pair[1]), "{lightnesses:?}");
+    }
+
+    #[test]
+    fn random_harmonious_palettes_start_from_the_base() {
+        use rand::SeedableRng;
+
+        let palette = random_harmonious(RED, 6, &mut rand::rngs::StdRng::seed_from_u64(0));
+        assert_eq!(palette.len(), 6);
+        assert_eq!(palette[0], RED);
+        assert_eq!(palette, random_harmonious(RED, 6, &mut rand::rngs::StdRng::seed_from_u64(0)));
+    }
+}
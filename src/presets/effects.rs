@@ -0,0 +1,205 @@
+use std::ops::Range;
+
+use crate::coloring::{BlendMode, LinearGradient, RadialGradient, SolidColor, TransparentColor};
+use crate::shapes::{Arc, Ellipse, Point, Rect};
+use crate::DrawInstruction;
+
+/// Scatters soft, translucent circles of light across `bounds`. `blur` is the portion of each circle's radius,
+/// from 0 to 1, that fades out towards its edge.
+/// The circles are added onto the canvas, so they brighten where they overlap like real light does.
+pub fn bokeh<R: rand::Rng>(
+    bounds: &Rect,
+    count: usize,
+    size_range: Range<f64>,
+    color_palette: &[SolidColor],
+    blur: f64,
+    rng: &mut R,
+) -> Vec<DrawInstruction<R>> {
+    if color_palette.is_empty() || size_range.is_empty() {
+        return Vec::new();
+    }
+
+    let min_point = bounds.min_point();
+    let max_point = bounds.max_point();
+    let blur = blur.clamp(0., 1.);
+
+    (0..count).map(|_| {
+        let center = Point {
+            x: min_point.x + rng.random::<f64>() * (max_point.x - min_point.x),
+            y: min_point.y + rng.random::<f64>() * (max_point.y - min_point.y),
+        };
+        let radius = rng.random_range(size_range.clone());
+        let color = color_palette[rng.random_range(0..color_palette.len())];
+        let alpha = rng.random_range(40..120);
+
+        let core_color = TransparentColor { red: color.red, green: color.green, blue: color.blue, alpha };
+        let edge_color = TransparentColor { alpha: 0, ..core_color };
+        // keep a sliver of falloff so the rings stay distinct even without blur
+        let core_radius = (radius * (1. - blur)).min(radius * 0.999);
+
+        DrawInstruction {
+            pre_clip_noise: None,
+            clipping_shape: Ellipse::circle(center, radius).into(),
+            coloring: RadialGradient::with_rings(center, (core_radius, core_color), (radius, edge_color)).into(),
+            post_clip_noise: None,
+            post_draw_noise: None,
+            opacity: 1.,
+            blend_mode: BlendMode::Add,
+            dithering: None,
+        }
+    }).collect()
+}
+
+/// Concentric arcs around `pole`, like stars photographed with a long exposure. Every trail gets its own jitter in
+/// length, thickness and brightness, and fades in from its starting end.
+pub fn star_trails<R: rand::Rng>(
+    pole: Point,
+    count: usize,
+    radius_range: Range<f64>,
+    sweep_angle: f64,
+    stroke_width: f64,
+    color_palette: &[SolidColor],
+    rng: &mut R,
+) -> Vec<DrawInstruction<R>> {
+    if color_palette.is_empty() || radius_range.is_empty() {
+        return Vec::new();
+    }
+
+    (0..count).map(|_| {
+        let radius = rng.random_range(radius_range.clone());
+        let start_angle = rng.random_range(0.0..std::f64::consts::TAU);
+        let trail = Arc::new(
+            pole,
+            radius,
+            start_angle,
+            sweep_angle * rng.random_range(0.8..1.2),
+            stroke_width * rng.random_range(0.5..1.5),
+        );
+
+        let color = color_palette[rng.random_range(0..color_palette.len())];
+        let brightness = rng.random_range(60..=255);
+        let head_color = TransparentColor { red: color.red, green: color.green, blue: color.blue, alpha: brightness };
+        let tail_color = TransparentColor { alpha: brightness / 8, ..head_color };
+
+        let (tail, head) = (trail.start_point(), trail.end_point());
+        let coloring = if tail == head {
+            RadialGradient::with_rings(pole, (0., head_color), (1., head_color)).into()
+        } else {
+            LinearGradient::with_poles((tail, tail_color), (head, head_color)).into()
+        };
+
+        DrawInstruction {
+            pre_clip_noise: None,
+            clipping_shape: trail.into(),
+            coloring,
+            post_clip_noise: None,
+            post_draw_noise: None,
+            opacity: 1.,
+            blend_mode: BlendMode::Add,
+            dithering: None,
+        }
+    }).collect()
+}
+
+/// A bright glow at `light_source` with a chain of soft ghost circles reflected through the center of `canvas`,
+/// like the internal reflections of a camera lens pointed near a light
+pub fn lens_flare<R: rand::Rng>(canvas: &Rect, light_source: Point, ghost_count: usize, color: SolidColor) -> Vec<DrawInstruction<R>> {
+    let min_point = canvas.min_point();
+    let max_point = canvas.max_point();
+    let center = Point { x: (min_point.x + max_point.x) / 2., y: (min_point.y + max_point.y) / 2. };
+    let flare_scale = f64::min(max_point.x - min_point.x, max_point.y - min_point.y);
+
+    let soft_circle = |center: Point, radius: f64, alpha: u8| {
+        let core_color = TransparentColor { red: color.red, green: color.green, blue: color.blue, alpha };
+        let edge_color = TransparentColor { alpha: 0, ..core_color };
+        DrawInstruction {
+            pre_clip_noise: None,
+            clipping_shape: Ellipse::circle(center, radius).into(),
+            coloring: RadialGradient::with_rings(center, (radius * 0.2, core_color), (radius, edge_color)).into(),
+            post_clip_noise: None,
+            post_draw_noise: None,
+            opacity: 1.,
+            blend_mode: BlendMode::Add,
+            dithering: None,
+        }
+    };
+
+    let mut instructions = vec![soft_circle(light_source, flare_scale / 5., 220)];
+    for ghost_index in 0..ghost_count {
+        // ghosts spread out from the light source, past the center and out the other side
+        let progress = 0.4 + 1.4 * (ghost_index as f64 + 1.) / (ghost_count as f64 + 1.);
+        let ghost_center = Point {
+            x: light_source.x + (center.x - light_source.x) * progress,
+            y: light_source.y + (center.y - light_source.y) * progress,
+        };
+        // alternate between small sharp ghosts and large faint ones
+        let (size, alpha) = if ghost_index % 2 == 0 { (0.03, 90) } else { (0.09, 45) };
+        let radius = flare_scale * size * (1. + (progress - 1.).abs());
+        instructions.push(soft_circle(ghost_center, radius, alpha));
+    }
+
+    instructions
+}
+
+/// A soft wash of `color` that bleeds into `canvas` from `origin`, which usually sits on or just past one of its edges
+pub fn light_leak<R: rand::Rng>(canvas: &Rect, origin: Point, reach: f64, color: SolidColor) -> DrawInstruction<R> {
+    let leak_color = TransparentColor { red: color.red, green: color.green, blue: color.blue, alpha: 160 };
+
+    DrawInstruction {
+        pre_clip_noise: None,
+        clipping_shape: (*canvas).into(),
+        coloring: RadialGradient::with_rings(
+            origin,
+            (0., leak_color),
+            (reach.max(f64::EPSILON), TransparentColor { alpha: 0, ..leak_color }),
+        ).into(),
+        post_clip_noise: None,
+        post_draw_noise: None,
+        opacity: 1.,
+        blend_mode: BlendMode::Normal,
+        dithering: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::Image;
+
+    const WARM: SolidColor = SolidColor { red: 255, green: 180, blue: 90 };
+
+    fn canvas(width: usize, height: usize) -> Rect {
+        Rect::from_points(&Point::ORIGIN, &Point { x: width as f64, y: height as f64 })
+    }
+
+    #[test]
+    fn flares_and_leaks_draw_at_small_sizes() {
+        for (width, height) in [(1, 1), (2, 3), (7, 1), (16, 9)] {
+            let mut rng = StdRng::seed_from_u64(0);
+            let mut image = Image::with_size(width, height, SolidColor::BLACK);
+            for ghost_count in [0, 1, 5] {
+                let flare = lens_flare(&canvas(width, height), Point { x: 0.5, y: 0.5 }, ghost_count, WARM);
+                assert_eq!(flare.len(), ghost_count + 1);
+                for instruction in &flare {
+                    image.draw_custom(instruction, &mut rng);
+                }
+            }
+            for reach in [0., 1., 40.] {
+                image.draw_custom(&light_leak(&canvas(width, height), Point { x: -1., y: 0. }, reach, WARM), &mut rng);
+            }
+        }
+    }
+
+    #[test]
+    fn light_leaks_fade_away_from_their_origin() {
+        let mut image = Image::with_size(40, 10, SolidColor::BLACK);
+        image.draw_custom(&light_leak::<StdRng>(&canvas(40, 10), Point { x: 0., y: 5. }, 30., WARM), &mut StdRng::seed_from_u64(0));
+
+        let red: Vec<u8> = (0..40).step_by(8).map(|x| image.get_pixel(x, 5).red).collect();
+        assert!(red.windows(2).all(|pair| pair[0] > pair[1]), "{red:?}");
+        assert_eq!(*image.get_pixel(39, 5), SolidColor::BLACK);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::coloring::{BlendMode, ColorScheme, LinearGradient, SolidColor, TransparentColor, Warp};
+use crate::noise::{BrightnessNoise, Fbm, PerlinNoise, ValueNoise};
+use crate::scene::Scene;
+use crate::shapes::{Ellipse, Point, Rect, Shape, WaveBand};
+use crate::presets::effects;
+use crate::DrawInstruction;
+
+fn plain_instruction<R: rand::Rng>(clipping_shape: Shape, coloring: ColorScheme<TransparentColor>) -> DrawInstruction<R> {
+    DrawInstruction {
+        pre_clip_noise: None,
+        clipping_shape,
+        coloring,
+        post_clip_noise: None,
+        post_draw_noise: None,
+        opacity: 1.,
+        blend_mode: BlendMode::Normal,
+        dithering: None,
+    }
+}
+
+fn with_alpha(color: SolidColor, alpha: u8) -> TransparentColor {
+    TransparentColor {
+        red: color.red,
+        green: color.green,
+        blue: color.blue,
+        alpha,
+    }
+}
+
+fn full_canvas<R: rand::Rng>(width: usize, height: usize, top_left: SolidColor, bottom_right: SolidColor) -> DrawInstruction<R> {
+    let far_corner = Point { x: width as f64, y: height as f64 };
+    plain_instruction(
+        Rect::from_points(&Point::ORIGIN, &far_corner).into(),
+        LinearGradient::with_poles((Point::ORIGIN, top_left.into()), (far_corner, bottom_right.into())).into(),
+    )
+}
+
+/// A diagonal gradient with a circle of a second gradient in the middle
+pub fn gradient_wallpaper<R: rand::Rng>(
+    width: usize, height: usize,
+    background: (SolidColor, SolidColor),
+    circle: (SolidColor, SolidColor),
+) -> Scene<R> {
+    let mut scene = Scene::new(width, height, background.0);
+    scene.push(full_canvas(width, height, background.0, background.1));
+
+    let far_corner = Point { x: width as f64, y: height as f64 };
+    let center = Point { x: far_corner.x / 2., y: far_corner.y / 2. };
+    let radius = 3. * f64::min(far_corner.x, far_corner.y) / 8.;
+    scene.push(plain_instruction(
+        Ellipse::circle(center, radius).into(),
+        LinearGradient::with_poles((Point::ORIGIN, circle.0.into()), (far_corner, circle.1.into())).into(),
+    ));
+
+    scene
+}
+
+/// A diagonal gradient swirled by Perlin noise. `seed` picks the swirls, and `strength` is about how
+/// many pixels the colors get pushed.
+pub fn warped_gradient<R: rand::Rng>(
+    width: usize, height: usize,
+    colors: (SolidColor, SolidColor),
+    seed: u64,
+    strength: f64,
+) -> Scene<R> {
+    let mut scene = Scene::new(width, height, colors.0);
+
+    let far_corner = Point { x: width as f64, y: height as f64 };
+    let gradient = LinearGradient::with_poles((Point::ORIGIN, colors.0.into()), (far_corner, colors.1.into()));
+    let scale = f64::max(far_corner.x, far_corner.y) / 3.;
+    scene.push(plain_instruction(
+        Rect::from_points(&Point::ORIGIN, &far_corner).into(),
+        Warp::new(gradient, Fbm::new(PerlinNoise::new(seed), 4), scale, strength).into(),
+    ));
+
+    scene
+}
+
+/// A diagonal gradient with film grain over it. `grain` is the most a channel changes, from 0 to 1.
+pub fn grain_gradient<R: rand::Rng>(
+    width: usize, height: usize,
+    colors: (SolidColor, SolidColor),
+    seed: u64,
+    grain: f64,
+) -> Scene<R> {
+    let mut scene = Scene::new(width, height, colors.0);
+
+    let mut instruction = full_canvas(width, height, colors.0, colors.1);
+    // value noise a pixel across is different at nearly every pixel, which reads as grain
+    instruction.post_clip_noise = Some(Box::new(BrightnessNoise::new(ValueNoise::new(seed), 1., grain)));
+    scene.push(instruction);
+
+    scene
+}
+
+/// Translucent wavy curtains of color over a dark sky, one band per color
+pub fn aurora<R: rand::Rng>(width: usize, height: usize, sky: (SolidColor, SolidColor), band_colors: &[SolidColor]) -> Scene<R> {
+    let mut scene = Scene::new(width, height, sky.0);
+    scene.push(full_canvas(width, height, sky.0, sky.1));
+
+    let width = width as f64;
+    let height = height as f64;
+    for (band_index, band_color) in band_colors.iter().enumerate() {
+        let band_fraction = (band_index as f64 + 1.) / (band_colors.len() as f64 + 1.);
+        let base_y = height * (0.25 + 0.4 * band_fraction);
+        let thickness = height * 0.3;
+        let amplitude = height * 0.08;
+
+        // the curtain glows brightest along its bottom edge and fades out upwards
+        scene.push(plain_instruction(
+            WaveBand::new(base_y - thickness / 2., amplitude, width * (0.6 + 0.3 * band_fraction), band_index as f64 * 1.7, thickness).into(),
+            LinearGradient::with_poles(
+                (Point { x: 0., y: base_y - thickness - amplitude }, TransparentColor::TRANSPARENT),
+                (Point { x: 0., y: base_y + amplitude }, with_alpha(*band_color, 150)),
+            ).into(),
+        ));
+    }
+
+    scene
+}
+
+/// Scattered soft translucent circles over a dark gradient, reminiscent of out of focus lights
+pub fn bokeh<R: rand::Rng>(
+    width: usize, height: usize,
+    background: (SolidColor, SolidColor),
+    light_colors: &[SolidColor],
+    count: usize,
+    rng: &mut R,
+) -> Scene<R> {
+    let mut scene = Scene::new(width, height, background.0);
+    scene.push(full_canvas(width, height, background.0, background.1));
+
+    let far_corner = Point { x: width as f64, y: height as f64 };
+    let min_dimension = f64::min(far_corner.x, far_corner.y);
+    let lights = effects::bokeh(
+        &Rect::from_points(&Point::ORIGIN, &far_corner),
+        count,
+        min_dimension / 40. .. min_dimension / 8.,
+        light_colors,
+        0.6,
+        rng,
+    );
+    for light in lights {
+        scene.push(light);
+    }
+
+    scene
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    const NIGHT: (SolidColor, SolidColor) = (SolidColor { red: 5, green: 10, blue: 30 }, SolidColor { red: 20, green: 30, blue: 70 });
+    const LIGHTS: [SolidColor; 3] = [
+        SolidColor { red: 60, green: 220, blue: 140 },
+        SolidColor { red: 140, green: 80, blue: 220 },
+        SolidColor { red: 250, green: 200, blue: 90 },
+    ];
+
+    #[test]
+    fn presets_render_at_small_sizes() {
+        for (width, height) in [(1, 1), (2, 3), (7, 1), (16, 9)] {
+            let mut rng = StdRng::seed_from_u64(0);
+            let scenes: Vec<Scene<StdRng>> = vec![
+                gradient_wallpaper(width, height, NIGHT, (LIGHTS[0], LIGHTS[1])),
+                warped_gradient(width, height, NIGHT, 3, 10.),
+                grain_gradient(width, height, NIGHT, 3, 0.1),
+                aurora(width, height, NIGHT, &LIGHTS),
+                aurora(width, height, NIGHT, &[]),
+                bokeh(width, height, NIGHT, &LIGHTS, 6, &mut rng),
+                bokeh(width, height, NIGHT, &[], 6, &mut rng),
+            ];
+
+            for scene in scenes {
+                let image = scene.render(&mut rng);
+                assert_eq!((image.width(), image.height()), (width, height));
+            }
+        }
+    }
+
+    #[test]
+    fn wallpapers_start_from_the_first_background_color() {
+        let image = gradient_wallpaper::<StdRng>(32, 18, NIGHT, (LIGHTS[0], LIGHTS[1])).render(&mut StdRng::seed_from_u64(0));
+        assert_eq!(*image.get_pixel(0, 0), NIGHT.0);
+        assert_ne!(*image.get_pixel(16, 9), *image.get_pixel(0, 9));
+    }
+}
@@ -0,0 +1,708 @@
+use std::collections::HashMap;
+
+use crate::{
+    coloring::{ColorScheme, Compositing, Dithering, SolidColor, TransparentColor},
+    limits::{LimitExceeded, Limits},
+    noisy::{NoisySource, Parameter, ParameterSchema, ReadFileError, Value},
+    ora::Layer,
+    scoring::Scorer,
+    shapes::{Antialiasing, CheckInside, Point, Rect, Shape},
+    theme::Theme,
+    DrawInstruction, DrawSettings, Image, Matte, RenderedLayer, Style,
+};
+
+pub struct Scene<R: rand::Rng> {
+    width: usize,
+    height: usize,
+    background_color: SolidColor,
+    styles: HashMap<String, Style<R>>,
+    instructions: Vec<SceneInstruction<R>>,
+    // only scenes loaded from a .noisy file have these
+    source: Option<NoisySource>,
+    parameters: Vec<Parameter>,
+    compositing: Compositing,
+    dithering: Dithering,
+    antialiasing: Antialiasing,
+    theme: Option<Theme>,
+    // where the `theme.<slot>` colors of the .noisy file ended up
+    theme_bindings: Vec<ThemeBinding>,
+}
+
+/// A color in a scene that comes from a theme slot, so it can be swapped out when the theme changes
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ThemeBinding {
+    pub(crate) slot: String,
+    pub(crate) target: ThemeTarget,
+    pub(crate) part: ColorPart,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ThemeTarget {
+    Background,
+    /// By index into the scene's instructions
+    Instruction(usize),
+    Style(String),
+}
+
+/// Which color of a coloring a theme slot fills in
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum ColorPart {
+    Solid,
+    /// The gradient pole at this point
+    GradientPole(Point),
+}
+
+/// A piece of a scene to render on its own. Everything is public so jobs can be handed to other processes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileJob {
+    pub index: usize,
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub margin: usize,
+}
+
+/// One render from `Scene::sweep`
+pub struct SweepResult {
+    parameter: String,
+    value: Value,
+    image: Image,
+}
+
+impl SweepResult {
+    pub fn parameter(&self) -> &str {
+        &self.parameter
+    }
+
+    pub fn value(&self) -> Value {
+        self.value
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    /// Such as `sigma=12`, for captions and file names
+    pub fn label(&self) -> String {
+        format!("{}={}", self.parameter, self.value)
+    }
+}
+
+/// The winner of `Scene::render_best_of`. Rendering again with an rng seeded from `seed` gives the same image.
+pub struct ScoredRender {
+    pub image: Image,
+    pub score: f64,
+    pub seed: u64,
+}
+
+/// A rendered `TileJob`
+pub struct Tile {
+    job: TileJob,
+    image: Image,
+}
+
+impl Tile {
+    /// For tiles rendered somewhere else, like an image another process saved. Fails if the image isn't the size of the job.
+    pub fn new(job: TileJob, image: Image) -> Result<Self, ()> {
+        if image.width() != job.width || image.height() != job.height {
+            return Err(());
+        }
+        Ok(Tile { job, image })
+    }
+
+    pub fn job(&self) -> &TileJob {
+        &self.job
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+}
+
+pub enum SceneInstruction<R: rand::Rng> {
+    Custom(DrawInstruction<R>),
+    Styled {
+        clipping_shape: Shape,
+        style_name: String,
+    },
+}
+
+impl<R: rand::Rng> Scene<R> {
+    pub fn new(width: usize, height: usize, background_color: SolidColor) -> Self {
+        Scene {
+            width,
+            height,
+            background_color,
+            styles: HashMap::new(),
+            instructions: Vec::new(),
+            source: None,
+            parameters: Vec::new(),
+            compositing: Compositing::Srgb,
+            dithering: Dithering::None,
+            antialiasing: Antialiasing::None,
+            theme: None,
+            theme_bindings: Vec::new(),
+        }
+    }
+
+    /// How every render of the scene draws translucent colors
+    pub fn set_compositing(&mut self, compositing: Compositing) {
+        self.compositing = compositing;
+    }
+
+    /// How every render of the scene dithers its gradients
+    pub fn set_dithering(&mut self, dithering: Dithering) {
+        self.dithering = dithering;
+    }
+
+    /// How every render of the scene smooths the edges of its shapes
+    pub fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        self.antialiasing = antialiasing;
+    }
+
+    /// Fills in the `theme.<slot>` colors of the .noisy file the scene was loaded from with `theme`'s
+    /// every time the scene is rendered, so one scene can be rendered in light, dark and high contrast
+    /// versions without loading it again. Fails, leaving the theme as it was, if `theme` is missing a
+    /// slot the scene uses or fills the canvas color with a see-through one.
+    pub fn set_theme(&mut self, theme: Theme) -> Result<(), ()> {
+        for binding in &self.theme_bindings {
+            let color = theme.get_slot(&binding.slot).ok_or(())?;
+            if binding.target == ThemeTarget::Background {
+                TryInto::<SolidColor>::try_into(color)?;
+            }
+        }
+        self.theme = Some(theme);
+        Ok(())
+    }
+
+    pub fn theme(&self) -> Option<&Theme> {
+        self.theme.as_ref()
+    }
+
+    pub(crate) fn bind_theme_slot(&mut self, binding: ThemeBinding) {
+        self.theme_bindings.push(binding);
+    }
+
+    /// `coloring` with the colors the theme fills in for `target` swapped in, or nothing if there aren't any
+    fn themed_coloring(&self, target: &ThemeTarget, coloring: &ColorScheme<TransparentColor>) -> Option<ColorScheme<TransparentColor>> {
+        let theme = self.theme.as_ref()?;
+        let mut bindings = self.theme_bindings.iter().filter(|binding| binding.target == *target).peekable();
+        bindings.peek()?;
+
+        let mut coloring = coloring.clone();
+        for binding in bindings {
+            let color = theme.get_slot(&binding.slot).expect("Themes are checked for every slot the scene uses when they're set");
+            match (&mut coloring, binding.part) {
+                (ColorScheme::Solid(solid_color), ColorPart::Solid) => *solid_color = color,
+                (ColorScheme::LinearGradient(gradient), ColorPart::GradientPole(location)) => gradient.set_pole_color(location, color),
+                _ => {},
+            }
+        }
+        Some(coloring)
+    }
+
+    /// The canvas color, from the theme if the scene's canvas uses a theme slot
+    fn background_color(&self) -> SolidColor {
+        let binding = self.theme_bindings.iter().find(|binding| binding.target == ThemeTarget::Background);
+        match (&self.theme, binding) {
+            (Some(theme), Some(binding)) => theme.get_slot(&binding.slot)
+                .and_then(|color| color.try_into().ok())
+                .expect("Themes are checked for an opaque canvas color when they're set"),
+            _ => self.background_color,
+        }
+    }
+
+    /// Writes the theme's colors into the scene for good, so changing the theme afterwards does nothing
+    fn bake_theme(&mut self) {
+        self.background_color = self.background_color();
+
+        let instruction_colorings: Vec<(usize, ColorScheme<TransparentColor>)> = self.instructions.iter().enumerate()
+            .filter_map(|(index, instruction)| match instruction {
+                SceneInstruction::Custom(instruction) => self.themed_coloring(&ThemeTarget::Instruction(index), &instruction.coloring).map(|coloring| (index, coloring)),
+                SceneInstruction::Styled { .. } => None,
+            })
+            .collect();
+        for (index, coloring) in instruction_colorings {
+            if let SceneInstruction::Custom(instruction) = &mut self.instructions[index] {
+                instruction.coloring = coloring;
+            }
+        }
+
+        let style_colorings: Vec<(String, ColorScheme<TransparentColor>)> = self.styles.iter()
+            .filter_map(|(name, style)| self.themed_coloring(&ThemeTarget::Style(name.clone()), &style.coloring).map(|coloring| (name.clone(), coloring)))
+            .collect();
+        for (name, coloring) in style_colorings {
+            self.styles.get_mut(&name).expect("The name came from the styles").coloring = coloring;
+        }
+
+        self.theme_bindings.clear();
+    }
+
+    pub(crate) fn set_source(&mut self, source: NoisySource) {
+        self.source = Some(source);
+    }
+
+    pub(crate) fn set_parameters(&mut self, parameters: Vec<Parameter>) {
+        self.parameters = parameters;
+    }
+
+    /// The `#param`s of the .noisy file the scene was loaded from, which can be changed with `sweep`
+    /// or `NoisyLoader::with_constant`. Scenes built in code don't have any.
+    pub fn parameter_schema(&self) -> ParameterSchema {
+        ParameterSchema::new(self.parameters.clone())
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn push(&mut self, instruction: DrawInstruction<R>) {
+        self.instructions.push(SceneInstruction::Custom(instruction));
+    }
+
+    /// Registers a style under `name`, replacing (and restyling every instruction using) any style already registered with that name
+    pub fn add_style(&mut self, name: &str, style: Style<R>) {
+        self.theme_bindings.retain(|binding| binding.target != ThemeTarget::Style(name.to_owned()));
+        self.styles.insert(name.to_owned(), style);
+    }
+
+    pub fn get_style(&self, name: &str) -> Option<&Style<R>> {
+        self.styles.get(name)
+    }
+
+    /// Draws `clipping_shape` with the style registered as `style_name`. Fails if no such style has been registered
+    pub fn push_styled(&mut self, clipping_shape: Shape, style_name: &str) -> Result<(),()> {
+        if !self.styles.contains_key(style_name) {
+            return Err(());
+        }
+
+        self.instructions.push(SceneInstruction::Styled {
+            clipping_shape,
+            style_name: style_name.to_owned(),
+        });
+        Ok(())
+    }
+
+    pub fn instructions(&self) -> &[SceneInstruction<R>] {
+        &self.instructions
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(width = self.width, height = self.height, instructions = self.instructions.len())))]
+    pub fn render(&self, rng: &mut R) -> Image {
+        let mut image = Image::with_size(self.width, self.height, self.background_color()).with_compositing(self.compositing).with_dithering(self.dithering).with_antialiasing(self.antialiasing);
+        for index in 0..self.instructions.len() {
+            self.draw_instruction(&mut image, index, Point::ORIGIN, None, rng);
+        }
+        image
+    }
+
+    /// Renders the scene once for each of `values` of the constant `param_name`, which has to be a `#const`
+    /// or `#param` in the .noisy file the scene was loaded from. Every render starts from a copy of `rng`, so the
+    /// parameter is the only thing that changes between them. The file is loaded again for each value,
+    /// so anything added to the scene after loading it isn't part of the sweep.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(param_name = param_name)))]
+    pub fn sweep<V: Into<Value>>(&self, param_name: &str, values: impl IntoIterator<Item = V>, rng: &mut R) -> Result<Vec<SweepResult>, ReadFileError>
+    where R: Clone {
+        let source = self.source.as_ref().ok_or_else(|| ReadFileError::SyntaxError(
+            "Only scenes loaded from a .noisy file can be swept".into()
+        ))?;
+
+        values.into_iter().map(|value| {
+            let value = value.into();
+            let mut scene: Scene<R> = source.reload_with(param_name, value)?;
+            scene.set_compositing(self.compositing);
+            scene.set_dithering(self.dithering);
+            scene.set_antialiasing(self.antialiasing);
+            scene.theme = self.theme.clone();
+            Ok(SweepResult {
+                parameter: param_name.to_owned(),
+                value,
+                image: scene.render(&mut rng.clone()),
+            })
+        }).collect()
+    }
+
+    /// Renders `variants` versions of the scene, each with an rng seeded from `rng`, and keeps the one
+    /// `scorer` rates highest. Only noise differs between versions.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(variants = variants)))]
+    pub fn render_best_of(&self, variants: usize, scorer: &dyn Scorer, rng: &mut R) -> ScoredRender
+    where R: rand::SeedableRng {
+        assert!(variants > 0, "There has to be at least one variant to pick from");
+
+        (0..variants).map(|_| {
+            let seed = rng.random();
+            let image = self.render(&mut R::seed_from_u64(seed));
+            ScoredRender {
+                score: scorer.score(&image),
+                image,
+                seed,
+            }
+        })
+        .max_by(|render1, render2| render1.score.total_cmp(&render2.score))
+        .expect("There's at least one variant")
+    }
+
+    /// Renders the scene and also returns the coverage matte of every instruction, in draw order
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(width = self.width, height = self.height, instructions = self.instructions.len())))]
+    pub fn render_with_mattes(&self, rng: &mut R) -> (Image, Vec<Matte>) {
+        let mut image = Image::with_size(self.width, self.height, self.background_color()).with_compositing(self.compositing).with_dithering(self.dithering).with_antialiasing(self.antialiasing);
+        let mattes = (0..self.instructions.len())
+            .map(|index| self.draw_instruction(&mut image, index, Point::ORIGIN, None, rng).matte(self.width, self.height))
+            .collect();
+        (image, mattes)
+    }
+
+    /// Renders the scene as separate layers for `ora::write_ora`: the background, then one layer per instruction
+    /// holding the pixels it changed. Instructions that don't change anything don't get a layer.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(width = self.width, height = self.height, instructions = self.instructions.len())))]
+    pub fn render_layers(&self, rng: &mut R) -> Vec<Layer> {
+        let mut image = Image::with_size(self.width, self.height, self.background_color()).with_compositing(self.compositing).with_dithering(self.dithering).with_antialiasing(self.antialiasing);
+        let mut layers = vec![Layer::opaque("Background", &image)];
+
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            let before = image.clone();
+            self.draw_instruction(&mut image, index, Point::ORIGIN, None, rng);
+
+            let name = match instruction {
+                SceneInstruction::Custom(instruction) => format!("{} {}", shape_name(&instruction.clipping_shape), index + 1),
+                SceneInstruction::Styled { clipping_shape, style_name } => format!("{} {} ({style_name})", shape_name(clipping_shape), index + 1),
+            };
+            layers.extend(Layer::from_changes(&name, &before, &image));
+        }
+        layers
+    }
+
+    /// Renders the scene, giving up as soon as it goes past any of `limits`
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(width = self.width, height = self.height, instructions = self.instructions.len())))]
+    pub fn render_with_limits(&self, rng: &mut R, limits: &Limits) -> Result<Image, LimitExceeded> {
+        limits.check_canvas(self.width, self.height)?;
+        limits.check_instructions(self.instructions.len())?;
+
+        let start_time = std::time::Instant::now();
+        let deadline = limits.max_render_time.and_then(|limit| start_time.checked_add(limit));
+        let mut image = Image::with_size(self.width, self.height, self.background_color()).with_compositing(self.compositing).with_dithering(self.dithering).with_antialiasing(self.antialiasing);
+        for index in 0..self.instructions.len() {
+            limits.check_render_time(start_time.elapsed())?;
+            self.draw_instruction(&mut image, index, Point::ORIGIN, deadline, rng);
+        }
+        limits.check_render_time(start_time.elapsed())?;
+
+        Ok(image)
+    }
+
+    /// Rewrites the scene to draw the same image with less work: constant gradients become solid colors,
+    /// simple transforms are baked into their shapes, and draws that can't be seen are dropped. Those are
+    /// fully transparent or off canvas draws, repeats of the same opaque draw, and anything an opaque
+    /// full canvas draw covers up later on. Dropped draws don't use the random number generator, so noise
+    /// in the rest of the scene can come out differently. The scene's theme is written into it for good
+    /// first, so setting another theme afterwards doesn't change anything.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(instructions = self.instructions.len())))]
+    pub fn optimize(&mut self) {
+        self.bake_theme();
+        for style in self.styles.values_mut() {
+            style.coloring.fold_constant();
+        }
+        for instruction in self.instructions.iter_mut() {
+            match instruction {
+                SceneInstruction::Custom(instruction) => {
+                    instruction.clipping_shape = instruction.clipping_shape.bake_transforms();
+                    instruction.coloring.fold_constant();
+                },
+                SceneInstruction::Styled { clipping_shape, .. } => *clipping_shape = clipping_shape.bake_transforms(),
+            }
+        }
+
+        let canvas = Rect::from_points(&Point::ORIGIN, &Point { x: self.width as f64 - 1., y: self.height as f64 - 1. });
+        let instructions = std::mem::take(&mut self.instructions);
+
+        // everything before the last opaque draw over the whole canvas gets painted over
+        let first_visible = instructions.iter().rposition(|instruction| {
+            let settings = self.settings(instruction);
+            !settings.has_noise() && settings.is_opaque() && match settings.clipping_shape {
+                Shape::Rect(rect) => rect.contains(&canvas.min_point()) && rect.contains(&canvas.max_point()),
+                _ => false,
+            }
+        }).unwrap_or(0);
+
+        for instruction in instructions.into_iter().skip(first_visible) {
+            let settings = self.settings(&instruction);
+            if !settings.has_noise() && (settings.is_invisible() || settings.clipping_shape.bounding_rect().intersection(&canvas).is_none()) {
+                continue;
+            }
+
+            let repeats_previous = self.instructions.last().is_some_and(|previous| {
+                let previous = self.settings(previous);
+                !settings.has_noise() && !previous.has_noise() && settings.is_opaque() && previous.is_opaque()
+                    && previous.clipping_shape == settings.clipping_shape && previous.coloring == settings.coloring
+                    && previous.dithering == settings.dithering
+            });
+            if !repeats_previous {
+                self.instructions.push(instruction);
+            }
+        }
+    }
+
+    fn settings<'a>(&'a self, instruction: &'a SceneInstruction<R>) -> DrawSettings<'a, R> {
+        match instruction {
+            SceneInstruction::Custom(instruction) => instruction.settings(),
+            SceneInstruction::Styled { clipping_shape, style_name } => {
+                self.styles.get(style_name).expect("Styles are registered before they can be referenced").settings(clipping_shape)
+            },
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    fn draw_instruction(&self, image: &mut Image, index: usize, origin: Point, deadline: Option<std::time::Instant>, rng: &mut R) -> RenderedLayer {
+        let (mut settings, target) = match &self.instructions[index] {
+            SceneInstruction::Custom(instruction) => (instruction.settings(), ThemeTarget::Instruction(index)),
+            SceneInstruction::Styled { clipping_shape, style_name } => {
+                let style = self.styles.get(style_name).expect("Styles are registered before they can be referenced");
+                (style.settings(clipping_shape), ThemeTarget::Style(style_name.clone()))
+            },
+        };
+
+        let themed_coloring = self.themed_coloring(&target, settings.coloring);
+        if let Some(coloring) = &themed_coloring {
+            settings.coloring = coloring;
+        }
+        settings.deadline = deadline;
+        image.draw_with_settings(settings, origin, rng)
+    }
+
+    /// Splits the canvas into tiles of at most `tile_width` by `tile_height` that can be rendered separately,
+    /// even by different processes that each load the same scene, and put back together with `Image::assemble`.
+    /// Each tile also renders `margin` pixels past its edges, so noise that moves or blends nearby pixels
+    /// sees the same neighbors it would in a full render. Noise that reaches further than that doesn't.
+    pub fn partition(&self, tile_width: usize, tile_height: usize, margin: usize) -> Vec<TileJob> {
+        assert!(tile_width > 0 && tile_height > 0, "Tiles must have a size");
+
+        let mut jobs = Vec::new();
+        for y in (0..self.height).step_by(tile_height) {
+            for x in (0..self.width).step_by(tile_width) {
+                jobs.push(TileJob {
+                    index: jobs.len(),
+                    x,
+                    y,
+                    width: tile_width.min(self.width - x),
+                    height: tile_height.min(self.height - y),
+                    margin,
+                });
+            }
+        }
+        jobs
+    }
+
+    /// Renders one tile from `partition`. Noise that takes random numbers from `rng` takes them in a
+    /// different order than a full render does, so only noise fields are sure to line up across tiles.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(index = job.index)))]
+    pub fn render_tile(&self, job: &TileJob, rng: &mut R) -> Tile {
+        let left = job.x.saturating_sub(job.margin);
+        let top = job.y.saturating_sub(job.margin);
+        let right = (job.x + job.width + job.margin).min(self.width);
+        let bottom = (job.y + job.height + job.margin).min(self.height);
+
+        let mut image = Image::with_size(right - left, bottom - top, self.background_color()).with_compositing(self.compositing).with_dithering(self.dithering).with_antialiasing(self.antialiasing);
+        let origin = Point { x: left as f64, y: top as f64 };
+        for index in 0..self.instructions.len() {
+            self.draw_instruction(&mut image, index, origin, None, rng);
+        }
+
+        Tile {
+            job: *job,
+            image: image.crop(&Rect::from_points(
+                &Point { x: (job.x - left) as f64, y: (job.y - top) as f64 },
+                &Point { x: (job.x - left + job.width - 1) as f64, y: (job.y - top + job.height - 1) as f64 },
+            )).expect("Tiles from partition aren't empty"),
+        }
+    }
+
+    /// Renders the scene one tile at a time and puts the tiles back together. Each draw only ever needs a
+    /// temporary layer the size of a tile, rather than one the size of the whole canvas.
+    pub fn render_tiled(&self, tile_width: usize, tile_height: usize, margin: usize, rng: &mut R) -> Image {
+        let tiles: Vec<Tile> = self.partition(tile_width, tile_height, margin).iter().map(|job| self.render_tile(job, rng)).collect();
+        Image::assemble(self.width, self.height, tiles).expect("Partitioned tiles cover the canvas exactly")
+    }
+
+    /// Renders the scene a row of tiles at a time, writing each row of tiles into a PNG file as soon as
+    /// it's done, so the whole canvas is never held in memory at once. Tiles are laid out like `partition`'s.
+    pub fn render_to_png(&self, filename: &str, tile_width: usize, tile_height: usize, margin: usize, rng: &mut R) -> Result<(),()> {
+        let file = std::fs::File::create(filename).map_err(|_|())?;
+        self.stream_png(std::io::BufWriter::new(file), tile_width, tile_height, margin, rng)
+    }
+
+    /// Like `render_to_png`, but writes the PNG's bytes to `writer`
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(width = self.width, height = self.height)))]
+    pub fn stream_png(&self, writer: impl std::io::Write, tile_width: usize, tile_height: usize, margin: usize, rng: &mut R) -> Result<(),()> {
+        let mut encoder = png::Encoder::new(writer, self.width.try_into().map_err(|_|())?, self.height.try_into().map_err(|_|())?);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut png_writer = encoder.write_header().map_err(|_|())?;
+        let mut stream = png_writer.stream_writer().map_err(|_|())?;
+
+        let jobs = self.partition(tile_width, tile_height, margin);
+        for tile_row in jobs.chunk_by(|job1, job2| job1.y == job2.y) {
+            let mut rows = vec![0; self.width * tile_row[0].height * 3];
+            for job in tile_row {
+                let tile = self.render_tile(job, rng);
+                for y in 0..job.height {
+                    for x in 0..job.width {
+                        let color = tile.image().get_pixel(x, y);
+                        let index = (job.x + x + y * self.width) * 3;
+                        rows[index..index + 3].copy_from_slice(&[color.red, color.green, color.blue]);
+                    }
+                }
+            }
+            std::io::Write::write_all(&mut stream, &rows).map_err(|_|())?;
+        }
+
+        stream.finish().map_err(|_|())
+    }
+}
+
+fn shape_name(shape: &Shape) -> &'static str {
+    match shape {
+        Shape::Rect(_) => "Rect",
+        Shape::Ellipse(_) => "Ellipse",
+        Shape::Polygon(_) => "Polygon",
+        Shape::Triangle(_) => "Triangle",
+        Shape::Mask(_) => "Mask",
+        Shape::Arc(_) => "Arc",
+        Shape::WaveBand(_) => "Wave band",
+        Shape::TransformedShape(_) => "Transformed shape",
+        Shape::Offset(_) => "Offset shape",
+        Shape::Outline(_) => "Outline",
+        Shape::RadialSymmetry(_) => "Radial symmetry",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::coloring::{BlendMode, Composite};
+
+    fn draw(clipping_shape: Shape, color: SolidColor, opacity: f64, blend_mode: BlendMode) -> DrawInstruction<StdRng> {
+        DrawInstruction {
+            pre_clip_noise: None,
+            clipping_shape,
+            coloring: ColorScheme::Solid(color.into()),
+            post_clip_noise: None,
+            post_draw_noise: None,
+            opacity,
+            blend_mode,
+            dithering: None,
+        }
+    }
+
+    fn full_canvas() -> Shape {
+        Rect::from_points(&Point::ORIGIN, &Point { x: 8., y: 8. }).into()
+    }
+
+    fn square() -> Shape {
+        Rect::from_points(&Point { x: 2., y: 2. }, &Point { x: 5., y: 5. }).into()
+    }
+
+    fn render(scene: &Scene<StdRng>) -> Vec<SolidColor> {
+        scene.render(&mut StdRng::seed_from_u64(0)).canvas
+    }
+
+    /// Optimizes the scene, checking it still renders the same. Returns how many draws are left.
+    fn optimized_len(mut scene: Scene<StdRng>) -> usize {
+        let before = render(&scene);
+        scene.optimize();
+        assert!(render(&scene) == before, "optimizing changed the render");
+        scene.instructions.len()
+    }
+
+    #[test]
+    fn translucent_draws_over_the_canvas_keep_what_is_under_them() {
+        let mut scene = Scene::new(8, 8, SolidColor::BLACK);
+        scene.push(draw(square(), SolidColor::WHITE, 1., BlendMode::Normal));
+        scene.push(draw(full_canvas(), SolidColor { red: 200, green: 10, blue: 10 }, 0.5, BlendMode::Normal));
+        assert_eq!(optimized_len(scene), 2);
+    }
+
+    #[test]
+    fn opaque_draws_over_the_canvas_hide_what_is_under_them() {
+        let mut scene = Scene::new(8, 8, SolidColor::BLACK);
+        scene.push(draw(square(), SolidColor::WHITE, 1., BlendMode::Normal));
+        scene.push(draw(full_canvas(), SolidColor { red: 200, green: 10, blue: 10 }, 1., BlendMode::Normal));
+        assert_eq!(optimized_len(scene), 1);
+    }
+
+    #[test]
+    fn draws_with_no_opacity_are_dropped() {
+        let mut scene = Scene::new(8, 8, SolidColor::BLACK);
+        scene.push(draw(square(), SolidColor::WHITE, 0., BlendMode::Normal));
+        scene.push(draw(square(), SolidColor::WHITE, 0.001, BlendMode::Normal));
+        assert_eq!(optimized_len(scene), 0);
+    }
+
+    #[test]
+    fn only_opaque_repeats_are_dropped() {
+        let mut scene = Scene::new(8, 8, SolidColor::BLACK);
+        scene.push(draw(square(), SolidColor::WHITE, 0.5, BlendMode::Normal));
+        scene.push(draw(square(), SolidColor::WHITE, 0.5, BlendMode::Normal));
+        scene.push(draw(square(), SolidColor::WHITE, 1., BlendMode::Normal));
+        scene.push(draw(square(), SolidColor::WHITE, 1., BlendMode::Normal));
+        assert_eq!(optimized_len(scene), 3);
+    }
+
+    #[test]
+    fn opaque_draws_with_other_blend_modes_keep_what_is_under_them() {
+        for blend_mode in [BlendMode::Multiply, BlendMode::Screen, BlendMode::Difference] {
+            let mut scene = Scene::new(8, 8, SolidColor::BLACK);
+            scene.push(draw(square(), SolidColor::WHITE, 1., BlendMode::Normal));
+            scene.push(draw(full_canvas(), SolidColor { red: 200, green: 10, blue: 10 }, 1., blend_mode));
+            scene.push(draw(full_canvas(), SolidColor { red: 200, green: 10, blue: 10 }, 1., blend_mode));
+            assert_eq!(optimized_len(scene), 3);
+        }
+    }
+
+    #[test]
+    fn stacked_translucent_colors_are_not_folded() {
+        let translucent = TransparentColor { red: 200, green: 10, blue: 10, alpha: 100 };
+        let mut scene = Scene::new(8, 8, SolidColor::BLACK);
+        let mut stacked = draw(square(), SolidColor::WHITE, 1., BlendMode::Normal);
+        stacked.coloring = Composite::new(ColorScheme::Solid(translucent)).with_layer(ColorScheme::Solid(translucent), 1.).into();
+        scene.push(stacked);
+        assert_eq!(optimized_len(scene), 1);
+    }
+
+    #[test]
+    fn tiles_line_up_with_a_full_render() {
+        use crate::coloring::LinearGradient;
+        use crate::noise::{BrightnessNoise, PerlinNoise, ValueNoise};
+        use crate::shapes::Ellipse;
+
+        let mut scene = Scene::new(23, 17, SolidColor::BLACK);
+        let mut background = draw(Rect::from_points(&Point::ORIGIN, &Point { x: 23., y: 17. }).into(), SolidColor::BLACK, 1., BlendMode::Normal);
+        background.coloring = LinearGradient::with_poles(
+            (Point::ORIGIN, SolidColor { red: 5, green: 47, blue: 95 }.into()),
+            (Point { x: 23., y: 17. }, SolidColor { red: 6, green: 167, blue: 125 }.into()),
+        ).into();
+        background.post_draw_noise = Some(Box::new(BrightnessNoise::new(ValueNoise::new(3), 1., 0.1)));
+        scene.push(background);
+
+        let mut circle = draw(Ellipse::circle(Point { x: 11., y: 8. }, 6.).into(), SolidColor { red: 186, green: 46, blue: 55 }, 0.8, BlendMode::Normal);
+        circle.post_clip_noise = Some(Box::new(BrightnessNoise::new(PerlinNoise::new(7), 4., 0.3)));
+        scene.push(circle);
+
+        let full = render(&scene);
+        for (tile_width, tile_height) in [(5, 7), (8, 8), (23, 1)] {
+            let tiled = scene.render_tiled(tile_width, tile_height, 0, &mut StdRng::seed_from_u64(0));
+            assert!(tiled.canvas == full, "{tile_width} by {tile_height} tiles don't line up");
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::Image;
+
+/// Rates how good a rendered image looks, higher being better, so batches of renders can be curated
+/// automatically. Scores from different scorers aren't on the same scale.
+pub trait Scorer {
+    fn score(&self, image: &Image) -> f64;
+}
+
+/// Any `Fn(&Image) -> f64` can score images
+impl<F: Fn(&Image) -> f64> Scorer for F {
+    fn score(&self, image: &Image) -> f64 {
+        self(image)
+    }
+}
+
+/// How much fine detail the image has: the variance of the Laplacian of its brightness. Blurry or flat images score low.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Sharpness;
+
+impl Scorer for Sharpness {
+    fn score(&self, image: &Image) -> f64 {
+        if image.width() < 3 || image.height() < 3 {
+            return 0.;
+        }
+
+        let luminance = |x: usize, y: usize| image.get_pixel(x, y).relative_luminance();
+        let laplacians: Vec<f64> = (1..image.height() - 1).flat_map(|y| (1..image.width() - 1).map(move |x| (x, y)))
+            .map(|(x, y)| luminance(x - 1, y) + luminance(x + 1, y) + luminance(x, y - 1) + luminance(x, y + 1) - 4. * luminance(x, y))
+            .collect();
+        variance(&laplacians)
+    }
+}
+
+/// How vivid the colors are, using Hasler and Süsstrunk's colorfulness metric. Grayscale images score 0.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Colorfulness;
+
+impl Scorer for Colorfulness {
+    fn score(&self, image: &Image) -> f64 {
+        let (red_green, yellow_blue): (Vec<f64>, Vec<f64>) = image.canvas.iter().map(|color| {
+            let (red, green, blue) = (color.red as f64, color.green as f64, color.blue as f64);
+            (red - green, (red + green) / 2. - blue)
+        }).unzip();
+
+        let spread = f64::hypot(variance(&red_green).sqrt(), variance(&yellow_blue).sqrt());
+        let average = f64::hypot(mean(&red_green), mean(&yellow_blue));
+        spread + 0.3 * average
+    }
+}
+
+/// How much of the image's detail sits near the four points where lines dividing it into thirds cross,
+/// from 0 to 1. Edges stand in for what draws the eye.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RuleOfThirds;
+
+impl Scorer for RuleOfThirds {
+    fn score(&self, image: &Image) -> f64 {
+        if image.width() < 2 || image.height() < 2 {
+            return 0.;
+        }
+
+        let (width, height) = (image.width() as f64, image.height() as f64);
+        let thirds_points = [(1., 1.), (2., 1.), (1., 2.), (2., 2.)].map(|(x, y)| (x * width / 3., y * height / 3.));
+        // about a tenth of the diagonal around each point counts as near it
+        let spread = f64::hypot(width, height) / 10.;
+        let luminance = |x: usize, y: usize| image.get_pixel(x, y).relative_luminance();
+
+        let mut total_detail = 0.;
+        let mut weighted_detail = 0.;
+        for y in 0..image.height() - 1 {
+            for x in 0..image.width() - 1 {
+                let detail = f64::hypot(luminance(x + 1, y) - luminance(x, y), luminance(x, y + 1) - luminance(x, y));
+                let closest_dist = thirds_points.iter()
+                    .map(|(thirds_x, thirds_y)| f64::hypot(x as f64 - thirds_x, y as f64 - thirds_y))
+                    .fold(f64::INFINITY, f64::min);
+
+                total_detail += detail;
+                weighted_detail += detail * (-(closest_dist * closest_dist) / (2. * spread * spread)).exp();
+            }
+        }
+
+        if total_detail == 0. {
+            0.
+        } else {
+            weighted_detail / total_detail
+        }
+    }
+}
+
+/// Adds up the scores of several scorers, each multiplied by its weight
+#[derive(Default)]
+pub struct WeightedScorer {
+    scorers: Vec<(Box<dyn Scorer>, f64)>,
+}
+
+impl WeightedScorer {
+    pub fn new() -> Self {
+        WeightedScorer { scorers: Vec::new() }
+    }
+
+    pub fn with(mut self, scorer: impl Scorer + 'static, weight: f64) -> Self {
+        self.scorers.push((Box::new(scorer), weight));
+        self
+    }
+}
+
+impl Scorer for WeightedScorer {
+    fn score(&self, image: &Image) -> f64 {
+        self.scorers.iter().map(|(scorer, weight)| scorer.score(image) * weight).sum()
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn variance(values: &[f64]) -> f64 {
+    let average = mean(values);
+    let squared_diffs: Vec<f64> = values.iter().map(|value| (value - average) * (value - average)).collect();
+    mean(&squared_diffs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coloring::SolidColor;
+
+    const GRAY: SolidColor = SolidColor { red: 128, green: 128, blue: 128 };
+
+    fn image_from(width: usize, height: usize, color_at: impl Fn(usize, usize) -> SolidColor) -> Image {
+        let mut image = Image::with_size(width, height, SolidColor::BLACK);
+        for y in 0..height {
+            for x in 0..width {
+                *image.get_pixel_mut(x, y) = color_at(x, y);
+            }
+        }
+        image
+    }
+
+    /// A white square on black, with its middle at (`x`, `y`)
+    fn square_at(x: usize, y: usize) -> Image {
+        image_from(60, 60, |pixel_x, pixel_y| {
+            if pixel_x.abs_diff(x) <= 3 && pixel_y.abs_diff(y) <= 3 { SolidColor::WHITE } else { SolidColor::BLACK }
+        })
+    }
+
+    #[test]
+    fn sharpness_prefers_fine_detail() {
+        let checkers = image_from(20, 20, |x, y| if (x + y) % 2 == 0 { SolidColor::WHITE } else { SolidColor::BLACK });
+        let gradient = image_from(20, 20, |x, _| {
+            let gray = (x * 12) as u8;
+            SolidColor { red: gray, green: gray, blue: gray }
+        });
+        let flat = Image::with_size(20, 20, GRAY);
+
+        assert!(Sharpness.score(&checkers) > Sharpness.score(&gradient));
+        assert!(Sharpness.score(&gradient) > Sharpness.score(&flat));
+        assert_eq!(Sharpness.score(&flat), 0.);
+    }
+
+    #[test]
+    fn colorfulness_prefers_vivid_colors() {
+        let vivid = image_from(10, 10, |x, _| if x < 5 { SolidColor { red: 255, green: 0, blue: 0 } } else { SolidColor { red: 0, green: 0, blue: 255 } });
+        let muted = Image::with_size(10, 10, SolidColor { red: 150, green: 120, blue: 110 });
+        let grays = image_from(10, 10, |x, _| if x < 5 { GRAY } else { SolidColor::WHITE });
+
+        assert!(Colorfulness.score(&vivid) > Colorfulness.score(&muted));
+        assert!(Colorfulness.score(&muted) > Colorfulness.score(&grays));
+        assert_eq!(Colorfulness.score(&grays), 0.);
+    }
+
+    #[test]
+    fn rule_of_thirds_prefers_detail_near_the_thirds() {
+        let on_thirds = RuleOfThirds.score(&square_at(20, 40));
+        let centered = RuleOfThirds.score(&square_at(30, 30));
+        let cornered = RuleOfThirds.score(&square_at(5, 5));
+
+        assert!(on_thirds > centered && centered > cornered, "{on_thirds} {centered} {cornered}");
+        assert!(on_thirds <= 1.);
+        assert_eq!(RuleOfThirds.score(&Image::with_size(60, 60, GRAY)), 0.);
+    }
+
+    #[test]
+    fn weighted_scorers_add_up_weighted_scores() {
+        let scorer = WeightedScorer::new()
+            .with(|_: &Image| 2., 1.5)
+            .with(|image: &Image| image.width() as f64, -0.5);
+        assert_eq!(scorer.score(&Image::with_size(4, 1, GRAY)), 1.);
+        assert_eq!(WeightedScorer::new().score(&Image::with_size(4, 1, GRAY)), 0.);
+    }
+}
@@ -1,4 +1,6 @@
-use std::ops::Div;
+use std::ops::{Add, Div, Mul, Neg, Range, Sub};
+
+use crate::assets::{self, AssetResolver, FileSystemResolver};
 
 
 #[derive(Copy, Clone, Debug,  PartialEq)]
@@ -18,9 +20,78 @@ impl Point {
     pub fn dist_to(&self, other: &Point) -> f64 {
         self.square_dist_to(other).sqrt()
     }
+
+    /// Halfway between the two points
+    pub fn midpoint(&self, other: &Point) -> Point {
+        (*self + *other) / 2.
+    }
+
+    // the rest treat the point as a vector from the origin
+
+    pub fn dot(&self, other: &Point) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The z component of the 3d cross product, positive when `other` is counterclockwise from `self`
+    pub fn cross(&self, other: &Point) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length(&self) -> f64 {
+        f64::hypot(self.x, self.y)
+    }
+
+    /// Same direction, length 1. The origin has no direction, so it stays at the origin.
+    pub fn normalize(&self) -> Point {
+        let length = self.length();
+        if length == 0. {
+            return Point::ORIGIN;
+        }
+        *self / length
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl Mul<f64> for Point {
+    type Output = Point;
+
+    fn mul(self, scalar: f64) -> Point {
+        Point { x: self.x * scalar, y: self.y * scalar }
+    }
+}
+
+impl Div<f64> for Point {
+    type Output = Point;
+
+    fn div(self, scalar: f64) -> Point {
+        Point { x: self.x / scalar, y: self.y / scalar }
+    }
+}
+
+impl Neg for Point {
+    type Output = Point;
+
+    fn neg(self) -> Point {
+        Point { x: -self.x, y: -self.y }
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Area {
     pub height: f64,
     pub width: f64,
@@ -52,10 +123,179 @@ pub trait CheckInside {
     fn contains(&self, point: &Point) -> bool;
 }
 
+/// Distance from a point to the boundary of a shape: negative inside, positive outside.
+pub trait SignedDistance {
+    fn signed_distance(&self, point: &Point) -> f64;
+}
+
+/// How the edges of a shape are smoothed when it's drawn. Without antialiasing each pixel is either
+/// fully inside or fully outside, which leaves jagged stair steps along curves and slanted edges.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Antialiasing {
+    #[default]
+    None,
+    /// Estimates how much of each pixel is covered from its distance to the edge. Almost free, but only
+    /// as good as the shape's signed distance, so it can soften sharp corners slightly.
+    Distance,
+    /// Checks an `n` by `n` grid of points inside each pixel near the edge. Pixels more than a pixel
+    /// away from the edge are only checked once, so it costs much less than supersampling everything.
+    EdgeSamples(usize),
+}
+
+impl Antialiasing {
+    /// How much of the pixel centered on `point` is inside `shape`, from 0 to 1. `canvas` spans the centers
+    /// of the canvas's edge pixels. Half of each edge pixel hangs off the canvas, so only the half on the
+    /// canvas is measured, which keeps a shape that reaches the edge from fading out along it.
+    pub fn coverage(&self, shape: &Shape, point: &Point, canvas: &Rect) -> f64 {
+        let inside = |point: &Point| if shape.contains(point) { 1. } else { 0. };
+
+        let (canvas_min, canvas_max) = (canvas.min_point(), canvas.max_point());
+        let min = Point { x: (point.x - 0.5).max(canvas_min.x), y: (point.y - 0.5).max(canvas_min.y) };
+        let max = Point { x: (point.x + 0.5).min(canvas_max.x), y: (point.y + 0.5).min(canvas_max.y) };
+
+        match *self {
+            Antialiasing::None => inside(point),
+            Antialiasing::Distance => {
+                let half_size = (max.x - min.x).min(max.y - min.y) / 2.;
+                if half_size <= 0. {
+                    return inside(point);
+                }
+                (0.5 - shape.signed_distance(&min.midpoint(&max)) / (2. * half_size)).clamp(0., 1.)
+            }
+            Antialiasing::EdgeSamples(samples) => {
+                // a pixel's corners are about 0.71 from its center
+                if samples <= 1 || shape.signed_distance(point).abs() > 1. {
+                    return inside(point);
+                }
+
+                let offset = |index: usize, min: f64, max: f64| min + (index as f64 + 0.5) / samples as f64 * (max - min);
+                let hits: f64 = (0..samples).flat_map(|row| (0..samples).map(move |column| (column, row)))
+                    .map(|(column, row)| inside(&Point { x: offset(column, min.x, max.x), y: offset(row, min.y, max.y) }))
+                    .sum();
+                hits / (samples * samples) as f64
+            }
+        }
+    }
+}
+
+/// Sorted, non-overlapping runs of pixel columns
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RangeSet {
+    ranges: Vec<Range<usize>>,
+}
+
+impl RangeSet {
+    pub fn new() -> Self {
+        RangeSet { ranges: Vec::new() }
+    }
+
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// Adds `range`, merging it with any runs it overlaps or touches
+    pub fn insert(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let first = self.ranges.partition_point(|existing| existing.end < range.start);
+        let last = self.ranges.partition_point(|existing| existing.start <= range.end);
+        if first == last {
+            self.ranges.insert(first, range);
+            return;
+        }
+
+        let merged = self.ranges[first].start.min(range.start)..self.ranges[last - 1].end.max(range.end);
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    pub fn contains(&self, x: usize) -> bool {
+        let index = self.ranges.partition_point(|range| range.end <= x);
+        self.ranges.get(index).is_some_and(|range| range.contains(&x))
+    }
+
+    /// Number of columns covered
+    pub fn len(&self) -> usize {
+        self.ranges.iter().map(|range| range.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Every covered column, in order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.ranges.iter().flat_map(|range| range.clone())
+    }
+
+    /// Checks every column in `x_range` one at a time, for shapes without anything smarter
+    fn from_fn(x_range: Range<usize>, mut is_inside: impl FnMut(usize) -> bool) -> Self {
+        let mut range_set = RangeSet::new();
+        let mut run_start = None;
+        for x in x_range.clone() {
+            match (is_inside(x), run_start) {
+                (true, None) => run_start = Some(x),
+                (false, Some(start)) => {
+                    range_set.ranges.push(start..x);
+                    run_start = None;
+                },
+                _ => {},
+            }
+        }
+        if let Some(start) = run_start {
+            range_set.ranges.push(start..x_range.end);
+        }
+        range_set
+    }
+}
+
+/// The columns of `x_range` that fall between `min_x` and `max_x`, inclusive
+pub(crate) fn columns_between(min_x: f64, max_x: f64, x_range: &Range<usize>) -> Range<usize> {
+    let start = min_x.ceil().clamp(x_range.start as f64, x_range.end as f64) as usize;
+    let end = (max_x.floor() + 1.).clamp(x_range.start as f64, x_range.end as f64) as usize;
+    start..end.max(start)
+}
+
+/// The columns of a convex shape's row between `min_x` and `max_x`, with the ends checked against
+/// `inside` since the edges worked out for the whole row can land a rounding error away from where
+/// the shape's `contains` puts them. If the row misses the shape, `min_x` and `max_x` should be the
+/// closest it gets.
+fn convex_columns(min_x: f64, max_x: f64, x_range: &Range<usize>, inside: impl Fn(usize) -> bool) -> Range<usize> {
+    let mut columns = columns_between(min_x, max_x, x_range);
+    if columns.is_empty() {
+        let closest = (((min_x + max_x) / 2.).round().max(0.) as usize).clamp(x_range.start, x_range.end);
+        columns = closest..closest;
+    }
+
+    while columns.start > x_range.start && inside(columns.start - 1) {
+        columns.start -= 1;
+    }
+    while columns.start < columns.end && !inside(columns.start) {
+        columns.start += 1;
+    }
+    while columns.end < x_range.end && inside(columns.end) {
+        columns.end += 1;
+    }
+    while columns.end > columns.start && !inside(columns.end - 1) {
+        columns.end -= 1;
+    }
+    columns
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Shape {
     Rect(Rect),
     Ellipse(Ellipse),
+    Polygon(Polygon),
+    Triangle(Triangle),
+    Mask(Mask),
+    Arc(Arc),
+    WaveBand(WaveBand),
     TransformedShape(TransformedShape),
+    Offset(OffsetShape),
+    Outline(OutlineShape),
+    RadialSymmetry(RadialSymmetry),
 }
 
 impl CheckInside for Shape {
@@ -63,12 +303,484 @@ impl CheckInside for Shape {
         match self {
             Shape::Rect(rect) => rect.contains(point),
             Shape::Ellipse(ellipse) => ellipse.contains(point),
+            Shape::Polygon(polygon) => polygon.contains(point),
+            Shape::Triangle(triangle) => triangle.contains(point),
+            Shape::Mask(mask) => mask.contains(point),
+            Shape::Arc(arc) => arc.contains(point),
+            Shape::WaveBand(wave_band) => wave_band.contains(point),
             Shape::TransformedShape(trans_shape) => trans_shape.contains(point),
+            Shape::Offset(offset_shape) => offset_shape.contains(point),
+            Shape::Outline(outline_shape) => outline_shape.contains(point),
+            Shape::RadialSymmetry(symmetry) => symmetry.contains(point),
+        }
+    }
+}
+
+impl SignedDistance for Shape {
+    fn signed_distance(&self, point: &Point) -> f64 {
+        match self {
+            Shape::Rect(rect) => rect.signed_distance(point),
+            Shape::Ellipse(ellipse) => ellipse.signed_distance(point),
+            Shape::Polygon(polygon) => polygon.signed_distance(point),
+            Shape::Triangle(triangle) => triangle.signed_distance(point),
+            Shape::Mask(mask) => mask.signed_distance(point),
+            Shape::Arc(arc) => arc.signed_distance(point),
+            Shape::WaveBand(wave_band) => wave_band.signed_distance(point),
+            Shape::TransformedShape(trans_shape) => trans_shape.signed_distance(point),
+            Shape::Offset(offset_shape) => offset_shape.signed_distance(point),
+            Shape::Outline(outline_shape) => outline_shape.signed_distance(point),
+            Shape::RadialSymmetry(symmetry) => symmetry.signed_distance(point),
+        }
+    }
+}
+
+impl Shape {
+    /// The columns in `x_range` whose points on row `y` are inside the shape, the same as calling
+    /// `contains` on each of them. Rects, ellipses, polygons and triangles work out whole runs at once.
+    pub fn contains_row(&self, y: f64, x_range: Range<usize>) -> RangeSet {
+        match self {
+            Shape::Rect(rect) => rect.contains_row(y, x_range),
+            Shape::Ellipse(ellipse) => ellipse.contains_row(y, x_range),
+            Shape::Polygon(polygon) => polygon.contains_row(y, x_range),
+            Shape::Triangle(triangle) => triangle.contains_row(y, x_range),
+            _ => RangeSet::from_fn(x_range, |x| self.contains(&Point { x: x as f64, y })),
+        }
+    }
+
+    /// Whether the two shapes share any point. Rects, polygons and circles are checked exactly, and
+    /// everything else might report an overlap when the shapes come within about a pixel of each other.
+    pub fn intersects(&self, other: &Shape) -> bool {
+        let Some(overlap) = self.bounding_rect().intersection(&other.bounding_rect()) else {
+            return false;
+        };
+
+        match (self.as_polygon(), other.as_polygon(), self.as_circle(), other.as_circle()) {
+            (Some(polygon), Some(other_polygon), _, _) => polygon.intersects(&other_polygon),
+            (Some(_), _, _, Some((center, radius))) => self.signed_distance(&center) <= radius,
+            (_, Some(_), Some((center, radius)), _) => other.signed_distance(&center) <= radius,
+            (_, _, Some((center, radius)), Some((other_center, other_radius))) => center.dist_to(&other_center) <= radius + other_radius,
+            _ => self.sampled_intersects(other, &overlap),
+        }
+    }
+
+    fn as_polygon(&self) -> Option<Polygon> {
+        match self {
+            Shape::Rect(rect) => {
+                let (min_point, max_point) = (rect.min_point, rect.max_point());
+                Some(Polygon::new(vec![
+                    min_point,
+                    Point { x: max_point.x, y: min_point.y },
+                    max_point,
+                    Point { x: min_point.x, y: max_point.y },
+                ], FillRule::NonZero))
+            },
+            Shape::Polygon(polygon) => Some(polygon.clone()),
+            Shape::Triangle(triangle) => Some(triangle.to_polygon()),
+            _ => None,
+        }
+    }
+
+    fn as_circle(&self) -> Option<(Point, f64)> {
+        match self {
+            Shape::Ellipse(ellipse) if ellipse.is_circle() => Some((ellipse.center, ellipse.bounding_area.width / 2.)),
+            _ => None,
+        }
+    }
+
+    /// Checks a grid of points over `overlap`. Every point of a real overlap is within half a cell diagonal of
+    /// some grid point, where both signed distances can be at most that far outside.
+    fn sampled_intersects(&self, other: &Shape, overlap: &Rect) -> bool {
+        const SAMPLES_PER_SIDE: usize = 64;
+
+        if !overlap.size.width.is_finite() || !overlap.size.height.is_finite() {
+            return true;
+        }
+        let cell_width = overlap.size.width / SAMPLES_PER_SIDE as f64;
+        let cell_height = overlap.size.height / SAMPLES_PER_SIDE as f64;
+        let tolerance = f64::hypot(cell_width, cell_height) / 2.;
+
+        (0..SAMPLES_PER_SIDE * SAMPLES_PER_SIDE).any(|index| {
+            let point = Point {
+                x: overlap.min_point.x + (index % SAMPLES_PER_SIDE) as f64 * cell_width + cell_width / 2.,
+                y: overlap.min_point.y + (index / SAMPLES_PER_SIDE) as f64 * cell_height + cell_height / 2.,
+            };
+            self.signed_distance(&point) <= tolerance && other.signed_distance(&point) <= tolerance
+        })
+    }
+
+    /// The smallest axis aligned rect that's guaranteed to contain the whole shape
+    pub fn bounding_rect(&self) -> Rect {
+        match self {
+            Shape::Rect(rect) => *rect,
+            Shape::Ellipse(ellipse) => {
+                let (half_width, half_height) = ellipse.half_extents();
+                Rect::from_points(
+                    &Point { x: ellipse.center.x - half_width, y: ellipse.center.y - half_height },
+                    &Point { x: ellipse.center.x + half_width, y: ellipse.center.y + half_height },
+                )
+            },
+            Shape::Polygon(polygon) => Rect::bounding(&polygon.vertices),
+            Shape::Triangle(triangle) => Rect::bounding(&triangle.vertices),
+            Shape::Mask(mask) => Rect::from_points(&Point::ORIGIN, &Point { x: mask.width as f64, y: mask.height as f64 }),
+            Shape::Arc(arc) => {
+                let reach = arc.radius + arc.stroke_width / 2.;
+                Rect::from_points(
+                    &Point { x: arc.center.x - reach, y: arc.center.y - reach },
+                    &Point { x: arc.center.x + reach, y: arc.center.y + reach },
+                )
+            },
+            Shape::WaveBand(wave_band) => {
+                let reach = wave_band.amplitude.abs() + wave_band.thickness / 2.;
+                Rect::from_points(
+                    &Point { x: f64::NEG_INFINITY, y: wave_band.center_y - reach },
+                    &Point { x: f64::INFINITY, y: wave_band.center_y + reach },
+                )
+            },
+            Shape::TransformedShape(trans_shape) => {
+                let inner_rect = trans_shape.inner_shape.bounding_rect();
+                let (min_point, max_point) = (inner_rect.min_point, inner_rect.max_point());
+                let corners = [
+                    min_point,
+                    Point { x: max_point.x, y: min_point.y },
+                    max_point,
+                    Point { x: min_point.x, y: max_point.y },
+                ].map(|corner| trans_shape.transformation.inverse_transform(&corner));
+                Rect::bounding(&corners)
+            },
+            Shape::Offset(offset_shape) => offset_shape.inner_shape.bounding_rect().expand(offset_shape.amount.max(0.)),
+            Shape::Outline(outline_shape) => outline_shape.inner_shape.bounding_rect().expand(outline_shape.stroke_width / 2.),
+            Shape::RadialSymmetry(symmetry) => {
+                // every copy stays as far from the center as the farthest corner of the original
+                let inner_rect = symmetry.inner_shape.bounding_rect();
+                let reach = [
+                    inner_rect.min_point,
+                    Point { x: inner_rect.max_point().x, y: inner_rect.min_point.y },
+                    inner_rect.max_point(),
+                    Point { x: inner_rect.min_point.x, y: inner_rect.max_point().y },
+                ].iter().map(|corner| corner.dist_to(&symmetry.center)).fold(0., f64::max);
+                Rect::centered(symmetry.center, Area { height: 2. * reach, width: 2. * reach })
+            },
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        match self {
+            Shape::Rect(rect) => rect.area(),
+            Shape::Ellipse(ellipse) => ellipse.area(),
+            Shape::Polygon(polygon) if polygon.is_simple() => polygon.area(),
+            Shape::Triangle(triangle) => triangle.area(),
+            Shape::Mask(mask) => mask.inside.iter().filter(|inside| **inside).count() as f64,
+            Shape::Arc(arc) if arc.stroke_width < 2. * arc.radius && arc.sweep_angle < std::f64::consts::TAU => arc.area(),
+            Shape::WaveBand(wave_band) if wave_band.thickness > 0. => f64::INFINITY,
+            _ => self.estimate_area_and_perimeter().0,
+        }
+    }
+
+    /// Exact for rects, polygons and triangles, very close for ellipses and estimated from a sampling grid for everything else
+    pub fn perimeter(&self) -> f64 {
+        match self {
+            Shape::Rect(rect) => rect.perimeter(),
+            Shape::Ellipse(ellipse) => ellipse.perimeter(),
+            Shape::Polygon(polygon) if polygon.is_simple() => polygon.perimeter(),
+            Shape::Triangle(triangle) => triangle.perimeter(),
+            Shape::Arc(arc) if arc.stroke_width < 2. * arc.radius && arc.sweep_angle < std::f64::consts::TAU => arc.perimeter(),
+            Shape::WaveBand(wave_band) if wave_band.thickness > 0. => f64::INFINITY,
+            _ => self.estimate_area_and_perimeter().1,
         }
     }
+
+    fn estimate_area_and_perimeter(&self) -> (f64, f64) {
+        const SAMPLES_PER_SIDE: usize = 512;
+
+        let bounds = self.bounding_rect();
+        if !(bounds.size.width > 0. && bounds.size.height > 0. && bounds.size.width.is_finite() && bounds.size.height.is_finite()) {
+            return (0., 0.);
+        }
+        let cell_width = bounds.size.width / SAMPLES_PER_SIDE as f64;
+        let cell_height = bounds.size.height / SAMPLES_PER_SIDE as f64;
+
+        let inside: Vec<bool> = (0..SAMPLES_PER_SIDE * SAMPLES_PER_SIDE).map(|index| self.contains(&Point {
+            x: bounds.min_point.x + (index % SAMPLES_PER_SIDE) as f64 * cell_width + cell_width / 2.,
+            y: bounds.min_point.y + (index / SAMPLES_PER_SIDE) as f64 * cell_height + cell_height / 2.,
+        })).collect();
+        let is_inside = |x: isize, y: isize| {
+            x >= 0 && y >= 0 && (x as usize) < SAMPLES_PER_SIDE && (y as usize) < SAMPLES_PER_SIDE
+                && inside[x as usize + y as usize * SAMPLES_PER_SIDE]
+        };
+
+        let mut horizontal_transitions = 0;
+        let mut vertical_transitions = 0;
+        for y in 0..=SAMPLES_PER_SIDE as isize {
+            for x in 0..=SAMPLES_PER_SIDE as isize {
+                if is_inside(x, y) != is_inside(x - 1, y) {
+                    horizontal_transitions += 1;
+                }
+                if is_inside(x, y) != is_inside(x, y - 1) {
+                    vertical_transitions += 1;
+                }
+            }
+        }
+
+        let area = inside.iter().filter(|inside| **inside).count() as f64 * cell_width * cell_height;
+        // counting grid crossings overestimates the length of a curve by 4/pi on average
+        let perimeter = std::f64::consts::FRAC_PI_4
+            * (horizontal_transitions as f64 * cell_height + vertical_transitions as f64 * cell_width);
+        (area, perimeter)
+    }
+
+    /// Applies translations and scales directly to rects, ellipses, polygons and triangles, so drawing doesn't have to
+    /// transform every pixel. Anything else is left as it is.
+    pub fn bake_transforms(&self) -> Shape {
+        let Shape::TransformedShape(trans_shape) = self else {
+            return self.clone();
+        };
+        let inner_shape = trans_shape.inner_shape.bake_transforms();
+        let transformation = trans_shape.transformation;
+        let is_bakeable = match transformation {
+            Transformation::Rotation(_) => false,
+            Transformation::Translation(_) => true,
+            Transformation::Scale(scale) => scale.scalar.width != 0. && scale.scalar.height != 0.,
+        };
+
+        // points are transformed before checking the inner shape, so the inner shape moves the opposite way
+        let baked_shape: Option<Shape> = match (&inner_shape, transformation) {
+            _ if !is_bakeable => None,
+            (Shape::Rect(rect), _) => Some(Rect::from_points(
+                &transformation.inverse_transform(&rect.min_point),
+                &transformation.inverse_transform(&rect.max_point()),
+            ).into()),
+            (Shape::Polygon(polygon), _) => Some(Polygon::new(
+                polygon.vertices.iter().map(|vertex| transformation.inverse_transform(vertex)).collect(),
+                polygon.fill_rule,
+            ).into()),
+            (Shape::Triangle(triangle), _) => Some(Triangle {
+                vertices: triangle.vertices.map(|vertex| transformation.inverse_transform(&vertex)),
+            }.into()),
+            (Shape::Ellipse(ellipse), Transformation::Translation(_)) => Some(Ellipse {
+                center: transformation.inverse_transform(&ellipse.center),
+                ..*ellipse
+            }.into()),
+            (Shape::Ellipse(ellipse), Transformation::Scale(scale)) if ellipse.angle == 0. => Some(Ellipse {
+                center: transformation.inverse_transform(&ellipse.center),
+                bounding_area: Area {
+                    width: ellipse.bounding_area.width / scale.scalar.width.abs(),
+                    height: ellipse.bounding_area.height / scale.scalar.height.abs(),
+                },
+                angle: 0.,
+            }.into()),
+            _ => None,
+        };
+
+        baked_shape.unwrap_or_else(|| TransformedShape {
+            inner_shape: Box::new(inner_shape),
+            transformation,
+        }.into())
+    }
+
+    /// Pushes the boundary of the shape outwards by `amount`. Rects shrink and circles resize exactly,
+    /// everything else is offset through its signed distance field.
+    pub fn grow(&self, amount: f64) -> Shape {
+        match self {
+            Shape::Rect(rect) if amount <= 0. && rect.size.width + 2. * amount >= 0. && rect.size.height + 2. * amount >= 0. => {
+                Rect {
+                    min_point: Point {
+                        x: rect.min_point.x - amount,
+                        y: rect.min_point.y - amount,
+                    },
+                    size: Area {
+                        height: rect.size.height + 2. * amount,
+                        width: rect.size.width + 2. * amount,
+                    },
+                }.into()
+            },
+            Shape::Ellipse(ellipse) if ellipse.is_circle() && ellipse.bounding_area.width + 2. * amount >= 0. => {
+                Ellipse::circle(ellipse.center, ellipse.bounding_area.width / 2. + amount).into()
+            },
+            Shape::Offset(offset_shape) => OffsetShape {
+                inner_shape: offset_shape.inner_shape.clone(),
+                amount: offset_shape.amount + amount,
+            }.into(),
+            _ => OffsetShape {
+                inner_shape: Box::new(self.clone()),
+                amount,
+            }.into(),
+        }
+    }
+
+    /// Pulls the boundary of the shape inwards by `amount`.
+    pub fn shrink(&self, amount: f64) -> Shape {
+        self.grow(-amount)
+    }
+
+    /// Loads an image where every pixel brighter than `threshold` (or more opaque, if the image has an alpha channel)
+    /// counts as inside the shape. Each pixel covers a 1x1 square starting at its coordinates.
+    pub fn from_mask(filename: &str, threshold: u8) -> Result<Shape, ()> {
+        Ok(Mask::open(filename, threshold)?.into())
+    }
+
+    pub fn from_mask_with(resolver: &dyn AssetResolver, name: &str, threshold: u8) -> Result<Shape, ()> {
+        Ok(Mask::open_with(resolver, name, threshold)?.into())
+    }
+
+    /// The smallest convex polygon containing every point, with its vertices going counterclockwise
+    pub fn convex_hull(points: &[Point]) -> Polygon {
+        let mut sorted_points = points.to_vec();
+        sorted_points.sort_by(|point1, point2| point1.x.total_cmp(&point2.x).then(point1.y.total_cmp(&point2.y)));
+        sorted_points.dedup();
+
+        if sorted_points.len() < 3 {
+            return Polygon::new(sorted_points, FillRule::NonZero);
+        }
+
+        let turns_left = |origin: &Point, point1: &Point, point2: &Point| {
+            (*point1 - *origin).cross(&(*point2 - *origin)) > 0.
+        };
+
+        // Andrew's monotone chain: build the lower and upper halves separately, then join them
+        let mut hull: Vec<Point> = Vec::with_capacity(sorted_points.len() + 1);
+        for pass_points in [sorted_points.clone(), sorted_points.into_iter().rev().collect()] {
+            let half_start = hull.len();
+            for point in pass_points {
+                while hull.len() >= half_start + 2 && !turns_left(&hull[hull.len() - 2], &hull[hull.len() - 1], &point) {
+                    hull.pop();
+                }
+                hull.push(point);
+            }
+            // the last point of each half is the first point of the other
+            hull.pop();
+        }
+
+        Polygon::new(hull, FillRule::NonZero)
+    }
+
+    /// A band of `stroke_width` centered on the boundary of the shape
+    pub fn outline(&self, stroke_width: f64) -> Shape {
+        OutlineShape {
+            inner_shape: Box::new(self.clone()),
+            stroke_width,
+        }.into()
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OffsetShape {
+    inner_shape: Box<Shape>,
+    amount: f64,
+}
+
+impl Into<Shape> for OffsetShape {
+    fn into(self) -> Shape {
+        Shape::Offset(self)
+    }
+}
+
+impl CheckInside for OffsetShape {
+    fn contains(&self, point: &Point) -> bool {
+        self.signed_distance(point) <= 0.
+    }
+}
+
+impl SignedDistance for OffsetShape {
+    fn signed_distance(&self, point: &Point) -> f64 {
+        self.inner_shape.signed_distance(point) - self.amount
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineShape {
+    inner_shape: Box<Shape>,
+    stroke_width: f64,
+}
+
+impl Into<Shape> for OutlineShape {
+    fn into(self) -> Shape {
+        Shape::Outline(self)
+    }
+}
+
+impl CheckInside for OutlineShape {
+    fn contains(&self, point: &Point) -> bool {
+        self.signed_distance(point) <= 0.
+    }
+}
+
+impl SignedDistance for OutlineShape {
+    fn signed_distance(&self, point: &Point) -> f64 {
+        self.inner_shape.signed_distance(point).abs() - self.stroke_width / 2.
+    }
+}
+
+
+/// Repeats a shape `copies` times around `center`, like a mandala. The shape is drawn as it would be in the
+/// wedge starting at angle 0, along the positive x axis, and that wedge is copied around the circle, so
+/// anything outside it gets cut off.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RadialSymmetry {
+    inner_shape: Box<Shape>,
+    copies: usize,
+    center: Point,
+    mirrored: bool,
+}
+
+impl Into<Shape> for RadialSymmetry {
+    fn into(self) -> Shape {
+        Shape::RadialSymmetry(self)
+    }
+}
+
+impl RadialSymmetry {
+    pub fn new(shape: Shape, copies: usize, center: Point) -> Self {
+        if copies == 0 {
+            panic!("Radial symmetry needs at least one copy");
+        }
+        RadialSymmetry {
+            inner_shape: Box::new(shape),
+            copies,
+            center,
+            mirrored: false,
+        }
+    }
+
+    /// Like `new`, but every other copy is flipped like in a kaleidoscope, so the copies meet
+    /// seamlessly. The shape is drawn as it would be in the first half of the wedge.
+    pub fn kaleidoscope(shape: Shape, copies: usize, center: Point) -> Self {
+        RadialSymmetry {
+            mirrored: true,
+            ..RadialSymmetry::new(shape, copies, center)
+        }
+    }
+
+    /// Turns `point` around the center into the wedge the shape is drawn in
+    fn fold(&self, point: &Point) -> Point {
+        let offset = *point - self.center;
+        let wedge_angle = std::f64::consts::TAU / self.copies as f64;
+        let mut angle = offset.y.atan2(offset.x).rem_euclid(wedge_angle);
+        if self.mirrored && angle > wedge_angle / 2. {
+            angle = wedge_angle - angle;
+        }
+
+        let (sin, cos) = angle.sin_cos();
+        self.center + Point { x: cos, y: sin } * offset.length()
+    }
+}
+
+impl CheckInside for RadialSymmetry {
+    fn contains(&self, point: &Point) -> bool {
+        self.inner_shape.contains(&self.fold(point))
+    }
+}
+
+impl SignedDistance for RadialSymmetry {
+    // only exact when the shape doesn't reach out of its wedge
+    fn signed_distance(&self, point: &Point) -> f64 {
+        self.inner_shape.signed_distance(&self.fold(point))
+    }
 }
 
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct TransformedShape {
     inner_shape: Box<Shape>,
     transformation: Transformation,
@@ -86,6 +798,13 @@ impl CheckInside for TransformedShape {
     }
 }
 
+impl SignedDistance for TransformedShape {
+    // only exact for rigid transformations, scales stretch the distance field along with the shape
+    fn signed_distance(&self, point: &Point) -> f64 {
+        self.inner_shape.as_ref().signed_distance(&self.transformation.transform(point))
+    }
+}
+
 
 pub trait Transform {
     fn transform(&self, point: &Point) -> Point;
@@ -96,7 +815,7 @@ pub trait Transform {
 }
 
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Transformation {
     Rotation(Rotation),
     Translation(Translation),
@@ -120,7 +839,7 @@ impl Transform for Transformation {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Rotation {
     angle: f64,
     center_of_rotation: Translation,
@@ -172,7 +891,7 @@ impl Transform for Rotation {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Translation {
     new_origin: Point,
 }
@@ -211,7 +930,7 @@ impl Transform for Translation {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Scale {
     fixed_point: Translation,
     scalar: Area,
@@ -264,7 +983,7 @@ impl Transform for Scale {
 }
 
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Rect {
     min_point: Point,
     size: Area,
@@ -302,55 +1021,1477 @@ impl Rect {
         }
     }
 
-    pub fn max_point(&self) -> Point {
-        Point {
-            x: self.min_point.x + self.size.width,
-            y: self.min_point.y + self.size.height,
+    /// A rect of `size` with its center at `center`
+    pub fn centered(center: Point, size: Area) -> Self {
+        Rect::from_points(
+            &Point { x: center.x - size.width / 2., y: center.y - size.height / 2. },
+            &Point { x: center.x + size.width / 2., y: center.y + size.height / 2. },
+        )
+    }
+
+    /// The smallest rect containing every point
+    pub fn bounding(points: &[Point]) -> Self {
+        if points.is_empty() {
+            return Rect::from_points(&Point::ORIGIN, &Point::ORIGIN);
         }
+
+        let min_point = points.iter().fold(Point { x: f64::INFINITY, y: f64::INFINITY }, |min, point| Point {
+            x: min.x.min(point.x),
+            y: min.y.min(point.y),
+        });
+        let max_point = points.iter().fold(Point { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY }, |max, point| Point {
+            x: max.x.max(point.x),
+            y: max.y.max(point.y),
+        });
+        Rect::from_points(&min_point, &max_point)
     }
-}
 
-impl CheckInside for Rect {
-    fn contains(&self, point: &Point) -> bool {
-        return point.x >= self.min_point.x 
-            && point.y >= self.min_point.y 
-            && point.x <= self.max_point().x 
-            && point.y <= self.max_point().y
+    fn expand(&self, amount: f64) -> Self {
+        Rect::from_points(
+            &Point { x: self.min_point.x - amount, y: self.min_point.y - amount },
+            &Point { x: self.max_point().x + amount, y: self.max_point().y + amount },
+        )
     }
-}
 
+    pub fn area(&self) -> f64 {
+        self.size.width * self.size.height
+    }
 
-#[derive(Copy, Clone, Debug)]
-pub struct Ellipse {
-    center: Point,
-    bounding_area: Area,
-}
+    pub fn perimeter(&self) -> f64 {
+        2. * (self.size.width + self.size.height)
+    }
 
-impl Into<Shape> for Ellipse {
-    fn into(self) -> Shape {
-        Shape::Ellipse(self)
+    pub fn min_point(&self) -> Point {
+        self.min_point
     }
-}
 
-impl CheckInside for Ellipse {
-    fn contains(&self, point: &Point) -> bool {
-        let compute_part = |test_val: f64, center_val: f64, radius: f64| {
+    /// Same as `min_point`, the top left corner
+    pub fn min(&self) -> Point {
+        self.min_point
+    }
+
+    /// Same as `max_point`, the bottom right corner
+    pub fn max(&self) -> Point {
+        self.max_point()
+    }
+
+    pub fn center(&self) -> Point {
+        self.min_point.midpoint(&self.max_point())
+    }
+
+    pub fn size(&self) -> Area {
+        self.size
+    }
+
+    pub fn contains_row(&self, y: f64, x_range: Range<usize>) -> RangeSet {
+        let mut range_set = RangeSet::new();
+        if y >= self.min_point.y && y <= self.max_point().y {
+            range_set.insert(columns_between(self.min_point.x, self.max_point().x, &x_range));
+        }
+        range_set
+    }
+
+    pub fn max_point(&self) -> Point {
+        // an infinite rect would otherwise end at -inf + inf
+        let far_edge = |min: f64, size: f64| if size == f64::INFINITY { f64::INFINITY } else { min + size };
+        Point {
+            x: far_edge(self.min_point.x, self.size.width),
+            y: far_edge(self.min_point.y, self.size.height),
+        }
+    }
+
+    /// The area covered by both rects, if there is any
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let min_point = Point {
+            x: self.min_point.x.max(other.min_point.x),
+            y: self.min_point.y.max(other.min_point.y),
+        };
+        let max_point = Point {
+            x: self.max_point().x.min(other.max_point().x),
+            y: self.max_point().y.min(other.max_point().y),
+        };
+
+        (min_point.x <= max_point.x && min_point.y <= max_point.y).then(|| Rect::from_points(&min_point, &max_point))
+    }
+}
+
+impl CheckInside for Rect {
+    fn contains(&self, point: &Point) -> bool {
+        point.x >= self.min_point.x 
+            && point.y >= self.min_point.y 
+            && point.x <= self.max_point().x 
+            && point.y <= self.max_point().y
+    }
+}
+
+impl SignedDistance for Rect {
+    fn signed_distance(&self, point: &Point) -> f64 {
+        let half_width = self.size.width / 2.;
+        let half_height = self.size.height / 2.;
+        let x_dist = (point.x - (self.min_point.x + half_width)).abs() - half_width;
+        let y_dist = (point.y - (self.min_point.y + half_height)).abs() - half_height;
+
+        let outside_dist = f64::hypot(x_dist.max(0.), y_dist.max(0.));
+        let inside_dist = f64::min(x_dist.max(y_dist), 0.);
+
+        outside_dist + inside_dist
+    }
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ellipse {
+    center: Point,
+    // size along the ellipse's own axes, before it's rotated by `angle`
+    bounding_area: Area,
+    angle: f64,
+}
+
+impl Into<Shape> for Ellipse {
+    fn into(self) -> Shape {
+        Shape::Ellipse(self)
+    }
+}
+
+impl CheckInside for Ellipse {
+    fn contains(&self, point: &Point) -> bool {
+        let compute_part = |test_val: f64, center_val: f64, radius: f64| {
             let numer_sqrt = test_val - center_val;
             (numer_sqrt * numer_sqrt) / (radius * radius)
         };
 
-        let x_part = compute_part(point.x, self.center.x, self.bounding_area.width / 2.);
-        let y_part = compute_part(point.y, self.center.y, self.bounding_area.height / 2.);
+        let local_point = self.local_point(point);
+        let x_part = compute_part(local_point.x, 0., self.bounding_area.width / 2.);
+        let y_part = compute_part(local_point.y, 0., self.bounding_area.height / 2.);
 
         x_part + y_part <= 1.
     }
 }
 
+impl SignedDistance for Ellipse {
+    // exact for circles, a first order approximation for everything else
+    fn signed_distance(&self, point: &Point) -> f64 {
+        let x_radius = self.bounding_area.width / 2.;
+        let y_radius = self.bounding_area.height / 2.;
+        let Point { x: x_diff, y: y_diff } = self.local_point(point);
+
+        if self.is_circle() {
+            return f64::hypot(x_diff, y_diff) - x_radius;
+        }
+
+        let scaled_dist = f64::hypot(x_diff / x_radius, y_diff / y_radius);
+        let gradient_len = f64::hypot(x_diff / (x_radius * x_radius), y_diff / (y_radius * y_radius));
+        if gradient_len == 0. {
+            return -f64::min(x_radius, y_radius);
+        }
+
+        scaled_dist * (scaled_dist - 1.) / gradient_len
+    }
+}
+
 impl Ellipse {
     pub fn circle(center: Point, radius: f64) -> Self {
         Ellipse {
             center, 
-            bounding_area: Area { height: radius * 2., width: radius * 2. } 
+            bounding_area: Area { height: radius * 2., width: radius * 2. },
+            angle: 0.,
+        }
+    }
+
+    pub fn new(center: Point, x_radius: f64, y_radius: f64) -> Self {
+        Ellipse {
+            center,
+            bounding_area: Area { height: y_radius * 2., width: x_radius * 2. },
+            angle: 0.,
+        }
+    }
+
+    /// The ellipse that just touches the middle of each side of `rect`
+    pub fn from_rect(rect: &Rect) -> Self {
+        Ellipse {
+            center: rect.min_point.midpoint(&rect.max_point()),
+            bounding_area: rect.size,
+            angle: 0.,
+        }
+    }
+
+    /// `major_axis` is the full length of the ellipse along the line through the foci, which has to be at
+    /// least the distance between them. The ellipse is tilted to line up with the foci.
+    pub fn from_foci(focus1: Point, focus2: Point, major_axis: f64) -> Self {
+        let focal_dist = focus1.dist_to(&focus2);
+        if major_axis < focal_dist {
+            panic!("The major axis of an ellipse can't be shorter than the distance between its foci");
+        }
+        let minor_axis = (major_axis * major_axis - focal_dist * focal_dist).sqrt();
+        let direction = focus2 - focus1;
+
+        Ellipse {
+            center: focus1.midpoint(&focus2),
+            bounding_area: Area { height: minor_axis, width: major_axis },
+            angle: direction.y.atan2(direction.x),
+        }
+    }
+
+    /// Moves `point` into the ellipse's own frame, centered on the origin with its axes along x and y
+    fn local_point(&self, point: &Point) -> Point {
+        let offset = *point - self.center;
+        if self.angle == 0. {
+            return offset;
+        }
+        let (sin, cos) = self.angle.sin_cos();
+        Point {
+            x: offset.x * cos + offset.y * sin,
+            y: offset.y * cos - offset.x * sin,
+        }
+    }
+
+    /// Half the width and height of the axis aligned box around the ellipse
+    fn half_extents(&self) -> (f64, f64) {
+        let x_radius = self.bounding_area.width / 2.;
+        let y_radius = self.bounding_area.height / 2.;
+        let (sin, cos) = self.angle.sin_cos();
+        (f64::hypot(x_radius * cos, y_radius * sin), f64::hypot(x_radius * sin, y_radius * cos))
+    }
+
+    pub fn area(&self) -> f64 {
+        std::f64::consts::PI * self.bounding_area.width * self.bounding_area.height / 4.
+    }
+
+    /// Ramanujan's approximation, which is exact for circles
+    pub fn perimeter(&self) -> f64 {
+        let x_radius = self.bounding_area.width / 2.;
+        let y_radius = self.bounding_area.height / 2.;
+        std::f64::consts::PI * (3. * (x_radius + y_radius) - ((3. * x_radius + y_radius) * (x_radius + 3. * y_radius)).sqrt())
+    }
+
+    pub fn is_circle(&self) -> bool {
+        self.bounding_area.width == self.bounding_area.height
+    }
+
+    pub fn center(&self) -> Point {
+        self.center
+    }
+
+    /// Half the width along the ellipse's own x axis, before it's rotated
+    pub fn x_radius(&self) -> f64 {
+        self.bounding_area.width / 2.
+    }
+
+    /// Half the height along the ellipse's own y axis, before it's rotated
+    pub fn y_radius(&self) -> f64 {
+        self.bounding_area.height / 2.
+    }
+
+    /// The full width and height along the ellipse's own axes, before it's rotated
+    pub fn size(&self) -> Area {
+        self.bounding_area
+    }
+
+    /// How far the ellipse is turned, in radians clockwise on screen
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    /// The same ellipse turned to `angle`, in radians clockwise on screen
+    pub fn with_angle(self, angle: f64) -> Self {
+        Ellipse { angle, ..self }
+    }
+
+    pub fn contains_row(&self, y: f64, x_range: Range<usize>) -> RangeSet {
+        let x_radius = self.bounding_area.width / 2.;
+        let y_radius = self.bounding_area.height / 2.;
+
+        // where the row crosses the ellipse, or the closest it gets if it doesn't
+        let (min_x, max_x) = if self.angle == 0. {
+            let y_part = (y - self.center.y) / y_radius;
+            let half_width = x_radius * (1. - y_part * y_part).max(0.).sqrt();
+            (self.center.x - half_width, self.center.x + half_width)
+        } else {
+            // the ellipse equation in the local frame is a quadratic in x along the row
+            let (sin, cos) = self.angle.sin_cos();
+            let y_diff = y - self.center.y;
+            let (x_radius_sq, y_radius_sq) = (x_radius * x_radius, y_radius * y_radius);
+            let a = cos * cos / x_radius_sq + sin * sin / y_radius_sq;
+            let b = 2. * sin * cos * y_diff * (1. / x_radius_sq - 1. / y_radius_sq);
+            let c = y_diff * y_diff * (sin * sin / x_radius_sq + cos * cos / y_radius_sq) - 1.;
+
+            let root = (b * b - 4. * a * c).max(0.).sqrt();
+            (self.center.x + (-b - root) / (2. * a), self.center.x + (-b + root) / (2. * a))
+        };
+
+        let mut range_set = RangeSet::new();
+        range_set.insert(convex_columns(min_x, max_x, &x_range, |x| self.contains(&Point { x: x as f64, y })));
+        range_set
+    }
+}
+
+
+/// How a polygon decides which regions are inside when its edges cross over each other
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FillRule {
+    /// A point is inside when a ray from it crosses an odd number of edges
+    EvenOdd,
+    /// A point is inside when the edges wind around it at all, in either direction
+    NonZero,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+    fill_rule: FillRule,
+}
+
+impl Into<Shape> for Polygon {
+    fn into(self) -> Shape {
+        Shape::Polygon(self)
+    }
+}
+
+impl Polygon {
+    /// The last vertex connects back to the first one
+    pub fn new(vertices: Vec<Point>, fill_rule: FillRule) -> Self {
+        Polygon {
+            vertices,
+            fill_rule,
+        }
+    }
+
+    /// A polygon made of several closed outlines, such as a shape with holes. Every outline is joined to the
+    /// first one by an edge walked once in each direction, so the joins cancel out when filling. Signed
+    /// distances still see those edges.
+    pub fn from_contours(contours: Vec<Vec<Point>>, fill_rule: FillRule) -> Self {
+        let mut vertices = Vec::new();
+        for contour in contours.iter().filter(|contour| !contour.is_empty()) {
+            if let Some(anchor) = vertices.first().copied() {
+                vertices.push(anchor);
+            }
+            vertices.extend_from_slice(contour);
+            vertices.push(contour[0]);
+        }
+        Polygon::new(vertices, fill_rule)
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    pub fn fill_rule(&self) -> FillRule {
+        self.fill_rule
+    }
+
+    pub fn with_fill_rule(self, fill_rule: FillRule) -> Self {
+        Polygon {
+            vertices: self.vertices,
+            fill_rule,
+        }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (&Point, &Point)> {
+        self.vertices.iter().zip(self.vertices.iter().cycle().skip(1))
+    }
+
+    /// Only meaningful when the polygon doesn't cross over itself, see `is_simple`
+    pub fn area(&self) -> f64 {
+        let twice_signed_area: f64 = self.edges().map(|(start, end)| start.x * end.y - end.x * start.y).sum();
+        twice_signed_area.abs() / 2.
+    }
+
+    pub fn perimeter(&self) -> f64 {
+        self.edges().map(|(start, end)| start.dist_to(end)).sum()
+    }
+
+    /// Whether no two edges of the polygon cross each other
+    pub fn is_simple(&self) -> bool {
+        let edges: Vec<(&Point, &Point)> = self.edges().collect();
+        let orientation = |a: &Point, b: &Point, c: &Point| {
+            let cross = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+            if cross > 0. { 1 } else if cross < 0. { -1 } else { 0 }
+        };
+
+        for (index, (start1, end1)) in edges.iter().enumerate() {
+            // neighboring edges always share a vertex, so only edges further along can cross
+            for (start2, end2) in edges.iter().skip(index + 2) {
+                if index == 0 && std::ptr::eq(*end2, edges[0].0) {
+                    continue;
+                }
+                if orientation(start1, end1, start2) != orientation(start1, end1, end2)
+                    && orientation(start2, end2, start1) != orientation(start2, end2, end1) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    pub fn contains_row(&self, y: f64, x_range: Range<usize>) -> RangeSet {
+        // a point's winding number only counts the edges crossing the row to its right, using the same
+        // half-open rule as winding_number so both agree on which points are inside
+        let mut crossings: Vec<(f64, i32)> = self.edges().filter_map(|(start, end)| {
+            let direction = if start.y <= y && end.y > y {
+                1
+            } else if start.y > y && end.y <= y {
+                -1
+            } else {
+                return None;
+            };
+            Some((start.x + (y - start.y) / (end.y - start.y) * (end.x - start.x), direction))
+        }).collect();
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut range_set = RangeSet::new();
+        let mut winding_number = 0;
+        for pair in crossings.windows(2) {
+            let ((crossing_x, direction), (next_x, _)) = (pair[0], pair[1]);
+            winding_number -= direction;
+            let is_inside = match self.fill_rule {
+                FillRule::EvenOdd => winding_number % 2 != 0,
+                FillRule::NonZero => winding_number != 0,
+            };
+            if is_inside {
+                // columns from this crossing up to, but not including, the next one
+                range_set.insert(columns_between(crossing_x, next_x.ceil() - 1., &x_range));
+            }
+        }
+        range_set
+    }
+
+    /// Whether any edges cross, or failing that, whether either polygon sits inside the other
+    pub fn intersects(&self, other: &Polygon) -> bool {
+        let segments_cross = |(start1, end1): (&Point, &Point), (start2, end2): (&Point, &Point)| {
+            let side = |a: &Point, b: &Point, c: &Point| (*b - *a).cross(&(*c - *a));
+            let (side1, side2) = (side(start1, end1, start2), side(start1, end1, end2));
+            let (side3, side4) = (side(start2, end2, start1), side(start2, end2, end1));
+            // touching counts, so zeros are allowed on both sides
+            (side1 * side2 <= 0.) && (side3 * side4 <= 0.)
+                && f64::max(start1.x, end1.x) >= f64::min(start2.x, end2.x) && f64::max(start2.x, end2.x) >= f64::min(start1.x, end1.x)
+                && f64::max(start1.y, end1.y) >= f64::min(start2.y, end2.y) && f64::max(start2.y, end2.y) >= f64::min(start1.y, end1.y)
+        };
+
+        if self.edges().any(|edge| other.edges().any(|other_edge| segments_cross(edge, other_edge))) {
+            return true;
+        }
+        self.vertices.first().is_some_and(|vertex| other.contains(vertex))
+            || other.vertices.first().is_some_and(|vertex| self.contains(vertex))
+    }
+
+    fn winding_number(&self, point: &Point) -> i32 {
+        let mut winding_number = 0;
+        for (start, end) in self.edges() {
+            // which side of the edge the point is on
+            let cross = (end.x - start.x) * (point.y - start.y) - (point.x - start.x) * (end.y - start.y);
+            if start.y <= point.y {
+                if end.y > point.y && cross > 0. {
+                    winding_number += 1;
+                }
+            } else if end.y <= point.y && cross < 0. {
+                winding_number -= 1;
+            }
+        }
+        winding_number
+    }
+}
+
+impl CheckInside for Polygon {
+    fn contains(&self, point: &Point) -> bool {
+        match self.fill_rule {
+            FillRule::EvenOdd => self.winding_number(point) % 2 != 0,
+            FillRule::NonZero => self.winding_number(point) != 0,
+        }
+    }
+}
+
+impl SignedDistance for Polygon {
+    fn signed_distance(&self, point: &Point) -> f64 {
+        let edge_dist = self.edges().map(|(start, end)| {
+            let edge = *end - *start;
+            let edge_len_sq = edge.dot(&edge);
+            let along_edge = if edge_len_sq == 0. {
+                0.
+            } else {
+                ((*point - *start).dot(&edge) / edge_len_sq).clamp(0., 1.)
+            };
+            point.dist_to(&(*start + edge * along_edge))
+        }).fold(f64::INFINITY, f64::min);
+
+        if self.contains(point) {
+            -edge_dist
+        } else {
+            edge_dist
+        }
+    }
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Triangle {
+    vertices: [Point; 3],
+}
+
+impl Into<Shape> for Triangle {
+    fn into(self) -> Shape {
+        Shape::Triangle(self)
+    }
+}
+
+impl Triangle {
+    /// The vertices can go either way around. A triangle with no area doesn't contain any points.
+    pub fn new(vertex1: Point, vertex2: Point, vertex3: Point) -> Self {
+        Triangle {
+            vertices: [vertex1, vertex2, vertex3],
+        }
+    }
+
+    pub fn vertices(&self) -> &[Point; 3] {
+        &self.vertices
+    }
+
+    /// Positive when the vertices go clockwise on screen, since y points down
+    fn twice_signed_area(&self) -> f64 {
+        let [vertex1, vertex2, vertex3] = self.vertices;
+        (vertex2 - vertex1).cross(&(vertex3 - vertex1))
+    }
+
+    pub fn area(&self) -> f64 {
+        self.twice_signed_area().abs() / 2.
+    }
+
+    pub fn perimeter(&self) -> f64 {
+        self.to_polygon().perimeter()
+    }
+
+    pub fn centroid(&self) -> Point {
+        self.from_barycentric([1. / 3.; 3])
+    }
+
+    /// How much each vertex contributes to `point`, so that `from_barycentric` gives the point back. The weights
+    /// add up to 1, and inside the triangle none of them are negative. `None` if the triangle has no area.
+    pub fn barycentric(&self, point: &Point) -> Option<[f64; 3]> {
+        let twice_area = self.twice_signed_area();
+        if twice_area == 0. {
+            return None;
+        }
+
+        // each vertex is weighted by the area of the triangle opposite it, with the point as its new corner
+        let [vertex1, vertex2, vertex3] = self.vertices;
+        let weight1 = (vertex3 - vertex2).cross(&(*point - vertex2)) / twice_area;
+        let weight2 = (vertex1 - vertex3).cross(&(*point - vertex3)) / twice_area;
+        Some([weight1, weight2, 1. - weight1 - weight2])
+    }
+
+    /// The point with these vertex weights. The weights should add up to 1.
+    pub fn from_barycentric(&self, weights: [f64; 3]) -> Point {
+        let [vertex1, vertex2, vertex3] = self.vertices;
+        vertex1 * weights[0] + vertex2 * weights[1] + vertex3 * weights[2]
+    }
+
+    pub fn contains_row(&self, y: f64, x_range: Range<usize>) -> RangeSet {
+        let twice_area = self.twice_signed_area();
+        if twice_area == 0. {
+            return RangeSet::new();
+        }
+
+        // along the row every barycentric weight is a line in x, and each one being at least 0 cuts off a side
+        let mut min_x = f64::NEG_INFINITY;
+        let mut max_x = f64::INFINITY;
+        for index in 0..3 {
+            let (start, end) = (self.vertices[(index + 1) % 3], self.vertices[(index + 2) % 3]);
+            let slope = -(end.y - start.y) / twice_area;
+            let offset = ((end.x - start.x) * (y - start.y) + (end.y - start.y) * start.x) / twice_area;
+
+            if slope > 0. {
+                min_x = min_x.max(-offset / slope);
+            } else if slope < 0. {
+                max_x = max_x.min(-offset / slope);
+            } else if offset < 0. {
+                return RangeSet::new();
+            }
+        }
+
+        let mut range_set = RangeSet::new();
+        range_set.insert(convex_columns(min_x, max_x, &x_range, |x| self.contains(&Point { x: x as f64, y })));
+        range_set
+    }
+
+    fn to_polygon(self) -> Polygon {
+        Polygon::new(self.vertices.to_vec(), FillRule::NonZero)
+    }
+}
+
+impl CheckInside for Triangle {
+    // every edge gets its own cross product, unlike `barycentric`, which works out the last weight from the
+    // other two and so can put points right on an edge just outside it
+    fn contains(&self, point: &Point) -> bool {
+        let twice_area = self.twice_signed_area();
+        twice_area != 0. && (0..3).all(|index| {
+            let (start, end) = (self.vertices[(index + 1) % 3], self.vertices[(index + 2) % 3]);
+            (end - start).cross(&(*point - start)) * twice_area.signum() >= 0.
+        })
+    }
+}
+
+impl SignedDistance for Triangle {
+    fn signed_distance(&self, point: &Point) -> f64 {
+        let edge_dist = self.to_polygon().signed_distance(point).abs();
+        if self.contains(point) {
+            -edge_dist
+        } else {
+            edge_dist
         }
     }
 }
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    width: usize,
+    height: usize,
+    inside: Vec<bool>,
+    // signed distance from the center of every pixel to the edge of the mask
+    distances: Vec<f64>,
+}
+
+impl Into<Shape> for Mask {
+    fn into(self) -> Shape {
+        Shape::Mask(self)
+    }
+}
+
+impl Mask {
+    pub fn open(filename: &str, threshold: u8) -> Result<Self, ()> {
+        Mask::open_with(&FileSystemResolver::new(), filename, threshold)
+    }
+
+    /// Like `open`, but gets the image from `resolver`
+    pub fn open_with(resolver: &dyn AssetResolver, name: &str, threshold: u8) -> Result<Self, ()> {
+        let image = assets::load_image(resolver, name)?;
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+
+        let inside = if image.color().has_alpha() {
+            image.to_luma_alpha8().pixels().map(|pixel| pixel[1] > threshold).collect()
+        } else {
+            image.to_luma8().pixels().map(|pixel| pixel[0] > threshold).collect()
+        };
+
+        Ok(Mask::new(width, height, inside))
+    }
+
+    /// `inside` is row major and must have exactly `width * height` entries
+    pub fn new(width: usize, height: usize, inside: Vec<bool>) -> Self {
+        assert_eq!(inside.len(), width * height, "Mask values have a width/height that matches the mask size");
+
+        // pad the mask with a ring of outside pixels so everything past the edge counts as outside
+        let padded_width = width + 2;
+        let padded_height = height + 2;
+        let padded_inside = |index: usize| {
+            let (x, y) = (index % padded_width, index / padded_width);
+            x > 0 && y > 0 && x <= width && y <= height && inside[(x - 1) + (y - 1) * width]
+        };
+
+        let dist_to_inside = chamfer_distances(padded_width, padded_height, padded_inside);
+        let dist_to_outside = chamfer_distances(padded_width, padded_height, |index| !padded_inside(index));
+
+        let mut distances = Vec::with_capacity(width * height);
+        for y in 1..=height {
+            for x in 1..=width {
+                let index = x + y * padded_width;
+                distances.push(if padded_inside(index) {
+                    0.5 - dist_to_outside[index]
+                } else {
+                    dist_to_inside[index] - 0.5
+                });
+            }
+        }
+
+        Mask {
+            width,
+            height,
+            inside,
+            distances,
+        }
+    }
+
+    fn pixel_index(&self, point: &Point) -> Option<usize> {
+        if point.x < 0. || point.y < 0. || point.x >= self.width as f64 || point.y >= self.height as f64 {
+            return None;
+        }
+        Some(point.x as usize + point.y as usize * self.width)
+    }
+}
+
+impl CheckInside for Mask {
+    fn contains(&self, point: &Point) -> bool {
+        self.pixel_index(point).is_some_and(|index| self.inside[index])
+    }
+}
+
+impl SignedDistance for Mask {
+    // approximate, since it's measured between pixel centers
+    fn signed_distance(&self, point: &Point) -> f64 {
+        if self.width == 0 || self.height == 0 {
+            return f64::INFINITY;
+        }
+
+        let clamped_point = Point {
+            x: point.x.clamp(0., self.width as f64 - 1.),
+            y: point.y.clamp(0., self.height as f64 - 1.),
+        };
+        let index = self.pixel_index(&clamped_point).expect("The point was clamped inside the mask");
+
+        self.distances[index] + point.dist_to(&clamped_point)
+    }
+}
+
+/// Two pass chamfer distance from every pixel to the nearest pixel where `is_seed` is true
+fn chamfer_distances(width: usize, height: usize, is_seed: impl Fn(usize) -> bool) -> Vec<f64> {
+    const DIAGONAL: f64 = std::f64::consts::SQRT_2;
+
+    let mut distances: Vec<f64> = (0..width * height).map(|index| if is_seed(index) { 0. } else { f64::INFINITY }).collect();
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = x + y * width;
+            let mut dist = distances[index];
+            if x > 0 { dist = dist.min(distances[index - 1] + 1.); }
+            if y > 0 {
+                dist = dist.min(distances[index - width] + 1.);
+                if x > 0 { dist = dist.min(distances[index - width - 1] + DIAGONAL); }
+                if x + 1 < width { dist = dist.min(distances[index - width + 1] + DIAGONAL); }
+            }
+            distances[index] = dist;
+        }
+    }
+
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            let index = x + y * width;
+            let mut dist = distances[index];
+            if x + 1 < width { dist = dist.min(distances[index + 1] + 1.); }
+            if y + 1 < height {
+                dist = dist.min(distances[index + width] + 1.);
+                if x + 1 < width { dist = dist.min(distances[index + width + 1] + DIAGONAL); }
+                if x > 0 { dist = dist.min(distances[index + width - 1] + DIAGONAL); }
+            }
+            distances[index] = dist;
+        }
+    }
+
+    distances
+}
+
+
+/// A stroke with round caps along part of a circle
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Arc {
+    center: Point,
+    radius: f64,
+    start_angle: f64,
+    sweep_angle: f64,
+    stroke_width: f64,
+}
+
+impl Into<Shape> for Arc {
+    fn into(self) -> Shape {
+        Shape::Arc(self)
+    }
+}
+
+impl Arc {
+    /// Angles are in radians, turning from the positive x axis towards the positive y axis
+    pub fn new(center: Point, radius: f64, start_angle: f64, sweep_angle: f64, stroke_width: f64) -> Self {
+        let (start_angle, sweep_angle) = if sweep_angle < 0. {
+            (start_angle + sweep_angle, -sweep_angle)
+        } else {
+            (start_angle, sweep_angle)
+        };
+
+        Arc {
+            center,
+            radius,
+            start_angle: start_angle.rem_euclid(std::f64::consts::TAU),
+            sweep_angle: sweep_angle.min(std::f64::consts::TAU),
+            stroke_width,
+        }
+    }
+
+    pub fn point_at_angle(&self, angle: f64) -> Point {
+        Point {
+            x: self.center.x + self.radius * angle.cos(),
+            y: self.center.y + self.radius * angle.sin(),
+        }
+    }
+
+    pub fn start_point(&self) -> Point {
+        self.point_at_angle(self.start_angle)
+    }
+
+    pub fn end_point(&self) -> Point {
+        self.point_at_angle(self.start_angle + self.sweep_angle)
+    }
+
+    /// Only exact when the stroke doesn't overlap itself
+    pub fn area(&self) -> f64 {
+        let cap_radius = self.stroke_width / 2.;
+        self.sweep_angle * self.radius * self.stroke_width + std::f64::consts::PI * cap_radius * cap_radius
+    }
+
+    /// Only exact when the stroke doesn't overlap itself
+    pub fn perimeter(&self) -> f64 {
+        2. * self.sweep_angle * self.radius + std::f64::consts::PI * self.stroke_width
+    }
+}
+
+impl CheckInside for Arc {
+    fn contains(&self, point: &Point) -> bool {
+        self.signed_distance(point) <= 0.
+    }
+}
+
+impl SignedDistance for Arc {
+    fn signed_distance(&self, point: &Point) -> f64 {
+        let angle = (point.y - self.center.y).atan2(point.x - self.center.x);
+        let angle_along_arc = (angle - self.start_angle).rem_euclid(std::f64::consts::TAU);
+
+        let dist_to_curve = if angle_along_arc <= self.sweep_angle {
+            (point.dist_to(&self.center) - self.radius).abs()
+        } else {
+            f64::min(point.dist_to(&self.start_point()), point.dist_to(&self.end_point()))
+        };
+
+        dist_to_curve - self.stroke_width / 2.
+    }
+}
+
+
+/// The horizontal band between two sine waves, running forever to the left and right
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WaveBand {
+    center_y: f64,
+    amplitude: f64,
+    wavelength: f64,
+    phase: f64,
+    thickness: f64,
+}
+
+impl Into<Shape> for WaveBand {
+    fn into(self) -> Shape {
+        Shape::WaveBand(self)
+    }
+}
+
+impl WaveBand {
+    /// The middle of the band follows `center_y + amplitude * sin(2 pi x / wavelength + phase)`, and the band extends
+    /// `thickness / 2` above and below it
+    pub fn new(center_y: f64, amplitude: f64, wavelength: f64, phase: f64, thickness: f64) -> Self {
+        WaveBand {
+            center_y,
+            amplitude,
+            wavelength,
+            phase,
+            thickness,
+        }
+    }
+
+    fn wave_angle(&self, x: f64) -> f64 {
+        std::f64::consts::TAU * x / self.wavelength + self.phase
+    }
+
+    pub fn center_at(&self, x: f64) -> f64 {
+        self.center_y + self.amplitude * self.wave_angle(x).sin()
+    }
+}
+
+impl CheckInside for WaveBand {
+    fn contains(&self, point: &Point) -> bool {
+        (point.y - self.center_at(point.x)).abs() <= self.thickness / 2.
+    }
+}
+
+impl SignedDistance for WaveBand {
+    // the vertical distance, corrected for the slope of the wave
+    fn signed_distance(&self, point: &Point) -> f64 {
+        let slope = self.amplitude * std::f64::consts::TAU / self.wavelength * self.wave_angle(point.x).cos();
+        let vertical_dist = (point.y - self.center_at(point.x)).abs() - self.thickness / 2.;
+
+        vertical_dist / (1. + slope * slope).sqrt()
+    }
+}
+
+/// The Koch snowflake, starting from an upward pointing triangle with corners `radius` from `center`.
+/// Every level of `depth` multiplies the number of vertices by 4.
+pub fn koch_snowflake(center: Point, radius: f64, depth: usize) -> Polygon {
+    let mut vertices = equilateral_triangle(center, radius).to_vec();
+    // the triangle goes clockwise on screen, so the outside is always to the left of each edge
+    let outward = |edge: Point| Point { x: edge.y, y: -edge.x };
+
+    for _ in 0..depth {
+        vertices = vertices.iter().zip(vertices.iter().cycle().skip(1)).flat_map(|(start, end)| {
+            let third = (*end - *start) / 3.;
+            let first_point = *start + third;
+            let peak = *start + third * 1.5 + outward(third) * (3_f64.sqrt() / 2.);
+            [*start, first_point, peak, *start + third * 2.]
+        }).collect();
+    }
+    Polygon::new(vertices, FillRule::NonZero)
+}
+
+/// The Sierpinski triangle, starting from an upward pointing triangle with corners `radius` from `center`.
+/// The removed triangles are holes in a single polygon, and there are 3^`depth` triangles left.
+pub fn sierpinski_triangle(center: Point, radius: f64, depth: usize) -> Polygon {
+    let outer_triangle = equilateral_triangle(center, radius);
+    let mut contours = vec![outer_triangle.to_vec()];
+    let mut triangles = vec![outer_triangle];
+
+    for _ in 0..depth {
+        triangles = triangles.iter().flat_map(|[top, right, left]| {
+            let (top_right, bottom, left_top) = (top.midpoint(right), right.midpoint(left), left.midpoint(top));
+            contours.push(vec![top_right, bottom, left_top]);
+            [[*top, top_right, left_top], [top_right, *right, bottom], [left_top, bottom, *left]]
+        }).collect();
+    }
+    Polygon::from_contours(contours, FillRule::EvenOdd)
+}
+
+/// Top, bottom right then bottom left
+fn equilateral_triangle(center: Point, radius: f64) -> [Point; 3] {
+    let corner = |angle: f64| center + Point { x: angle.cos(), y: -angle.sin() } * radius;
+    [90_f64, -30., 210.].map(|degrees| corner(degrees.to_radians()))
+}
+
+/// Splits `bounds` into one convex polygon per seed, covering the points closer to that seed than to any other
+pub fn voronoi_cells(seeds: &[Point], bounds: Rect) -> Vec<Shape> {
+    let min_point = bounds.min_point;
+    let max_point = bounds.max_point();
+    let bounds_corners = vec![
+        min_point,
+        Point { x: max_point.x, y: min_point.y },
+        max_point,
+        Point { x: min_point.x, y: max_point.y },
+    ];
+
+    seeds.iter().enumerate().map(|(seed_index, seed)| {
+        let mut cell = bounds_corners.clone();
+
+        for (other_index, other_seed) in seeds.iter().enumerate() {
+            if other_index == seed_index || other_seed == seed {
+                continue;
+            }
+
+            // keep the half of the cell on this seed's side of the perpendicular bisector
+            let midpoint = seed.midpoint(other_seed);
+            let side = |point: &Point| (*point - midpoint).dot(&(*other_seed - *seed));
+
+            let mut clipped_cell = Vec::with_capacity(cell.len() + 1);
+            for (index, start) in cell.iter().enumerate() {
+                let end = &cell[(index + 1) % cell.len()];
+                let (start_side, end_side) = (side(start), side(end));
+
+                if start_side <= 0. {
+                    clipped_cell.push(*start);
+                }
+                if (start_side < 0. && end_side > 0.) || (start_side > 0. && end_side < 0.) {
+                    let crossing = start_side / (start_side - end_side);
+                    clipped_cell.push(*start + (*end - *start) * crossing);
+                }
+            }
+            cell = clipped_cell;
+
+            if cell.is_empty() {
+                break;
+            }
+        }
+
+        Polygon::new(cell, FillRule::NonZero).into()
+    }).collect()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TileShape {
+    Square,
+    /// Hexagons with a point at the top, in rows where every other row is shifted by half a cell
+    Hexagon,
+    /// Alternating up and down pointing equilateral triangles
+    Triangle,
+}
+
+/// Iterates over tiles that together cover a region, row by row. Tiles on the edges stick out past the region
+/// rather than being cut off.
+#[derive(Clone, Debug)]
+pub struct Tiling {
+    tile_shape: TileShape,
+    bounds: Rect,
+    cell_size: f64,
+    gap: f64,
+    lattice_origin: Point,
+    cols: usize,
+    rows: usize,
+    next_index: usize,
+}
+
+impl Tiling {
+    /// `cell_size` is the width of a single tile, and `gap` is the space left between neighboring tiles.
+    /// `offset` shifts the whole pattern.
+    pub fn new(tile_shape: TileShape, bounds: Rect, cell_size: f64, gap: f64, offset: Point) -> Self {
+        let (col_step, row_step) = Self::steps(tile_shape, cell_size);
+        // how far the pattern goes before repeating itself exactly
+        let (period_x, period_y) = match tile_shape {
+            TileShape::Square => (cell_size, cell_size),
+            TileShape::Hexagon | TileShape::Triangle => (cell_size, 2. * row_step),
+        };
+
+        let lattice_origin = Point {
+            x: bounds.min_point.x + offset.x.rem_euclid(period_x) - period_x,
+            y: bounds.min_point.y + offset.y.rem_euclid(period_y) - period_y,
+        };
+        let (cols, rows) = if cell_size > 0. && cell_size.is_finite() {
+            (
+                ((bounds.size.width + 2. * period_x) / col_step).ceil() as usize + 1,
+                ((bounds.size.height + 2. * period_y) / row_step).ceil() as usize + 1,
+            )
+        } else {
+            (0, 0)
+        };
+
+        Tiling {
+            tile_shape,
+            bounds,
+            cell_size,
+            gap,
+            lattice_origin,
+            cols,
+            rows,
+            next_index: 0,
+        }
+    }
+
+    fn steps(tile_shape: TileShape, cell_size: f64) -> (f64, f64) {
+        match tile_shape {
+            TileShape::Square => (cell_size, cell_size),
+            // pointy hexagons that are cell_size wide have a circumradius of cell_size / sqrt(3)
+            TileShape::Hexagon => (cell_size, 1.5 * cell_size / 3f64.sqrt()),
+            TileShape::Triangle => (cell_size / 2., cell_size * 3f64.sqrt() / 2.),
+        }
+    }
+
+    fn tile_at(&self, col: usize, row: usize) -> Option<Shape> {
+        let (col_step, row_step) = Self::steps(self.tile_shape, self.cell_size);
+        let x = self.lattice_origin.x + col as f64 * col_step;
+        let y = self.lattice_origin.y + row as f64 * row_step;
+
+        let tile: Shape = match self.tile_shape {
+            TileShape::Square => {
+                let inset = self.gap / 2.;
+                if 2. * inset >= self.cell_size {
+                    return None;
+                }
+                Rect::from_points(
+                    &Point { x: x + inset, y: y + inset },
+                    &Point { x: x + self.cell_size - inset, y: y + self.cell_size - inset },
+                ).into()
+            },
+            TileShape::Hexagon => {
+                let center = Point {
+                    x: x + if row % 2 == 1 { self.cell_size / 2. } else { 0. },
+                    y,
+                };
+                let radius = (self.cell_size - self.gap) / 3f64.sqrt();
+                if radius <= 0. {
+                    return None;
+                }
+                let vertices = (0..6).map(|corner| {
+                    let angle = std::f64::consts::FRAC_PI_3 * corner as f64 - std::f64::consts::FRAC_PI_2;
+                    Point { x: center.x + radius * angle.cos(), y: center.y + radius * angle.sin() }
+                }).collect();
+                Polygon::new(vertices, FillRule::NonZero).into()
+            },
+            TileShape::Triangle => {
+                let height = row_step;
+                let vertices = if (col + row).is_multiple_of(2) {
+                    [Point { x, y: y + height }, Point { x: x + self.cell_size, y: y + height }, Point { x: x + col_step, y }]
+                } else {
+                    [Point { x, y }, Point { x: x + self.cell_size, y }, Point { x: x + col_step, y: y + height }]
+                };
+
+                // shrink towards the centroid so every edge moves in by half the gap
+                let inradius = self.cell_size / (2. * 3f64.sqrt());
+                let scale = (inradius - self.gap / 2.) / inradius;
+                if scale <= 0. {
+                    return None;
+                }
+                let centroid = Point {
+                    x: vertices.iter().map(|vertex| vertex.x).sum::<f64>() / 3.,
+                    y: vertices.iter().map(|vertex| vertex.y).sum::<f64>() / 3.,
+                };
+                let vertices = vertices.map(|vertex| centroid + (vertex - centroid) * scale);
+                Polygon::new(vertices.to_vec(), FillRule::NonZero).into()
+            },
+        };
+
+        // triangles near a corner can have a bounding rect that reaches the bounds when they don't
+        tile.intersects(&self.bounds.into()).then_some(tile)
+    }
+}
+
+impl Iterator for Tiling {
+    type Item = Shape;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next_index < self.cols * self.rows {
+            let (col, row) = (self.next_index % self.cols, self.next_index / self.cols);
+            self.next_index += 1;
+
+            if let Some(tile) = self.tile_at(col, row) {
+                return Some(tile);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::MemoryResolver;
+
+    /// Checks every pixel of a canvas around `shape` against `contains`
+    fn assert_rows_match_contains(shape: &Shape) {
+        for y in -2..60 {
+            let y = y as f64;
+            let row = shape.contains_row(y, 0..60);
+            for x in 0..60 {
+                assert_eq!(
+                    row.ranges().iter().any(|span| span.contains(&x)),
+                    shape.contains(&Point { x: x as f64, y }),
+                    "{shape:?} disagrees at ({x}, {y})",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ellipse_rows_match_contains() {
+        for angle in [0., 0.3, std::f64::consts::FRAC_PI_4, std::f64::consts::FRAC_PI_2, 2.5] {
+            for (center, x_radius, y_radius) in [
+                (Point { x: 30., y: 30. }, 20., 10.),
+                (Point { x: 30.5, y: 29.5 }, 12.5, 12.5),
+                (Point { x: 25., y: 30. }, 20., 0.5),
+                (Point { x: 2., y: 40. }, 10., 25.),
+            ] {
+                assert_rows_match_contains(&Ellipse::new(center, x_radius, y_radius).with_angle(angle).into());
+            }
+        }
+    }
+
+    #[test]
+    fn triangle_rows_match_contains() {
+        let triangles = [
+            [Point { x: 5., y: 5. }, Point { x: 50., y: 5. }, Point { x: 20., y: 45. }],
+            [Point { x: 10.5, y: 2.5 }, Point { x: 40., y: 30. }, Point { x: 3., y: 52.5 }],
+            [Point { x: 0., y: 0. }, Point { x: 59., y: 59. }, Point { x: 0., y: 59. }],
+            [Point { x: 30., y: 10. }, Point { x: 10., y: 50. }, Point { x: 50., y: 50. }],
+            [Point { x: 1. / 3., y: 7.1 }, Point { x: 57.7, y: 19.3 }, Point { x: 23.9, y: 41. }],
+        ];
+        for [vertex1, vertex2, vertex3] in triangles {
+            assert_rows_match_contains(&Triangle::new(vertex1, vertex2, vertex3).into());
+            assert_rows_match_contains(&Triangle::new(vertex3, vertex2, vertex1).into());
+        }
+    }
+
+    #[test]
+    fn antialiasing_covers_edge_pixels_of_a_full_canvas_rect() {
+        let canvas = Rect::from_points(&Point { x: 0., y: 0. }, &Point { x: 19., y: 9. });
+        let shape: Shape = canvas.into();
+        for antialiasing in [Antialiasing::Distance, Antialiasing::EdgeSamples(4)] {
+            for point in [Point { x: 0., y: 0. }, Point { x: 19., y: 4. }, Point { x: 7., y: 9. }, Point { x: 7., y: 4. }] {
+                assert_eq!(antialiasing.coverage(&shape, &point, &canvas), 1., "{antialiasing:?} at {point:?}");
+            }
+        }
+
+        // an edge inside the canvas still fades
+        let shape: Shape = Rect::from_points(&Point { x: 0., y: 0. }, &Point { x: 10., y: 9. }).into();
+        assert_eq!(Antialiasing::Distance.coverage(&shape, &Point { x: 10., y: 4. }, &canvas), 0.5);
+        assert_eq!(Antialiasing::EdgeSamples(4).coverage(&shape, &Point { x: 10., y: 4. }, &canvas), 0.5);
+    }
+
+    /// A five-pointed star drawn in one stroke, so its middle is wound around twice
+    fn pentagram() -> Polygon {
+        let vertices = (0..5).map(|index| {
+            let angle = index as f64 * 2. * std::f64::consts::TAU / 5. - std::f64::consts::FRAC_PI_2;
+            Point { x: 30. + 25. * angle.cos(), y: 30. + 25. * angle.sin() }
+        }).collect();
+        Polygon::new(vertices, FillRule::NonZero)
+    }
+
+    #[test]
+    fn fill_rules_differ_where_edges_wind_twice() {
+        let center = Point { x: 30., y: 30. };
+        let point = Point { x: 30., y: 8. };
+        let nonzero = pentagram();
+        let even_odd = pentagram().with_fill_rule(FillRule::EvenOdd);
+
+        assert!(nonzero.contains(&center) && !even_odd.contains(&center));
+        assert!(nonzero.contains(&point) && even_odd.contains(&point));
+        assert!(!nonzero.contains(&Point { x: 5., y: 5. }) && !even_odd.contains(&Point { x: 5., y: 5. }));
+        assert_rows_match_contains(&nonzero.into());
+        assert_rows_match_contains(&even_odd.into());
+    }
+
+    #[test]
+    fn only_polygons_without_crossing_edges_are_simple() {
+        let square = Polygon::new(vec![Point { x: 0., y: 0. }, Point { x: 4., y: 0. }, Point { x: 4., y: 4. }, Point { x: 0., y: 4. }], FillRule::NonZero);
+        let bow_tie = Polygon::new(vec![Point { x: 0., y: 0. }, Point { x: 4., y: 4. }, Point { x: 4., y: 0. }, Point { x: 0., y: 4. }], FillRule::NonZero);
+        let triangle = Polygon::new(vec![Point { x: 0., y: 0. }, Point { x: 4., y: 0. }, Point { x: 0., y: 3. }], FillRule::NonZero);
+
+        assert!(square.is_simple() && triangle.is_simple());
+        assert!(!bow_tie.is_simple() && !pentagram().is_simple());
+        assert_eq!((square.area(), square.perimeter()), (16., 16.));
+        assert_eq!((triangle.area(), triangle.perimeter()), (6., 12.));
+    }
+
+    #[test]
+    fn intersects_finds_overlaps_and_containment() {
+        let rect = |x1: f64, y1: f64, x2: f64, y2: f64| -> Shape { Rect::from_points(&Point { x: x1, y: y1 }, &Point { x: x2, y: y2 }).into() };
+        let circle = |x: f64, y: f64, radius: f64| -> Shape { Ellipse::circle(Point { x, y }, radius).into() };
+        let triangle: Shape = Triangle::new(Point { x: 0., y: 0. }, Point { x: 10., y: 0. }, Point { x: 0., y: 10. }).into();
+
+        for (shape1, shape2, overlap) in [
+            (rect(0., 0., 4., 4.), rect(3., 3., 8., 8.), true),
+            (rect(0., 0., 4., 4.), rect(4., 0., 8., 4.), true),
+            (rect(0., 0., 4., 4.), rect(5., 0., 8., 4.), false),
+            (rect(0., 0., 10., 10.), rect(4., 4., 5., 5.), true),
+            // the triangle's bounding rect covers this rect, but its slanted edge doesn't reach it
+            (triangle.clone(), rect(7., 7., 9., 9.), false),
+            (triangle.clone(), rect(4., 4., 9., 9.), true),
+            (triangle.clone(), circle(8., 8., 2.), false),
+            (triangle, circle(8., 8., 4.5), true),
+            (circle(0., 0., 3.), circle(5., 0., 2.), true),
+            (circle(0., 0., 3.), circle(5.5, 0., 2.), false),
+            (pentagram().into(), circle(30., 30., 1.), true),
+            (pentagram().with_fill_rule(FillRule::EvenOdd).into(), circle(30., 30., 1.), false),
+            (circle(20., 20., 5.).outline(1.), rect(19., 19., 21., 21.), false),
+            (circle(20., 20., 5.).outline(1.), rect(19., 14., 21., 16.), true),
+        ] {
+            assert_eq!(shape1.intersects(&shape2), overlap, "{shape1:?} and {shape2:?}");
+            assert_eq!(shape2.intersects(&shape1), overlap, "{shape2:?} and {shape1:?}");
+        }
+    }
+
+    fn png_bytes(image: image::DynamicImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn masks_keep_pixels_past_the_threshold() {
+        let gray = image::GrayImage::from_fn(4, 2, |x, y| image::Luma([[0, 100, 101, 255][x as usize] * (1 - y as u8)]));
+        // only alpha counts when there is any, however bright the pixel is
+        let gray_alpha = image::GrayAlphaImage::from_fn(4, 1, |x, _| image::LumaA([255 - x as u8, [0, 100, 101, 255][x as usize]]));
+        let resolver = MemoryResolver::new()
+            .with_asset("gray.png", png_bytes(gray.into()))
+            .with_asset("alpha.png", png_bytes(gray_alpha.into()));
+
+        let expected = [false, false, true, true];
+        for name in ["gray.png", "alpha.png"] {
+            let mask = Shape::from_mask_with(&resolver, name, 100).unwrap();
+            for (x, inside) in expected.into_iter().enumerate() {
+                let point = Point { x: x as f64, y: 0. };
+                assert_eq!(mask.contains(&point), inside, "{name} at {x}");
+                assert_eq!(mask.signed_distance(&point) < 0., inside, "{name} at {x}");
+            }
+            assert!(!mask.contains(&Point { x: 4., y: 0.5 }) && !mask.contains(&Point { x: 3., y: -0.5 }));
+        }
+        assert!(!Shape::from_mask_with(&resolver, "gray.png", 100).unwrap().contains(&Point { x: 3., y: 1. }));
+        assert!(Shape::from_mask_with(&resolver, "missing.png", 100).is_err());
+    }
+
+    #[test]
+    fn arcs_contain_their_stroke_and_round_caps() {
+        use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+        let center = Point { x: 30., y: 30. };
+        let quarter = Arc::new(center, 20., 0., FRAC_PI_2, 4.);
+        let backwards = Arc::new(center, 20., FRAC_PI_2, -FRAC_PI_2, 4.);
+        let on_circle = |angle: f64, radius: f64| center + Point { x: angle.cos(), y: angle.sin() } * radius;
+
+        for arc in [quarter, backwards] {
+            assert!(arc.contains(&on_circle(FRAC_PI_4, 20.)) && arc.contains(&on_circle(FRAC_PI_4, 21.9)));
+            assert!(!arc.contains(&on_circle(FRAC_PI_4, 22.1)) && !arc.contains(&center));
+            assert!(!arc.contains(&on_circle(std::f64::consts::PI, 20.)));
+            // the cap bulges a little past the start and end
+            assert!(arc.contains(&Point { x: 51., y: 28.5 }) && arc.contains(&Point { x: 28.5, y: 51. }));
+            assert!(!arc.contains(&Point { x: 50., y: 27.5 }));
+            assert_eq!(arc.start_point(), quarter.start_point());
+            assert_rows_match_contains(&arc.into());
+        }
+    }
+
+    #[test]
+    fn wave_bands_follow_their_wave() {
+        let band = WaveBand::new(30., 8., 20., 0.5, 6.);
+        for x in [-13., 0., 4.5, 17.] {
+            let middle = band.center_at(x);
+            assert!((band.center_at(x + 20.) - middle).abs() < 1e-9);
+            for (offset, inside) in [(0., true), (2.9, true), (-2.9, true), (3.1, false), (-3.1, false)] {
+                let point = Point { x, y: middle + offset };
+                assert_eq!(band.contains(&point), inside, "{point:?}");
+                assert_eq!(band.signed_distance(&point) <= 0., inside, "{point:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn convex_hulls_skip_duplicate_and_collinear_points() {
+        let corners = [Point { x: 0., y: 0. }, Point { x: 6., y: 0. }, Point { x: 6., y: 4. }, Point { x: 0., y: 4. }];
+        let mut points = corners.to_vec();
+        points.extend([
+            // on the edges, inside, and repeats of corners
+            Point { x: 3., y: 0. }, Point { x: 6., y: 2. }, Point { x: 2., y: 4. }, Point { x: 0., y: 1. },
+            Point { x: 3., y: 2. }, Point { x: 1., y: 3. },
+            corners[0], corners[2], corners[2],
+        ]);
+
+        let hull = Shape::convex_hull(&points);
+        assert_eq!(hull.vertices().len(), 4);
+        assert!(corners.iter().all(|corner| hull.vertices().contains(corner)));
+        assert!(hull.is_simple() && hull.area() == 24.);
+        assert!(points.iter().all(|point| hull.signed_distance(point) <= 0.));
+
+        let line = [Point { x: 0., y: 0. }, Point { x: 2., y: 2. }, Point { x: 1., y: 1. }, Point { x: 3., y: 3. }, Point { x: 2., y: 2. }];
+        assert_eq!(Shape::convex_hull(&line).vertices(), [Point { x: 0., y: 0. }, Point { x: 3., y: 3. }]);
+        assert_eq!(Shape::convex_hull(&[corners[1]; 3]).vertices(), [corners[1]]);
+        assert!(Shape::convex_hull(&[]).vertices().is_empty());
+    }
+
+    #[test]
+    fn voronoi_cells_split_the_bounds_by_nearest_seed() {
+        let bounds = Rect::from_points(&Point { x: 0., y: 0. }, &Point { x: 40., y: 30. });
+        let seeds = [
+            Point { x: 5., y: 5. }, Point { x: 30., y: 8. }, Point { x: 18., y: 22. },
+            // outside the bounds, and a repeat that gets the same cell as the seed it copies
+            Point { x: 45., y: 28. }, Point { x: 5., y: 5. },
+        ];
+        let cells = voronoi_cells(&seeds, bounds);
+        assert_eq!(cells.len(), seeds.len());
+        assert_eq!(cells[0], cells[4]);
+
+        let total_area: f64 = cells[..4].iter().map(|cell| cell.area()).sum();
+        assert!((total_area - bounds.area()).abs() < 1e-9, "{total_area}");
+
+        for y in 0..30 {
+            for x in 0..40 {
+                let point = Point { x: x as f64 + 0.37, y: y as f64 + 0.61 };
+                let nearest = (0..4).min_by(|index1, index2| point.dist_to(&seeds[*index1]).total_cmp(&point.dist_to(&seeds[*index2]))).unwrap();
+                let containing: Vec<usize> = (0..4).filter(|index| cells[*index].contains(&point)).collect();
+                assert_eq!(containing, [nearest], "{point:?}");
+            }
+        }
+
+        assert!(voronoi_cells(&[], bounds).is_empty());
+        assert_eq!(voronoi_cells(&seeds[..1], bounds)[0].area(), bounds.area());
+    }
+
+    #[test]
+    fn tilings_cover_their_bounds() {
+        let bounds = Rect::from_points(&Point { x: 0., y: 0. }, &Point { x: 100., y: 50. });
+        // with the grid shifted off the edges, 11 columns and 6 rows of squares reach into the bounds
+        let squares: Vec<Shape> = Tiling::new(TileShape::Square, bounds, 10., 2., Point { x: 5., y: 5. }).collect();
+        assert_eq!(squares.len(), 66);
+        assert!(squares.iter().all(|square| square.area() == 64.));
+
+        for tile_shape in [TileShape::Square, TileShape::Hexagon, TileShape::Triangle] {
+            let tiles: Vec<Shape> = Tiling::new(tile_shape, bounds, 10., 0., Point { x: 3., y: -7. }).collect();
+            assert!(tiles.iter().all(|tile| tile.intersects(&bounds.into())), "{tile_shape:?}");
+            for y in 0..50 {
+                for x in 0..100 {
+                    let point = Point { x: x as f64 + 0.37, y: y as f64 + 0.61 };
+                    assert_eq!(tiles.iter().filter(|tile| tile.contains(&point)).count(), 1, "{tile_shape:?} at {point:?}");
+                }
+            }
+
+            assert_eq!(Tiling::new(tile_shape, bounds, 0., 0., Point::ORIGIN).count(), 0);
+            assert_eq!(Tiling::new(tile_shape, bounds, 10., 10., Point::ORIGIN).count(), 0);
+        }
+    }
+
+    #[test]
+    fn growing_and_shrinking_move_the_boundary() {
+        let rect: Shape = Rect::from_points(&Point { x: 0., y: 0. }, &Point { x: 10., y: 6. }).into();
+        assert_eq!(rect.shrink(1.), Rect::from_points(&Point { x: 1., y: 1. }, &Point { x: 9., y: 5. }).into());
+
+        // growing rounds the corners off, so it's no longer a rect
+        let grown = rect.grow(2.);
+        assert!(grown.contains(&Point { x: -1.9, y: 3. }) && !grown.contains(&Point { x: -2.1, y: 3. }));
+        assert!(grown.contains(&Point { x: -1.2, y: -1.2 }) && !grown.contains(&Point { x: -1.5, y: -1.5 }));
+        assert!(!rect.shrink(4.).contains(&Point { x: 5., y: 3. }));
+
+        let circle: Shape = Ellipse::circle(Point { x: 20., y: 20. }, 5.).into();
+        assert_eq!(circle.grow(3.), Ellipse::circle(Point { x: 20., y: 20. }, 8.).into());
+        assert_eq!(circle.shrink(2.), Ellipse::circle(Point { x: 20., y: 20. }, 3.).into());
+        assert!(!circle.shrink(6.).contains(&Point { x: 20., y: 20. }));
+
+        let ellipse: Shape = Ellipse::new(Point { x: 20., y: 20. }, 8., 4.).into();
+        let grown = ellipse.grow(1.);
+        assert!(grown.contains(&Point { x: 28.9, y: 20. }) && !grown.contains(&Point { x: 29.1, y: 20. }));
+        assert!(grown.contains(&Point { x: 20., y: 24.9 }) && !grown.contains(&Point { x: 20., y: 25.1 }));
+        let shrunk = ellipse.shrink(1.);
+        assert!(shrunk.contains(&Point { x: 26.9, y: 20. }) && !shrunk.contains(&Point { x: 27.1, y: 20. }));
+
+        // offsets add up instead of nesting
+        assert_eq!(ellipse.grow(2.).shrink(0.5), ellipse.grow(1.5));
+    }
+
+    #[test]
+    fn fractals_start_from_a_plain_triangle() {
+        let center = Point { x: 30., y: 30. };
+        let triangle_area = 3. * 3_f64.sqrt() / 4. * 20. * 20.;
+
+        let koch = koch_snowflake(center, 20., 0);
+        assert_eq!(koch.vertices().len(), 3);
+        assert!((koch.area() - triangle_area).abs() < 1e-9);
+        assert!((koch.vertices()[0].x - 30.).abs() < 1e-9 && koch.vertices()[0].y == 10.);
+
+        // every level adds a triangle a ninth the size on each edge
+        let koch = koch_snowflake(center, 20., 1);
+        assert_eq!(koch.vertices().len(), 12);
+        assert!(koch.is_simple());
+        assert!((koch.area() - triangle_area * 4. / 3.).abs() < 1e-9);
+        assert_eq!(koch_snowflake(center, 20., 3).vertices().len(), 3 * 4_usize.pow(3));
+
+        let sierpinski: Shape = sierpinski_triangle(center, 20., 0).into();
+        let plain: Shape = Polygon::new(equilateral_triangle(center, 20.).to_vec(), FillRule::NonZero).into();
+        for y in 0..60 {
+            for x in 0..60 {
+                let point = Point { x: x as f64 + 0.37, y: y as f64 + 0.61 };
+                assert_eq!(sierpinski.contains(&point), plain.contains(&point), "{point:?}");
+            }
+        }
+
+        // the middle quarter is cut out, leaving the three corners
+        let sierpinski = sierpinski_triangle(center, 20., 1);
+        assert!(!sierpinski.contains(&center));
+        assert!(equilateral_triangle(center, 20.).iter().all(|corner| sierpinski.contains(&(center + (*corner - center) * 0.8))));
+    }
+
+    #[test]
+    fn radial_symmetry_copies_and_mirrors_the_first_wedge() {
+        let center = Point { x: 30., y: 30. };
+        let around = |angle: f64, distance: f64| center + Point { x: angle.cos(), y: angle.sin() } * distance;
+        // a dot a little above the x axis, in the first part of the first wedge of 4
+        let dot: Shape = Ellipse::circle(around(0.3, 15.), 2.).into();
+
+        let repeated = RadialSymmetry::new(dot.clone(), 4, center);
+        let mirrored = RadialSymmetry::kaleidoscope(dot, 4, center);
+        for copy in 0..4 {
+            let turn = copy as f64 * std::f64::consts::FRAC_PI_2;
+            assert!(repeated.contains(&around(turn + 0.3, 15.)), "copy {copy}");
+            assert!(!repeated.contains(&around(turn - 0.3, 15.)), "copy {copy}");
+            // every other copy is flipped, so the dots come in pairs either side of each wedge line
+            assert!(mirrored.contains(&around(turn + 0.3, 15.)) && mirrored.contains(&around(turn - 0.3, 15.)), "copy {copy}");
+        }
+
+        assert!(!repeated.contains(&around(0.8, 15.)) && !mirrored.contains(&around(0.8, 15.)));
+        assert_rows_match_contains(&repeated.into());
+        assert_rows_match_contains(&mirrored.into());
+    }
+}
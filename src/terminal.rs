@@ -0,0 +1,153 @@
+use std::io::Cursor;
+
+use crate::Image;
+
+impl Image {
+    /// Encodes the image as a Sixel graphic, scaled down to at most `max_width` pixels wide. Colors are reduced to a
+    /// 6x6x6 color cube since Sixel is palette based.
+    pub fn render_sixel(&self, max_width: usize) -> String {
+        const LEVELS: usize = 6;
+
+        if max_width == 0 || self.canvas.is_empty() {
+            return String::new();
+        }
+
+        let scale = (self.canvas_width as f64 / max_width as f64).max(1.);
+        let width = (self.canvas_width as f64 / scale) as usize;
+        let height = (self.canvas_height() as f64 / scale) as usize;
+        let palette_index = |x: usize, y: usize| {
+            let color = self.get_pixel(((x as f64 * scale) as usize).min(self.canvas_width - 1), ((y as f64 * scale) as usize).min(self.canvas_height() - 1));
+            let level = |channel: u8| (channel as usize * (LEVELS - 1) + 127) / 255;
+            level(color.red) * LEVELS * LEVELS + level(color.green) * LEVELS + level(color.blue)
+        };
+
+        let mut output = format!("\x1bPq\"1;1;{width};{height}");
+        for index in 0..LEVELS * LEVELS * LEVELS {
+            let percent = |level: usize| level * 100 / (LEVELS - 1);
+            output.push_str(&format!("#{index};2;{};{};{}", percent(index / (LEVELS * LEVELS)), percent(index / LEVELS % LEVELS), percent(index % LEVELS)));
+        }
+
+        // every sixel character covers a column of 6 pixels
+        for band_start in (0..height).step_by(6) {
+            let band_rows = (band_start..(band_start + 6).min(height)).collect::<Vec<_>>();
+            let band_indices: Vec<Vec<usize>> = band_rows.iter().map(|y| (0..width).map(|x| palette_index(x, *y)).collect()).collect();
+
+            let mut used_colors: Vec<usize> = band_indices.iter().flatten().copied().collect();
+            used_colors.sort_unstable();
+            used_colors.dedup();
+
+            for color in used_colors {
+                output.push_str(&format!("#{color}"));
+
+                let column_bits = |x: usize| band_indices.iter().enumerate()
+                    .filter(|(_, row)| row[x] == color)
+                    .fold(0u8, |bits, (bit, _)| bits | (1 << bit));
+
+                let mut x = 0;
+                while x < width {
+                    let bits = column_bits(x);
+                    let run_start = x;
+                    while x < width && column_bits(x) == bits {
+                        x += 1;
+                    }
+                    let sixel = (63 + bits) as char;
+                    match x - run_start {
+                        1 => output.push(sixel),
+                        run_length => output.push_str(&format!("!{run_length}{sixel}")),
+                    }
+                }
+                // go back to the start of the band for the next color
+                output.push('$');
+            }
+            output.push('-');
+        }
+
+        output.push_str("\x1b\\");
+        output
+    }
+
+    /// Encodes the image with the iTerm2 inline image protocol, which is also understood by WezTerm and a few others
+    pub fn render_iterm2(&self) -> Result<String, ()> {
+        let mut png_bytes = Vec::new();
+        self.to_rgb_image()?
+            .write_to(&mut Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+            .map_err(|_|())?;
+
+        Ok(format!("\x1b]1337;File=inline=1;size={}:{}\x07\n", png_bytes.len(), base64_encode(&png_bytes)))
+    }
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for (position, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            if position <= chunk.len() {
+                encoded.push(ALPHABET[(group >> shift & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coloring::SolidColor;
+
+    #[test]
+    fn base64_matches_the_rfc_examples() {
+        for (bytes, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64_encode(bytes.as_bytes()), encoded);
+        }
+        assert_eq!(base64_encode(&[0xFF, 0xFE]), "//4=");
+    }
+
+    #[test]
+    fn sixels_draw_each_color_in_turn() {
+        let mut image = Image::with_size(2, 1, SolidColor { red: 255, green: 0, blue: 0 });
+        *image.get_pixel_mut(1, 0) = SolidColor { red: 0, green: 0, blue: 255 };
+        let sixel = image.render_sixel(10);
+
+        assert!(sixel.starts_with("\x1bPq\"1;1;2;1#0;2;0;0;0#1;2;0;0;20#"));
+        assert!(sixel.contains("#180;2;100;0;0#"));
+        // blue (5) only fills the second column and red (180) only the first
+        assert!(sixel.ends_with("#215;2;100;100;100#5?@$#180@?$-\x1b\\"));
+
+        let white = Image::with_size(4, 8, SolidColor::WHITE).render_sixel(10);
+        assert!(white.ends_with("#215;2;100;100;100#215!4~$-#215!4B$-\x1b\\"));
+    }
+
+    #[test]
+    fn sixels_scale_down_to_fit() {
+        let image = Image::with_size(8, 6, SolidColor::WHITE);
+        assert!(image.render_sixel(2).starts_with("\x1bPq\"1;1;2;1#"));
+        assert_eq!(image.render_sixel(0), "");
+    }
+
+    #[test]
+    fn iterm2_images_are_base64_pngs() {
+        let preview = Image::with_size(3, 2, SolidColor::WHITE).render_iterm2().unwrap();
+        let (header, payload) = preview.strip_prefix("\x1b]1337;File=inline=1;size=").unwrap().split_once(':').unwrap();
+        let payload = payload.strip_suffix("\x07\n").unwrap();
+
+        assert_eq!(payload.len(), header.parse::<usize>().unwrap().div_ceil(3) * 4);
+        // the base64 of PNG's signature
+        assert!(payload.starts_with("iVBORw0KGgo"));
+    }
+}
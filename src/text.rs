@@ -0,0 +1,124 @@
+use ab_glyph::{Font as _, FontVec, GlyphId, OutlineCurve, PxScale, PxScaleFont, ScaleFont};
+
+use crate::assets::{AssetResolver, FileSystemResolver};
+use crate::shapes::{FillRule, Point, Polygon, Shape};
+
+/// A TrueType or OpenType font for turning text into shapes
+pub struct Font {
+    font: FontVec,
+}
+
+impl Font {
+    pub fn open(filename: &str) -> Result<Self, ()> {
+        Font::open_with(&FileSystemResolver::new(), filename)
+    }
+
+    /// Like `open`, but gets the font from `resolver`
+    pub fn open_with(resolver: &dyn AssetResolver, name: &str) -> Result<Self, ()> {
+        Font::from_bytes(resolver.load(name).map_err(|_|())?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, ()> {
+        Ok(Font {
+            font: FontVec::try_from_vec(data).map_err(|_|())?,
+        })
+    }
+
+    /// One shape per visible glyph of `text`, starting from `origin` on the baseline. `size` is the height
+    /// of a line in pixels, and every `\n` starts a new line below the last one.
+    pub fn text_shapes(&self, text: &str, origin: Point, size: f64) -> Vec<Shape> {
+        let scaled_font = self.font.as_scaled(PxScale::from(size as f32));
+        let line_height = (scaled_font.height() + scaled_font.line_gap()) as f64;
+
+        let mut shapes = Vec::new();
+        for (line_index, line) in text.lines().enumerate() {
+            let mut pen = Point { x: origin.x, y: origin.y + line_index as f64 * line_height };
+            let mut previous_glyph: Option<GlyphId> = None;
+
+            for c in line.chars() {
+                let glyph = self.font.glyph_id(c);
+                if let Some(previous_glyph) = previous_glyph {
+                    pen.x += scaled_font.kern(previous_glyph, glyph) as f64;
+                }
+                if let Some(shape) = self.glyph_shape(glyph, pen, &scaled_font) {
+                    shapes.push(shape);
+                }
+                pen.x += scaled_font.h_advance(glyph) as f64;
+                previous_glyph = Some(glyph);
+            }
+        }
+        shapes
+    }
+
+    /// How far the pen moves across the longest line of `text`
+    pub fn text_width(&self, text: &str, size: f64) -> f64 {
+        let scaled_font = self.font.as_scaled(PxScale::from(size as f32));
+
+        text.lines().map(|line| {
+            let glyphs: Vec<GlyphId> = line.chars().map(|c| self.font.glyph_id(c)).collect();
+            let advance: f32 = glyphs.iter().map(|glyph| scaled_font.h_advance(*glyph)).sum();
+            let kerning: f32 = glyphs.windows(2).map(|pair| scaled_font.kern(pair[0], pair[1])).sum();
+            (advance + kerning) as f64
+        }).fold(0., f64::max)
+    }
+
+    fn glyph_shape(&self, glyph: GlyphId, pen: Point, scaled_font: &PxScaleFont<&FontVec>) -> Option<Shape> {
+        let outline = self.font.outline(glyph)?;
+        let (x_scale, y_scale) = (scaled_font.h_scale_factor() as f64, scaled_font.v_scale_factor() as f64);
+        // font units point up from the baseline, pixels point down
+        let to_canvas = |point: ab_glyph::Point| Point {
+            x: pen.x + point.x as f64 * x_scale,
+            y: pen.y - point.y as f64 * y_scale,
+        };
+
+        let mut contours: Vec<Vec<Point>> = Vec::new();
+        let mut last_point: Option<Point> = None;
+        for curve in outline.curves.iter() {
+            let (start, end) = match curve {
+                OutlineCurve::Line(start, end) => (*start, *end),
+                OutlineCurve::Quad(start, _, end) => (*start, *end),
+                OutlineCurve::Cubic(start, _, _, end) => (*start, *end),
+            };
+            let (start, end) = (to_canvas(start), to_canvas(end));
+
+            // a curve that doesn't pick up where the last one ended starts a new contour
+            if last_point != Some(start) {
+                contours.push(vec![start]);
+            }
+            let contour = contours.last_mut().expect("A contour was just started if there wasn't one");
+
+            match curve {
+                OutlineCurve::Line(..) => contour.push(end),
+                OutlineCurve::Quad(_, control, _) => {
+                    let control = to_canvas(*control);
+                    flatten(&[start, control, end], contour);
+                },
+                OutlineCurve::Cubic(_, control1, control2, _) => {
+                    let (control1, control2) = (to_canvas(*control1), to_canvas(*control2));
+                    flatten(&[start, control1, control2, end], contour);
+                },
+            }
+            last_point = Some(end);
+        }
+
+        let polygon = Polygon::from_contours(contours, FillRule::NonZero);
+        (polygon.vertices().len() >= 3).then(|| polygon.into())
+    }
+}
+
+/// Adds points along the bezier curve with `controls` to `vertices`, leaving out the start point.
+/// Longer curves get more points, about one every two pixels.
+fn flatten(controls: &[Point], vertices: &mut Vec<Point>) {
+    let control_len: f64 = controls.windows(2).map(|pair| pair[0].dist_to(&pair[1])).sum();
+    let segments = (control_len / 2.).ceil().clamp(1., 32.) as usize;
+
+    for segment in 1..=segments {
+        let t = segment as f64 / segments as f64;
+        // de Casteljau's algorithm
+        let mut points = controls.to_vec();
+        while points.len() > 1 {
+            points = points.windows(2).map(|pair| pair[0] + (pair[1] - pair[0]) * t).collect();
+        }
+        vertices.push(points[0]);
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use crate::coloring::TransparentColor;
+
+/// A set of named color slots that scenes can refer to (as `theme.<slot>` in .noisy files), so the same scene can be
+/// rendered in several coordinated color schemes
+#[derive(Clone, Debug, Default)]
+pub struct Theme {
+    slots: HashMap<String, TransparentColor>,
+}
+
+impl Theme {
+    pub fn new() -> Self {
+        Theme {
+            slots: HashMap::new(),
+        }
+    }
+
+    /// Slot names are case insensitive
+    pub fn with_slot(mut self, slot: &str, color: TransparentColor) -> Self {
+        self.set_slot(slot, color);
+        self
+    }
+
+    pub fn set_slot(&mut self, slot: &str, color: TransparentColor) {
+        self.slots.insert(slot.to_lowercase(), color);
+    }
+
+    pub fn get_slot(&self, slot: &str) -> Option<TransparentColor> {
+        self.slots.get(&slot.to_lowercase()).copied()
+    }
+
+    /// Dark text and saturated accents on an off-white background. The built-in themes all fill the
+    /// `background`, `foreground`, `muted`, `accent1`, `accent2` and `accent3` slots.
+    pub fn light() -> Self {
+        Theme::with_standard_slots(["#F7F7F5", "#1F2328", "#8C959F", "#0969DA", "#CF4A0A", "#1A7F37"])
+    }
+
+    /// Light text and softer accents on a near-black background
+    pub fn dark() -> Self {
+        Theme::with_standard_slots(["#0D1117", "#E6EDF3", "#6E7681", "#58A6FF", "#FF8A4C", "#3FB950"])
+    }
+
+    /// Pure black and white with fully saturated accents, for the most contrast between every slot
+    pub fn high_contrast() -> Self {
+        Theme::with_standard_slots(["#000000", "#FFFFFF", "#C0C0C0", "#FFFF00", "#00FFFF", "#FF00FF"])
+    }
+
+    fn with_standard_slots(hex_codes: [&str; 6]) -> Self {
+        ["background", "foreground", "muted", "accent1", "accent2", "accent3"].into_iter().zip(hex_codes)
+            .fold(Theme::new(), |theme, (slot, hex_code)| theme.with_slot(slot, TransparentColor::from_hex_code(hex_code)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slot_names_ignore_case() {
+        let red = TransparentColor::from_hex_code("#FF0000");
+        let theme = Theme::new().with_slot("Accent1", red);
+        assert_eq!(theme.get_slot("accent1"), Some(red));
+        assert_eq!(theme.get_slot("ACCENT1"), Some(red));
+        assert_eq!(theme.get_slot("accent2"), None);
+    }
+
+    #[test]
+    fn built_in_themes_fill_every_standard_slot() {
+        for theme in [Theme::light(), Theme::dark(), Theme::high_contrast()] {
+            for slot in ["background", "foreground", "muted", "accent1", "accent2", "accent3"] {
+                assert!(theme.get_slot(slot).is_some_and(|color| color.alpha == u8::MAX), "{slot}");
+            }
+        }
+        assert_eq!(Theme::high_contrast().get_slot("background"), Some(TransparentColor::from_hex_code("#000000")));
+    }
+}